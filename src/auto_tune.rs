@@ -0,0 +1,59 @@
+use std::time::Instant;
+use tracing::warn;
+use url::Url;
+
+use crate::fetcher::Fetcher;
+
+/// How many of the seeds are fetched to measure the latency
+pub const PROBES: usize = 3;
+/// The latency assumed when no probe got through
+const DEFAULT_LATENCY_MS: f64 = 500.0;
+/// Rough CPU time spent parsing and scraping a page
+const CPU_MS_PER_PAGE: f64 = 20.0;
+const MAX_WORKERS: u64 = 64;
+/// Browsers open up to 6 connections per host
+const MAX_PER_HOST: usize = 6;
+
+/// The concurrency picked for a crawl from the CPU
+/// cores available and the latency of the sites
+#[derive(Clone, Copy, Debug)]
+pub struct AutoTuning {
+    pub workers: u64,
+    /// requests made to the same host at the same time
+    pub per_host: usize,
+    pub cores: usize,
+    /// the median time taken to fetch the probed seeds
+    pub latency_ms: f64,
+}
+
+impl AutoTuning {
+    /// Times fetching the first few `urls`, the workers mostly
+    /// wait on the network so the slower the sites are the more
+    /// workers each core can keep busy
+    pub async fn measure(fetcher: &dyn Fetcher, urls: &[String]) -> AutoTuning {
+        let mut latencies: Vec<f64> = Vec::new();
+        for url in urls.iter().filter_map(|u| Url::parse(u).ok()).take(PROBES) {
+            let start = Instant::now();
+            match fetcher.fetch(&url).await {
+                Ok(_) => latencies.push(start.elapsed().as_secs_f64() * 1000.0),
+                Err(e) => warn!("Could not probe {}: {:#}", url, e),
+            }
+        }
+        latencies.sort_by(f64::total_cmp);
+        let latency_ms = latencies
+            .get(latencies.len() / 2)
+            .copied()
+            .unwrap_or(DEFAULT_LATENCY_MS);
+
+        let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let workers = ((cores as f64 * (1.0 + latency_ms / CPU_MS_PER_PAGE)).round() as u64)
+            .clamp(cores as u64, MAX_WORKERS);
+
+        AutoTuning {
+            workers,
+            per_host: MAX_PER_HOST.min(workers as usize),
+            cores,
+            latency_ms,
+        }
+    }
+}
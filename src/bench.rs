@@ -0,0 +1,55 @@
+use std::time::Instant;
+use url::Url;
+
+use crate::crawler::{extract_page, ScrapeConfig, ScrapeOption};
+
+/// How fast the pages were scraped with one of the options
+pub struct BenchResult {
+    /// the option, `None` for the links alone
+    pub option: Option<ScrapeOption>,
+    pub pages_per_sec: f64,
+    /// the time per page the option adds to scraping the links
+    pub overhead_ms: f64,
+}
+
+/// Scrapes the `pages` (url, html) `rounds` times with each
+/// `ScrapeOption` on its own, to measure what enabling it costs
+/// on top of the links, which are always scraped
+pub fn bench_extraction(pages: &[(Url, String)], rounds: usize) -> Vec<BenchResult> {
+    let mut config = ScrapeConfig {
+        options: Default::default(),
+        max_field_length: 4096,
+        parsers: Default::default(),
+        custom_selectors: Default::default(),
+        searches: Default::default(),
+        extractors: Default::default(),
+    };
+
+    let mut time_per_page = |options: Vec<ScrapeOption>| {
+        config.options = options;
+        let start = Instant::now();
+        for _ in 0..rounds {
+            for (url, html) in pages {
+                extract_page(html, url, &config);
+            }
+        }
+        start.elapsed().as_secs_f64() / (rounds * pages.len()).max(1) as f64
+    };
+
+    let links_time = time_per_page(Vec::new());
+    let mut results = vec![BenchResult {
+        option: None,
+        pages_per_sec: 1.0 / links_time,
+        overhead_ms: 0.0,
+    }];
+    for option in ScrapeOption::ALL {
+        let time = time_per_page(vec![option]);
+        results.push(BenchResult {
+            option: Some(option),
+            pages_per_sec: 1.0 / time,
+            overhead_ms: (time - links_time) * 1000.0,
+        });
+    }
+
+    results
+}
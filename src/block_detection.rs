@@ -0,0 +1,115 @@
+use reqwest::{header::HeaderMap, StatusCode};
+use scraper::Html;
+
+use crate::crawler::get_text;
+use crate::model::BlockKind;
+
+/// Markers in the body of CAPTCHA pages
+const CAPTCHA_MARKERS: [&str; 4] = [
+    "g-recaptcha",
+    "h-captcha",
+    "hcaptcha.com",
+    "captcha-delivery",
+];
+
+/// Markers in the body of generic anti-bot pages
+const BOT_DETECTED_MARKERS: [&str; 4] = [
+    "are you a robot",
+    "unusual traffic from your computer",
+    "automated access",
+    "please verify you are a human",
+];
+
+/// The most words a page can show and still be a challenge
+/// page rather than a page embedding a CAPTCHA (e.g. in a form)
+const MAX_INTERSTITIAL_WORDS: usize = 100;
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+}
+
+/// Classifies the response as an anti-bot page, if it is one,
+/// from its status code, headers and body
+pub fn detect_block(status: StatusCode, headers: &HeaderMap, body: &str) -> Option<BlockKind> {
+    let body = body.to_ascii_lowercase();
+    let server = header(headers, "server").to_ascii_lowercase();
+
+    let cloudflare = header(headers, "cf-mitigated") == "challenge"
+        || body.contains("cf-challenge")
+        || body.contains("challenge-platform")
+        || (server == "cloudflare" && body.contains("attention required!"));
+    if cloudflare {
+        return Some(BlockKind::CloudflareChallenge);
+    }
+
+    let akamai = server.contains("akamaighost")
+        && status == StatusCode::FORBIDDEN
+        && body.contains("access denied");
+    if akamai {
+        return Some(BlockKind::Akamai);
+    }
+
+    // Ordinary pages mention these too (sign up forms, bot policies)
+    let kind = if CAPTCHA_MARKERS.iter().any(|m| body.contains(m)) {
+        BlockKind::Captcha
+    } else if BOT_DETECTED_MARKERS.iter().any(|m| body.contains(m)) {
+        BlockKind::BotDetected
+    } else {
+        return None;
+    };
+    is_interstitial(status, &body).then_some(kind)
+}
+
+/// Whether the page stands in for the one asked for: it
+/// was refused, or it has (almost) nothing else to read
+fn is_interstitial(status: StatusCode, body: &str) -> bool {
+    let refused = [
+        StatusCode::FORBIDDEN,
+        StatusCode::TOO_MANY_REQUESTS,
+        StatusCode::SERVICE_UNAVAILABLE,
+    ];
+    refused.contains(&status)
+        || get_text(&Html::parse_document(body))
+            .split_whitespace()
+            .count()
+            <= MAX_INTERSTITIAL_WORDS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(status: u16, body: &str) -> Option<BlockKind> {
+        let status = StatusCode::from_u16(status).unwrap();
+        detect_block(status, &HeaderMap::new(), body)
+    }
+
+    #[test]
+    fn captcha_interstitials_are_detected() {
+        let challenge = r#"<html><body><h1>Are you a robot?</h1>
+            <div class="g-recaptcha" data-sitekey="key"></div></body></html>"#;
+
+        assert_eq!(detect(200, challenge), Some(BlockKind::Captcha));
+        assert_eq!(
+            detect(429, "<p>Are you a robot? Please try again later.</p>"),
+            Some(BlockKind::BotDetected)
+        );
+    }
+
+    #[test]
+    fn pages_with_a_captcha_form_are_not_blocked() {
+        let article = "Our newsletter covers crawling the web politely. ".repeat(30);
+        let page = format!(
+            r#"<html><body><article><p>{}</p></article>
+            <form><div class="g-recaptcha"></div></form>
+            <p>We block automated access to the sign up form.</p></body></html>"#,
+            article
+        );
+
+        assert_eq!(detect(200, &page), None);
+        assert_eq!(detect(403, &page), Some(BlockKind::Captcha));
+    }
+}
@@ -0,0 +1,133 @@
+//! The pages that failed to load, with the pages linking to
+//! them, see `crawl --broken-links`
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::export::tables::write_rows;
+use crate::model::{ErrorKind, Link, LinkGraph, LinkId};
+
+/// A page that failed to load and the pages linking to it
+#[derive(Debug, Serialize)]
+pub struct BrokenLink {
+    pub url: String,
+    /// the 4xx or 5xx status the page was served with
+    pub status: Option<u16>,
+    /// why the page failed, e.g. a timeout when it had no status
+    pub error: Option<ErrorKind>,
+    /// the urls of the pages linking to this one, sorted
+    pub referrers: Vec<String>,
+}
+
+/// A row of the broken links CSV, one per referrer
+#[derive(Serialize)]
+struct BrokenLinkRow<'a> {
+    url: &'a str,
+    status: Option<u16>,
+    error: Option<ErrorKind>,
+    referrer: Option<&'a str>,
+}
+
+/// Whether `link` was served with an error status or failed to load
+fn is_broken(link: &Link) -> bool {
+    link.status.is_some_and(|status| status >= 400) || link.error.is_some()
+}
+
+/// The broken pages of `link_graph`, the most
+/// linked to first, then by url
+pub fn broken_links(link_graph: &LinkGraph) -> Vec<BrokenLink> {
+    let urls: HashMap<LinkId, &str> = link_graph
+        .into_iter()
+        .map(|(id, link)| (*id, link.url.as_str()))
+        .collect();
+
+    let referrers = link_graph.referrers();
+
+    let mut broken: Vec<BrokenLink> = link_graph
+        .into_iter()
+        .map(|(_, link)| link)
+        .filter(|link| is_broken(link))
+        .map(|link| {
+            let mut referrers: Vec<String> = referrers
+                .get(&link.id)
+                .into_iter()
+                .flatten()
+                .filter_map(|referrer| urls.get(referrer))
+                .map(|url| url.to_string())
+                .collect();
+            referrers.sort();
+            BrokenLink {
+                url: link.url.clone(),
+                status: link.status,
+                error: link.error,
+                referrers,
+            }
+        })
+        .collect();
+
+    broken.sort_by(|a, b| {
+        b.referrers
+            .len()
+            .cmp(&a.referrers.len())
+            .then_with(|| a.url.cmp(&b.url))
+    });
+    broken
+}
+
+/// The `broken` links as CSV, a row per broken url and page
+/// linking to it, with an empty referrer for the seeds
+pub fn to_csv(broken: &[BrokenLink]) -> Result<String> {
+    let rows = broken.iter().flat_map(|link| {
+        let row = |referrer| BrokenLinkRow {
+            url: &link.url,
+            status: link.status,
+            error: link.error,
+            referrer,
+        };
+        if link.referrers.is_empty() {
+            vec![row(None)]
+        } else {
+            link.referrers
+                .iter()
+                .map(|referrer| row(Some(referrer.as_str())))
+                .collect()
+        }
+    });
+    write_rows(rows, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Edge, ScrapeOutput};
+
+    fn page(links: &[&str], status: u16) -> ScrapeOutput {
+        ScrapeOutput {
+            links: links.iter().map(|l| l.to_string()).collect(),
+            edges: links
+                .iter()
+                .map(|to| Edge {
+                    to: to.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            status: Some(status),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn broken_link_lists_every_referrer() {
+        let mut graph = LinkGraph::default();
+        graph.update("a", "", &page(&["b", "gone"], 200)).unwrap();
+        graph.update("b", "a", &page(&["gone"], 200)).unwrap();
+        graph.update("gone", "a", &page(&[], 404)).unwrap();
+
+        let broken = broken_links(&graph);
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].url, "gone");
+        assert_eq!(broken[0].status, Some(404));
+        assert_eq!(broken[0].referrers, vec!["a", "b"]);
+    }
+}
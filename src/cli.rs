@@ -0,0 +1,1311 @@
+use anyhow::{anyhow, Result};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use std::{collections::HashSet, ffi::OsString};
+
+use tracing::Level;
+
+use rusty_crawler::{
+    config, crawler::ScrapeOption, logger::LogFormat, proxy_pool::ProxyRotation, schedule,
+};
+
+/// Crawls websites for their links and images, and works
+/// on the link graphs saved by previous crawls
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// How the lines written to log.txt are formatted
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// The least severe level written to log.txt: error,
+    /// warn, info, debug or trace
+    #[arg(long, global = true, default_value_t = Level::INFO)]
+    pub log_level: Level,
+
+    /// TOML file with the options of the command, named like
+    /// their flags, e.g. `max-links = 500`. The flags given in
+    /// the command line take precedence over the file
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Crawl from the given starting urls
+    Crawl(CrawlArgs),
+    /// Continue a crawl from the state file it saved
+    Resume(ResumeArgs),
+    /// Convert a saved links.json to other formats
+    Export(ExportArgs),
+    /// Show the most important pages of a saved links.json
+    /// and the pages serving the same, or nearly the same, content
+    Analyze(AnalyzeArgs),
+    /// Show the statistics of a saved links.json: sizes,
+    /// depths, statuses, domains and largest pages
+    Stats(StatsArgs),
+    /// Compare two saved links.json (or state files): new and
+    /// removed pages, changed titles and changed links
+    Diff(DiffArgs),
+    /// Find the query parameters of a saved links.json that
+    /// don't change the content of the pages
+    Params(ParamsArgs),
+    /// Download the images found in a saved links.json
+    Images(ImagesArgs),
+    /// Measure how fast the pages in a response cache are
+    /// scraped with each of the scrape options
+    Bench(BenchArgs),
+    /// Serve a saved links.json over a read-only JSON API:
+    /// its links by domain, their children and parents,
+    /// and a search of their titles. Also over GraphQL,
+    /// at /graphql
+    ServeGraph(ServeGraphArgs),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CrawlArgs {
+    /// Url to start crawling from, can be given multiple times
+    #[arg(short, long, required_unless_present_any = ["seed_file", "incremental"])]
+    pub starting_url: Vec<String>,
+
+    /// File with one starting url per line, or `-` to
+    /// read them from stdin
+    #[arg(long)]
+    pub seed_file: Option<String>,
+
+    /// Sitemap (or sitemap index) whose urls are queued
+    /// before crawling, can be given multiple times
+    #[arg(long)]
+    pub sitemap: Vec<String>,
+
+    /// Also queue the urls in the sitemaps listed in
+    /// the robots.txt of each starting url
+    #[arg(long, default_value_t = false)]
+    pub discover_sitemaps: bool,
+
+    /// Save the link graph and the links still queued to
+    /// this file, so the crawl can be continued with `resume`
+    #[arg(long)]
+    pub state_file: Option<String>,
+
+    /// Crawl each starting url on its own, a few side by side,
+    /// with its own --max-links budget and section in the output.
+    /// The pages found in the sitemaps go with the starting url
+    /// of their site, and the crawls share the rate limits
+    #[arg(long, default_value_t = false, conflicts_with = "state_file")]
+    pub isolate_seeds: bool,
+
+    /// Recrawl the site of this previous --links-json: its pages are
+    /// asked for with their ETag and Last-Modified, and only the ones
+    /// that changed are scraped again. The rest are carried over
+    /// into the new graph, along with the new pages found
+    #[arg(long, conflicts_with_all = ["isolate_seeds", "max_unseen_runs"])]
+    pub incremental: Option<String>,
+
+    /// Keep running, crawling again on the --schedule. Each run
+    /// writes its outputs (links.json, reports, sinks and saved
+    /// pages) with the time of the run in their name, e.g.
+    /// links-20240101T030000.json. With --max-unseen-runs, each
+    /// run carries over the pages of the one before
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "schedule",
+        conflicts_with_all = ["state_file", "incremental"]
+    )]
+    pub daemon: bool,
+
+    /// When the --daemon crawls run, as a cron expression
+    /// in local time, e.g. "0 3 * * *" for every day at 3am
+    #[arg(long, requires = "daemon")]
+    pub schedule: Option<String>,
+
+    /// Compare each --daemon run with the previous one, writing
+    /// the changes next to its links.json, e.g. links-20240101T030000.diff.json
+    #[arg(long, default_value_t = false, requires = "daemon")]
+    pub diff_previous: bool,
+
+    #[command(flatten)]
+    pub options: CrawlOptions,
+}
+
+#[derive(Args, Debug)]
+pub struct ResumeArgs {
+    /// State file saved by a previous `crawl --state-file`,
+    /// it is updated when the crawl finishes
+    pub state_file: String,
+
+    #[command(flatten)]
+    pub options: CrawlOptions,
+}
+
+/// The options shared by `crawl` and `resume`
+#[derive(Args, Debug, Clone)]
+pub struct CrawlOptions {
+    /// What to scrape from each page on top of its links, e.g.
+    /// `--scrape images,meta,text`. Defaults to images,titles;
+    /// the flags below (e.g. --meta) add to the list
+    #[arg(long, value_delimiter = ',')]
+    pub scrape: Option<Vec<ScrapeArg>>,
+
+    /// Fetch the RSS/Atom feeds found on each page and
+    /// crawl their entries as the page's children
+    #[arg(long, default_value_t = false)]
+    pub follow_feeds: bool,
+
+    /// Keep the description and keywords meta tags of each page
+    #[arg(long, default_value_t = false)]
+    pub meta: bool,
+
+    /// Keep the Open Graph and Twitter Card tags of each page
+    #[arg(long, default_value_t = false)]
+    pub open_graph: bool,
+
+    /// Keep the JSON-LD structured data of each page
+    #[arg(long, default_value_t = false)]
+    pub structured_data: bool,
+
+    /// Keep the readable text of each page, without
+    /// its scripts, styles and navigation
+    #[arg(long, default_value_t = false)]
+    pub text: bool,
+
+    /// Write the readable text of each page to a file in this
+    /// directory, listed with its url in urls.tsv
+    #[arg(long)]
+    pub text_dir: Option<String>,
+
+    /// Keep the language each page declares in <html lang>,
+    /// and the one detected from its text
+    #[arg(long, default_value_t = false)]
+    pub language: bool,
+
+    /// Keep the iframes, videos and audio embedded in each page
+    #[arg(long, default_value_t = false)]
+    pub media: bool,
+
+    /// Also keep the background images set in the inline
+    /// styles and `<style>` blocks of each page
+    #[arg(long, default_value_t = false)]
+    pub css_images: bool,
+
+    /// Keep a SimHash fingerprint of the text of each page,
+    /// for `analyze` to find the near-duplicate pages
+    #[arg(long, default_value_t = false)]
+    pub simhash: bool,
+
+    /// Keep the CSP, HSTS, X-Frame-Options and X-Content-Type-Options
+    /// headers of each page, and show which pages miss them
+    #[arg(long, default_value_t = false)]
+    pub security_headers: bool,
+
+    /// Only follow links this many hops away from the starting
+    /// urls' domains, e.g. 1 records the external pages linked
+    /// to without following their links
+    #[arg(long)]
+    pub external_depth: Option<u32>,
+
+    /// Stop fetching pages once their bodies add up to this many
+    /// bytes, a better proxy for cost than the number of links
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_total_bytes: Option<u64>,
+
+    /// Maximum links to find
+    #[arg(long, default_value_t = 100)]
+    pub max_links: u64,
+
+    /// Number of worker threads
+    #[arg(short, long, default_value_t = 4)]
+    pub n_worker_threads: u64,
+
+    /// Pick the number of workers and of concurrent requests per
+    /// host from the CPU cores and the latency of the seeds
+    #[arg(long, default_value_t = false, conflicts_with = "n_worker_threads")]
+    pub auto_workers: bool,
+
+    /// Number of workers parsing the fetched pages, separate from
+    /// the ones fetching them. Defaults to the number of CPU cores
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub parse_workers: Option<u64>,
+
+    /// Crawl this many pages of every new domain first, then share
+    /// the --max-links between the domains by how many links they
+    /// have, how fast and how reliable they were
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub warm_up: Option<u64>,
+
+    /// Crawl the queued links in a random order, spreading the
+    /// requests between the domains instead of exhausting the
+    /// starting urls' domain first
+    #[arg(long, default_value_t = false)]
+    pub shuffle_frontier: bool,
+
+    /// Enable logging the current status
+    #[arg(short, long, default_value_t = false)]
+    pub log_status: bool,
+
+    /// Show a full-screen dashboard while crawling, instead of the
+    /// progress bar: the queue, what each worker is fetching, the
+    /// throughput, the errors and the latest log lines
+    #[arg(long, default_value_t = false, conflicts_with = "log_status")]
+    pub dashboard: bool,
+
+    /// The file to save the link information to
+    #[arg(long, default_value_t = String::from("links.json"))]
+    pub links_json: String,
+
+    /// For recurring crawls: carry over the pages of the previous
+    /// run's --links-json that weren't crawled again, dropping the
+    /// ones not seen for this many runs
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_unseen_runs: Option<u32>,
+
+    /// The --links-json of the previous run, when each run writes
+    /// its own (--daemon). Otherwise --max-unseen-runs carries the
+    /// pages over from the --links-json about to be replaced
+    #[arg(skip)]
+    pub previous_links_json: Option<String>,
+
+    /// JSON file with the allow/deny url patterns and the
+    /// rate limit, reloaded when the process receives SIGHUP
+    #[arg(long)]
+    pub scope_file: Option<String>,
+
+    /// Tag stored in all the outputs, e.g. `--tag project=blog`.
+    /// Can be given multiple times
+    #[arg(long = "tag", value_parser = parse_key_value)]
+    pub tags: Vec<(String, String)>,
+
+    /// Url of a login form to submit before crawling, the
+    /// session cookies it returns are used for the whole crawl
+    #[arg(long)]
+    pub login_url: Option<String>,
+
+    /// Field posted to the login form, e.g. `--login-form-field user=me`.
+    /// Can be given multiple times
+    #[arg(long = "login-form-field", value_parser = parse_key_value, requires = "login_url")]
+    pub login_form_fields: Vec<(String, String)>,
+
+    /// Skip the links disallowed by each site's robots.txt
+    #[arg(long, default_value_t = false)]
+    pub respect_robots: bool,
+
+    /// Once the crawl is done, fetch the robots.txt, sitemap.xml,
+    /// security.txt, humans.txt and feed of each domain crawled
+    #[arg(long, default_value_t = false)]
+    pub probe_well_known: bool,
+
+    /// File caching the fetched robots.txt and sitemap files
+    /// between runs
+    #[arg(long)]
+    pub robots_cache: Option<String>,
+
+    /// How long the cached robots.txt and sitemap files
+    /// stay valid, in seconds
+    #[arg(long, default_value_t = 86400)]
+    pub robots_cache_ttl: u64,
+
+    /// Maximum number of characters kept in each scraped text
+    /// field (titles, alt and anchor texts, meta tags, the page
+    /// text), longer ones are truncated
+    #[arg(long, default_value_t = 4096)]
+    pub max_field_length: usize,
+
+    /// Write a sitemap.xml of the crawled pages on
+    /// the starting urls' domains to this path
+    #[arg(long)]
+    pub export_sitemap: Option<String>,
+
+    /// Write the pages that failed to load, with every page linking
+    /// to them, to this file: as CSV if it ends in .csv, else as JSON
+    #[arg(long)]
+    pub broken_links: Option<String>,
+
+    /// Write the pages with a missing or duplicate title, no
+    /// description, several h1s or images without alt text to this
+    /// file: as HTML if it ends in .html, else as JSON. Scrapes the
+    /// titles, meta tags and images
+    #[arg(long)]
+    pub seo_audit: Option<String>,
+
+    /// Show this many of the heaviest pages once crawled,
+    /// going by the size of their HTML and of their images
+    #[arg(long)]
+    pub heaviest_pages: Option<usize>,
+
+    /// Write a self-contained HTML report of the crawl to this file:
+    /// its status codes, depths, most linked to pages, broken links
+    /// and images
+    #[arg(long)]
+    pub report: Option<String>,
+
+    /// Send a HEAD request for each image found, to add the
+    /// size of the images to the weight of the heaviest pages.
+    /// The requests keep to the crawl's rate limits and, with
+    /// `--respect-robots`, to the sites' robots.txt
+    #[arg(long, default_value_t = false, requires = "heaviest_pages")]
+    pub weigh_images: bool,
+
+    /// JSON file with the JSON pointers and regexes used to
+    /// get records out of the JSON and plain text pages
+    #[arg(long)]
+    pub parse_rules: Option<String>,
+
+    /// Custom field scraped from each page with a CSS selector,
+    /// e.g. `--extract "price=span.price"`. Can be given multiple times
+    #[arg(long = "extract", value_parser = parse_key_value)]
+    pub extract: Vec<(String, String)>,
+
+    /// Look for this term (a regex) in the text of each page,
+    /// recording the matches and some context around them.
+    /// Can be given multiple times
+    #[arg(long)]
+    pub search: Vec<String>,
+
+    /// WebAssembly extractor run sandboxed on every page,
+    /// can be given multiple times
+    #[arg(long)]
+    pub wasm_plugin: Vec<String>,
+
+    /// Append a JSON line per page to this file as soon as the page
+    /// is scraped, so the results can be followed while crawling and
+    /// survive a crash. The same as `--sink jsonl:PATH`
+    #[arg(long)]
+    pub output_ndjson: Option<String>,
+
+    /// Write the HTML of each page to a file in this directory,
+    /// listed with its url in urls.tsv, to scrape the pages again
+    /// later without crawling them. The same as `--sink html:DIR`
+    #[arg(long)]
+    pub save_html: Option<String>,
+
+    /// Also write every page to this sink while crawling, one of
+    /// jsonl:PATH, sqlite:PATH, text:DIR, html:DIR, an http(s) url to POST them to,
+    /// kafka://BROKER/TOPIC (built with `--features kafka`) or
+    /// nats://SERVER/SUBJECT (built with `--features nats`).
+    /// Can be given multiple times
+    #[arg(long)]
+    pub sink: Vec<String>,
+
+    /// POST the crawl events as JSON to this url: each page
+    /// crawled, each page that failed and, at the end, a summary
+    /// of the crawl. They are sent in batches of `{"events": [...]}`
+    #[arg(long)]
+    pub webhook_url: Option<String>,
+
+    /// How many events are sent to the --webhook-url at once
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u64).range(1..))]
+    pub webhook_batch: u64,
+
+    /// How long, in seconds, an event waits for its batch
+    /// to fill before being sent anyway
+    #[arg(long, default_value_t = 5)]
+    pub webhook_flush_secs: u64,
+
+    /// How many times a batch the --webhook-url failed to take
+    /// (a network error, 429 or 5xx) is sent again, waiting
+    /// twice as long each time
+    #[arg(long, default_value_t = 3)]
+    pub webhook_retries: u32,
+
+    /// Rhai script run on every page, able to add fields,
+    /// rewrite the page's links or stop them being crawled
+    #[arg(long)]
+    pub script: Option<String>,
+
+    #[command(flatten)]
+    pub client: ClientArgs,
+
+    #[command(flatten)]
+    pub images: ImageArgs,
+}
+
+/// How the requests are made
+#[derive(Args, Debug, Clone)]
+pub struct ClientArgs {
+    /// Cookies to start with, in the Netscape
+    /// (cookies.txt) or JSON format
+    #[arg(long)]
+    pub cookies_file: Option<String>,
+
+    /// Proxy used for all the requests, supports the
+    /// http://, https:// and socks5:// schemes
+    #[arg(long)]
+    pub proxy: Option<String>,
+
+    /// File with one proxy url per line, page requests
+    /// rotate between them and dead proxies are evicted
+    #[arg(long, conflicts_with = "proxy")]
+    pub proxy_list: Option<String>,
+
+    /// How the proxy in the proxy list is picked
+    #[arg(long, value_enum, default_value_t = ProxyRotation::PerRequest)]
+    pub proxy_rotation: ProxyRotation,
+
+    /// Directory caching the fetched pages, later runs are
+    /// served from it instead of the network
+    #[arg(long)]
+    pub cache_dir: Option<String>,
+
+    /// Ask the server whether each cached page changed, with its
+    /// ETag and Last-Modified, instead of trusting the cache.
+    /// Unchanged pages (304 Not Modified) are read from the cache
+    #[arg(long, requires = "cache_dir", default_value_t = false)]
+    pub revalidate_cache: bool,
+
+    /// File with one user agent per line, rotated
+    /// between the page requests
+    #[arg(long)]
+    pub user_agent_file: Option<String>,
+
+    /// Always use the same user agent for the same host
+    #[arg(long, default_value_t = false, requires = "user_agent_file")]
+    pub user_agent_sticky: bool,
+
+    /// Accept invalid TLS certificates, e.g. self-signed ones
+    #[arg(long, default_value_t = false)]
+    pub insecure: bool,
+
+    /// PEM or DER file with extra CA certificates to trust
+    #[arg(long)]
+    pub ca_cert: Option<String>,
+
+    /// Only make HTTPS requests, never downgrading to HTTP
+    #[arg(long, default_value_t = false)]
+    pub https_only: bool,
+
+    /// Only fetch the first KB of the larger pages, enough for
+    /// their head and early links. Such pages are marked truncated
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_page_kb: Option<u64>,
+
+    /// Share one connection pool between all the workers,
+    /// instead of giving each worker its own client
+    #[arg(long, default_value_t = false)]
+    pub shared_client: bool,
+
+    /// Header sent with every request, e.g.
+    /// `--header Accept-Language=en`. Can be given multiple times
+    #[arg(long = "header", value_parser = parse_key_value)]
+    pub headers: Vec<(String, String)>,
+}
+
+/// Where and how many images are downloaded
+#[derive(Args, Debug, Clone)]
+pub struct ImageArgs {
+    /// Max images
+    #[arg(long, default_value_t = 100)]
+    pub max_images: u64,
+
+    /// The directory to save all the images scraped
+    #[arg(short, long, default_value_t = String::from("images/"))]
+    pub img_save_dir: String,
+
+    /// Where to save the images instead of --img-save-dir: a
+    /// directory, or s3://BUCKET/PREFIX to upload them (and their
+    /// database) to an S3 bucket. Needs the `s3` feature
+    #[arg(long, conflicts_with = "img_save_dir")]
+    pub img_dest: Option<String>,
+
+    /// The S3 compatible server to upload to with
+    /// --img-dest s3://..., e.g. http://localhost:9000 for MinIO
+    #[arg(long, requires = "img_dest")]
+    pub s3_endpoint: Option<String>,
+
+    /// Write an HTML gallery of the downloaded images
+    /// to the image directory
+    #[arg(long, default_value_t = false)]
+    pub gallery: bool,
+
+    /// Compare with the database.json of the previous run in the
+    /// image directory: only download the new images and write
+    /// what changed to image_changes.json
+    #[arg(long, default_value_t = false)]
+    pub diff_images: bool,
+
+    /// Read the real width and height of the downloaded
+    /// images into the database
+    #[arg(long, default_value_t = false)]
+    pub probe_image_sizes: bool,
+
+    /// Remove the images narrower or shorter than this many
+    /// pixels (tracking pixels, spacers) from the database and the
+    /// image directory, going by their real size once downloaded
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub min_image_size: Option<u32>,
+}
+
+impl ImageArgs {
+    /// Where the images are saved, see `--img-dest`
+    pub fn destination(&self) -> &str {
+        self.img_dest.as_deref().unwrap_or(&self.img_save_dir)
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// The links.json saved by a crawl
+    pub links_json: String,
+
+    /// The format to export to
+    #[arg(short, long, value_enum)]
+    pub format: ExportFormat,
+
+    /// The file to write the export to
+    #[arg(short, long)]
+    pub output: String,
+
+    /// Only export the pages on this domain, can be given
+    /// multiple times. Defaults to the starting urls' domains
+    #[arg(long)]
+    pub domain: Vec<String>,
+
+    /// Only export the pages this many links away from the
+    /// starting urls (graphml, gexf and dot)
+    #[arg(long)]
+    pub max_depth: Option<usize>,
+
+    /// Only export this many of the most linked to pages
+    /// (graphml, gexf and dot)
+    #[arg(long)]
+    pub max_nodes: Option<usize>,
+
+    /// Separate the columns with tabs instead of commas
+    /// (links-csv, edges-csv, images-csv and alt-text-csv)
+    #[arg(long, default_value_t = false)]
+    pub tsv: bool,
+}
+
+/// The values of `--scrape`, see `ScrapeOption`
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ScrapeArg {
+    /// The images of the page, and their alt texts
+    Images,
+    /// The `<h1>`, `<h2>` and `<title>` texts of
+    /// the page, and how many `<h1>`s it has
+    Titles,
+    /// Fetch the RSS/Atom feeds the page advertises
+    /// and add their entries to the page's links
+    Feeds,
+    /// The `description` and `keywords` meta tags
+    Meta,
+    /// The Open Graph (`og:*`) and Twitter Card
+    /// (`twitter:*`) meta tags
+    OpenGraph,
+    /// The JSON-LD (`application/ld+json`) blocks
+    StructuredData,
+    /// The readable text of the page, without the
+    /// scripts, styles and navigation
+    Text,
+    /// The language the page declares in `<html lang>`
+    /// and the one detected from its text
+    Language,
+    /// The iframes, videos and audio embedded in the page
+    Media,
+    /// The background images set in the `style` attributes
+    /// and `<style>` blocks, added to the images
+    CssImages,
+    /// A SimHash fingerprint of the readable text, to
+    /// find the near-duplicate pages
+    Simhash,
+    /// The CSP, HSTS, X-Frame-Options and
+    /// X-Content-Type-Options response headers
+    SecurityHeaders,
+}
+
+impl From<ScrapeArg> for ScrapeOption {
+    fn from(arg: ScrapeArg) -> Self {
+        match arg {
+            ScrapeArg::Images => ScrapeOption::Images,
+            ScrapeArg::Titles => ScrapeOption::Titles,
+            ScrapeArg::Feeds => ScrapeOption::Feeds,
+            ScrapeArg::Meta => ScrapeOption::Meta,
+            ScrapeArg::OpenGraph => ScrapeOption::OpenGraph,
+            ScrapeArg::StructuredData => ScrapeOption::StructuredData,
+            ScrapeArg::Text => ScrapeOption::Text,
+            ScrapeArg::Language => ScrapeOption::Language,
+            ScrapeArg::Media => ScrapeOption::Media,
+            ScrapeArg::CssImages => ScrapeOption::CssImages,
+            ScrapeArg::Simhash => ScrapeOption::Simhash,
+            ScrapeArg::SecurityHeaders => ScrapeOption::SecurityHeaders,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExportFormat {
+    /// sitemap.xml of the crawled pages
+    Sitemap,
+    /// sitemap.xml of the images on the crawled pages
+    ImageSitemap,
+    /// CSV for CMS and redirect manager imports (url,
+    /// status, redirect target, canonical, title)
+    CmsCsv,
+    /// the same records as cms-csv, as JSON
+    CmsJson,
+    /// GraphML of the pages and the links between them,
+    /// for yEd or Gephi
+    Graphml,
+    /// GEXF of the pages and the links between them, for Gephi
+    Gexf,
+    /// Graphviz DOT of the pages and the links between them
+    Dot,
+    /// CSV of the pages: url, title, status, depth, in
+    /// and out degree
+    LinksCsv,
+    /// CSV of the links found on each page: parent, child,
+    /// anchor text and rel
+    EdgesCsv,
+    /// CSV of the images on each page: page, image, alt,
+    /// width and height
+    ImagesCsv,
+    /// JSON of the images without alt text, by page: the pages
+    /// with the most of them first, then the images shown on the
+    /// most pages
+    AltText,
+    /// the same as alt-text, as CSV: page, image and the
+    /// number of pages showing the image without alt text
+    AltTextCsv,
+    /// links.parquet, edges.parquet and images.parquet, the
+    /// same tables as the CSVs, in the --output directory
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+#[derive(Args, Debug)]
+pub struct AnalyzeArgs {
+    /// The links.json saved by a crawl
+    pub links_json: String,
+
+    /// How many of the highest ranked pages to show
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+
+    /// Write every page, ranked by PageRank, with its in and
+    /// out degree and betweenness to this CSV file
+    #[arg(long)]
+    pub report: Option<String>,
+
+    /// Estimate the betweenness from the shortest paths of this
+    /// many pages, as following them from every page is slow
+    /// on large graphs
+    #[arg(long, default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..))]
+    pub betweenness_sources: u64,
+
+    /// How many of the 64 bits of their SimHash two pages can
+    /// differ by to be near duplicates, at most 8, see `crawl --simhash`
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(0..=8))]
+    pub simhash_distance: u32,
+}
+
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    /// The links.json saved by a crawl
+    pub links_json: String,
+
+    /// How many of the top domains and largest pages to show
+    #[arg(long, default_value_t = 10)]
+    pub top: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// The links.json (or state file) of the older crawl
+    pub old: String,
+
+    /// The links.json (or state file) of the newer crawl
+    pub new: String,
+
+    /// Also save the changes to this JSON file
+    #[arg(long)]
+    pub json: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct ParamsArgs {
+    /// The links.json saved by a crawl
+    pub links_json: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ImagesArgs {
+    /// The links.json saved by a crawl
+    pub links_json: String,
+
+    #[command(flatten)]
+    pub client: ClientArgs,
+
+    #[command(flatten)]
+    pub images: ImageArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// The --cache-dir of a previous crawl, whose HTML pages are scraped
+    pub cache_dir: String,
+
+    /// How many times each page is scraped with each option
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u64).range(1..))]
+    pub rounds: u64,
+
+    /// Only scrape this many of the cached pages
+    #[arg(long)]
+    pub max_pages: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+pub struct ServeGraphArgs {
+    /// The links.json saved by a crawl
+    pub links_json: String,
+
+    /// The address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub bind: String,
+}
+
+impl CrawlOptions {
+    /// What to scrape from each page, on top of the links
+    pub fn scrape_options(&self) -> Vec<ScrapeOption> {
+        let mut options = match &self.scrape {
+            Some(scrape) => scrape.iter().map(|&arg| arg.into()).collect(),
+            None => vec![ScrapeOption::Images, ScrapeOption::Titles],
+        };
+        let flags = [
+            (self.follow_feeds, ScrapeOption::Feeds),
+            (self.seo_audit.is_some(), ScrapeOption::Titles),
+            (self.seo_audit.is_some(), ScrapeOption::Images),
+            (self.meta || self.seo_audit.is_some(), ScrapeOption::Meta),
+            (self.open_graph, ScrapeOption::OpenGraph),
+            (self.structured_data, ScrapeOption::StructuredData),
+            (self.text || self.text_dir.is_some(), ScrapeOption::Text),
+            (self.language, ScrapeOption::Language),
+            (self.media, ScrapeOption::Media),
+            (self.css_images, ScrapeOption::CssImages),
+            (self.simhash, ScrapeOption::Simhash),
+            (self.security_headers, ScrapeOption::SecurityHeaders),
+        ];
+        for (enabled, option) in flags {
+            if enabled && !options.contains(&option) {
+                options.push(option);
+            }
+        }
+
+        options
+    }
+
+    /// These options with the `stamp` of a `--daemon` run in the
+    /// name of each output file, sink and text or HTML directory.
+    /// The images and cache directories are shared between the runs
+    pub fn timestamped(&self, stamp: &str) -> CrawlOptions {
+        let timestamped = |path: &Option<String>| {
+            path.as_deref()
+                .map(|path| schedule::timestamped(path, stamp))
+        };
+        CrawlOptions {
+            links_json: schedule::timestamped(&self.links_json, stamp),
+            output_ndjson: timestamped(&self.output_ndjson),
+            export_sitemap: timestamped(&self.export_sitemap),
+            broken_links: timestamped(&self.broken_links),
+            seo_audit: timestamped(&self.seo_audit),
+            report: timestamped(&self.report),
+            text_dir: timestamped(&self.text_dir),
+            save_html: timestamped(&self.save_html),
+            sink: self
+                .sink
+                .iter()
+                .map(|sink| schedule::timestamped_sink(sink, stamp))
+                .collect(),
+            ..self.clone()
+        }
+    }
+}
+
+/// The command line `args` with the flags of the `--config` file,
+/// if given, placed after the subcommand. The flags of the file
+/// that are also in `args` are left out, so `args` take precedence
+pub fn with_config_args(args: Vec<OsString>) -> Result<Vec<OsString>> {
+    let Some(config) = args
+        .iter()
+        .position(|arg| arg == "--config")
+        .and_then(|i| args.get(i + 1))
+        .or_else(|| {
+            args.iter()
+                .find(|arg| arg.to_string_lossy().starts_with("--config="))
+        })
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            arg.strip_prefix("--config=").unwrap_or(&arg).to_string()
+        })
+    else {
+        return Ok(args);
+    };
+
+    let cli = Cli::command();
+    let Some((position, subcommand)) = args.iter().enumerate().skip(1).find_map(|(i, arg)| {
+        let arg = arg.to_str()?;
+        cli.find_subcommand(arg).map(|subcommand| (i, subcommand))
+    }) else {
+        return Ok(args);
+    };
+
+    // The long names of the flags given, with the short ones resolved
+    let mut given: HashSet<String> = Default::default();
+    for arg in args.iter().skip(position + 1) {
+        let arg = arg.to_string_lossy();
+        if let Some(long) = arg.strip_prefix("--") {
+            given.insert(long.split('=').next().unwrap_or_default().to_string());
+        } else if let Some(short) = arg.strip_prefix('-').and_then(|s| s.chars().next()) {
+            let long = subcommand
+                .get_arguments()
+                .find(|a| a.get_short() == Some(short))
+                .and_then(|a| a.get_long());
+            given.extend(long.map(String::from));
+        }
+    }
+
+    let mut args = args;
+    let config_args = config::config_args(&config, &given)?;
+    args.splice(
+        position + 1..position + 1,
+        config_args.into_iter().map(OsString::from),
+    );
+    Ok(args)
+}
+
+/// Parses `name=value` pairs given in the command line
+fn parse_key_value(arg: &str) -> Result<(String, String)> {
+    let (name, value) = arg
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected name=value, got {}", arg))?;
+
+    Ok((name.to_string(), value.to_string()))
+}
+
+pub fn pretty_print_crawl_args(args: &CrawlArgs) {
+    println!(
+        "{}",
+        console::style("CRAWLER INPUT ARGUMENTS").white().on_black()
+    );
+    for starting_url in args.starting_url.iter() {
+        println!(
+            "{}  Starting URL: {}",
+            console::Emoji("🌐", ""),
+            console::style(starting_url).bold().cyan()
+        );
+    }
+    if let Some(seed_file) = &args.seed_file {
+        println!(
+            "{}  Seed file: {}",
+            console::Emoji("🌱", ""),
+            console::style(seed_file).bold().cyan()
+        );
+    }
+    if let Some(state_file) = &args.state_file {
+        println!(
+            "{}  State file: {}",
+            console::Emoji("💾", ""),
+            console::style(state_file).bold().cyan()
+        );
+    }
+    if args.isolate_seeds {
+        println!(
+            "{}  Crawling each starting url on its own",
+            console::Emoji("🧱", "")
+        );
+    }
+    if let Some(incremental) = &args.incremental {
+        println!(
+            "{}  Recrawling: {}",
+            console::Emoji("🔁", ""),
+            console::style(incremental).bold().cyan()
+        );
+    }
+    if let Some(schedule) = &args.schedule {
+        println!(
+            "{}  Crawling on the schedule: {}",
+            console::Emoji("⏰", ""),
+            console::style(schedule).bold().cyan()
+        );
+    }
+    pretty_print_options(&args.options);
+}
+
+pub fn pretty_print_resume_args(args: &ResumeArgs) {
+    println!(
+        "{}",
+        console::style("CRAWLER INPUT ARGUMENTS").white().on_black()
+    );
+    println!(
+        "{}  Resuming from: {}",
+        console::Emoji("💾", ""),
+        console::style(&args.state_file).bold().cyan()
+    );
+    pretty_print_options(&args.options);
+}
+
+fn pretty_print_options(args: &CrawlOptions) {
+    println!(
+        "{}  Maximum visited links: {}",
+        console::Emoji("🔗", ""),
+        console::style(&args.max_links).bold().cyan()
+    );
+    println!(
+        "{}  Maximum number of images: {}",
+        console::Emoji("🖼️", ""),
+        console::style(&args.images.max_images).bold().cyan()
+    );
+    let workers = if args.auto_workers {
+        String::from("auto")
+    } else {
+        args.n_worker_threads.to_string()
+    };
+    println!(
+        "{}  Number of workers: {}",
+        console::Emoji("⚒️", ""),
+        console::style(workers).bold().cyan()
+    );
+    if let Some(parse_workers) = args.parse_workers {
+        println!(
+            "{}  Number of parse workers: {}",
+            console::Emoji("🧩", ""),
+            console::style(parse_workers).bold().cyan()
+        );
+    }
+    if let Some(warm_up) = args.warm_up {
+        println!(
+            "{}  Warm-up pages per domain: {}",
+            console::Emoji("🌡️", ""),
+            console::style(warm_up).bold().cyan()
+        );
+    }
+    if args.shuffle_frontier {
+        println!("{}  Shuffling the frontier", console::Emoji("🔀", ""));
+    }
+    println!(
+        "{}  Should log progress? {}",
+        console::Emoji("❔", ""),
+        console::style(args.log_status).bold().cyan()
+    );
+    println!(
+        "{}  Show the dashboard? {}",
+        console::Emoji("🖥️", ""),
+        console::style(args.dashboard).bold().cyan()
+    );
+    println!(
+        "{}  Image directory: {}",
+        console::Emoji("📁", ""),
+        console::style(args.images.destination()).bold().cyan()
+    );
+    if let Some(min_image_size) = args.images.min_image_size {
+        println!(
+            "{}  Minimum image size: {}px",
+            console::Emoji("📏", ""),
+            console::style(min_image_size).bold().cyan()
+        );
+    }
+    println!(
+        "{}  Output json path: {}",
+        console::Emoji("📁", ""),
+        console::style(&args.links_json).bold().cyan()
+    );
+    if let Some(max_unseen_runs) = args.max_unseen_runs {
+        println!(
+            "{}  Dropping pages unseen for: {} runs",
+            console::Emoji("⏳", ""),
+            console::style(max_unseen_runs).bold().cyan()
+        );
+    }
+    for (name, value) in args.tags.iter() {
+        println!(
+            "{}  Tag: {}={}",
+            console::Emoji("🏷️", ""),
+            console::style(name).bold().cyan(),
+            console::style(value).bold().cyan()
+        );
+    }
+    pretty_print_client_args(&args.client);
+    if let Some(login_url) = &args.login_url {
+        println!(
+            "{}  Login url: {}",
+            console::Emoji("🔑", ""),
+            console::style(login_url).bold().cyan()
+        );
+    }
+    println!(
+        "{}  Respect robots.txt? {}",
+        console::Emoji("🤖", ""),
+        console::style(args.respect_robots).bold().cyan()
+    );
+    println!(
+        "{}  Probe well-known files? {}",
+        console::Emoji("📌", ""),
+        console::style(args.probe_well_known).bold().cyan()
+    );
+    let scrape_options = args.scrape_options();
+    let scrapes = |option| scrape_options.contains(&option);
+    println!(
+        "{}  Scrape images? {}",
+        console::Emoji("🖼️", ""),
+        console::style(scrapes(ScrapeOption::Images)).bold().cyan()
+    );
+    println!(
+        "{}  Scrape titles? {}",
+        console::Emoji("🔤", ""),
+        console::style(scrapes(ScrapeOption::Titles)).bold().cyan()
+    );
+    println!(
+        "{}  Follow feeds? {}",
+        console::Emoji("📰", ""),
+        console::style(scrapes(ScrapeOption::Feeds)).bold().cyan()
+    );
+    println!(
+        "{}  Scrape meta tags? {}",
+        console::Emoji("📝", ""),
+        console::style(scrapes(ScrapeOption::Meta)).bold().cyan()
+    );
+    println!(
+        "{}  Scrape Open Graph tags? {}",
+        console::Emoji("📣", ""),
+        console::style(scrapes(ScrapeOption::OpenGraph))
+            .bold()
+            .cyan()
+    );
+    println!(
+        "{}  Scrape structured data? {}",
+        console::Emoji("🗂️", ""),
+        console::style(scrapes(ScrapeOption::StructuredData))
+            .bold()
+            .cyan()
+    );
+    println!(
+        "{}  Scrape text? {}",
+        console::Emoji("📖", ""),
+        console::style(scrapes(ScrapeOption::Text)).bold().cyan()
+    );
+    println!(
+        "{}  Detect languages? {}",
+        console::Emoji("🌐", ""),
+        console::style(scrapes(ScrapeOption::Language))
+            .bold()
+            .cyan()
+    );
+    println!(
+        "{}  Scrape media? {}",
+        console::Emoji("🎬", ""),
+        console::style(scrapes(ScrapeOption::Media)).bold().cyan()
+    );
+    println!(
+        "{}  Scrape CSS background images? {}",
+        console::Emoji("🎨", ""),
+        console::style(scrapes(ScrapeOption::CssImages))
+            .bold()
+            .cyan()
+    );
+    println!(
+        "{}  Fingerprint the text (SimHash)? {}",
+        console::Emoji("🧬", ""),
+        console::style(scrapes(ScrapeOption::Simhash)).bold().cyan()
+    );
+    println!(
+        "{}  Security headers? {}",
+        console::Emoji("🛡️", ""),
+        console::style(scrapes(ScrapeOption::SecurityHeaders))
+            .bold()
+            .cyan()
+    );
+    if let Some(text_dir) = &args.text_dir {
+        println!(
+            "{}  Text directory: {}",
+            console::Emoji("📁", ""),
+            console::style(text_dir).bold().cyan()
+        );
+    }
+    if let Some(output_ndjson) = &args.output_ndjson {
+        println!(
+            "{}  NDJSON output: {}",
+            console::Emoji("📜", ""),
+            console::style(output_ndjson).bold().cyan()
+        );
+    }
+    if let Some(save_html) = &args.save_html {
+        println!(
+            "{}  HTML directory: {}",
+            console::Emoji("🗄️", ""),
+            console::style(save_html).bold().cyan()
+        );
+    }
+    if let Some(broken_links) = &args.broken_links {
+        println!(
+            "{}  Broken links report: {}",
+            console::Emoji("🩹", ""),
+            console::style(broken_links).bold().cyan()
+        );
+    }
+    if let Some(seo_audit) = &args.seo_audit {
+        println!(
+            "{}  SEO audit: {}",
+            console::Emoji("🔎", ""),
+            console::style(seo_audit).bold().cyan()
+        );
+    }
+    if let Some(report) = &args.report {
+        println!(
+            "{}  HTML report: {}",
+            console::Emoji("📈", ""),
+            console::style(report).bold().cyan()
+        );
+    }
+    if let Some(heaviest_pages) = args.heaviest_pages {
+        println!(
+            "{}  Heaviest pages: {} (images weighed: {})",
+            console::Emoji("🏋️", ""),
+            console::style(heaviest_pages).bold().cyan(),
+            console::style(args.weigh_images).bold().cyan()
+        );
+    }
+    if let Some(max_total_bytes) = args.max_total_bytes {
+        println!(
+            "{}  Maximum total bytes: {}",
+            console::Emoji("⚖️", ""),
+            console::style(max_total_bytes).bold().cyan()
+        );
+    }
+    if let Some(external_depth) = args.external_depth {
+        println!(
+            "{}  External depth: {}",
+            console::Emoji("🛰️", ""),
+            console::style(external_depth).bold().cyan()
+        );
+    }
+    if let Some(scope_file) = &args.scope_file {
+        println!(
+            "{}  Scope file: {}",
+            console::Emoji("🎯", ""),
+            console::style(scope_file).bold().cyan()
+        );
+    }
+    for (name, selector) in args.extract.iter() {
+        println!(
+            "{}  Extract: {}={}",
+            console::Emoji("🔍", ""),
+            console::style(name).bold().cyan(),
+            console::style(selector).bold().cyan()
+        );
+    }
+    for search in args.search.iter() {
+        println!(
+            "{}  Search: {}",
+            console::Emoji("🔎", ""),
+            console::style(search).bold().cyan()
+        );
+    }
+    for wasm_plugin in args.wasm_plugin.iter() {
+        println!(
+            "{}  WebAssembly plugin: {}",
+            console::Emoji("🔌", ""),
+            console::style(wasm_plugin).bold().cyan()
+        );
+    }
+    for sink in args.sink.iter() {
+        println!(
+            "{}  Sink: {}",
+            console::Emoji("🚰", ""),
+            console::style(sink).bold().cyan()
+        );
+    }
+    if let Some(webhook_url) = &args.webhook_url {
+        println!(
+            "{}  Webhook: {} (batches of {}, {} retries)",
+            console::Emoji("🪝", ""),
+            console::style(webhook_url).bold().cyan(),
+            args.webhook_batch,
+            args.webhook_retries
+        );
+    }
+    if let Some(script) = &args.script {
+        println!(
+            "{}  Page script: {}",
+            console::Emoji("📜", ""),
+            console::style(script).bold().cyan()
+        );
+    }
+    if let Some(parse_rules) = &args.parse_rules {
+        println!(
+            "{}  Parse rules: {}",
+            console::Emoji("🧩", ""),
+            console::style(parse_rules).bold().cyan()
+        );
+    }
+    println!()
+}
+
+fn pretty_print_client_args(args: &ClientArgs) {
+    if let Some(cookies_file) = &args.cookies_file {
+        println!(
+            "{}  Cookies file: {}",
+            console::Emoji("🍪", ""),
+            console::style(cookies_file).bold().cyan()
+        );
+    }
+    if let Some(proxy) = &args.proxy {
+        println!(
+            "{}  Proxy: {}",
+            console::Emoji("🔀", ""),
+            console::style(proxy).bold().cyan()
+        );
+    }
+    if let Some(proxy_list) = &args.proxy_list {
+        println!(
+            "{}  Proxy list: {} ({:?})",
+            console::Emoji("🔀", ""),
+            console::style(proxy_list).bold().cyan(),
+            args.proxy_rotation
+        );
+    }
+    println!(
+        "{}  Shared client? {}",
+        console::Emoji("🤝", ""),
+        console::style(args.shared_client).bold().cyan()
+    );
+    if let Some(cache_dir) = &args.cache_dir {
+        println!(
+            "{}  Response cache: {} (revalidated: {})",
+            console::Emoji("🗄️", ""),
+            console::style(cache_dir).bold().cyan(),
+            console::style(args.revalidate_cache).bold().cyan()
+        );
+    }
+    if let Some(user_agent_file) = &args.user_agent_file {
+        println!(
+            "{}  User agents: {} (sticky: {})",
+            console::Emoji("🕵️", ""),
+            console::style(user_agent_file).bold().cyan(),
+            console::style(args.user_agent_sticky).bold().cyan()
+        );
+    }
+    if args.insecure {
+        println!(
+            "{}  Accepting invalid TLS certificates",
+            console::Emoji("⚠️", "")
+        );
+    }
+    if args.https_only {
+        println!("{}  HTTPS only", console::Emoji("🔒", ""));
+    }
+    if let Some(ca_cert) = &args.ca_cert {
+        println!(
+            "{}  CA certificate: {}",
+            console::Emoji("🔒", ""),
+            console::style(ca_cert).bold().cyan()
+        );
+    }
+    if let Some(max_page_kb) = args.max_page_kb {
+        println!(
+            "{}  Maximum page size: {}KB",
+            console::Emoji("✂️", ""),
+            console::style(max_page_kb).bold().cyan()
+        );
+    }
+}
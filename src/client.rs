@@ -0,0 +1,142 @@
+use anyhow::{bail, Context, Result};
+use reqwest::{
+    cookie::Jar,
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Certificate, Client, Proxy,
+};
+use std::sync::Arc;
+
+use crate::fetcher::{Fetcher, ReqwestFetcher};
+use crate::proxy_pool::ProxyPoolFetcher;
+use crate::response_cache::CachingFetcher;
+use crate::user_agents::UserAgents;
+
+/// Options used to build every http client the
+/// crawler creates, so that all workers (and the
+/// image downloads) behave the same way
+#[derive(Default, Clone)]
+pub struct ClientOptions {
+    /// the cookie store shared between all the clients,
+    /// so a session started by one worker is seen by all
+    pub cookie_jar: Arc<Jar>,
+    /// the proxy all the requests go through, e.g.
+    /// `http://proxy:8080` or `socks5://proxy:1080`
+    pub proxy: Option<String>,
+    /// the pool of proxies page requests rotate between,
+    /// shared by all the workers so dead proxies are
+    /// evicted for everyone
+    pub proxy_pool: Option<Arc<ProxyPoolFetcher>>,
+    /// directory caching the page responses, so they are
+    /// only fetched from the network once
+    pub cache_dir: Option<String>,
+    /// check with the server that the cached responses are
+    /// still current, see `CachingFetcher::revalidate`
+    pub revalidate_cache: bool,
+    /// the user agents page requests rotate between
+    pub user_agents: Option<Arc<UserAgents>>,
+    /// accept invalid (e.g. self-signed) TLS certificates
+    pub accept_invalid_certs: bool,
+    /// extra root certificates to trust, e.g. a private CA
+    pub ca_certificates: Vec<Certificate>,
+    /// refuse any plain HTTP request, including redirects
+    pub https_only: bool,
+    /// only fetch the start of the page bodies
+    /// longer than this many bytes
+    pub max_body_bytes: Option<usize>,
+    /// share one client (and its connection pool) between
+    /// all the workers, instead of a client per worker
+    pub shared_client: bool,
+    /// headers sent with every request
+    pub headers: HeaderMap,
+}
+
+/// Builds the headers sent with every request from
+/// their `(name, value)` pairs
+pub fn parse_headers(headers: &[(String, String)]) -> Result<HeaderMap> {
+    let mut header_map = HeaderMap::new();
+    for (name, value) in headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .with_context(|| format!("invalid header name {}", name))?;
+        let value = HeaderValue::from_str(value)
+            .with_context(|| format!("invalid value for header {}", name))?;
+        header_map.append(name, value);
+    }
+
+    Ok(header_map)
+}
+
+/// Loads the certificates in the PEM (possibly a bundle
+/// of several certificates) or DER file at `path`
+pub fn load_ca_certificates(path: &str) -> Result<Vec<Certificate>> {
+    let contents =
+        std::fs::read(path).with_context(|| format!("could not read CA certificate {}", path))?;
+
+    let Ok(pem) = std::str::from_utf8(&contents) else {
+        return Ok(vec![Certificate::from_der(&contents)?]);
+    };
+
+    const PEM_END: &str = "-----END CERTIFICATE-----";
+    let certificates = pem
+        .split_inclusive(PEM_END)
+        .filter(|block| block.contains(PEM_END))
+        .map(|block| Certificate::from_pem(block.as_bytes()))
+        .collect::<reqwest::Result<Vec<_>>>()?;
+
+    if certificates.is_empty() {
+        bail!("no certificates found in {}", path);
+    }
+
+    Ok(certificates)
+}
+
+impl ClientOptions {
+    /// Create a new `reqwest::Client` configured with
+    /// these options
+    pub fn build_client(&self) -> Result<Client> {
+        let mut builder = Client::builder()
+            .cookie_provider(self.cookie_jar.clone())
+            .default_headers(self.headers.clone())
+            .danger_accept_invalid_certs(self.accept_invalid_certs)
+            .https_only(self.https_only);
+
+        for certificate in self.ca_certificates.iter() {
+            builder = builder.add_root_certificate(certificate.clone());
+        }
+
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+
+        let client = builder.build()?;
+
+        Ok(client)
+    }
+
+    /// Create a `Fetcher` to get pages with
+    pub fn build_fetcher(&self) -> Result<Arc<dyn Fetcher>> {
+        self.fetcher_for(None)
+    }
+
+    /// Create the `Fetcher` of crawl worker number `worker`
+    pub fn build_worker_fetcher(&self, worker: usize) -> Result<Arc<dyn Fetcher>> {
+        self.fetcher_for(Some(worker))
+    }
+
+    fn fetcher_for(&self, worker: Option<usize>) -> Result<Arc<dyn Fetcher>> {
+        let fetcher: Arc<dyn Fetcher> = match (&self.proxy_pool, worker) {
+            (Some(proxy_pool), Some(worker)) => proxy_pool.for_worker(worker),
+            (Some(proxy_pool), None) => proxy_pool.clone(),
+            (None, _) => Arc::new(
+                ReqwestFetcher::new(self.build_client()?, self.user_agents.clone())
+                    .max_body_bytes(self.max_body_bytes),
+            ),
+        };
+
+        match &self.cache_dir {
+            Some(cache_dir) => Ok(Arc::new(
+                CachingFetcher::new(fetcher, cache_dir)?.revalidate(self.revalidate_cache),
+            )),
+            None => Ok(fetcher),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::Result;
+use serde::Serialize;
+use tokio::fs;
+
+use super::load_link_graph;
+use crate::cli::AnalyzeArgs;
+use rusty_crawler::{
+    duplicates::{exact_duplicates, near_duplicates, DuplicateCluster},
+    export::tables::write_rows,
+};
+
+/// A row of the --report CSV
+#[derive(Serialize)]
+struct CentralityRow<'a> {
+    url: &'a str,
+    page_rank: f64,
+    /// the crawled pages linking to this one
+    in_degree: usize,
+    /// the crawled pages this one links to
+    out_degree: usize,
+    /// the share of the shortest paths between
+    /// two other pages going through this one
+    betweenness: f64,
+}
+
+/// Shows the size of a saved link graph, its most central
+/// pages and the pages serving the same (or nearly) content
+pub async fn run(args: AnalyzeArgs) -> Result<()> {
+    let link_graph = load_link_graph(&args.links_json).await?;
+
+    let n_edges: usize = (&link_graph)
+        .into_iter()
+        .map(|(_, link)| link.children.len())
+        .sum();
+    let n_images: usize = (&link_graph)
+        .into_iter()
+        .map(|(_, link)| link.images.len())
+        .sum();
+    let n_blocked = (&link_graph)
+        .into_iter()
+        .filter(|(_, link)| link.blocked.is_some())
+        .count();
+
+    println!(
+        "{}  {} pages, {} links between them, {} images, {} blocked pages",
+        console::Emoji("📊", ""),
+        console::style(link_graph.len()).bold().cyan(),
+        console::style(n_edges).bold().cyan(),
+        console::style(n_images).bold().cyan(),
+        console::style(n_blocked).bold().cyan()
+    );
+
+    let page_rank = link_graph.page_rank();
+    let betweenness = link_graph.betweenness(args.betweenness_sources as usize);
+    let in_degrees = link_graph.in_degrees();
+    let mut ranked: Vec<CentralityRow> = (&link_graph)
+        .into_iter()
+        .map(|(id, link)| CentralityRow {
+            url: &link.url,
+            page_rank: page_rank.get(id).copied().unwrap_or_default(),
+            in_degree: in_degrees.get(id).copied().unwrap_or(0),
+            out_degree: link.children.len(),
+            betweenness: betweenness.get(id).copied().unwrap_or_default(),
+        })
+        .collect();
+    ranked.sort_by(|a, b| b.page_rank.total_cmp(&a.page_rank));
+
+    for row in ranked.iter().take(args.top) {
+        println!(
+            "{:>8.5}  in {:>4}  out {:>4}  between {:>7.5}  {}",
+            row.page_rank,
+            row.in_degree,
+            row.out_degree,
+            row.betweenness,
+            console::style(row.url).bold()
+        );
+    }
+    if let Some(report) = &args.report {
+        fs::write(report, write_rows(ranked.iter(), false)?).await?;
+        println!(
+            "{}  Wrote the ranking of every page to {}",
+            console::Emoji("📤", ""),
+            console::style(report).bold().cyan()
+        );
+    }
+
+    let duplicates = exact_duplicates(&link_graph);
+    if !duplicates.is_empty() {
+        println!("\n{}", console::style("Duplicate pages").bold());
+    }
+    print_clusters(&duplicates, args.top);
+
+    println!("\n{}", console::style("Near-duplicate pages").bold());
+    if (&link_graph)
+        .into_iter()
+        .all(|(_, link)| link.simhash.is_none())
+    {
+        println!("  crawl with --simhash to find the near-duplicate pages");
+    }
+    print_clusters(
+        &near_duplicates(&link_graph, args.simhash_distance),
+        args.top,
+    );
+
+    Ok(())
+}
+
+/// Shows the `top` clusters, a url per line
+fn print_clusters(clusters: &[DuplicateCluster], top: usize) {
+    for cluster in clusters.iter().take(top) {
+        for (i, url) in cluster.urls.iter().enumerate() {
+            match i {
+                0 => println!("{:>8}  {}", cluster.urls.len(), url),
+                _ => println!("{:>8}  {}", "", url),
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::{bail, Result};
+use reqwest::StatusCode;
+
+use crate::cli::BenchArgs;
+use rusty_crawler::{bench::bench_extraction, response_cache::load_cached_responses};
+
+/// Scrapes the HTML pages saved in a response cache with
+/// each scrape option, showing the pages scraped per second
+pub async fn run(args: BenchArgs) -> Result<()> {
+    let responses = load_cached_responses(&args.cache_dir).await?;
+
+    let mut pages: Vec<_> = responses
+        .iter()
+        .filter(|response| {
+            let content_type = response
+                .headers
+                .get("content-type")
+                .and_then(|v| v.to_str().ok());
+            response.status == StatusCode::OK && is_html(content_type)
+        })
+        .map(|response| (response.url.clone(), response.text()))
+        .collect();
+    // The cache directory isn't in any particular order
+    pages.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+    if let Some(max_pages) = args.max_pages {
+        pages.truncate(max_pages);
+    }
+    if pages.is_empty() {
+        bail!("no HTML pages in {}", args.cache_dir);
+    }
+
+    println!(
+        "{}  Scraping {} pages {} times with each option",
+        console::Emoji("⏱️", ""),
+        console::style(pages.len()).bold().cyan(),
+        console::style(args.rounds).bold().cyan()
+    );
+
+    let rounds = args.rounds as usize;
+    let results = tokio::task::spawn_blocking(move || bench_extraction(&pages, rounds)).await?;
+    for result in results {
+        let name = match result.option {
+            Some(option) => format!("{:?}", option),
+            None => String::from("Links"),
+        };
+        println!(
+            "{:>16}  {:>10.1} pages/sec  {:>+8.3}ms per page",
+            console::style(name).bold(),
+            result.pages_per_sec,
+            result.overhead_ms
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether the page is scraped as HTML, pages
+/// without a content type are assumed to be
+fn is_html(content_type: Option<&str>) -> bool {
+    content_type
+        .and_then(|c| c.split(';').next())
+        .map(|c| c.trim().to_ascii_lowercase())
+        .is_none_or(|c| c == "text/html" || c == "application/xhtml+xml")
+}
@@ -0,0 +1,889 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::Local;
+use futures::{future::try_join_all, stream, StreamExt, TryStreamExt};
+use regex::Regex;
+use scraper::Selector;
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{error, info};
+use url::Url;
+
+use super::{
+    client_options,
+    images::{save_images, SavedImages},
+    load_link_graph, serialize_links,
+};
+use crate::cli::{CrawlArgs, CrawlOptions};
+use rusty_crawler::{
+    auto_tune::{self, AutoTuning},
+    broken_links::{self, broken_links},
+    client::ClientOptions,
+    crawler::{LinkPath, ScrapeConfig},
+    diff::CrawlDiff,
+    export,
+    gallery::format_size,
+    logger::spinner::{Colour, Spinner},
+    login::login,
+    model::{Discovery, ErrorCounts, LinkGraph},
+    page_weight::{self, WeighingLimits},
+    parsers::{ParseRules, ParserRegistry},
+    policy_cache::PolicyCache,
+    rate_limiter::RateLimiter,
+    report,
+    robots::Robots,
+    schedule::Schedule,
+    scope::ScopeRules,
+    script::ScriptHook,
+    seeds::read_seed_file,
+    seo_audit::SeoAudit,
+    sink::{
+        html::HtmlSink,
+        jsonl::JsonlSink,
+        open_sink,
+        text::TextSink,
+        webhook::{WebhookOptions, WebhookSink},
+        FanOut, OutputSink,
+    },
+    sitemap::collect_sitemap_urls,
+    snapshot::save_snapshot,
+    wasm_plugin::WasmExtractor,
+    Crawler, CrawlerBuilder, SharedLimits,
+};
+
+/// How many of the crawls of `--isolate-seeds` run at the same time
+const MAX_SIDE_BY_SIDE_CRAWLS: usize = 4;
+
+/// Crawls from the starting urls given by the user,
+/// again and again on the schedule with `--daemon`
+pub async fn run(args: CrawlArgs) -> Result<()> {
+    match &args.schedule {
+        Some(schedule) if args.daemon => run_daemon(&args, &Schedule::parse(schedule)?).await,
+        _ => crawl(&args).await,
+    }
+}
+
+/// Crawls on the `schedule` until the process is interrupted. A
+/// failed run is logged and the next one still happens
+async fn run_daemon(args: &CrawlArgs, schedule: &Schedule) -> Result<()> {
+    let mut previous_links_json: Option<String> = None;
+    loop {
+        let next_run = schedule.next_after(&Local::now())?;
+        println!(
+            "{}  Next crawl at {}",
+            console::Emoji("⏰", ""),
+            console::style(next_run.format("%Y-%m-%d %H:%M:%S"))
+                .bold()
+                .cyan()
+        );
+        let wait = (next_run - Local::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+
+        let mut run_args = args.clone();
+        run_args.options = args
+            .options
+            .timestamped(&next_run.format("%Y%m%dT%H%M%S").to_string());
+        run_args.options.previous_links_json = previous_links_json.clone();
+        info!("Starting the crawl of {}", next_run);
+        if let Err(e) = crawl(&run_args).await {
+            error!("The crawl of {} failed: {:?}", next_run, e);
+            continue;
+        }
+
+        let links_json = run_args.options.links_json;
+        if let Some(previous) = previous_links_json.as_ref().filter(|_| args.diff_previous) {
+            if let Err(e) = write_run_diff(previous, &links_json).await {
+                error!(
+                    "Could not compare {} with {}: {:?}",
+                    links_json, previous, e
+                );
+            }
+        }
+        previous_links_json = Some(links_json);
+    }
+}
+
+/// Writes what changed since the `previous` run next
+/// to the `links_json` of the latest one
+async fn write_run_diff(previous: &str, links_json: &str) -> Result<()> {
+    let diff = CrawlDiff::between(
+        &load_link_graph(previous).await?,
+        &load_link_graph(links_json).await?,
+    );
+    let path = Path::new(links_json).with_extension("diff.json");
+    tokio::fs::write(&path, serde_json::to_string_pretty(&diff)?)
+        .await
+        .with_context(|| format!("could not write {:?}", path))?;
+
+    println!(
+        "{}  Since the last run: {} new pages, {} removed pages, {} retitled pages, {} pages with changed links, written to {}",
+        console::Emoji("🔍", ""),
+        console::style(diff.added.len()).bold().cyan(),
+        console::style(diff.removed.len()).bold().cyan(),
+        console::style(diff.retitled.len()).bold().cyan(),
+        console::style(diff.outlinks.len()).bold().cyan(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Runs one crawl from the starting urls
+async fn crawl(args: &CrawlArgs) -> Result<()> {
+    let client_options = client_options(&args.options.client)?;
+    let robots = Arc::new(Robots::new(PolicyCache::load(
+        args.options.robots_cache.clone(),
+        Duration::from_secs(args.options.robots_cache_ttl),
+    )?));
+
+    let mut seeds: Vec<(String, Discovery)> = args
+        .starting_url
+        .iter()
+        .map(|url| (url.clone(), Discovery::Seed))
+        .collect();
+    if let Some(seed_file) = &args.seed_file {
+        seeds.extend(
+            read_seed_file(seed_file)?
+                .into_iter()
+                .map(|url| (url, Discovery::Seed)),
+        );
+    }
+
+    let previous = match &args.incremental {
+        Some(previous_json) => Some(load_link_graph(previous_json).await?),
+        None => None,
+    };
+    if let Some(previous) = &previous {
+        seeds.extend(
+            previous
+                .seeds()
+                .iter()
+                .map(|url| (url.clone(), Discovery::Seed)),
+        );
+    }
+
+    let seed_urls: Vec<String> = seeds.iter().map(|(url, _)| url.clone()).collect();
+    let sitemap_urls = find_sitemaps(args, &seed_urls, &robots, &client_options).await?;
+    if !sitemap_urls.is_empty() {
+        let fetcher = client_options.build_fetcher()?;
+        let urls = collect_sitemap_urls(fetcher.as_ref(), robots.cache(), sitemap_urls).await;
+        seeds.extend(urls.into_iter().map(|url| (url, Discovery::Sitemap)));
+    }
+
+    // Seeds listed twice (e.g. in a sitemap too) are only crawled once
+    let mut unique_seeds: HashSet<String> = Default::default();
+    seeds.retain(|(seed, _)| unique_seeds.insert(seed.clone()));
+
+    let crawlers = if args.isolate_seeds {
+        isolate_seeds(seeds)
+            .into_iter()
+            .map(|seeds| {
+                seeds
+                    .into_iter()
+                    .fold(Crawler::builder(), |crawler, (seed, discovery)| {
+                        crawler.seed_with(seed, discovery)
+                    })
+                    .robots(robots.clone())
+            })
+            .collect()
+    } else {
+        let mut crawler = seeds
+            .into_iter()
+            .fold(Crawler::builder(), |crawler, (seed, discovery)| {
+                crawler.seed_with(seed, discovery)
+            });
+        if let Some(previous) = previous {
+            crawler = crawler.incremental(previous);
+        }
+        vec![crawler.robots(robots)]
+    };
+
+    run_crawl(
+        &args.options,
+        client_options,
+        crawlers,
+        args.state_file.as_deref(),
+    )
+    .await
+}
+
+/// Splits the `seeds` between the sub-crawls of `--isolate-seeds`:
+/// one for each seed the user gave, which also gets the pages
+/// found in the sitemaps of its host
+fn isolate_seeds(seeds: Vec<(String, Discovery)>) -> Vec<Vec<(String, Discovery)>> {
+    let host = |url: &str| Url::parse(url).ok()?.host_str().map(String::from);
+    let (given, found): (Vec<_>, Vec<_>) = seeds
+        .into_iter()
+        .partition(|(_, discovery)| *discovery == Discovery::Seed);
+
+    let mut groups: Vec<Vec<(String, Discovery)>> =
+        given.into_iter().map(|seed| vec![seed]).collect();
+    for (url, discovery) in found {
+        let url_host = host(&url);
+        match groups
+            .iter_mut()
+            .position(|group| host(&group[0].0) == url_host)
+        {
+            Some(index) => groups[index].push((url, discovery)),
+            None if groups.is_empty() => groups.push(vec![(url, discovery)]),
+            None => groups[0].push((url, discovery)),
+        }
+    }
+    groups
+}
+
+/// Runs the crawls set up by `crawlers` side by side with the
+/// user's options, then saves the results (and the state file).
+/// Each crawl gets its own budget and section in the output
+/// when there are several of them, sharing the rate limits
+/// with the others. At most `MAX_SIDE_BY_SIDE_CRAWLS` run
+/// at the same time
+pub async fn run_crawl(
+    args: &CrawlOptions,
+    client_options: ClientOptions,
+    crawlers: Vec<CrawlerBuilder>,
+    state_file: Option<&str>,
+) -> Result<()> {
+    let start = Instant::now();
+    if let Some(login_url) = &args.login_url {
+        let client = client_options.build_client()?;
+        login(&client, login_url, &args.login_form_fields).await?;
+    }
+
+    let mut sinks: Vec<Arc<dyn OutputSink>> =
+        try_join_all(args.sink.iter().map(|uri| open_sink(uri))).await?;
+    if let Some(text_dir) = &args.text_dir {
+        sinks.push(Arc::new(TextSink::create(text_dir).await?));
+    }
+    if let Some(output_ndjson) = &args.output_ndjson {
+        sinks.push(Arc::new(JsonlSink::create(output_ndjson).await?));
+    }
+    if let Some(save_html) = &args.save_html {
+        sinks.push(Arc::new(HtmlSink::create(save_html).await?));
+    }
+    if let Some(webhook_url) = &args.webhook_url {
+        let options = WebhookOptions {
+            batch_size: args.webhook_batch as usize,
+            flush_interval: Duration::from_secs(args.webhook_flush_secs),
+            retries: args.webhook_retries,
+        };
+        sinks.push(Arc::new(WebhookSink::new(webhook_url, options)?));
+    }
+
+    let tuning = if args.auto_workers {
+        let probe_urls: Vec<String> = crawlers
+            .iter()
+            .flat_map(|crawler| crawler.queued_urls())
+            .take(auto_tune::PROBES)
+            .map(String::from)
+            .collect();
+        let fetcher = client_options.build_fetcher()?;
+        Some(AutoTuning::measure(fetcher.as_ref(), &probe_urls).await)
+    } else {
+        None
+    };
+
+    let request_interval = match &args.scope_file {
+        Some(scope_file) => ScopeRules::from_file(scope_file)?.request_interval,
+        None => None,
+    };
+    let limits = SharedLimits::new(request_interval, tuning.as_ref().map(|t| t.per_host));
+
+    // The progress bars of several crawls would overwrite each other
+    let log_status = args.log_status && crawlers.len() == 1;
+    let dashboard = args.dashboard && crawlers.len() == 1;
+    let crawls = crawlers.into_iter().map(|crawler| async {
+        let mut crawler = configure(args, &client_options, &sinks, crawler)?
+            .shared_limits(limits.clone())
+            .log_status(log_status)
+            .dashboard(dashboard);
+        if let Some(tuning) = &tuning {
+            crawler = crawler.workers(tuning.workers);
+        }
+        crawler.build()?.run_with_queue().await
+    });
+    let crawl_start = Instant::now();
+    let mut results: Vec<_> = stream::iter(crawls)
+        .buffered(MAX_SIDE_BY_SIDE_CRAWLS)
+        .try_collect()
+        .await?;
+    let crawl_time = crawl_start.elapsed();
+
+    let (mut link_graph, link_queue) = if results.len() == 1 {
+        results.remove(0)
+    } else {
+        let mut link_graph = LinkGraph::default();
+        let mut link_queue: VecDeque<LinkPath> = Default::default();
+        for (seed_graph, seed_queue) in results {
+            link_graph.merge_section(seed_graph)?;
+            link_queue.extend(seed_queue);
+        }
+        (link_graph, link_queue)
+    };
+    print_politeness_summary(&link_graph);
+    print_error_summary(&link_graph);
+    print_warm_up_summary(&link_graph);
+    print_recrawl_summary(&link_graph);
+    print_well_known_summary(&link_graph);
+    if let Some(tuning) = &tuning {
+        print_auto_tuning(tuning);
+    }
+    print_search_summary(&link_graph, &args.search);
+    print_security_summary(&link_graph);
+    if let Some(top) = args.heaviest_pages {
+        print_heaviest_pages(&link_graph, args, &client_options, top).await?;
+    }
+
+    let spinner = Spinner::new();
+    let images = save_images(&link_graph, &client_options, &args.images, &spinner).await?;
+
+    if let Some(max_unseen_runs) = args.max_unseen_runs {
+        let previous_run = args
+            .previous_links_json
+            .as_deref()
+            .unwrap_or(&args.links_json);
+        carry_over_previous_run(&mut link_graph, previous_run, max_unseen_runs, &spinner).await?;
+    }
+
+    spinner.status(format!("serializing links to {}", args.links_json));
+    serialize_links(&link_graph, &args.links_json).await?;
+    spinner.print_above(
+        format!("  serialized links to {}", args.links_json),
+        Colour::Green,
+    );
+
+    // Once for all the crawls sharing the sinks
+    let sinks: FanOut = sinks.into_iter().collect();
+    sinks.on_finish(&link_graph).await?;
+
+    if let Some(state_file) = state_file {
+        spinner.status(format!("saving the crawl state to {}", state_file));
+        save_snapshot(state_file, &link_graph, &link_queue).await?;
+        spinner.print_above(
+            format!("  saved the crawl state to {}", state_file),
+            Colour::Green,
+        );
+    }
+
+    if let Some(sitemap_path) = &args.export_sitemap {
+        spinner.status(format!("writing the sitemap to {}", sitemap_path));
+        let sitemap = export::sitemap::to_sitemap(&link_graph, &link_graph.seed_domains())?;
+        tokio::fs::write(sitemap_path, sitemap).await?;
+        spinner.print_above(
+            format!("  wrote the sitemap to {}", sitemap_path),
+            Colour::Green,
+        );
+    }
+
+    if let Some(path) = &args.broken_links {
+        write_broken_links(&link_graph, path).await?;
+    }
+    if let Some(path) = &args.seo_audit {
+        write_seo_audit(&link_graph, path).await?;
+    }
+    if let Some(path) = &args.report {
+        spinner.status(format!("writing the report to {}", path));
+        tokio::fs::write(path, report::to_html_report(&link_graph)?).await?;
+        spinner.print_above(format!("  wrote the report to {}", path), Colour::Green);
+    }
+
+    print_crawl_summary(&link_graph, &images, crawl_time, start.elapsed());
+    Ok(())
+}
+
+/// Shows what the crawl did, in numbers: the pages crawled and
+/// failed, how fast, and the images and bytes downloaded
+fn print_crawl_summary(
+    link_graph: &LinkGraph,
+    images: &SavedImages,
+    crawl_time: Duration,
+    elapsed: Duration,
+) {
+    let crawled = link_graph
+        .into_iter()
+        .filter(|(_, link)| link.status.is_some() || link.error.is_some())
+        .count();
+    let mut errors = ErrorCounts::default();
+    for info in link_graph.domains().values() {
+        errors.extend(&info.errors);
+    }
+    let html_bytes: u64 = link_graph
+        .into_iter()
+        .filter_map(|(_, link)| link.size)
+        .sum();
+
+    println!("{}", console::style("CRAWL SUMMARY").white().on_black());
+    println!(
+        "{}  Pages crawled: {} ({} found)",
+        console::Emoji("📄", ""),
+        console::style(crawled).bold().cyan(),
+        link_graph.len()
+    );
+    let kinds: Vec<String> = errors
+        .named()
+        .into_iter()
+        .map(|(kind, count)| format!("{} {}", count, kind))
+        .collect();
+    println!(
+        "{}  Failed pages: {}{}",
+        console::Emoji("💥", ""),
+        console::style(errors.total()).bold().cyan(),
+        if kinds.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", kinds.join(", "))
+        }
+    );
+    println!(
+        "{}  Elapsed: {} ({:.1}s crawling, {:.1} pages/s)",
+        console::Emoji("⏱️", ""),
+        console::style(format!("{:.1}s", elapsed.as_secs_f64()))
+            .bold()
+            .cyan(),
+        crawl_time.as_secs_f64(),
+        crawled as f64 / crawl_time.as_secs_f64().max(0.001)
+    );
+    println!(
+        "{}  Images: {} found, {} saved",
+        console::Emoji("🖼️", ""),
+        console::style(images.found).bold().cyan(),
+        images.saved
+    );
+    println!(
+        "{}  Downloaded: {} ({} of pages, {} of images)",
+        console::Emoji("📦", ""),
+        console::style(format_size(html_bytes + images.bytes))
+            .bold()
+            .cyan(),
+        format_size(html_bytes),
+        format_size(images.bytes)
+    );
+}
+
+/// Writes the broken links of `link_graph` to `path`,
+/// as CSV or JSON depending on its extension
+async fn write_broken_links(link_graph: &LinkGraph, path: &str) -> Result<()> {
+    let broken = broken_links(link_graph);
+    let report = if path.ends_with(".csv") {
+        broken_links::to_csv(&broken)?
+    } else {
+        serde_json::to_string_pretty(&broken)?
+    };
+    tokio::fs::write(path, report)
+        .await
+        .with_context(|| format!("could not write the broken links to {}", path))?;
+
+    let referrers: HashSet<&str> = broken
+        .iter()
+        .flat_map(|link| link.referrers.iter().map(String::as_str))
+        .collect();
+    println!(
+        "{}  {} broken links, linked to from {} pages, written to {}",
+        console::Emoji("🩹", ""),
+        broken.len(),
+        referrers.len(),
+        console::style(path).bold().cyan()
+    );
+    Ok(())
+}
+
+/// Writes the SEO audit of `link_graph` to `path`,
+/// as HTML or JSON depending on its extension
+async fn write_seo_audit(link_graph: &LinkGraph, path: &str) -> Result<()> {
+    let audit = SeoAudit::of(link_graph);
+    let report = if path.ends_with(".html") {
+        audit.to_html()?
+    } else {
+        serde_json::to_string_pretty(&audit)?
+    };
+    tokio::fs::write(path, report)
+        .await
+        .with_context(|| format!("could not write the SEO audit to {}", path))?;
+
+    println!(
+        "{}  {} SEO problems on {} pages, written to {}",
+        console::Emoji("🔎", ""),
+        audit.problems(),
+        audit.pages,
+        console::style(path).bold().cyan()
+    );
+    Ok(())
+}
+
+/// Carries the pages of the previous run's graph at `links_json`
+/// over to `link_graph`, then drops the ones not seen for too long
+async fn carry_over_previous_run(
+    link_graph: &mut LinkGraph,
+    links_json: &str,
+    max_unseen_runs: u32,
+    spinner: &Spinner,
+) -> Result<()> {
+    if !Path::new(links_json).is_file() {
+        return Ok(());
+    }
+
+    let previous = load_link_graph(links_json).await?;
+    let carried = link_graph.carry_over(previous)?;
+    let dropped = link_graph.prune_unseen(max_unseen_runs);
+    for url in dropped.iter() {
+        info!("Dropped {}, not seen for {} runs", url, max_unseen_runs);
+    }
+
+    spinner.print_above(
+        format!(
+            "  run {}: carried over {} pages, dropped {} not seen for {} runs",
+            link_graph.run(),
+            carried - dropped.len(),
+            dropped.len(),
+            max_unseen_runs
+        ),
+        Colour::Green,
+    );
+    Ok(())
+}
+
+/// Applies the user's options to `crawler`
+fn configure(
+    args: &CrawlOptions,
+    client_options: &ClientOptions,
+    sinks: &[Arc<dyn OutputSink>],
+    crawler: CrawlerBuilder,
+) -> Result<CrawlerBuilder> {
+    let parse_rules = match &args.parse_rules {
+        Some(parse_rules) => ParseRules::from_file(parse_rules)?,
+        None => Default::default(),
+    };
+
+    let mut crawler = crawler
+        .max_links(args.max_links as usize)
+        .workers(args.n_worker_threads)
+        .parse_workers(args.parse_workers)
+        .client_options(client_options.clone())
+        .respect_robots(args.respect_robots)
+        .probe_well_known(args.probe_well_known)
+        .shuffle_frontier(args.shuffle_frontier)
+        .warm_up(args.warm_up.map(|warm_up| warm_up as usize))
+        .external_depth(args.external_depth)
+        .max_total_bytes(args.max_total_bytes)
+        .scrape_config(ScrapeConfig {
+            options: args.scrape_options(),
+            max_field_length: args.max_field_length,
+            parsers: ParserRegistry::with_rules(&parse_rules)?,
+            custom_selectors: custom_selectors(&args.extract)?,
+            searches: args
+                .search
+                .iter()
+                .map(|search| {
+                    Regex::new(search).with_context(|| format!("invalid search {}", search))
+                })
+                .collect::<Result<_>>()?,
+            extractors: args
+                .wasm_plugin
+                .iter()
+                .map(|path| Ok(Arc::new(WasmExtractor::from_file(path)?)))
+                .collect::<Result<_>>()?,
+        });
+    if let Some(scope_file) = &args.scope_file {
+        crawler = crawler
+            .scope(ScopeRules::from_file(scope_file)?)
+            .reload_scope_from(scope_file);
+    }
+    if let Some(script) = &args.script {
+        crawler = crawler.observer(ScriptHook::from_file(script)?);
+    }
+    for (name, value) in args.tags.iter() {
+        crawler = crawler.tag(name, value);
+    }
+    for sink in sinks.iter() {
+        crawler = crawler.sink(sink.clone());
+    }
+
+    Ok(crawler)
+}
+
+/// Compiles the `--extract` selectors
+fn custom_selectors(extract: &[(String, String)]) -> Result<Vec<(String, Selector)>> {
+    extract
+        .iter()
+        .map(|(name, selector)| {
+            let compiled = Selector::parse(selector)
+                .map_err(|e| anyhow!("invalid selector {}: {:?}", selector, e))?;
+            Ok((name.clone(), compiled))
+        })
+        .collect()
+}
+
+/// The sitemaps given by the user, plus the ones in the
+/// robots.txt of each seed when discovery is enabled
+async fn find_sitemaps(
+    args: &CrawlArgs,
+    seeds: &[String],
+    robots: &Robots,
+    client_options: &ClientOptions,
+) -> Result<Vec<Url>> {
+    let mut sitemaps: Vec<Url> = args
+        .sitemap
+        .iter()
+        .map(|s| Url::parse(s))
+        .collect::<Result<_, _>>()?;
+
+    if args.discover_sitemaps {
+        let fetcher = client_options.build_fetcher()?;
+        for seed in seeds.iter().filter_map(|s| Url::parse(s).ok()) {
+            match robots.rules_for(fetcher.as_ref(), &seed).await {
+                Ok(rules) => {
+                    sitemaps.extend(rules.sitemaps.iter().filter_map(|s| Url::parse(s).ok()))
+                }
+                Err(e) => error!("Could not get robots.txt for {}: {:#}", seed, e),
+            }
+        }
+    }
+
+    Ok(sitemaps)
+}
+
+/// Shows the domains the crawler had to slow down for
+fn print_politeness_summary(link_graph: &LinkGraph) {
+    for (domain, info) in link_graph.domains() {
+        if info.slowdowns == 0 && info.soft_blocks.is_empty() {
+            continue;
+        }
+
+        println!(
+            "{}  {}: slowed down {} times, {} anti-bot pages, settled on {}ms between requests",
+            console::Emoji("🐢", ""),
+            console::style(domain).bold().yellow(),
+            info.slowdowns,
+            info.soft_blocks.len(),
+            info.request_delay_ms
+        );
+    }
+}
+
+/// Shows the domains whose pages failed, the worst first
+fn print_error_summary(link_graph: &LinkGraph) {
+    let mut domains: Vec<_> = link_graph
+        .domains()
+        .iter()
+        .filter(|(_, info)| !info.errors.is_empty())
+        .collect();
+    domains.sort_by_key(|(_, info)| std::cmp::Reverse(info.errors.total()));
+
+    for (domain, info) in domains {
+        let kinds: Vec<String> = info
+            .errors
+            .named()
+            .into_iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect();
+        println!(
+            "{}  {}: {} failed pages ({})",
+            console::Emoji("💥", ""),
+            console::style(domain).bold().yellow(),
+            info.errors.total(),
+            kinds.join(", ")
+        );
+    }
+}
+
+/// Shows how many pages of each domain pass the security
+/// header checks, and how many fail each of them
+fn print_security_summary(link_graph: &LinkGraph) {
+    // (pages, pages passing every check, failures by check), by domain
+    let mut domains: BTreeMap<String, (u32, u32, BTreeMap<&str, u32>)> = Default::default();
+    for (_, link) in link_graph {
+        let Some(headers) = &link.security_headers else {
+            continue;
+        };
+        let Some(url) = Url::parse(&link.url).ok() else {
+            continue;
+        };
+
+        let failures = headers.failures(url.scheme() == "https");
+        let domain = url.host_str().unwrap_or_default().to_string();
+        let (pages, passing, failures_by_check) = domains.entry(domain).or_default();
+        *pages += 1;
+        if failures.is_empty() {
+            *passing += 1;
+        }
+        for check in failures {
+            *failures_by_check.entry(check).or_default() += 1;
+        }
+    }
+
+    for (domain, (pages, passing, failures)) in domains {
+        let failing: Vec<String> = failures
+            .iter()
+            .map(|(check, count)| format!("{} {}", count, check))
+            .collect();
+        println!(
+            "{}  {}: {}/{} pages pass the security header checks{}",
+            console::Emoji("🛡️", ""),
+            console::style(domain).bold().yellow(),
+            passing,
+            pages,
+            if failing.is_empty() {
+                String::new()
+            } else {
+                format!(" (failed: {})", failing.join(", "))
+            }
+        );
+    }
+}
+
+/// Shows the `top` heaviest pages, weighing their images
+/// with HEAD requests if `--weigh-images` is set
+async fn print_heaviest_pages(
+    link_graph: &LinkGraph,
+    args: &CrawlOptions,
+    client_options: &ClientOptions,
+    top: usize,
+) -> Result<()> {
+    let image_sizes = if args.weigh_images {
+        let spinner = Spinner::new();
+        spinner.status("weighing the images");
+        let client = client_options.build_client()?;
+        let fetcher = client_options.build_fetcher()?;
+        let robots = Robots::new(PolicyCache::load(
+            args.robots_cache.clone(),
+            Duration::from_secs(args.robots_cache_ttl),
+        )?);
+        let request_interval = match &args.scope_file {
+            Some(scope_file) => ScopeRules::from_file(scope_file)?.request_interval,
+            None => None,
+        };
+        let limits = WeighingLimits {
+            robots: Some((&robots, fetcher.as_ref())).filter(|_| args.respect_robots),
+            rate_limiter: &RateLimiter::new(request_interval),
+            domain_delays: link_graph
+                .domains()
+                .iter()
+                .map(|(domain, info)| {
+                    (domain.clone(), Duration::from_millis(info.request_delay_ms))
+                })
+                .collect(),
+        };
+        page_weight::weigh_images(&client, page_weight::image_urls(link_graph), &limits).await
+    } else {
+        Default::default()
+    };
+
+    println!("{}  Heaviest pages", console::Emoji("🏋️", ""));
+    for page in page_weight::heaviest_pages(link_graph, &image_sizes, top) {
+        let unweighed = match page.unweighed_images {
+            0 => String::new(),
+            count => format!(" ({} unweighed)", count),
+        };
+        println!(
+            "{:>10}  html {:>10}  {:>3} images {:>10}{}  {}",
+            format_size(page.total_bytes()),
+            format_size(page.html_bytes),
+            page.images,
+            format_size(page.image_bytes),
+            unweighed,
+            console::style(page.url).bold()
+        );
+    }
+    Ok(())
+}
+
+/// Shows the budget `--warm-up` gave each domain, the biggest first
+fn print_warm_up_summary(link_graph: &LinkGraph) {
+    let mut domains: Vec<_> = link_graph
+        .domains()
+        .iter()
+        .filter_map(|(domain, info)| Some((domain, info.warm_up.as_ref()?)))
+        .collect();
+    domains.sort_by_key(|(_, estimate)| std::cmp::Reverse(estimate.budget));
+
+    for (domain, estimate) in domains {
+        println!(
+            "{}  {}: budget of {} pages ({:.0}ms per page, {:.0}% errors, {} links found)",
+            console::Emoji("🌡️", ""),
+            console::style(domain).bold().yellow(),
+            estimate.budget,
+            estimate.mean_fetch_ms,
+            estimate.error_rate * 100.0,
+            estimate.discovered
+        );
+    }
+}
+
+/// Shows how an incremental crawl compares to the previous one
+fn print_recrawl_summary(link_graph: &LinkGraph) {
+    let Some(recrawl) = link_graph.recrawl() else {
+        return;
+    };
+
+    println!(
+        "{}  Recrawl: {} changed, {} unchanged, {} new, {} not checked",
+        console::Emoji("🔁", ""),
+        console::style(recrawl.changed).bold().yellow(),
+        console::style(recrawl.unchanged).bold().green(),
+        console::style(recrawl.new).bold().cyan(),
+        console::style(recrawl.unchecked).bold().red()
+    );
+}
+
+/// Shows the well-known files found on each domain
+fn print_well_known_summary(link_graph: &LinkGraph) {
+    for (domain, info) in link_graph.domains() {
+        if info.well_known.is_empty() {
+            continue;
+        }
+
+        let found: Vec<&str> = info
+            .well_known
+            .iter()
+            .filter(|(_, file)| file.found())
+            .map(|(path, _)| path.as_str())
+            .collect();
+        println!(
+            "{}  {}: {}",
+            console::Emoji("📌", ""),
+            console::style(domain).bold().yellow(),
+            if found.is_empty() {
+                String::from("no well-known files")
+            } else {
+                found.join(", ")
+            }
+        );
+    }
+}
+
+/// Shows the concurrency picked by `--auto-workers`
+fn print_auto_tuning(tuning: &AutoTuning) {
+    println!(
+        "{}  Auto workers: {} workers, {} per host ({} cores, {:.0}ms latency)",
+        console::Emoji("⚙️", ""),
+        console::style(tuning.workers).bold().yellow(),
+        console::style(tuning.per_host).bold().yellow(),
+        tuning.cores,
+        tuning.latency_ms
+    );
+}
+
+/// Shows how many pages each `--search` term was found on
+fn print_search_summary(link_graph: &LinkGraph, searches: &[String]) {
+    for search in searches {
+        let (pages, matches) = link_graph
+            .into_iter()
+            .filter_map(|(_, link)| link.search_hits.get(search))
+            .fold((0, 0), |(pages, matches), hits| {
+                (pages + 1, matches + hits.count)
+            });
+
+        println!(
+            "{}  {}: {} matches on {} pages",
+            console::Emoji("🔎", ""),
+            console::style(search).bold().yellow(),
+            matches,
+            pages
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use anyhow::{Context, Result};
+use tokio::fs;
+
+use super::load_link_graph;
+use crate::cli::DiffArgs;
+use rusty_crawler::diff::CrawlDiff;
+
+/// Shows what changed between two saved crawls
+pub async fn run(args: DiffArgs) -> Result<()> {
+    let old = load_link_graph(&args.old).await?;
+    let new = load_link_graph(&args.new).await?;
+    let diff = CrawlDiff::between(&old, &new);
+
+    println!(
+        "{}  {} new pages, {} removed pages, {} retitled pages, {} pages with changed links",
+        console::Emoji("🔍", ""),
+        console::style(diff.added.len()).bold().cyan(),
+        console::style(diff.removed.len()).bold().cyan(),
+        console::style(diff.retitled.len()).bold().cyan(),
+        console::style(diff.outlinks.len()).bold().cyan()
+    );
+
+    if diff.is_empty() {
+        println!("  the crawls found the same pages");
+    }
+
+    if !diff.added.is_empty() {
+        println!("\n{}", console::style("New pages").bold());
+        for url in diff.added.iter() {
+            println!("  {} {}", console::style("+").green(), url);
+        }
+    }
+
+    if !diff.removed.is_empty() {
+        println!("\n{}", console::style("Removed pages").bold());
+        for url in diff.removed.iter() {
+            println!("  {} {}", console::style("-").red(), url);
+        }
+    }
+
+    if !diff.retitled.is_empty() {
+        println!("\n{}", console::style("Changed titles").bold());
+        for change in diff.retitled.iter() {
+            println!("  {}", change.url);
+            println!(
+                "    {} {}",
+                console::style("-").red(),
+                change.old.join(" | ")
+            );
+            println!(
+                "    {} {}",
+                console::style("+").green(),
+                change.new.join(" | ")
+            );
+        }
+    }
+
+    if !diff.outlinks.is_empty() {
+        println!("\n{}", console::style("Changed links").bold());
+        for change in diff.outlinks.iter() {
+            println!("  {}", change.url);
+            for url in change.added.iter() {
+                println!("    {} {}", console::style("+").green(), url);
+            }
+            for url in change.removed.iter() {
+                println!("    {} {}", console::style("-").red(), url);
+            }
+        }
+    }
+
+    if let Some(path) = &args.json {
+        fs::write(path, serde_json::to_string_pretty(&diff)?)
+            .await
+            .with_context(|| format!("could not write {}", path))?;
+    }
+
+    Ok(())
+}
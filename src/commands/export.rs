@@ -0,0 +1,60 @@
+use anyhow::Result;
+use std::collections::HashSet;
+use tokio::fs;
+
+use super::load_link_graph;
+use crate::cli::{ExportArgs, ExportFormat};
+use rusty_crawler::export::{self, graph::GraphFilter};
+
+/// Converts a saved link graph to another format
+pub async fn run(args: ExportArgs) -> Result<()> {
+    let link_graph = load_link_graph(&args.links_json).await?;
+    let domains: HashSet<String> = if args.domain.is_empty() {
+        link_graph.seed_domains()
+    } else {
+        args.domain.into_iter().collect()
+    };
+
+    let filter = GraphFilter {
+        max_depth: args.max_depth,
+        max_nodes: args.max_nodes,
+    };
+
+    let output = match args.format {
+        ExportFormat::Sitemap => export::sitemap::to_sitemap(&link_graph, &domains)?,
+        ExportFormat::ImageSitemap => export::sitemap::to_image_sitemap(&link_graph, &domains)?,
+        ExportFormat::CmsCsv => export::cms::to_cms_csv(&link_graph, &domains)?,
+        ExportFormat::CmsJson => export::cms::to_cms_json(&link_graph, &domains)?,
+        ExportFormat::Graphml => export::graph::to_graphml(&link_graph, &domains, filter)?,
+        ExportFormat::Gexf => export::graph::to_gexf(&link_graph, &domains, filter)?,
+        ExportFormat::Dot => export::graph::to_dot(&link_graph, &domains, filter)?,
+        ExportFormat::LinksCsv => export::tables::to_links_csv(&link_graph, &domains, args.tsv)?,
+        ExportFormat::EdgesCsv => export::tables::to_edges_csv(&link_graph, &domains, args.tsv)?,
+        ExportFormat::ImagesCsv => export::tables::to_images_csv(&link_graph, &domains, args.tsv)?,
+        ExportFormat::AltText => export::alt_text::to_alt_text_json(&link_graph, &domains)?,
+        ExportFormat::AltTextCsv => {
+            export::alt_text::to_alt_text_csv(&link_graph, &domains, args.tsv)?
+        }
+        // Binary tables, written straight to the directory
+        #[cfg(feature = "parquet")]
+        ExportFormat::Parquet => {
+            let directory = std::path::Path::new(&args.output);
+            export::parquet::to_parquet(&link_graph, &domains, directory)?;
+            print_exported(&args.links_json, &args.output);
+            return Ok(());
+        }
+    };
+    fs::write(&args.output, output).await?;
+    print_exported(&args.links_json, &args.output);
+
+    Ok(())
+}
+
+fn print_exported(links_json: &str, output: &str) {
+    println!(
+        "{}  Exported {} to {}",
+        console::Emoji("📤", ""),
+        console::style(links_json).bold().cyan(),
+        console::style(output).bold().cyan()
+    );
+}
@@ -0,0 +1,135 @@
+use anyhow::Result;
+
+use super::{client_options, load_link_graph};
+use crate::cli::{ImageArgs, ImagesArgs};
+use rusty_crawler::{
+    client::ClientOptions,
+    export::images_edges::to_images_edges_csv,
+    gallery::write_gallery,
+    image_store::open_image_store,
+    image_utils::{
+        convert_links_to_images, download_images, load_image_database, prioritize_images,
+        remove_small_images, reuse_previous_images, ImageChanges, ImageDatabase,
+    },
+    logger::spinner::{Colour, Spinner},
+    model::{Image, LinkGraph},
+};
+
+/// Downloads the images of a saved link graph
+pub async fn run(args: ImagesArgs) -> Result<()> {
+    let client_options = client_options(&args.client)?;
+    let link_graph = load_link_graph(&args.links_json).await?;
+
+    let spinner = Spinner::new();
+    save_images(&link_graph, &client_options, &args.images, &spinner).await?;
+    Ok(())
+}
+
+/// How many of the images found were saved, and their size
+pub struct SavedImages {
+    pub found: usize,
+    pub saved: usize,
+    pub bytes: u64,
+}
+
+/// Downloads the images found in `link_graph` and writes
+/// their database (and gallery) to the image store, see `--img-dest`
+pub async fn save_images(
+    link_graph: &LinkGraph,
+    client_options: &ClientOptions,
+    args: &ImageArgs,
+    spinner: &Spinner,
+) -> Result<SavedImages> {
+    spinner.status("[1/3] converting image links");
+    let mut image_metadata = convert_links_to_images(link_graph);
+    let found = image_metadata.len();
+    spinner.print_above("  [1/3] converted image links", Colour::Green);
+
+    spinner.status("[2/3] downloading image metadata");
+    let client = client_options.build_client()?;
+    let store = open_image_store(args.destination(), args.s3_endpoint.as_deref()).await?;
+    let previous_images = if args.diff_images {
+        load_image_database(store.as_ref()).await?
+    } else {
+        None
+    };
+    let mut priority = prioritize_images(&image_metadata, link_graph);
+    if let Some(previous_images) = &previous_images {
+        let stored = store.list().await?;
+        let reused = reuse_previous_images(&mut image_metadata, previous_images, &stored);
+        priority.retain(|name| image_metadata.contains_key(name) && !reused.contains(name));
+    }
+    download_images(
+        &mut image_metadata,
+        &priority,
+        store.as_ref(),
+        args.max_images,
+        &client,
+        args.probe_image_sizes || args.min_image_size.is_some(),
+    )
+    .await?;
+    if let Some(min_image_size) = args.min_image_size {
+        let removed =
+            remove_small_images(&mut image_metadata, min_image_size, store.as_ref()).await?;
+        spinner.print_above(
+            format!(
+                "  removed {} images smaller than {}px",
+                removed, min_image_size
+            ),
+            Colour::Green,
+        );
+    }
+    spinner.print_above("  [2/3] downloaded image metadata", Colour::Green);
+
+    // Save this to image dir
+    spinner.status("[3/3] creating image database");
+    let image_database = serde_json::to_vec(&ImageDatabase {
+        tags: link_graph.tags(),
+        images: &image_metadata,
+    })?;
+    store.write("database.json", image_database).await?;
+    // The database keeps one entry per image, this keeps every page it is on
+    store
+        .write(
+            "images_edges.csv",
+            to_images_edges_csv(link_graph, &image_metadata)?.into_bytes(),
+        )
+        .await?;
+    spinner.print_above("  [3/3] created image database", Colour::Green);
+
+    if let Some(previous_images) = &previous_images {
+        let changes = ImageChanges::between(previous_images, &image_metadata);
+        store
+            .write("image_changes.json", serde_json::to_vec_pretty(&changes)?)
+            .await?;
+        spinner.print_above(
+            format!(
+                "  images since the last run: {} added, {} removed, {} moved, {} unchanged",
+                changes.added.len(),
+                changes.removed.len(),
+                changes.moved.len(),
+                changes.unchanged.len()
+            ),
+            Colour::Green,
+        );
+    }
+
+    if args.gallery {
+        spinner.status("writing the image gallery");
+        let gallery_path = write_gallery(store.as_ref()).await?;
+        spinner.print_above(
+            format!("  wrote the image gallery to {}", gallery_path),
+            Colour::Green,
+        );
+    }
+
+    let saved: Vec<&Image> = image_metadata
+        .values()
+        .filter(|image| image.file.is_some())
+        .collect();
+    Ok(SavedImages {
+        found,
+        saved: saved.len(),
+        bytes: saved.iter().filter_map(|image| image.size).sum(),
+    })
+}
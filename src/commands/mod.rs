@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use tokio::fs;
+use tracing::info;
+
+use crate::cli::ClientArgs;
+use rusty_crawler::{
+    client::{load_ca_certificates, parse_headers, ClientOptions},
+    cookies::load_cookies_file,
+    model::LinkGraph,
+    proxy_pool::{load_proxy_list, ProxyPoolFetcher},
+    user_agents::UserAgents,
+};
+
+pub mod analyze;
+pub mod bench;
+pub mod crawl;
+pub mod diff;
+pub mod export;
+pub mod images;
+pub mod params;
+pub mod resume;
+pub mod serve_graph;
+pub mod stats;
+
+/// Builds the options of the clients making the
+/// requests, failing early on invalid ones
+fn client_options(args: &ClientArgs) -> Result<ClientOptions> {
+    let mut client_options = ClientOptions {
+        proxy: args.proxy.clone(),
+        cache_dir: args.cache_dir.clone(),
+        revalidate_cache: args.revalidate_cache,
+        accept_invalid_certs: args.insecure,
+        https_only: args.https_only,
+        max_body_bytes: args.max_page_kb.map(|kb| kb as usize * 1024),
+        shared_client: args.shared_client,
+        headers: parse_headers(&args.headers)?,
+        ..Default::default()
+    };
+
+    if let Some(ca_cert) = &args.ca_cert {
+        client_options.ca_certificates = load_ca_certificates(ca_cert)?;
+    }
+
+    if let Some(user_agent_file) = &args.user_agent_file {
+        let user_agents = UserAgents::from_file(user_agent_file, args.user_agent_sticky)?;
+        client_options.user_agents = Some(Arc::new(user_agents));
+    }
+
+    if let Some(proxy_list) = &args.proxy_list {
+        let proxies = load_proxy_list(proxy_list)?;
+        let proxy_pool = ProxyPoolFetcher::new(&client_options, proxies, args.proxy_rotation)?;
+        client_options.proxy_pool = Some(Arc::new(proxy_pool));
+    }
+
+    // Fail early on invalid options, e.g. a bad proxy url
+    client_options.build_client()?;
+    if let Some(cookies_file) = &args.cookies_file {
+        let n_cookies = load_cookies_file(cookies_file, &client_options.cookie_jar)?;
+        info!("Loaded {} cookies from {}", n_cookies, cookies_file);
+    }
+
+    Ok(client_options)
+}
+
+/// Loads the link graph saved by a previous crawl, either
+/// its links.json or the graph in its state file
+async fn load_link_graph(path: &str) -> Result<LinkGraph> {
+    let json = fs::read_to_string(path)
+        .await
+        .with_context(|| format!("could not read {}", path))?;
+    let mut value: serde_json::Value =
+        serde_json::from_str(&json).with_context(|| format!("could not parse {}", path))?;
+    // Every field of the graph is optional, so a state file
+    // would otherwise load as an empty graph
+    if let Some(link_graph) = value.get_mut("link_graph") {
+        value = link_graph.take();
+    }
+
+    serde_json::from_value(value).with_context(|| format!("could not parse {}", path))
+}
+
+async fn serialize_links(links: &LinkGraph, destination: &str) -> Result<()> {
+    let json = serde_json::to_string(links)?;
+    fs::write(destination, json).await?;
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use anyhow::Result;
+
+use super::load_link_graph;
+use crate::cli::ParamsArgs;
+use rusty_crawler::url_params::param_report;
+
+/// Shows which query parameters of a saved link graph change
+/// the content of the pages, suggesting scope rules for the
+/// ones that don't
+pub async fn run(args: ParamsArgs) -> Result<()> {
+    let link_graph = load_link_graph(&args.links_json).await?;
+    let reports = param_report(&link_graph);
+    if reports.is_empty() {
+        println!(
+            "{}  No pages only differing by a query parameter were crawled",
+            console::Emoji("🔍", "")
+        );
+        return Ok(());
+    }
+
+    println!("{}", console::style("Query parameters").bold());
+    for report in reports.iter() {
+        let verdict = if report.ignorable() {
+            console::style("never changes the content").green()
+        } else {
+            console::style("changes the content").yellow()
+        };
+        println!(
+            "  {}: {} ({} of {} compared sets unchanged, on {} pages)",
+            console::style(&report.name).bold().cyan(),
+            verdict,
+            report.unchanged,
+            report.compared,
+            report.pages
+        );
+        if report.ignorable() {
+            for url in report.example.iter() {
+                println!("      {}", url);
+            }
+        }
+    }
+
+    let deny: Vec<String> = reports
+        .iter()
+        .filter(|report| report.ignorable())
+        .map(|report| report.deny_pattern())
+        .collect();
+    if !deny.is_empty() {
+        println!(
+            "\n{}  Suggested scope file deny rules:",
+            console::Emoji("💡", "")
+        );
+        println!("  \"deny\": {}", serde_json::to_string(&deny)?);
+    }
+
+    Ok(())
+}
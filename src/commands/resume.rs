@@ -0,0 +1,28 @@
+use anyhow::Result;
+use std::{sync::Arc, time::Duration};
+
+use super::{client_options, crawl::run_crawl};
+use crate::cli::ResumeArgs;
+use rusty_crawler::{policy_cache::PolicyCache, robots::Robots, snapshot::load_snapshot, Crawler};
+
+/// Continues a crawl from the links it still had queued,
+/// until the graph holds `--max-links` links in total
+pub async fn run(args: ResumeArgs) -> Result<()> {
+    let client_options = client_options(&args.options.client)?;
+    let robots = Arc::new(Robots::new(PolicyCache::load(
+        args.options.robots_cache.clone(),
+        Duration::from_secs(args.options.robots_cache_ttl),
+    )?));
+
+    let (link_graph, queue) = load_snapshot(&args.state_file).await?;
+    let crawler = Crawler::builder()
+        .resume_from(link_graph, queue)
+        .robots(robots);
+    run_crawl(
+        &args.options,
+        client_options,
+        vec![crawler],
+        Some(&args.state_file),
+    )
+    .await
+}
@@ -0,0 +1,24 @@
+use anyhow::{Context, Result};
+use tokio::net::TcpListener;
+
+use super::load_link_graph;
+use crate::cli::ServeGraphArgs;
+use rusty_crawler::graph_api::router;
+
+/// Serves the API over a saved link graph until stopped
+pub async fn run(args: ServeGraphArgs) -> Result<()> {
+    let link_graph = load_link_graph(&args.links_json).await?;
+    let listener = TcpListener::bind(&args.bind)
+        .await
+        .with_context(|| format!("could not listen on {}", args.bind))?;
+
+    println!(
+        "{}  Serving the {} pages of {} on {}",
+        console::Emoji("🌐", ""),
+        console::style(link_graph.len()).bold().cyan(),
+        args.links_json,
+        console::style(format!("http://{}", listener.local_addr()?)).bold()
+    );
+    axum::serve(listener, router(link_graph)).await?;
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+
+use super::load_link_graph;
+use crate::cli::StatsArgs;
+use rusty_crawler::{gallery::format_size, stats::CrawlStats};
+
+/// Shows the statistics of a saved link graph
+pub async fn run(args: StatsArgs) -> Result<()> {
+    let link_graph = load_link_graph(&args.links_json).await?;
+    let stats = CrawlStats::from_graph(&link_graph);
+
+    println!(
+        "{}  {} pages, {} links between them",
+        console::Emoji("📊", ""),
+        console::style(stats.pages).bold().cyan(),
+        console::style(stats.edges).bold().cyan()
+    );
+
+    println!("\n{}", console::style("Depth from the seeds").bold());
+    for (depth, pages) in stats.depths.iter() {
+        println!("{:>8}  {}", depth, pages);
+    }
+    if stats.unreachable > 0 {
+        println!("{:>8}  {}", "none", stats.unreachable);
+    }
+
+    println!("\n{}", console::style("Statuses").bold());
+    for (status, pages) in stats.statuses.iter() {
+        let status = match status {
+            Some(status) => status.to_string(),
+            None => String::from("unknown"),
+        };
+        println!("{:>8}  {}", status, pages);
+    }
+
+    if !stats.domain_errors.is_empty() {
+        println!("\n{}", console::style("Failed pages by domain").bold());
+    }
+    for (domain, errors) in stats.domain_errors.iter().take(args.top) {
+        let kinds: Vec<String> = errors
+            .named()
+            .into_iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect();
+        println!("{:>8}  {} ({})", errors.total(), domain, kinds.join(", "));
+    }
+
+    println!("\n{}", console::style("Top domains").bold());
+    for (domain, pages) in stats.domains.iter().take(args.top) {
+        println!("{:>8}  {}", pages, domain);
+    }
+
+    println!("\n{}", console::style("Largest pages").bold());
+    if stats.largest_pages.is_empty() {
+        println!("  the page sizes weren't recorded by this crawl");
+    }
+    for (url, size) in stats.largest_pages.iter().take(args.top) {
+        println!("{:>10}  {}", format_size(*size), url);
+    }
+
+    Ok(())
+}
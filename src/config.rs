@@ -0,0 +1,137 @@
+//! The options of a command in a TOML file, see `--config`, e.g.
+//! ```toml
+//! starting-url = ["https://matgomes.com"]
+//! max-links = 500
+//! scrape = ["images", "meta"]
+//! respect-robots = true
+//!
+//! [header]
+//! Accept-Language = "en"
+//!
+//! [scope]
+//! deny = ["\\.pdf$"]
+//! requests_per_second = 2.0
+//! ```
+//! The keys are the long names of the flags, the lists give a
+//! flag several times and the tables give it once per `name=value`
+//! pair. The `[scope]` table makes the file its own `--scope-file`
+
+use anyhow::{bail, Context, Result};
+use std::collections::HashSet;
+use toml::{Table, Value};
+
+/// The flags set by the config file at `path`, leaving out the
+/// ones in `given` (by their long name) so that the flags given
+/// in the command line take precedence
+pub fn config_args(path: &str, given: &HashSet<String>) -> Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read config file {}", path))?;
+    let config: Table =
+        toml::from_str(&contents).with_context(|| format!("invalid config file {}", path))?;
+
+    let mut args: Vec<String> = Default::default();
+    for (key, value) in config {
+        let name = key.replace('_', "-");
+        if name == "scope" {
+            if !given.contains("scope-file") {
+                args.extend([String::from("--scope-file"), path.to_string()]);
+            }
+            continue;
+        }
+        if given.contains(&name) {
+            continue;
+        }
+
+        let flag = format!("--{}", name);
+        match value {
+            Value::Boolean(enabled) => {
+                if enabled {
+                    args.push(flag);
+                }
+            }
+            Value::Array(values) => {
+                for value in values {
+                    args.extend([flag.clone(), scalar(&key, value)?]);
+                }
+            }
+            Value::Table(pairs) => {
+                for (name, value) in pairs {
+                    args.extend([flag.clone(), format!("{}={}", name, scalar(&key, value)?)]);
+                }
+            }
+            value => args.extend([flag, scalar(&key, value)?]),
+        }
+    }
+
+    Ok(args)
+}
+
+/// The single `value` of the option `key` as it is written in the command line
+fn scalar(key: &str, value: Value) -> Result<String> {
+    match value {
+        Value::String(value) => Ok(value),
+        Value::Integer(value) => Ok(value.to_string()),
+        Value::Float(value) => Ok(value.to_string()),
+        Value::Boolean(value) => Ok(value.to_string()),
+        Value::Datetime(value) => Ok(value.to_string()),
+        Value::Array(_) | Value::Table(_) => bail!("invalid value for {} in the config", key),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args_of(config: &str, given: &[&str]) -> Vec<String> {
+        let path = std::env::temp_dir().join(format!("{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, config).unwrap();
+        let given = given.iter().map(|flag| flag.to_string()).collect();
+        let args = config_args(path.to_str().unwrap(), &given).unwrap();
+        std::fs::remove_file(path).unwrap();
+        args
+    }
+
+    #[test]
+    fn config_values_become_flags() {
+        let args = args_of(
+            r#"
+            max_links = 500
+            respect-robots = true
+            shuffle-frontier = false
+            scrape = ["images", "meta"]
+
+            [header]
+            Accept-Language = "en"
+            "#,
+            &[],
+        );
+
+        assert_eq!(
+            args,
+            [
+                "--header",
+                "Accept-Language=en",
+                "--max-links",
+                "500",
+                "--respect-robots",
+                "--scrape",
+                "images",
+                "--scrape",
+                "meta",
+            ]
+        );
+    }
+
+    #[test]
+    fn given_flags_take_precedence() {
+        let args = args_of(
+            r#"
+            max-links = 500
+            links-json = "crawl.json"
+            "#,
+            &["max-links"],
+        );
+
+        assert_eq!(args, ["--links-json", "crawl.json"]);
+    }
+}
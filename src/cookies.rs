@@ -0,0 +1,95 @@
+use anyhow::{anyhow, bail, Context, Result};
+use reqwest::cookie::Jar;
+use serde::Deserialize;
+use url::Url;
+
+/// A cookie as exported by most browser extensions
+/// in the JSON format
+#[derive(Deserialize)]
+struct JsonCookie {
+    name: String,
+    value: String,
+    domain: String,
+    #[serde(default = "default_path")]
+    path: String,
+    #[serde(default)]
+    secure: bool,
+}
+
+fn default_path() -> String {
+    String::from("/")
+}
+
+/// Loads all the cookies in the file at `path` into
+/// the given `jar`. The file can either be in the
+/// Netscape (cookies.txt) format, or a JSON array of
+/// cookie objects. Returns the number of cookies loaded.
+pub fn load_cookies_file(path: &str, jar: &Jar) -> Result<usize> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read cookies file {}", path))?;
+
+    let cookies = if contents.trim_start().starts_with('[') {
+        parse_json_cookies(&contents)?
+    } else {
+        parse_netscape_cookies(&contents)?
+    };
+
+    for cookie in cookies.iter() {
+        let url = cookie_url(&cookie.domain, &cookie.path, cookie.secure)?;
+        let mut cookie_str = format!(
+            "{}={}; Domain={}; Path={}",
+            cookie.name, cookie.value, cookie.domain, cookie.path
+        );
+        if cookie.secure {
+            cookie_str.push_str("; Secure");
+        }
+
+        jar.add_cookie_str(&cookie_str, &url);
+    }
+
+    Ok(cookies.len())
+}
+
+fn parse_json_cookies(contents: &str) -> Result<Vec<JsonCookie>> {
+    serde_json::from_str(contents).context("invalid JSON cookies file")
+}
+
+/// Parses the Netscape cookies.txt format, where each line
+/// is a tab separated list of:
+/// domain, include subdomains, path, secure, expiry, name, value
+fn parse_netscape_cookies(contents: &str) -> Result<Vec<JsonCookie>> {
+    let mut cookies: Vec<JsonCookie> = Default::default();
+
+    for (line_number, line) in contents.lines().enumerate() {
+        // curl marks http only cookies with this prefix
+        let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            bail!("invalid cookie on line {}", line_number + 1);
+        }
+
+        cookies.push(JsonCookie {
+            domain: fields[0].to_string(),
+            path: fields[2].to_string(),
+            secure: fields[3].eq_ignore_ascii_case("TRUE"),
+            name: fields[5].to_string(),
+            value: fields[6].trim_end().to_string(),
+        });
+    }
+
+    Ok(cookies)
+}
+
+/// The jar needs a url to scope each cookie to, so
+/// build one from the cookie's domain and path
+fn cookie_url(domain: &str, path: &str, secure: bool) -> Result<Url> {
+    let scheme = if secure { "https" } else { "http" };
+    let host = domain.trim_start_matches('.');
+
+    Url::parse(&format!("{}://{}{}", scheme, host, path))
+        .map_err(|e| anyhow!("invalid cookie domain {}: {}", domain, e))
+}
@@ -0,0 +1,1065 @@
+//! Running a crawl: the worker loop and the builder
+//! used to configure and start it
+
+use anyhow::{anyhow, Result};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+use tokio::{
+    sync::{mpsc, Mutex, RwLock},
+    task::JoinSet,
+};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+use tracing::{error, info, info_span, warn, Instrument};
+use url::Url;
+
+use crate::{
+    client::ClientOptions,
+    crawler::{
+        classify_error, fetch_page, follow_feeds, parse_page, BadStatus, CrawlerState,
+        CrawlerStateRef, FetchedPage, LinkPath, ScrapeConfig, ScrapeOption,
+    },
+    domain_budget::DomainBudget,
+    fetcher::Fetcher,
+    host_limiter::HostLimiter,
+    incremental::PreviousCrawl,
+    logger::{
+        self,
+        dashboard::{CrawlSnapshot, Dashboard},
+    },
+    model::{CrawlResult, Discovery, DomainInfo, LinkGraph, Provenance, ScrapeOutput},
+    observer::CrawlObserver,
+    policy_cache::PolicyCache,
+    politeness::DomainPoliteness,
+    rate_limiter::RateLimiter,
+    robots::Robots,
+    scope::ScopeRules,
+    sink::{FanOut, OutputSink},
+    well_known::probe_domains,
+};
+
+/// How many scraped pages can wait for the consumer
+/// of [`Crawler::stream`] before the workers block
+const RESULTS_BUFFER: usize = 64;
+/// How many downloaded pages can wait for a parse
+/// worker before the fetch workers block
+const PARSE_BUFFER: usize = 32;
+/// How long a fetch worker with an empty queue waits
+/// for the pages being parsed to queue more links
+const IDLE_WAIT: Duration = Duration::from_millis(50);
+
+/// A configured crawl, e.g.
+///
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// let link_graph = rusty_crawler::Crawler::builder()
+///     .seed("https://example.com/")
+///     .max_links(50)
+///     .workers(4)
+///     .run()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Crawler {
+    state: CrawlerState,
+    workers: u64,
+    parse_workers: u64,
+    log_status: bool,
+    dashboard: bool,
+    scope_file: Option<String>,
+}
+
+/// The limits on the requests of crawlers running side by
+/// side (e.g. `crawl --isolate-seeds`), so that together
+/// they are as polite as a single crawl would be
+#[derive(Clone)]
+pub struct SharedLimits {
+    rate_limiter: Arc<RateLimiter>,
+    politeness: Arc<DomainPoliteness>,
+    host_limiter: Option<Arc<HostLimiter>>,
+}
+
+impl SharedLimits {
+    /// At most one request every `request_interval` and
+    /// `per_host_concurrency` requests to each host at the
+    /// same time, `None` for no limit
+    pub fn new(request_interval: Option<Duration>, per_host_concurrency: Option<usize>) -> Self {
+        SharedLimits {
+            rate_limiter: Arc::new(RateLimiter::new(request_interval)),
+            politeness: Default::default(),
+            host_limiter: per_host_concurrency.map(|limit| Arc::new(HostLimiter::new(limit))),
+        }
+    }
+}
+
+/// Sets up a [`Crawler`], see [`Crawler::builder`]
+pub struct CrawlerBuilder {
+    link_graph: LinkGraph,
+    seeds: Vec<String>,
+    tags: BTreeMap<String, String>,
+    queue: VecDeque<LinkPath>,
+    max_links: usize,
+    workers: u64,
+    parse_workers: Option<u64>,
+    log_status: bool,
+    dashboard: bool,
+    client_options: ClientOptions,
+    scope: ScopeRules,
+    scope_file: Option<String>,
+    robots: Option<Arc<Robots>>,
+    respect_robots: bool,
+    scrape_config: ScrapeConfig,
+    observers: Vec<Arc<dyn CrawlObserver>>,
+    sinks: FanOut,
+    external_depth: Option<u32>,
+    max_total_bytes: Option<u64>,
+    per_host_concurrency: Option<usize>,
+    shared_limits: Option<SharedLimits>,
+    probe_well_known: bool,
+    shuffle_frontier: bool,
+    warm_up: Option<usize>,
+    previous: Option<PreviousCrawl>,
+}
+
+impl Default for CrawlerBuilder {
+    fn default() -> Self {
+        CrawlerBuilder {
+            link_graph: Default::default(),
+            seeds: Default::default(),
+            tags: Default::default(),
+            queue: Default::default(),
+            max_links: 100,
+            workers: 4,
+            parse_workers: None,
+            log_status: false,
+            dashboard: false,
+            client_options: Default::default(),
+            scope: Default::default(),
+            scope_file: None,
+            robots: None,
+            respect_robots: false,
+            scrape_config: ScrapeConfig {
+                options: vec![ScrapeOption::Images, ScrapeOption::Titles],
+                max_field_length: 4096,
+                parsers: Default::default(),
+                custom_selectors: Default::default(),
+                searches: Default::default(),
+                extractors: Default::default(),
+            },
+            observers: Default::default(),
+            sinks: Default::default(),
+            external_depth: None,
+            max_total_bytes: None,
+            per_host_concurrency: None,
+            shared_limits: None,
+            probe_well_known: false,
+            shuffle_frontier: false,
+            warm_up: None,
+            previous: None,
+        }
+    }
+}
+
+impl CrawlerBuilder {
+    /// Adds a url to start crawling from
+    pub fn seed(self, url: impl Into<String>) -> Self {
+        self.seed_with(url, Discovery::Seed)
+    }
+
+    /// Adds a url to start crawling from, which was found
+    /// through `discovery`, e.g. in a sitemap
+    pub fn seed_with(mut self, url: impl Into<String>, discovery: Discovery) -> Self {
+        let url = url.into();
+        self.queue.push_back(LinkPath {
+            child: url.clone(),
+            discovery,
+            ..Default::default()
+        });
+        self.seeds.push(url);
+        self
+    }
+
+    /// Adds the urls to start crawling from
+    pub fn seeds(self, urls: impl IntoIterator<Item = String>) -> Self {
+        urls.into_iter()
+            .fold(self, |builder, url| builder.seed(url))
+    }
+
+    /// Continues the crawl that found `link_graph`
+    /// and still had the links in `queue` to visit
+    pub fn resume_from(mut self, link_graph: LinkGraph, queue: VecDeque<LinkPath>) -> Self {
+        self.link_graph = link_graph;
+        self.queue = queue;
+        self
+    }
+
+    /// Recrawls the pages of `previous`, the graph of an earlier
+    /// crawl, asking the servers whether they changed since. Only
+    /// the changed pages are scraped again, the rest are carried
+    /// over into the graph of this crawl (see `PreviousCrawl`)
+    pub fn incremental(mut self, previous: LinkGraph) -> Self {
+        self.previous = Some(PreviousCrawl::new(previous));
+        self
+    }
+
+    /// Stops once the graph holds this many links
+    pub fn max_links(mut self, max_links: usize) -> Self {
+        self.max_links = max_links;
+        self
+    }
+
+    /// How many pages are crawled concurrently
+    pub fn workers(mut self, workers: u64) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    /// How many pages are parsed concurrently, `None` for
+    /// as many as there are CPU cores. The pages are
+    /// fetched and parsed by separate pools of workers.
+    pub fn parse_workers(mut self, parse_workers: Option<u64>) -> Self {
+        self.parse_workers = parse_workers;
+        self
+    }
+
+    /// How many pages of the same host can be crawled
+    /// concurrently, `None` for as many as there are workers
+    pub fn per_host_concurrency(mut self, per_host_concurrency: Option<usize>) -> Self {
+        self.per_host_concurrency = per_host_concurrency;
+        self
+    }
+
+    /// Shares the rate limit, the delays learned for each domain
+    /// and the per-host limit with the other crawlers given the
+    /// same `limits`, instead of the scope's request rate and
+    /// `per_host_concurrency` of this crawler alone
+    pub fn shared_limits(mut self, limits: SharedLimits) -> Self {
+        self.shared_limits = Some(limits);
+        self
+    }
+
+    /// Crawls this many pages of every new domain first, to
+    /// estimate its latency and error rate, then shares the
+    /// links between the domains in proportion to how many links
+    /// they have, how fast and how reliable they are
+    pub fn warm_up(mut self, warm_up: Option<usize>) -> Self {
+        self.warm_up = warm_up;
+        self
+    }
+
+    /// Once the crawl is done, fetches the well-known files
+    /// (robots.txt, sitemap.xml, security.txt...) of each
+    /// domain crawled into its `DomainInfo`
+    pub fn probe_well_known(mut self, probe_well_known: bool) -> Self {
+        self.probe_well_known = probe_well_known;
+        self
+    }
+
+    /// Crawls the queued links in a random order instead of the
+    /// newest first, spreading the requests between the domains
+    /// early on rather than exhausting the seeds' domain first
+    pub fn shuffle_frontier(mut self, shuffle_frontier: bool) -> Self {
+        self.shuffle_frontier = shuffle_frontier;
+        self
+    }
+
+    /// The urls waiting to be crawled, seeds first
+    pub fn queued_urls(&self) -> impl Iterator<Item = &str> {
+        self.queue.iter().map(|link_path| link_path.child.as_str())
+    }
+
+    /// Shows a progress bar while crawling
+    pub fn log_status(mut self, log_status: bool) -> Self {
+        self.log_status = log_status;
+        self
+    }
+
+    /// Shows a full-screen dashboard while crawling,
+    /// instead of the progress bar
+    pub fn dashboard(mut self, dashboard: bool) -> Self {
+        self.dashboard = dashboard;
+        self
+    }
+
+    pub fn client_options(mut self, client_options: ClientOptions) -> Self {
+        self.client_options = client_options;
+        self
+    }
+
+    pub fn scope(mut self, scope: ScopeRules) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Reloads the scope rules from this file when
+    /// the process receives SIGHUP
+    pub fn reload_scope_from(mut self, scope_file: impl Into<String>) -> Self {
+        self.scope_file = Some(scope_file.into());
+        self
+    }
+
+    /// The robots.txt rules (and their cache) to use, which
+    /// can be shared between crawls. When not given nothing
+    /// is cached between runs
+    pub fn robots(mut self, robots: Arc<Robots>) -> Self {
+        self.robots = Some(robots);
+        self
+    }
+
+    /// Skips the links disallowed by each site's robots.txt
+    pub fn respect_robots(mut self, respect_robots: bool) -> Self {
+        self.respect_robots = respect_robots;
+        self
+    }
+
+    pub fn scrape_config(mut self, scrape_config: ScrapeConfig) -> Self {
+        self.scrape_config = scrape_config;
+        self
+    }
+
+    /// Runs the callbacks of `observer` while crawling,
+    /// can be given multiple times
+    pub fn observer(mut self, observer: impl CrawlObserver + 'static) -> Self {
+        self.observers.push(Arc::new(observer));
+        self
+    }
+
+    /// Writes every page to `sink` while crawling, can be
+    /// given multiple times. The sinks are finished by
+    /// [`Crawler::run`], [`Crawler::run_with_queue`] leaves
+    /// that to the caller
+    pub fn sink(mut self, sink: Arc<dyn OutputSink>) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    /// Only crawls this many hops away from the seeds'
+    /// domains, the pages at the last hop are recorded but
+    /// their links are not followed. `None` for no limit
+    pub fn external_depth(mut self, external_depth: Option<u32>) -> Self {
+        self.external_depth = external_depth;
+        self
+    }
+
+    /// Stops fetching pages once their bodies add up to this
+    /// many bytes, leaving the rest of the queue for a resume.
+    /// `None` for no limit
+    pub fn max_total_bytes(mut self, max_total_bytes: Option<u64>) -> Self {
+        self.max_total_bytes = max_total_bytes;
+        self
+    }
+
+    /// Adds the tag `name=value` to the graph, on top
+    /// of the tags of a resumed crawl
+    pub fn tag(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn build(mut self) -> Result<Crawler> {
+        if !self.seeds.is_empty() {
+            let mut seeds = self.link_graph.seeds().to_vec();
+            seeds.extend(self.seeds);
+            self.link_graph.set_seeds(seeds);
+        }
+        if !self.tags.is_empty() {
+            let mut tags = self.link_graph.tags().clone();
+            tags.extend(self.tags);
+            self.link_graph.set_tags(tags);
+        }
+
+        // Checked after the seeds, which may bring new pages
+        if let Some(previous) = &self.previous {
+            let queued: HashSet<String> = self.queue.iter().map(|l| l.child.clone()).collect();
+            for url in previous.pages() {
+                if !queued.contains(&url) {
+                    self.queue.push_back(LinkPath {
+                        child: url,
+                        discovery: Discovery::Previous,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+
+        let robots = match self.robots {
+            Some(robots) => robots,
+            None => Arc::new(Robots::new(PolicyCache::load(None, Duration::ZERO)?)),
+        };
+
+        let limits = self.shared_limits.unwrap_or_else(|| {
+            SharedLimits::new(self.scope.request_interval, self.per_host_concurrency)
+        });
+        let internal_domains = self.link_graph.seed_domains();
+        let state = CrawlerState {
+            link_queue: RwLock::new(self.queue),
+            link_graph: RwLock::new(self.link_graph),
+            max_links: self.max_links,
+            client_options: self.client_options,
+            rate_limiter: limits.rate_limiter,
+            scope: RwLock::new(self.scope),
+            robots,
+            respect_robots: self.respect_robots,
+            scrape_config: self.scrape_config,
+            politeness: limits.politeness,
+            results: None,
+            observers: self.observers,
+            sinks: self.sinks,
+            internal_domains,
+            external_depth: self.external_depth,
+            max_total_bytes: self.max_total_bytes,
+            downloaded_bytes: Default::default(),
+            host_limiter: limits.host_limiter,
+            probe_well_known: self.probe_well_known,
+            shuffle_frontier: self.shuffle_frontier,
+            in_flight: Default::default(),
+            domain_budget: self
+                .warm_up
+                .map(|sample_size| DomainBudget::new(sample_size, self.max_links)),
+            previous: self.previous,
+            fetching: std::sync::Mutex::new(vec![None; self.workers as usize]),
+            failed_pages: Default::default(),
+            stopped: Default::default(),
+        };
+
+        let parse_workers = self.parse_workers.unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, |cores| cores.get() as u64)
+        });
+
+        Ok(Crawler {
+            state,
+            workers: self.workers,
+            parse_workers,
+            log_status: self.log_status,
+            dashboard: self.dashboard,
+            scope_file: self.scope_file,
+        })
+    }
+
+    /// Builds the crawler and runs it, see [`Crawler::run`]
+    pub async fn run(self) -> Result<LinkGraph> {
+        self.build()?.run().await
+    }
+}
+
+impl Crawler {
+    pub fn builder() -> CrawlerBuilder {
+        Default::default()
+    }
+
+    /// Crawls until the graph holds the maximum number
+    /// of links, or there are no links left to visit
+    pub async fn run(self) -> Result<LinkGraph> {
+        let sinks = self.state.sinks.clone();
+        let link_graph = self.run_with_queue().await?.0;
+        sinks.on_finish(&link_graph).await?;
+        Ok(link_graph)
+    }
+
+    /// Runs the crawl in the background, yielding each page as
+    /// soon as it is scraped, e.g. to index it while crawling
+    ///
+    /// ```no_run
+    /// # async fn example() -> anyhow::Result<()> {
+    /// use tokio_stream::StreamExt;
+    ///
+    /// let crawler = rusty_crawler::Crawler::builder()
+    ///     .seed("https://example.com/")
+    ///     .build()?;
+    /// let mut pages = crawler.stream();
+    /// while let Some(page) = pages.next().await {
+    ///     println!("{}: {:?}", page.url, page.page.titles);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream(mut self) -> impl Stream<Item = CrawlResult> {
+        let (sender, receiver) = mpsc::channel(RESULTS_BUFFER);
+        self.state.results = Some(sender);
+        tokio::spawn(async move {
+            if let Err(e) = self.run().await {
+                error!("Crawl failed: {:?}", e);
+            }
+        });
+
+        ReceiverStream::new(receiver)
+    }
+
+    /// Like [`Crawler::run`], also returning the links still
+    /// queued so the crawl can be resumed later
+    pub async fn run_with_queue(self) -> Result<(LinkGraph, VecDeque<LinkPath>)> {
+        let state = Arc::new(self.state);
+        let reload_task = self.scope_file.clone().map(|scope_file| {
+            let crawler_state = state.clone();
+            tokio::spawn(async move { reload_scope_on_signal(crawler_state, scope_file).await })
+        });
+
+        // Made here so a terminal that can't show it fails the crawl
+        let dashboard = if self.dashboard {
+            Some(Dashboard::new()?)
+        } else {
+            None
+        };
+        let status_task = (self.log_status || self.dashboard).then(|| {
+            let crawler_state = state.clone();
+            match dashboard {
+                Some(dashboard) => {
+                    tokio::spawn(async move { output_dashboard(crawler_state, dashboard).await })
+                }
+                None => tokio::spawn(async move { output_status(crawler_state).await }),
+            }
+        });
+
+        // Add as many crawling workers as the user has specified
+        // A client per worker unless they are told to share one
+        let shared_fetcher = if state.client_options.shared_client {
+            Some(state.client_options.build_fetcher()?)
+        } else {
+            None
+        };
+        let (page_sender, page_receiver) = mpsc::channel(PARSE_BUFFER);
+        let mut tasks = JoinSet::new();
+        for worker in 0..self.workers as usize {
+            let crawler_state = state.clone();
+            let fetcher = match &shared_fetcher {
+                Some(fetcher) => fetcher.clone(),
+                None => state.client_options.build_worker_fetcher(worker)?,
+            };
+            let pages = page_sender.clone();
+            tasks.spawn(async move { fetch_pages(crawler_state, worker, fetcher, pages).await });
+        }
+        // The parse workers stop once every fetch worker is done
+        drop(page_sender);
+
+        // The parse workers only fetch the feeds of the pages
+        let feed_fetcher = match &shared_fetcher {
+            Some(fetcher) => fetcher.clone(),
+            None => state.client_options.build_fetcher()?,
+        };
+        let page_receiver = Arc::new(Mutex::new(page_receiver));
+        for _ in 0..self.parse_workers.max(1) {
+            let crawler_state = state.clone();
+            let fetcher = feed_fetcher.clone();
+            let pages = page_receiver.clone();
+            tasks.spawn(async move { parse_pages(crawler_state, fetcher, pages).await });
+        }
+
+        while let Some(result) = tasks.join_next().await {
+            if let Err(e) = result {
+                error!("Error: {:?}", e);
+            }
+        }
+        // FINISHED CRAWLING
+        for task in reload_task.into_iter().chain(status_task) {
+            task.abort();
+            // Only the tasks that ended on their own have a result
+            if let Ok(Err(e)) = task.await {
+                error!("Error: {:?}", e);
+            }
+        }
+
+        if let Err(e) = state.robots.cache().save().await {
+            error!("Could not save the robots cache: {:?}", e);
+        }
+
+        let mut domain_infos = state.politeness.take_domain_infos().await;
+        if state.probe_well_known {
+            let fetcher = match shared_fetcher {
+                Some(fetcher) => fetcher,
+                None => state.client_options.build_fetcher()?,
+            };
+            let origins = domain_origins(&*state.link_graph.read().await, &domain_infos);
+            let robots = state.respect_robots.then_some(state.robots.as_ref());
+            for (domain, files) in probe_domains(fetcher.as_ref(), origins, robots).await {
+                domain_infos.entry(domain).or_default().well_known = files;
+            }
+        }
+        if let Some(domain_budget) = &state.domain_budget {
+            for (domain, estimate) in domain_budget.estimates() {
+                domain_infos.entry(domain).or_default().warm_up = Some(estimate);
+            }
+        }
+        state.link_graph.write().await.set_domains(domain_infos);
+
+        let state =
+            Arc::try_unwrap(state).map_err(|_| anyhow!("the crawler state is still in use"))?;
+        let mut link_graph = state.link_graph.into_inner();
+        if let Some(previous) = state.previous {
+            previous.merge_into(&mut link_graph)?;
+        }
+        Ok((link_graph, state.link_queue.into_inner()))
+    }
+}
+
+/// The origin (e.g. `https://example.com/`) of each of the
+/// `domains`, taken from the first of its pages in the graph
+fn domain_origins(
+    link_graph: &LinkGraph,
+    domains: &HashMap<String, DomainInfo>,
+) -> Vec<(String, Url)> {
+    let mut origins: BTreeMap<String, Url> = Default::default();
+    for (_, link) in link_graph {
+        let Ok(mut url) = Url::parse(&link.url) else {
+            continue;
+        };
+        let Some(domain) = url.host_str().map(String::from) else {
+            continue;
+        };
+        if domains.contains_key(&domain) && !origins.contains_key(&domain) {
+            url.set_path("/");
+            url.set_query(None);
+            url.set_fragment(None);
+            origins.insert(domain, url);
+        }
+    }
+
+    origins.into_iter().collect()
+}
+
+async fn output_status(crawler_state: CrawlerStateRef) -> Result<()> {
+    let progress_bar = logger::progress_bar::ProgressBar::new(crawler_state.max_links as u64);
+    progress_bar.message("Finding links");
+    'output: loop {
+        let link_queue = crawler_state.link_queue.read().await;
+        let link_graph = crawler_state.link_graph.read().await;
+
+        if link_graph.len() > crawler_state.max_links {
+            // Show the links
+            info!("All links found: {:#?}", link_graph);
+            break 'output;
+        }
+
+        progress_bar.set_step(link_graph.len() as u64);
+
+        drop(link_queue);
+        drop(link_graph);
+
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    Ok(())
+}
+
+/// Shows the dashboard until the crawl is over, or stops
+/// the crawl if the user asks to quit from it, keeping
+/// what was crawled so far
+async fn output_dashboard(crawler_state: CrawlerStateRef, mut dashboard: Dashboard) -> Result<()> {
+    loop {
+        if dashboard.quit_requested()? {
+            warn!("Crawl stopped from the dashboard");
+            crawler_state.stop();
+            return Ok(());
+        }
+
+        let snapshot = CrawlSnapshot {
+            crawled: crawler_state.link_graph.read().await.len(),
+            max_links: crawler_state.max_links,
+            queued: crawler_state.link_queue.read().await.len(),
+            failed: crawler_state.failed_pages.load(Ordering::Relaxed),
+            workers: crawler_state.fetching.lock().unwrap().clone(),
+        };
+        dashboard.draw(&snapshot)?;
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// A page on its way from a fetch worker to a parse worker
+struct FetchedLink {
+    link_path: LinkPath,
+    provenance: Provenance,
+    domain: String,
+    page: Result<FetchedPage>,
+}
+
+/// Takes links off the queue and downloads their pages, handing
+/// them to the parse workers through `pages`
+async fn fetch_pages(
+    crawler_state: CrawlerStateRef,
+    worker: usize,
+    fetcher: Arc<dyn Fetcher>,
+    pages: mpsc::Sender<FetchedLink>,
+) -> Result<()> {
+    // Crawler loop
+    'crawler: loop {
+        let number_links_found = crawler_state.link_graph.read().await.len();
+        if number_links_found > crawler_state.max_links {
+            break 'crawler;
+        }
+        if crawler_state.over_byte_budget() {
+            info!("Byte budget used up, stopping the worker");
+            break 'crawler;
+        }
+
+        let Some(link_path) = crawler_state.pop_link().await else {
+            // The pages being parsed may still queue more links
+            if crawler_state.frontier_exhausted().await {
+                break 'crawler;
+            }
+            tokio::time::sleep(IDLE_WAIT).await;
+            continue;
+        };
+
+        let span = info_span!("fetch", url = %link_path.child);
+        crawler_state.fetching.lock().unwrap()[worker] = Some(link_path.child.clone());
+        let fetched = fetch_link(&crawler_state, fetcher.as_ref(), link_path)
+            .instrument(span)
+            .await;
+        crawler_state.fetching.lock().unwrap()[worker] = None;
+        match fetched {
+            Some(fetched) => {
+                if pages.send(fetched).await.is_err() {
+                    // All the parse workers are gone
+                    crawler_state.link_done();
+                    break 'crawler;
+                }
+            }
+            None => crawler_state.link_done(),
+        }
+    }
+
+    Ok(())
+}
+
+/// Downloads the page of `link_path`, `None` when the
+/// link isn't to be crawled after all
+async fn fetch_link(
+    crawler_state: &CrawlerState,
+    fetcher: &dyn Fetcher,
+    link_path: LinkPath,
+) -> Option<FetchedLink> {
+    let url = match Url::parse(&link_path.child) {
+        Ok(url) => url,
+        Err(e) => {
+            error!("Invalid link {}: {}", &link_path.child, e);
+            return None;
+        }
+    };
+
+    // The rules may have been reloaded since this link was queued
+    let scope = crawler_state.scope.read().await;
+    if !scope.in_scope(&link_path.child) {
+        info!("Link out of scope: {}", &link_path.child);
+        return None;
+    }
+    let provenance = Provenance {
+        parent: Some(link_path.parent.clone()).filter(|parent| !parent.is_empty()),
+        discovery: link_path.discovery,
+        scope_rule: scope.matching_rule(&link_path.child).map(String::from),
+    };
+    drop(scope);
+
+    if crawler_state.respect_robots {
+        match crawler_state.robots.rules_for(fetcher, &url).await {
+            Ok(rules) if !rules.allowed(&url) => {
+                info!("Link disallowed by robots.txt: {}", &link_path.child);
+                return None;
+            }
+            Ok(_) => {}
+            Err(e) => error!("Could not get robots.txt for {}: {}", &link_path.child, e),
+        }
+    }
+
+    crawler_state.rate_limiter.wait().await;
+    let domain = url.host_str().unwrap_or_default().to_string();
+    crawler_state.politeness.wait(&domain).await;
+    let host_permit = match &crawler_state.host_limiter {
+        Some(host_limiter) => Some(host_limiter.acquire(&domain).await),
+        None => None,
+    };
+
+    let headers = match &crawler_state.previous {
+        Some(previous) => previous.validators(&link_path.child),
+        None => Default::default(),
+    };
+    let page = fetch_page(url, fetcher, headers).await;
+    drop(host_permit);
+
+    if let Ok(page) = &page {
+        crawler_state
+            .downloaded_bytes
+            .fetch_add(page.response.body.len() as u64, Ordering::Relaxed);
+
+        // Carried over from the previous crawl instead
+        if let Some(previous) = &crawler_state.previous {
+            if previous.is_unchanged(&link_path.child, &page.response) {
+                info!("Unchanged since the previous crawl: {}", &link_path.child);
+                previous.mark_unchanged(&link_path.child);
+                return None;
+            }
+        }
+    }
+
+    Some(FetchedLink {
+        link_path,
+        provenance,
+        domain,
+        page,
+    })
+}
+
+/// Scrapes the pages downloaded by the fetch workers, adding
+/// them to the graph and queueing the links they contain
+async fn parse_pages(
+    crawler_state: CrawlerStateRef,
+    fetcher: Arc<dyn Fetcher>,
+    pages: Arc<Mutex<mpsc::Receiver<FetchedLink>>>,
+) -> Result<()> {
+    loop {
+        // Every fetch worker is done once this is `None`
+        let Some(fetched) = pages.lock().await.recv().await else {
+            break;
+        };
+        let span = info_span!("scrape", url = %fetched.link_path.child);
+        let result = record_page(&crawler_state, fetcher.as_ref(), fetched)
+            .instrument(span)
+            .await;
+        crawler_state.link_done();
+        result?;
+    }
+
+    Ok(())
+}
+
+/// Scrapes a `fetched` page, then adds it to the graph,
+/// the queue, the sinks and the results
+async fn record_page(
+    crawler_state: &CrawlerStateRef,
+    fetcher: &dyn Fetcher,
+    fetched: FetchedLink,
+) -> Result<()> {
+    let FetchedLink {
+        link_path:
+            LinkPath {
+                parent,
+                child,
+                external_depth,
+                ..
+            },
+        provenance,
+        domain,
+        page,
+    } = fetched;
+
+    let scraped = match page {
+        // Parsed on the blocking pool, so a huge page doesn't
+        // hold up the tasks waiting on the network
+        Ok(page) => {
+            let state = crawler_state.clone();
+            tokio::task::spawn_blocking(move || parse_page(&page, &state.scrape_config))
+                .await
+                .unwrap_or_else(|e| Err(e.into()))
+        }
+        Err(e) => Err(e),
+    };
+
+    let failed = scraped.is_err();
+    let scrape_output = match scraped {
+        Ok(mut scrape_output) => {
+            follow_feeds(&mut scrape_output, fetcher, &crawler_state.scrape_config).await;
+            for observer in crawler_state.observers.iter() {
+                observer.on_page_scraped(&child, &mut scrape_output);
+            }
+            scrape_output
+        }
+        // Log the errors
+        Err(e) => {
+            error!("Could not find links: {:#}", e);
+            crawler_state.failed_pages.fetch_add(1, Ordering::Relaxed);
+            for observer in crawler_state.observers.iter() {
+                observer.on_error(&child, &e);
+            }
+            let kind = classify_error(&e);
+            crawler_state.politeness.record_error(&domain, kind).await;
+            ScrapeOutput {
+                status: e.downcast_ref::<BadStatus>().map(|s| s.0.as_u16()),
+                error: Some(kind),
+                ..Default::default()
+            }
+        }
+    };
+
+    if let Some(server) = &scrape_output.server {
+        crawler_state
+            .politeness
+            .record_server(&domain, server)
+            .await;
+    }
+    if let Some(domain_budget) = &crawler_state.domain_budget {
+        domain_budget.record(
+            &domain,
+            scrape_output.timings.as_ref().map_or(0.0, |t| t.fetch_ms),
+            scrape_output
+                .body
+                .as_ref()
+                .map_or(0, |body| body.len() as u64),
+            failed,
+        );
+    }
+    if let Some(timings) = &scrape_output.timings {
+        crawler_state
+            .politeness
+            .record(
+                &domain,
+                &child,
+                timings.fetch_ms,
+                scrape_output.blocked.is_some(),
+            )
+            .await;
+    }
+
+    let scope = crawler_state.scope.read().await;
+    let mut link_queue = crawler_state.link_queue.write().await;
+    let mut link_graph = crawler_state.link_graph.write().await;
+    // Pages at the last external hop are recorded, not expanded
+    let expand = external_depth == 0
+        || crawler_state
+            .external_depth
+            .is_none_or(|max| external_depth < max);
+    // A page redirecting elsewhere is a link to where it redirects
+    let links = scrape_output
+        .links
+        .iter()
+        .map(|link| {
+            if scrape_output.feed_links.contains(link) {
+                (link, Discovery::Feed)
+            } else {
+                (link, Discovery::Anchor)
+            }
+        })
+        .chain(
+            scrape_output
+                .redirect
+                .iter()
+                .map(|redirect| (redirect, Discovery::Redirect)),
+        );
+    for (link, discovery) in links.filter(|_| expand) {
+        if !scope.in_scope(link) {
+            continue;
+        }
+
+        let link_depth = match Url::parse(link) {
+            Ok(url) if crawler_state.is_internal(&url) => 0,
+            _ => external_depth + 1,
+        };
+        if crawler_state
+            .external_depth
+            .is_some_and(|max| link_depth > max)
+        {
+            continue;
+        }
+
+        // The pages of the previous crawl are all queued already
+        let previous = crawler_state
+            .previous
+            .as_ref()
+            .is_some_and(|previous| previous.contains(link));
+        if !link_graph.link_visited(link) && !previous {
+            // Check if the link already visited
+            for observer in crawler_state.observers.iter() {
+                observer.on_link_discovered(link, &child);
+            }
+            if let Some(domain_budget) = &crawler_state.domain_budget {
+                domain_budget.discovered(link);
+            }
+            link_queue.push_back(LinkPath {
+                parent: child.clone(),
+                child: link.clone(),
+                external_depth: link_depth,
+                discovery,
+            })
+        } else {
+            info!("Link already found: {}", &link);
+        }
+    }
+
+    if let Err(e) = link_graph.update(&child, &parent, &scrape_output) {
+        error!("could not update the link graph with {:#?}", e);
+    }
+    if let Err(e) = link_graph.set_provenance(&child, provenance) {
+        error!("could not record why {} was crawled: {:#?}", child, e);
+    }
+    drop(link_graph);
+    drop(link_queue);
+    drop(scope);
+
+    let result = CrawlResult {
+        url: child,
+        parent,
+        page: scrape_output,
+    };
+    if !crawler_state.sinks.is_empty() {
+        crawler_state.sinks.on_page(&result).await?;
+        for image in result.page.images.iter() {
+            crawler_state.sinks.on_image(&result.url, image).await?;
+        }
+    }
+    if let Some(results) = &crawler_state.results {
+        // Nobody is listening anymore if this fails
+        let _ = results.send(result).await;
+    }
+
+    Ok(())
+}
+
+/// Reloads the scope rules from `scope_file` every time the
+/// process receives SIGHUP, so a long running crawl can have
+/// its rules and rate limit changed without restarting it
+#[cfg(unix)]
+async fn reload_scope_on_signal(crawler_state: CrawlerStateRef, scope_file: String) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = signal(SignalKind::hangup())?;
+    while hangups.recv().await.is_some() {
+        match ScopeRules::from_file(&scope_file) {
+            Ok(scope) => {
+                crawler_state
+                    .rate_limiter
+                    .set_interval(scope.request_interval)
+                    .await;
+                *crawler_state.scope.write().await = scope;
+                info!("Reloaded scope rules from {}", scope_file);
+            }
+            Err(e) => error!("Could not reload scope rules: {:?}", e),
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn reload_scope_on_signal(
+    _crawler_state: CrawlerStateRef,
+    _scope_file: String,
+) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tags_are_kept_when_resuming() {
+        let mut link_graph = LinkGraph::default();
+        link_graph.set_tags(BTreeMap::from([("project".into(), "blog".into())]));
+
+        let link_graph = Crawler::builder()
+            .tag("ticket", "42")
+            .resume_from(link_graph, Default::default())
+            .run()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            link_graph.tags(),
+            &BTreeMap::from([
+                ("project".to_string(), "blog".to_string()),
+                ("ticket".to_string(), "42".to_string()),
+            ])
+        );
+    }
+}
@@ -1,44 +1,292 @@
 use anyhow::{anyhow, bail, Result};
-use log2::*;
-use reqwest::{Client, StatusCode};
-use scraper::{Html, Selector};
-use std::{collections::VecDeque, sync::Arc, time::Duration};
-use tokio::sync::RwLock;
+use rand::Rng;
+use regex::Regex;
+use reqwest::{header::HeaderMap, StatusCode};
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    fmt,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, LazyLock,
+    },
+    time::Instant,
+};
+use tokio::sync::{mpsc::Sender, RwLock};
+use tracing::{error, info, warn};
+use unicode_normalization::UnicodeNormalization;
 use url::Url;
 
-use crate::model::Image;
+use crate::block_detection::detect_block;
+use crate::client::ClientOptions;
+use crate::domain_budget::DomainBudget;
+use crate::duplicates;
+use crate::feed::parse_feed;
+use crate::fetcher::{FetchResponse, Fetcher};
+use crate::host_limiter::HostLimiter;
+use crate::incremental::PreviousCrawl;
 use crate::model::LinkGraph;
-
-const LINK_REQUEST_TIMEOUT_S: u64 = 2;
+use crate::model::{
+    CrawlResult, Discovery, Edge, ErrorKind, Headings, Image, Media, MediaKind, PageTimings,
+    ScrapeOutput, SearchHits, SecurityHeaders, ServerFingerprint, SocialMeta,
+};
+use crate::observer::CrawlObserver;
+use crate::parsers::ParserRegistry;
+use crate::politeness::DomainPoliteness;
+use crate::rate_limiter::RateLimiter;
+use crate::robots::Robots;
+use crate::scope::ScopeRules;
+use crate::sink::FanOut;
+use crate::wasm_plugin::WasmExtractor;
 
 /// Enum to represent data to scrape from
 /// each link
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ScrapeOption {
-    /// Find any image link with the given
-    /// extensions. E.g. `Image("jpg")`
+    /// The images of the page, and their alt texts
     Images,
-    Titles, // TODO Add support for page titles
+    /// The `<h1>`, `<h2>` and `<title>` texts of
+    /// the page, and how many `<h1>`s it has
+    Titles,
+    /// Fetch the RSS/Atom feeds the page advertises
+    /// and add their entries to the page's links
+    Feeds,
+    /// The `description` and `keywords` meta tags
+    Meta,
+    /// The Open Graph (`og:*`) and Twitter Card
+    /// (`twitter:*`) meta tags
+    OpenGraph,
+    /// The JSON-LD (`application/ld+json`) blocks
+    StructuredData,
+    /// The readable text of the page, without the
+    /// scripts, styles and navigation
+    Text,
+    /// The language the page declares in `<html lang>`
+    /// and the one detected from its text
+    Language,
+    /// The iframes, videos and audio embedded in the page
+    Media,
+    /// The background images set in the `style` attributes
+    /// and `<style>` blocks, added to the images
+    CssImages,
+    /// A SimHash fingerprint of the readable text, to
+    /// find the near-duplicate pages
+    Simhash,
+    /// The CSP, HSTS, X-Frame-Options and
+    /// X-Content-Type-Options response headers
+    SecurityHeaders,
+}
+
+impl ScrapeOption {
+    /// Every option, in the order they are documented
+    pub const ALL: [ScrapeOption; 12] = [
+        ScrapeOption::Images,
+        ScrapeOption::Titles,
+        ScrapeOption::Feeds,
+        ScrapeOption::Meta,
+        ScrapeOption::OpenGraph,
+        ScrapeOption::StructuredData,
+        ScrapeOption::Text,
+        ScrapeOption::Language,
+        ScrapeOption::Media,
+        ScrapeOption::CssImages,
+        ScrapeOption::Simhash,
+        ScrapeOption::SecurityHeaders,
+    ];
+}
+
+/// How the pages are scraped
+pub struct ScrapeConfig {
+    /// what to scrape on top of the links
+    pub options: Vec<ScrapeOption>,
+    /// the maximum number of characters kept in each
+    /// scraped text field, see `--max-field-length`
+    pub max_field_length: usize,
+    /// how each content type is parsed
+    pub parsers: ParserRegistry,
+    /// (name, selector) of the custom fields scraped
+    /// from each HTML page, see `--extract`
+    pub custom_selectors: Vec<(String, Selector)>,
+    /// the WebAssembly extractors run on each page
+    pub extractors: Vec<Arc<WasmExtractor>>,
+    /// the terms looked for in the text of each
+    /// HTML page, see `--search`
+    pub searches: Vec<Regex>,
 }
 
+/// How many random links are looked at for one on a
+/// host with a free slot, see `CrawlerState::pop_link`
+const SHUFFLE_TRIES: usize = 8;
+
 /// TODO : Rename this to somthing better. This
 /// should hold the <parent link, link to visit>
 /// tuple
-#[derive(Default)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct LinkPath {
     pub parent: String,
     pub child: String,
-}
-
-pub struct ScrapeOutput {
-    pub links: Vec<String>,
-    pub images: Vec<Image>,
-    pub titles: Vec<String>,
+    /// how many hops away from the seed domains
+    /// the child is, 0 for the seed domains
+    #[serde(default)]
+    pub external_depth: u32,
+    /// how the child was found
+    #[serde(default)]
+    pub discovery: Discovery,
 }
 
 pub struct CrawlerState {
     pub link_queue: RwLock<VecDeque<LinkPath>>,
     pub link_graph: RwLock<LinkGraph>,
     pub max_links: usize,
+    pub client_options: ClientOptions,
+    pub scope: RwLock<ScopeRules>,
+    pub rate_limiter: Arc<RateLimiter>,
+    pub robots: Arc<Robots>,
+    pub respect_robots: bool,
+    pub scrape_config: ScrapeConfig,
+    pub politeness: Arc<DomainPoliteness>,
+    /// where the scraped pages are published, if
+    /// someone is listening (see `Crawler::stream`)
+    pub results: Option<Sender<CrawlResult>>,
+    /// the callbacks run for every page
+    pub observers: Vec<Arc<dyn CrawlObserver>>,
+    /// where every page is written to
+    pub sinks: FanOut,
+    /// the domains of the seeds, the rest are external
+    pub internal_domains: HashSet<String>,
+    /// how many hops onto external domains are
+    /// crawled, `None` for no limit
+    pub external_depth: Option<u32>,
+    /// the page bytes after which no more pages are
+    /// fetched, `None` for no limit
+    pub max_total_bytes: Option<u64>,
+    /// the page bytes downloaded so far
+    pub downloaded_bytes: AtomicU64,
+    /// caps the requests made to each host at the same time
+    pub host_limiter: Option<Arc<HostLimiter>>,
+    /// whether the well-known files of each domain
+    /// crawled are fetched once the crawl is done
+    pub probe_well_known: bool,
+    /// whether the links are crawled in a random order
+    /// rather than the newest first
+    pub shuffle_frontier: bool,
+    /// the links taken off the queue whose pages
+    /// haven't made it to the graph yet
+    pub in_flight: AtomicUsize,
+    /// shares the links between the domains, if
+    /// they are sampled first (see `--warm-up`)
+    pub domain_budget: Option<DomainBudget>,
+    /// the crawl this one recrawls, if incremental
+    pub previous: Option<PreviousCrawl>,
+    /// the url each fetch worker is downloading, by worker
+    pub fetching: std::sync::Mutex<Vec<Option<String>>>,
+    /// the pages that couldn't be scraped so far
+    pub failed_pages: AtomicUsize,
+    /// set when the crawl is stopped early (see `stop`),
+    /// the links still queued are left in the queue
+    pub stopped: AtomicBool,
+}
+
+impl CrawlerState {
+    /// Whether `url` is on one of the seeds' domains
+    pub fn is_internal(&self, url: &Url) -> bool {
+        url.host_str()
+            .is_some_and(|host| self.internal_domains.contains(host))
+    }
+
+    /// Takes the next link to crawl off the queue: the newest one,
+    /// or a random one when shuffling, preferring the hosts with a
+    /// free slot so the workers don't all wait on the same host.
+    /// With a domain budget, the domains still being sampled and
+    /// then the ones under their budget come first.
+    /// The link counts as in flight until `link_done` is called.
+    pub async fn pop_link(&self) -> Option<LinkPath> {
+        let mut link_queue = self.link_queue.write().await;
+        if link_queue.is_empty() || self.is_stopped() {
+            return None;
+        }
+        // Counted while the queue is locked, so no worker sees
+        // an empty queue with nothing in flight mid-crawl
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+
+        let Some(domain_budget) = &self.domain_budget else {
+            return self.take_link(&mut link_queue).await;
+        };
+        let busy_hosts = match &self.host_limiter {
+            Some(host_limiter) => host_limiter.busy_hosts().await,
+            None => Default::default(),
+        };
+        let picked = domain_budget.pick(&mut link_queue, &busy_hosts, self.shuffle_frontier);
+        if picked.is_some() {
+            return picked;
+        }
+
+        let link = self.take_link(&mut link_queue).await;
+        if let Some(link) = &link {
+            domain_budget.claim(&link.child);
+        }
+        link
+    }
+
+    /// The newest link of `link_queue`, or a random one when
+    /// shuffling, see `pop_link`
+    async fn take_link(&self, link_queue: &mut VecDeque<LinkPath>) -> Option<LinkPath> {
+        if !self.shuffle_frontier {
+            return link_queue.pop_back();
+        }
+
+        // Not kept across the awaits, as it isn't Send
+        let random_index = |len| rand::thread_rng().gen_range(0..len);
+        let mut index = random_index(link_queue.len());
+        if let Some(host_limiter) = &self.host_limiter {
+            for _ in 0..SHUFFLE_TRIES {
+                let host = Url::parse(&link_queue[index].child)
+                    .ok()
+                    .and_then(|url| url.host_str().map(String::from))
+                    .unwrap_or_default();
+                if host_limiter.has_free_slot(&host).await {
+                    break;
+                }
+                index = random_index(link_queue.len());
+            }
+        }
+
+        link_queue.swap_remove_back(index)
+    }
+
+    /// Marks a link from `pop_link` as done, once the links
+    /// found in its page have been queued
+    pub fn link_done(&self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Whether there is nothing queued nor in flight, or the
+    /// crawl was stopped, so the crawl is over
+    pub async fn frontier_exhausted(&self) -> bool {
+        if self.is_stopped() {
+            return true;
+        }
+        let link_queue = self.link_queue.read().await;
+        link_queue.is_empty() && self.in_flight.load(Ordering::SeqCst) == 0
+    }
+
+    /// Stops the crawl: the workers finish the pages they have
+    /// and no more links are taken off the queue
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Whether the pages downloaded so far used up the byte budget
+    pub fn over_byte_budget(&self) -> bool {
+        self.max_total_bytes
+            .is_some_and(|max| self.downloaded_bytes.load(Ordering::Relaxed) >= max)
+    }
 }
 
 pub type CrawlerStateRef = Arc<CrawlerState>;
@@ -46,7 +294,7 @@ pub type CrawlerStateRef = Arc<CrawlerState>;
 /// This will turn relative urls into
 /// full urls.
 /// E.g. get_url("/services/", "https://google.com/") -> "https://google.com/service/"
-fn get_url(path: &str, root_url: Url) -> Result<Url> {
+pub(crate) fn get_url(path: &str, root_url: Url) -> Result<Url> {
     if let Ok(url) = Url::parse(path) {
         return Ok(url);
     }
@@ -57,41 +305,280 @@ fn get_url(path: &str, root_url: Url) -> Result<Url> {
         .ok_or(anyhow!("could not join relative path"))
 }
 
+/// Parses a declared image dimension, e.g. "640" or "640px"
+fn parse_dimension(value: &str) -> Option<u32> {
+    value.trim().trim_end_matches("px").parse().ok()
+}
+
+/// How big an image candidate in a `srcset` is
+#[derive(Clone, Copy)]
+enum Descriptor {
+    /// a width in pixels, e.g. `640w`
+    Width(f64),
+    /// a pixel density, e.g. `2x`
+    Density(f64),
+}
+
+/// Parses a `srcset` into its (url, descriptor) candidates. The
+/// urls can have commas in them, so they end at a whitespace
+fn parse_srcset(srcset: &str) -> Vec<(&str, Descriptor)> {
+    let mut candidates = Vec::new();
+    let mut rest = srcset;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() {
+            return candidates;
+        }
+
+        let url_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let url = &rest[..url_end];
+        rest = &rest[url_end..];
+
+        // Without a space, a trailing comma ends the candidate
+        let (url, descriptor) = match url.strip_suffix(',') {
+            Some(url) => (url.trim_end_matches(','), ""),
+            None => {
+                let descriptor_end = rest.find(',').unwrap_or(rest.len());
+                let descriptor = &rest[..descriptor_end];
+                rest = &rest[descriptor_end..];
+                (url, descriptor.trim())
+            }
+        };
+
+        let descriptor = if let Some(width) = descriptor.strip_suffix('w') {
+            width.parse().ok().map(Descriptor::Width)
+        } else if let Some(density) = descriptor.strip_suffix('x') {
+            density.parse().ok().map(Descriptor::Density)
+        } else {
+            Some(Descriptor::Density(1.0))
+        };
+
+        if let Some(descriptor) = descriptor {
+            candidates.push((url, descriptor));
+        }
+    }
+}
+
+/// Orders the candidates by resolution. The densities can only be
+/// turned into widths when the image declares its width, otherwise
+/// they rank below the candidates with a known width
+fn resolution(descriptor: Descriptor, width: Option<u32>) -> (bool, f64) {
+    match (descriptor, width) {
+        (Descriptor::Width(w), _) => (true, w),
+        (Descriptor::Density(x), Some(width)) => (true, x * width as f64),
+        (Descriptor::Density(x), None) => (false, x),
+    }
+}
+
+/// Every url `img` can be loaded from: its `src`, the lazy-loading
+/// attributes, its `srcset` and the `<source>`s of its `<picture>`
+fn image_candidates(img: ElementRef<'_>) -> Vec<(&str, Descriptor)> {
+    let element = img.value();
+    let mut candidates: Vec<(&str, Descriptor)> = ["src", "data-src", "data-lazy-src"]
+        .into_iter()
+        .filter_map(|name| element.attr(name))
+        .map(|src| (src.trim(), Descriptor::Density(1.0)))
+        .collect();
+
+    for name in ["srcset", "data-srcset"] {
+        candidates.extend(element.attr(name).map(parse_srcset).unwrap_or_default());
+    }
+
+    let picture = img
+        .parent()
+        .and_then(ElementRef::wrap)
+        .filter(|parent| parent.value().name() == "picture");
+    if let Some(picture) = picture {
+        let source_selector = Selector::parse("source").unwrap();
+        for source in picture.select(&source_selector) {
+            for name in ["srcset", "data-srcset"] {
+                candidates.extend(
+                    source
+                        .value()
+                        .attr(name)
+                        .map(parse_srcset)
+                        .unwrap_or_default(),
+                );
+            }
+        }
+    }
+
+    // Lazy-loaded images often have a placeholder in `src`
+    candidates.retain(|(url, _)| !url.is_empty() && !url.starts_with("data:"));
+    candidates
+}
+
 // TODO : we're gonna need to know the ID of the URL
 fn get_images(html_dom: &Html, root_url: &Url) -> Vec<Image> {
-    let img_selector = Selector::parse("img[src]").unwrap();
-
-    let image_links = html_dom
-        .select(&img_selector)
-        .filter(|e| e.value().attr("src").is_some())
-        .map(|e| {
-            (
-                e.value().attr("src").unwrap(),
-                e.value().attr("alt").unwrap_or(""),
-            )
-        })
-        .map(|(link, alt)| Image {
-            link: link.to_string(),
-            alt: alt.to_string(),
-        });
+    let img_selector = Selector::parse("img").unwrap();
 
     let mut result: Vec<Image> = Default::default();
-    for image in image_links {
-        // TODO remove the clone by taking a reference
-        if let Ok(absolute_url) = get_url(&image.link, root_url.clone()) {
-            result.push(Image {
-                link: absolute_url.to_string(),
-                ..image
-            });
+    for img in html_dom.select(&img_selector) {
+        let dimension = |name| img.value().attr(name).and_then(parse_dimension);
+        let width = dimension("width");
+
+        let mut variants: Vec<(String, Descriptor)> = Default::default();
+        for (link, descriptor) in image_candidates(img) {
+            // TODO remove the clone by taking a reference
+            let Ok(absolute_url) = get_url(link, root_url.clone()) else {
+                error!("failed to join url"); // TODO : better image
+                continue;
+            };
+
+            let absolute_url = absolute_url.to_string();
+            if !variants.iter().any(|(url, _)| *url == absolute_url) {
+                variants.push((absolute_url, descriptor));
+            }
+        }
+
+        let Some(best) = variants
+            .iter()
+            .max_by(|(_, a), (_, b)| {
+                let (a, b) = (resolution(*a, width), resolution(*b, width));
+                a.0.cmp(&b.0).then(a.1.total_cmp(&b.1))
+            })
+            .map(|(url, _)| url.clone())
+        else {
             continue;
+        };
+
+        result.push(Image {
+            link: best,
+            alt: img.value().attr("alt").map(String::from),
+            width,
+            height: dimension("height"),
+            variants: match variants.len() {
+                1 => Default::default(),
+                _ => variants.into_iter().map(|(url, _)| url).collect(),
+            },
+            ..Default::default()
+        });
+    }
+
+    result
+}
+
+/// The value of the `background` and `background-image` declarations
+static CSS_BACKGROUND: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)background(?:-image)?\s*:([^;}]*)").unwrap());
+/// A `url(...)`, with or without quotes
+static CSS_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)url\(\s*(?:"([^"]*)"|'([^']*)'|([^)"'\s]*))\s*\)"#).unwrap()
+});
+
+/// The background images set in the inline styles and `<style>`
+/// blocks of the page, skipping the ones already in `images`
+fn get_css_images(html_dom: &Html, root_url: &Url, images: &[Image]) -> Vec<Image> {
+    let style_attr_selector = Selector::parse("[style]").unwrap();
+    let style_block_selector = Selector::parse("style").unwrap();
+
+    let styles = html_dom
+        .select(&style_attr_selector)
+        .filter_map(|e| e.value().attr("style").map(String::from))
+        .chain(
+            html_dom
+                .select(&style_block_selector)
+                .map(|e| e.text().collect::<String>()),
+        );
+
+    let mut seen: HashSet<String> = images.iter().map(|i| i.link.clone()).collect();
+    let mut result: Vec<Image> = Default::default();
+    for style in styles {
+        let urls = CSS_BACKGROUND
+            .captures_iter(&style)
+            .flat_map(|declaration| {
+                CSS_URL
+                    .captures_iter(declaration.get(1).unwrap().as_str())
+                    .filter_map(|c| c.get(1).or(c.get(2)).or(c.get(3)))
+                    .map(|m| m.as_str().trim().to_string())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|url| !url.is_empty() && !url.starts_with("data:"));
+
+        for url in urls {
+            let Ok(absolute_url) = get_url(&url, root_url.clone()) else {
+                error!("failed to join background image url {}", url);
+                continue;
+            };
+
+            if seen.insert(absolute_url.to_string()) {
+                result.push(Image {
+                    link: absolute_url.to_string(),
+                    background: true,
+                    ..Default::default()
+                });
+            }
         }
+    }
 
-        error!("failed to join url"); // TODO : better image
+    result
+}
+
+/// The iframes, videos and audio embedded in the page, with
+/// the `src` of either the element itself or its `<source>`s
+fn get_media(html_dom: &Html, root_url: &Url) -> Vec<Media> {
+    let kinds = [
+        (MediaKind::Iframe, "iframe[src]"),
+        (MediaKind::Video, "video[src], video source[src]"),
+        (MediaKind::Audio, "audio[src], audio source[src]"),
+    ];
+
+    let mut seen: HashSet<Media> = Default::default();
+    let mut result: Vec<Media> = Default::default();
+    for (kind, selector) in kinds {
+        let media_selector = Selector::parse(selector).unwrap();
+        for src in html_dom
+            .select(&media_selector)
+            .filter_map(|e| e.value().attr("src"))
+        {
+            let Ok(absolute_url) = get_url(src.trim(), root_url.clone()) else {
+                error!("failed to join media url {}", src);
+                continue;
+            };
+
+            let media = Media {
+                kind,
+                link: absolute_url.to_string(),
+            };
+            if seen.insert(media.clone()) {
+                result.push(media);
+            }
+        }
     }
 
     result
 }
 
+/// The links of the page, with their anchor text and `rel`
+fn get_edges(html_dom: &Html, page_url: &Url) -> Vec<Edge> {
+    let link_selector = Selector::parse("a").unwrap();
+    let img_selector = Selector::parse("img[alt]").unwrap();
+
+    html_dom
+        .select(&link_selector)
+        .filter_map(|e| {
+            let to = get_url(e.value().attr("href")?, page_url.clone()).ok()?;
+
+            let mut anchor_text = clean_text(&e.text().collect::<String>());
+            if anchor_text.is_empty() {
+                // Image links are described by their image
+                if let Some(alt) = e.select(&img_selector).find_map(|i| i.value().attr("alt")) {
+                    anchor_text = clean_text(alt);
+                }
+            }
+            Some(Edge {
+                to: to.to_string(),
+                anchor_text,
+                rel: e
+                    .value()
+                    .attr("rel")
+                    .map(|rel| rel.split_whitespace().map(str::to_lowercase).collect())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
 /// This function will scrape all the titles from
 /// the given page's DOM -> title tags, h1, and h2 tags
 fn get_titles(html_dom: &Html) -> Vec<String> {
@@ -110,82 +597,865 @@ fn get_titles(html_dom: &Html) -> Vec<String> {
     titles
 }
 
-/// Given a `url` and a `client`, it will parse the
+/// The `<title>` and the number of `<h1>`s of the page
+fn get_headings(html_dom: &Html) -> Headings {
+    let title_selector = Selector::parse("title").unwrap();
+    let h1_selector = Selector::parse("h1").unwrap();
+
+    Headings {
+        title: html_dom
+            .select(&title_selector)
+            .map(|e| e.text().collect::<String>())
+            .find(|title| !title.trim().is_empty()),
+        h1: html_dom.select(&h1_selector).count() as u32,
+    }
+}
+
+/// The content of the first `<meta name="...">` tag
+/// called `name` that isn't blank
+fn get_meta(html_dom: &Html, name: &str) -> Option<String> {
+    let meta_selector = Selector::parse("meta[name][content]").unwrap();
+
+    html_dom
+        .select(&meta_selector)
+        .filter(|e| {
+            e.value()
+                .attr("name")
+                .is_some_and(|n| n.trim().eq_ignore_ascii_case(name))
+        })
+        .filter_map(|e| e.value().attr("content"))
+        .map(|content| content.trim().to_string())
+        .find(|content| !content.is_empty())
+}
+
+/// The `og:*` and `twitter:*` meta tags, which sites
+/// put in either the `property` or the `name` attribute
+fn get_social_meta(html_dom: &Html) -> SocialMeta {
+    let meta_selector = Selector::parse("meta[content]").unwrap();
+
+    let mut social = SocialMeta::default();
+    for element in html_dom.select(&meta_selector) {
+        let element = element.value();
+        let (Some(property), Some(content)) = (
+            element.attr("property").or_else(|| element.attr("name")),
+            element.attr("content"),
+        ) else {
+            continue;
+        };
+
+        let property = property.trim().to_ascii_lowercase();
+        let (properties, name) = if let Some(name) = property.strip_prefix("og:") {
+            (&mut social.open_graph, name)
+        } else if let Some(name) = property.strip_prefix("twitter:") {
+            (&mut social.twitter, name)
+        } else {
+            continue;
+        };
+
+        // e.g. several og:image, the first one is the preview
+        properties
+            .entry(name.to_string())
+            .or_insert_with(|| content.trim().to_string());
+    }
+
+    social
+}
+
+/// The elements whose text isn't read by visitors
+const HIDDEN_TAGS: [&str; 7] = [
+    "head", "script", "style", "noscript", "template", "svg", "nav",
+];
+
+/// The elements starting a new line of text
+const BLOCK_TAGS: [&str; 22] = [
+    "p",
+    "div",
+    "br",
+    "li",
+    "dt",
+    "dd",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "tr",
+    "td",
+    "th",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "main",
+    "blockquote",
+    "pre",
+];
+
+/// The text a visitor would read on the page, a line per
+/// block (paragraph, heading, list item...) with the
+/// whitespace collapsed
+pub(crate) fn get_text(html_dom: &Html) -> String {
+    let mut text = String::new();
+    let mut new_line = false;
+
+    for node in html_dom.root_element().descendants() {
+        if let Some(element) = node.value().as_element() {
+            new_line |= BLOCK_TAGS.contains(&element.name());
+            continue;
+        }
+        let Some(node_text) = node.value().as_text() else {
+            continue;
+        };
+
+        let hidden = node
+            .ancestors()
+            .filter_map(|a| a.value().as_element())
+            .any(|e| HIDDEN_TAGS.contains(&e.name()));
+        let words = node_text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if hidden || words.is_empty() {
+            continue;
+        }
+
+        if !text.is_empty() {
+            text.push(if new_line { '\n' } else { ' ' });
+        }
+        text.push_str(&words);
+        new_line = false;
+    }
+
+    text
+}
+
+/// The primary language tag of `<html lang>`, e.g. `en` for `en-GB`
+fn get_declared_language(html_dom: &Html) -> Option<String> {
+    html_dom
+        .root_element()
+        .value()
+        .attr("lang")
+        .and_then(|lang| lang.split(['-', '_']).next())
+        .map(|lang| lang.trim().to_ascii_lowercase())
+        .filter(|lang| !lang.is_empty())
+}
+
+/// The ISO 639-3 code (e.g. `eng`) of the language
+/// of `text`, when the detection is reliable
+fn detect_language(text: &str) -> Option<String> {
+    whatlang::detect(text)
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang().code().to_string())
+}
+
+/// How many matches of each search are kept with their context
+const MAX_SNIPPETS: usize = 3;
+/// The characters kept on each side of the matches
+const SNIPPET_CONTEXT: usize = 40;
+
+/// The hits of each of the `searches` (by pattern)
+/// in `text`, leaving out the ones not found
+fn search_text(text: &str, searches: &[Regex]) -> BTreeMap<String, SearchHits> {
+    searches
+        .iter()
+        .filter_map(|search| {
+            let mut hits = SearchHits::default();
+            for found in search.find_iter(text) {
+                hits.count += 1;
+                if hits.snippets.len() < MAX_SNIPPETS {
+                    hits.snippets
+                        .push(snippet(text, found.start(), found.end()));
+                }
+            }
+            (hits.count > 0).then(|| (search.as_str().to_string(), hits))
+        })
+        .collect()
+}
+
+/// `text[start..end]` with up to `SNIPPET_CONTEXT`
+/// characters around it, on a single line
+fn snippet(text: &str, start: usize, end: usize) -> String {
+    let from = text[..start]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_CONTEXT - 1)
+        .map_or(0, |(i, _)| i);
+    let to = text[end..]
+        .char_indices()
+        .nth(SNIPPET_CONTEXT)
+        .map_or(text.len(), |(i, _)| end + i);
+
+    text[from..to]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The parsed JSON-LD blocks of the page, e.g. its
+/// schema.org `Product` or `Article`
+fn get_structured_data(html_dom: &Html, page_url: &Url) -> Vec<Value> {
+    let script_selector = Selector::parse("script[type]").unwrap();
+
+    html_dom
+        .select(&script_selector)
+        .filter(|e| {
+            e.value()
+                .attr("type")
+                .is_some_and(|t| t.trim().eq_ignore_ascii_case("application/ld+json"))
+        })
+        .filter_map(|e| {
+            let json = e.text().collect::<String>();
+            serde_json::from_str(&json)
+                .map_err(|err| warn!("Invalid JSON-LD at {}: {}", page_url, err))
+                .ok()
+        })
+        .collect()
+}
+
+/// The comma separated keywords of a keywords meta tag
+fn split_keywords(keywords: &str) -> Vec<String> {
+    keywords
+        .split(',')
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Decodes the entities left in scraped `text` (e.g. the
+/// double escaped `&amp;amp;`), NFC normalizes it, and
+/// replaces the control characters and runs of whitespace
+/// with single spaces
+fn clean_text(text: &str) -> String {
+    let decoded = html_escape::decode_html_entities(text);
+    let normalized: String = decoded
+        .nfc()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+
+    normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Truncates `text` to at most `max_length` characters,
+/// returning whether anything was cut off
+fn truncate_field(text: &mut String, max_length: usize) -> bool {
+    match text.char_indices().nth(max_length) {
+        Some((index, _)) => {
+            text.truncate(index);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Gets the text of the elements matching each custom selector
+fn get_custom_fields(
+    html_dom: &Html,
+    selectors: &[(String, Selector)],
+) -> BTreeMap<String, Vec<String>> {
+    selectors
+        .iter()
+        .map(|(name, selector)| {
+            let values = html_dom
+                .select(selector)
+                .map(|e| e.text().collect::<String>().trim().to_string())
+                .collect();
+            (name.clone(), values)
+        })
+        .collect()
+}
+
+/// Finds the links and images loaded over plain HTTP
+/// when the page itself was served over HTTPS
+fn get_mixed_content(page_url: &Url, links: &[String], images: &[Image]) -> Vec<String> {
+    if page_url.scheme() != "https" {
+        return Default::default();
+    }
+
+    links
+        .iter()
+        .chain(images.iter().map(|i| &i.link))
+        .filter(|l| l.starts_with("http://"))
+        .cloned()
+        .collect()
+}
+
+/// The error for the pages answered with
+/// something else than a 200 status
+#[derive(Debug)]
+pub struct BadStatus(pub StatusCode);
+
+impl fmt::Display for BadStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "page returned invalid response ({})", self.0)
+    }
+}
+
+impl std::error::Error for BadStatus {}
+
+/// What kind of error `error` is, for the error counts of each domain
+pub fn classify_error(error: &anyhow::Error) -> ErrorKind {
+    if let Some(BadStatus(status)) = error.downcast_ref::<BadStatus>() {
+        return match status {
+            status if status.is_client_error() => ErrorKind::ClientError,
+            status if status.is_server_error() => ErrorKind::ServerError,
+            _ => ErrorKind::Other,
+        };
+    }
+
+    let Some(request_error) = error
+        .chain()
+        .find_map(|e| e.downcast_ref::<reqwest::Error>())
+    else {
+        return ErrorKind::Other;
+    };
+    if request_error.is_timeout() {
+        return ErrorKind::Timeout;
+    }
+    if request_error.is_connect() {
+        // reqwest doesn't tell the resolver errors apart
+        let mut source = std::error::Error::source(request_error);
+        while let Some(e) = source {
+            let message = e.to_string();
+            if message.contains("dns error") || message.contains("failed to lookup address") {
+                return ErrorKind::Dns;
+            }
+            source = e.source();
+        }
+        return ErrorKind::Connect;
+    }
+
+    ErrorKind::Other
+}
+
+/// The headers, besides `Server`, telling what runs a site
+const FINGERPRINT_HEADERS: [&str; 8] = [
+    "x-powered-by",
+    "x-generator",
+    "via",
+    "x-cache",
+    "x-served-by",
+    "cf-cache-status",
+    "alt-svc",
+    "strict-transport-security",
+];
+
+/// The HTTP version and server headers of `response`
+fn get_server_fingerprint(response: &FetchResponse) -> ServerFingerprint {
+    let header = |name: &str| {
+        response
+            .headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+
+    ServerFingerprint {
+        http_version: format!("{:?}", response.version),
+        server: header("server"),
+        headers: FINGERPRINT_HEADERS
+            .into_iter()
+            .filter_map(|name| Some((name.to_string(), header(name)?)))
+            .collect(),
+    }
+}
+
+/// A page downloaded by a fetch worker, waiting to be parsed
+pub(crate) struct FetchedPage {
+    /// the url that was asked for
+    pub url: Url,
+    pub response: FetchResponse,
+    pub fetch_ms: f64,
+}
+
+/// Downloads the page at `url`, sending the extra `headers`
+pub(crate) async fn fetch_page(
+    url: Url,
+    fetcher: &dyn Fetcher,
+    headers: HeaderMap,
+) -> Result<FetchedPage> {
+    let fetch_start = Instant::now();
+    let response = fetcher.fetch_page(&url, headers).await?;
+    let fetch_ms = fetch_start.elapsed().as_secs_f64() * 1000.0;
+
+    Ok(FetchedPage {
+        url,
+        response,
+        fetch_ms,
+    })
+}
+
+/// Scrapes a downloaded `page`. This is the CPU bound part
+/// of crawling a page, so it never touches the network.
+pub(crate) fn parse_page(page: &FetchedPage, config: &ScrapeConfig) -> Result<ScrapeOutput> {
+    let FetchedPage {
+        url,
+        response,
+        fetch_ms,
+    } = page;
+    let fetch_ms = *fetch_ms;
+    let html = response.text();
+    let server = get_server_fingerprint(response);
+
+    // Anti-bot pages often come with error statuses, but we
+    // still want to record that the page was blocked
+    if let Some(block) = detect_block(response.status, &response.headers, &html) {
+        warn!("Blocked by an anti-bot page ({:?}) at {}", block, url);
+        return Ok(ScrapeOutput {
+            blocked: Some(block),
+            server: Some(server),
+            body: Some(html),
+            timings: Some(PageTimings {
+                fetch_ms,
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+    }
+
+    if response.status != StatusCode::OK {
+        return Err(BadStatus(response.status).into());
+    }
+
+    let content_type = response
+        .headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok());
+    let mut scrape_output = match config.parsers.parser_for(content_type) {
+        // Relative links are relative to the page we were
+        // redirected to, not the one we asked for
+        Some(parser) => parser.parse(&html, &response.url, config)?,
+        None => {
+            info!("No parser for {:?} at {}", content_type, url);
+            Default::default()
+        }
+    };
+    for extractor in config.extractors.iter() {
+        match extractor.extract(response.url.as_str(), &html) {
+            Ok(fields) => scrape_output.extracted.extend(fields),
+            Err(e) => error!("Plugin {} failed on {}: {:#}", extractor.name, url, e),
+        }
+    }
+
+    if response.truncated {
+        scrape_output.truncated_fields.push(String::from("body"));
+    }
+    scrape_output.content_type = content_type.map(|c| c.to_string());
+    scrape_output.status = Some(response.status.as_u16());
+    scrape_output.server = Some(server);
+    if response.url != *url {
+        scrape_output.redirect = Some(response.url.to_string());
+    }
+    scrape_output.body = Some(html);
+    scrape_output
+        .timings
+        .get_or_insert_with(Default::default)
+        .fetch_ms = fetch_ms;
+    let header = |name: &str| {
+        response
+            .headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
+    };
+    scrape_output.last_modified = header("last-modified");
+    scrape_output.etag = header("etag");
+    if config.options.contains(&ScrapeOption::SecurityHeaders) {
+        scrape_output.security_headers = Some(SecurityHeaders {
+            content_security_policy: header("content-security-policy"),
+            strict_transport_security: header("strict-transport-security"),
+            x_frame_options: header("x-frame-options"),
+            x_content_type_options: header("x-content-type-options"),
+        });
+    }
+
+    Ok(scrape_output)
+}
+
+/// Adds the entries of the feeds found in the page
+/// to its links, when following the feeds
+pub(crate) async fn follow_feeds(
+    scrape_output: &mut ScrapeOutput,
+    fetcher: &dyn Fetcher,
+    config: &ScrapeConfig,
+) {
+    if !config.options.contains(&ScrapeOption::Feeds) {
+        return;
+    }
+
+    for feed in scrape_output.feeds.clone() {
+        match get_feed_links(&feed, fetcher).await {
+            Ok(feed_links) => {
+                scrape_output.links.extend(feed_links.iter().cloned());
+                scrape_output.feed_links.extend(feed_links);
+            }
+            Err(e) => error!("Could not read feed {}: {:#}", feed, e),
+        }
+    }
+}
+
+/// Given a `url` and a `fetcher`, it will parse the
 /// HTML in a DOM structure, and scrape all the information
 /// requested. It will find links by default.
 /// It may return an error if the request fails.
-async fn scrape_page_helper(
+pub(crate) async fn scrape_page_helper(
     url: Url,
-    client: &Client,
-    options: &[ScrapeOption],
+    fetcher: &dyn Fetcher,
+    config: &ScrapeConfig,
 ) -> Result<ScrapeOutput> {
-    let response = client
-        .get(url.clone())
-        .timeout(Duration::from_secs(LINK_REQUEST_TIMEOUT_S))
-        .send()
-        .await?;
+    let page = fetch_page(url, fetcher, HeaderMap::new()).await?;
+    let mut scrape_output = parse_page(&page, config)?;
+    follow_feeds(&mut scrape_output, fetcher, config).await;
+
+    Ok(scrape_output)
+}
 
-    if response.status() != StatusCode::OK {
-        bail!("page returned invalid response");
+/// Fetches the feed at `feed_url`, returning the
+/// absolute links of all its entries
+async fn get_feed_links(feed_url: &str, fetcher: &dyn Fetcher) -> Result<Vec<String>> {
+    let feed_url = Url::parse(feed_url)?;
+    let response = fetcher.fetch(&feed_url).await?;
+    if response.status != StatusCode::OK {
+        bail!("feed returned invalid response");
     }
 
-    let html = response.text().await?;
+    Ok(parse_feed(&response.text())?
+        .iter()
+        .filter_map(|l| get_url(l, response.url.clone()).ok())
+        .map(|url| url.to_string())
+        .collect())
+}
 
-    let html_dom = scraper::Html::parse_document(&html);
+/// Finds the RSS/Atom feeds advertised in the page head
+fn get_feeds(html_dom: &Html, page_url: &Url) -> Vec<String> {
+    let feed_selector = Selector::parse("link[rel~=alternate][href]").unwrap();
 
-    let link_selector = Selector::parse("a").unwrap();
-    let links: Vec<String> = html_dom
-        .select(&link_selector)
+    html_dom
+        .select(&feed_selector)
+        .filter(|e| {
+            matches!(
+                e.value().attr("type"),
+                Some("application/rss+xml") | Some("application/atom+xml")
+            )
+        })
         .filter_map(|e| e.value().attr("href"))
-        .map(|href| href.to_string())
-        .collect();
+        .filter_map(|href| get_url(href, page_url.clone()).ok())
+        .map(|url| url.to_string())
+        .collect()
+}
+
+/// The canonical url the page declares for itself
+fn get_canonical(html_dom: &Html, page_url: &Url) -> Option<String> {
+    let canonical_selector = Selector::parse("link[rel~=canonical][href]").unwrap();
+
+    html_dom
+        .select(&canonical_selector)
+        .filter_map(|e| e.value().attr("href"))
+        .find_map(|href| get_url(href, page_url.clone()).ok())
+        .map(|url| url.to_string())
+}
+
+/// Parses the `html` of the page at `page_url` and scrapes
+/// everything in it. This is kept synchronous as the DOM can't
+/// be held across awaits.
+pub(crate) fn extract_page(html: &str, page_url: &Url, config: &ScrapeConfig) -> ScrapeOutput {
+    let parse_start = Instant::now();
+    let html_dom = scraper::Html::parse_document(html);
+    let extract_start = Instant::now();
+
+    let mut edges = get_edges(&html_dom, page_url);
+    let links: Vec<String> = edges.iter().map(|edge| edge.to.clone()).collect();
 
     // Now also want to get the scrape data
     let mut images: Vec<Image> = Vec::new();
     let mut titles: Vec<String> = Vec::new();
-    for option in options {
+    let mut headings: Option<Headings> = None;
+    let mut media: Vec<Media> = Vec::new();
+    let mut css_images = false;
+    let mut description: Option<String> = None;
+    let mut keywords: Vec<String> = Vec::new();
+    let mut social: Option<SocialMeta> = None;
+    let mut structured_data: Vec<Value> = Vec::new();
+    let mut text: Option<String> = None;
+    let mut declared_language: Option<String> = None;
+    let mut detected_language: Option<String> = None;
+    let mut simhash: Option<u64> = None;
+    for option in config.options.iter() {
         match option {
             ScrapeOption::Images => {
-                images = get_images(&html_dom, &url);
+                images = get_images(&html_dom, page_url);
             }
             ScrapeOption::Titles => {
                 titles = get_titles(&html_dom);
+                headings = Some(get_headings(&html_dom));
+            }
+            // The feeds are fetched once the DOM is gone
+            ScrapeOption::Feeds => {}
+            ScrapeOption::Meta => {
+                description = get_meta(&html_dom, "description");
+                keywords = get_meta(&html_dom, "keywords")
+                    .map(|k| split_keywords(&k))
+                    .unwrap_or_default();
+            }
+            ScrapeOption::OpenGraph => {
+                social = Some(get_social_meta(&html_dom)).filter(|s| !s.is_empty());
+            }
+            ScrapeOption::StructuredData => {
+                structured_data = get_structured_data(&html_dom, page_url);
+            }
+            ScrapeOption::Text => {
+                text = Some(get_text(&html_dom));
             }
+            ScrapeOption::Language => {
+                declared_language = get_declared_language(&html_dom);
+                detected_language = detect_language(&get_text(&html_dom));
+            }
+            ScrapeOption::Media => {
+                media = get_media(&html_dom, page_url);
+            }
+            // Added once the `<img>`s are known, to skip them
+            ScrapeOption::CssImages => css_images = true,
+            ScrapeOption::Simhash => {
+                simhash = duplicates::simhash(&get_text(&html_dom));
+            }
+            // Read from the response, see `parse_page`
+            ScrapeOption::SecurityHeaders => {}
+        }
+    }
+    if css_images {
+        let backgrounds = get_css_images(&html_dom, page_url, &images);
+        images.extend(backgrounds);
+    }
+
+    let mixed_content = get_mixed_content(page_url, &links, &images);
+
+    // Exports shouldn't have to deal with `&amp;` or stray control characters
+    for field in titles
+        .iter_mut()
+        .chain(headings.iter_mut().filter_map(|h| h.title.as_mut()))
+        .chain(images.iter_mut().filter_map(|image| image.alt.as_mut()))
+        .chain(description.iter_mut())
+        .chain(keywords.iter_mut())
+    {
+        *field = clean_text(field);
+    }
+    if let Some(social) = social.as_mut() {
+        for value in social
+            .open_graph
+            .values_mut()
+            .chain(social.twitter.values_mut())
+        {
+            *value = clean_text(value);
+        }
+    }
+
+    // Templating bugs can put megabytes of text in a title,
+    // so cap the text fields and keep track of what was cut
+    let mut truncated_fields: Vec<String> = Default::default();
+    let mut truncated_titles = false;
+    for title in titles
+        .iter_mut()
+        .chain(headings.iter_mut().filter_map(|h| h.title.as_mut()))
+    {
+        truncated_titles |= truncate_field(title, config.max_field_length);
+    }
+    if truncated_titles {
+        truncated_fields.push(String::from("titles"));
+    }
+
+    let mut truncated_alts = false;
+    for alt in images.iter_mut().filter_map(|image| image.alt.as_mut()) {
+        truncated_alts |= truncate_field(alt, config.max_field_length);
+    }
+    if truncated_alts {
+        truncated_fields.push(String::from("images.alt"));
+    }
+
+    if let Some(description) = description.as_mut() {
+        if truncate_field(description, config.max_field_length) {
+            truncated_fields.push(String::from("description"));
         }
     }
 
-    Ok(ScrapeOutput {
+    let mut truncated_keywords = false;
+    for keyword in keywords.iter_mut() {
+        truncated_keywords |= truncate_field(keyword, config.max_field_length);
+    }
+    if truncated_keywords {
+        truncated_fields.push(String::from("keywords"));
+    }
+
+    let mut truncated_anchors = false;
+    for edge in edges.iter_mut() {
+        truncated_anchors |= truncate_field(&mut edge.anchor_text, config.max_field_length);
+    }
+    if truncated_anchors {
+        truncated_fields.push(String::from("edges.anchor_text"));
+    }
+
+    if let Some(social) = social.as_mut() {
+        let mut truncated = false;
+        for value in social
+            .open_graph
+            .values_mut()
+            .chain(social.twitter.values_mut())
+        {
+            truncated |= truncate_field(value, config.max_field_length);
+        }
+        if truncated {
+            truncated_fields.push(String::from("social"));
+        }
+    }
+
+    let search_hits = if config.searches.is_empty() {
+        Default::default()
+    } else {
+        let page_text = text.clone().unwrap_or_else(|| get_text(&html_dom));
+        search_text(&page_text, &config.searches)
+    };
+    // Once searched, which goes through the whole text
+    if let Some(text) = text.as_mut() {
+        if truncate_field(text, config.max_field_length) {
+            truncated_fields.push(String::from("text"));
+        }
+    }
+
+    let mut custom = get_custom_fields(&html_dom, &config.custom_selectors);
+    for (name, values) in custom.iter_mut() {
+        let mut truncated = false;
+        for value in values.iter_mut() {
+            *value = clean_text(value);
+            truncated |= truncate_field(value, config.max_field_length);
+        }
+        if truncated {
+            truncated_fields.push(format!("custom.{}", name));
+        }
+    }
+
+    let feeds = get_feeds(&html_dom, page_url);
+    let canonical = get_canonical(&html_dom, page_url);
+
+    let timings = PageTimings {
+        fetch_ms: 0.0,
+        parse_ms: (extract_start - parse_start).as_secs_f64() * 1000.0,
+        extract_ms: extract_start.elapsed().as_secs_f64() * 1000.0,
+    };
+
+    ScrapeOutput {
         links,
+        edges,
         images,
         titles,
-    })
+        headings,
+        media,
+        description,
+        keywords,
+        social,
+        structured_data,
+        text,
+        search_hits,
+        declared_language,
+        detected_language,
+        simhash,
+        canonical,
+        mixed_content,
+        truncated_fields,
+        feeds,
+        timings: Some(timings),
+        custom,
+        ..Default::default()
+    }
 }
 
-/// Given a `url`, and a `client`, it will crawl
+/// Given a `url`, and a `fetcher`, it will crawl
 /// the HTML in `url` and find all the links in the
 /// page, returning them as a vector of strings
-pub async fn scrape_page(url: Url, client: &Client, options: &[ScrapeOption]) -> ScrapeOutput {
+pub async fn scrape_page(url: Url, fetcher: &dyn Fetcher, config: &ScrapeConfig) -> ScrapeOutput {
     // This will get all the "href" tags in all the anchors
     // TODO : Pass in the options
-    let mut scrape_output = match scrape_page_helper(url.clone(), client, options).await {
+    match scrape_page_helper(url, fetcher, config).await {
         Ok(output) => output,
         Err(e) => {
-            error!("Could not find links: {}", e);
-            ScrapeOutput {
-                images: Default::default(),
-                links: Default::default(),
-                titles: Default::default(),
-            }
+            error!("Could not find links: {:#}", e);
+            Default::default()
         }
-    };
+    }
+}
 
-    // Turn all links into absolute links
-    scrape_output.links = scrape_output
-        .links
-        .iter()
-        .filter_map(|l| get_url(l, url.clone()).ok())
-        .map(|url| url.to_string())
-        .collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    scrape_output
+    fn scrape(html: &str, options: Vec<ScrapeOption>, max_field_length: usize) -> ScrapeOutput {
+        let config = ScrapeConfig {
+            options,
+            max_field_length,
+            parsers: Default::default(),
+            custom_selectors: Default::default(),
+            extractors: Default::default(),
+            searches: Default::default(),
+        };
+        extract_page(html, &Url::parse("https://a.com/page").unwrap(), &config)
+    }
+
+    #[test]
+    fn long_fields_are_capped_and_recorded() {
+        let output = scrape(
+            r#"<html><head><meta name="keywords" content="short, a very long keyword"></head>
+            <body><a href="/b">a very long anchor</a><p>a very long text</p></body></html>"#,
+            vec![ScrapeOption::Meta, ScrapeOption::Text],
+            8,
+        );
+
+        assert_eq!(output.edges[0].anchor_text, "a very l");
+        assert_eq!(output.keywords, ["short", "a very l"]);
+        assert_eq!(output.text.unwrap().chars().count(), 8);
+        assert_eq!(
+            output.truncated_fields,
+            ["keywords", "edges.anchor_text", "text"]
+        );
+    }
+
+    #[test]
+    fn the_biggest_srcset_candidate_is_picked() {
+        let output = scrape(
+            r#"<picture><source srcset="/huge.webp 1920w">
+            <img src="data:image/gif;base64,R0lG" data-src="/small.jpg"
+                srcset="/medium.jpg 640w, /large.jpg 1280w"></picture>
+            <img width="100" srcset="/a,b.jpg, /c.jpg 3x">"#,
+            vec![ScrapeOption::Images],
+            usize::MAX,
+        );
+
+        assert_eq!(output.images[0].link, "https://a.com/huge.webp");
+        assert_eq!(
+            output.images[0].variants,
+            [
+                "https://a.com/small.jpg",
+                "https://a.com/medium.jpg",
+                "https://a.com/large.jpg",
+                "https://a.com/huge.webp",
+            ]
+        );
+        assert_eq!(output.images[1].link, "https://a.com/c.jpg");
+        assert_eq!(output.images[1].variants[0], "https://a.com/a,b.jpg");
+    }
+
+    #[test]
+    fn css_background_urls_are_added_once() {
+        let output = scrape(
+            r#"<style>
+                .hero { background: #fff url(/hero.jpg) no-repeat; }
+                .icon { background-image: url("data:image/png;base64,iVBO"); }
+                .card { background: URL( '/card.png' ); color: red; }
+            </style>
+            <div style="background-image: url(/card.png)"></div>
+            <img src="/hero.jpg" alt="hero">"#,
+            vec![ScrapeOption::Images, ScrapeOption::CssImages],
+            usize::MAX,
+        );
+
+        let links: Vec<_> = output.images.iter().map(|i| i.link.as_str()).collect();
+        assert_eq!(links, ["https://a.com/hero.jpg", "https://a.com/card.png"]);
+        assert!(!output.images[0].background);
+        assert!(output.images[1].background);
+    }
 }
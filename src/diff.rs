@@ -0,0 +1,178 @@
+//! What changed between two crawls of the same site,
+//! see the `diff` subcommand
+
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+use crate::model::{Link, LinkGraph, LinkId};
+
+/// A page whose titles changed between the crawls
+#[derive(Debug, Serialize)]
+pub struct TitleChange {
+    pub url: String,
+    pub old: Vec<String>,
+    pub new: Vec<String>,
+}
+
+/// The links of a page that appeared or disappeared
+#[derive(Debug, Serialize)]
+pub struct OutlinkChange {
+    pub url: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// The changes from an old crawl to a new one. The
+/// pages are matched by their url, sorted by url.
+#[derive(Debug, Default, Serialize)]
+pub struct CrawlDiff {
+    /// the pages only in the new crawl
+    pub added: Vec<String>,
+    /// the pages only in the old crawl
+    pub removed: Vec<String>,
+    pub retitled: Vec<TitleChange>,
+    pub outlinks: Vec<OutlinkChange>,
+}
+
+impl CrawlDiff {
+    pub fn between(old: &LinkGraph, new: &LinkGraph) -> CrawlDiff {
+        let old_urls = urls_by_id(old);
+        let new_urls = urls_by_id(new);
+        let mut diff = CrawlDiff::default();
+
+        for (_, link) in new {
+            let Some(old_link) = old.get(&link.url) else {
+                diff.added.push(link.url.clone());
+                continue;
+            };
+
+            if old_link.titles != link.titles {
+                diff.retitled.push(TitleChange {
+                    url: link.url.clone(),
+                    old: old_link.titles.clone(),
+                    new: link.titles.clone(),
+                });
+            }
+
+            // Like with like: a graph saved before the edges only has children
+            let from_edges = !old_link.edges.is_empty() && !link.edges.is_empty();
+            let old_outlinks = outlinks(old_link, &old_urls, from_edges);
+            let new_outlinks = outlinks(link, &new_urls, from_edges);
+            if old_outlinks != new_outlinks {
+                diff.outlinks.push(OutlinkChange {
+                    url: link.url.clone(),
+                    added: new_outlinks.difference(&old_outlinks).cloned().collect(),
+                    removed: old_outlinks.difference(&new_outlinks).cloned().collect(),
+                });
+            }
+        }
+        diff.removed = old
+            .into_iter()
+            .filter(|(_, link)| !new.link_visited(&link.url))
+            .map(|(_, link)| link.url.clone())
+            .collect();
+
+        diff.added.sort();
+        diff.removed.sort();
+        diff.retitled.sort_by(|a, b| a.url.cmp(&b.url));
+        diff.outlinks.sort_by(|a, b| a.url.cmp(&b.url));
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.retitled.is_empty()
+            && self.outlinks.is_empty()
+    }
+}
+
+fn urls_by_id(link_graph: &LinkGraph) -> HashMap<LinkId, &str> {
+    link_graph
+        .into_iter()
+        .map(|(id, link)| (*id, link.url.as_str()))
+        .collect()
+}
+
+/// Every url `link` links to `from_edges`, or the crawled
+/// pages it links to, its children, otherwise
+fn outlinks(link: &Link, urls: &HashMap<LinkId, &str>, from_edges: bool) -> BTreeSet<String> {
+    if from_edges {
+        return link.edges.iter().map(|edge| edge.to.clone()).collect();
+    }
+
+    link.children
+        .iter()
+        .filter_map(|id| urls.get(id))
+        .map(|url| url.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Edge, ScrapeOutput};
+
+    /// A page with the `titles` linking to `links`, with
+    /// their edges unless it was saved `without_edges`
+    fn page(links: &[&str], titles: &[&str], without_edges: bool) -> ScrapeOutput {
+        let edges = links.iter().map(|to| Edge {
+            to: to.to_string(),
+            ..Default::default()
+        });
+        ScrapeOutput {
+            links: links.iter().map(|link| link.to_string()).collect(),
+            edges: edges.filter(|_| !without_edges).collect(),
+            titles: titles.iter().map(|title| title.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn pages_added_removed_and_retitled() {
+        let mut old = LinkGraph::default();
+        old.update("a", "", &page(&["b"], &["A"], false)).unwrap();
+        old.update("b", "a", &page(&[], &["B"], false)).unwrap();
+        let mut new = LinkGraph::default();
+        new.update("a", "", &page(&["c"], &["New A"], false))
+            .unwrap();
+        new.update("c", "a", &page(&[], &["C"], false)).unwrap();
+
+        let diff = CrawlDiff::between(&old, &new);
+        assert_eq!(diff.added, ["c"]);
+        assert_eq!(diff.removed, ["b"]);
+        assert_eq!(diff.retitled[0].url, "a");
+        assert_eq!(diff.retitled[0].new, ["New A"]);
+        assert_eq!(diff.outlinks[0].added, ["c"]);
+        assert_eq!(diff.outlinks[0].removed, ["b"]);
+    }
+
+    #[test]
+    fn outlinks_compare_the_children_without_old_edges() {
+        // The old graph predates the edges, the new one also
+        // links to a page that wasn't crawled
+        let mut old = LinkGraph::default();
+        old.update("a", "", &page(&["b"], &[], true)).unwrap();
+        old.update("b", "a", &page(&[], &[], true)).unwrap();
+        let mut new = LinkGraph::default();
+        new.update("b", "", &page(&[], &[], false)).unwrap();
+        new.update("a", "", &page(&["b", "https://x.com/"], &[], false))
+            .unwrap();
+
+        assert!(CrawlDiff::between(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn outlinks_compare_the_edges_when_both_have_them() {
+        let mut old = LinkGraph::default();
+        old.update("a", "", &page(&["https://x.com/"], &[], false))
+            .unwrap();
+        let mut new = LinkGraph::default();
+        new.update("a", "", &page(&["https://y.com/"], &[], false))
+            .unwrap();
+
+        let diff = CrawlDiff::between(&old, &new);
+        assert_eq!(diff.outlinks[0].added, ["https://y.com/"]);
+        assert_eq!(diff.outlinks[0].removed, ["https://x.com/"]);
+    }
+}
@@ -0,0 +1,294 @@
+//! Shares the links of a broad crawl between its domains: the
+//! first pages of every new domain are crawled first as a sample,
+//! and the rest of the links go to the domains in proportion to
+//! how many links they have, how fast and how reliable they are
+
+use rand::Rng;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use url::Url;
+
+use crate::crawler::LinkPath;
+use crate::model::WarmUpEstimate;
+
+/// How long after its first page a domain stops being sampled,
+/// so a slow domain doesn't hold up the rest of the crawl
+const WARM_UP_TIME: Duration = Duration::from_secs(30);
+/// How many of the queued links are looked at for a domain to
+/// sample or under its budget: the newest ones, or the ones from
+/// a random link on when shuffling
+const SCAN_WINDOW: usize = 1024;
+
+#[derive(Default)]
+struct DomainSample {
+    first_fetch: Option<Instant>,
+    /// the pages taken off the queue
+    claimed: usize,
+    /// the pages fetched, with their errors, times and sizes
+    fetched: usize,
+    errors: usize,
+    fetch_ms: f64,
+    bytes: u64,
+    discovered: usize,
+}
+
+impl DomainSample {
+    fn warming_up(&self, sample_size: usize) -> bool {
+        self.claimed < sample_size
+            && self
+                .first_fetch
+                .is_none_or(|first_fetch| first_fetch.elapsed() < WARM_UP_TIME)
+    }
+
+    /// How much of the budget the domain deserves: its links,
+    /// less the ones likely to fail, favouring the fast domains
+    fn weight(&self) -> f64 {
+        if self.fetched == 0 {
+            return 0.0;
+        }
+
+        let success_rate = 1.0 - self.errors as f64 / self.fetched as f64;
+        let mean_fetch_secs = self.fetch_ms / self.fetched as f64 / 1000.0;
+        self.discovered.max(1) as f64 * success_rate / (1.0 + mean_fetch_secs)
+    }
+}
+
+/// The samples of the domains, and the budgets worked out from them
+#[derive(Default)]
+struct Samples {
+    domains: HashMap<String, DomainSample>,
+    /// only worked out again when a domain starts or
+    /// finishes its sample, rather than on every pick
+    budgets: Option<HashMap<String, usize>>,
+}
+
+/// Decides which domain the next link is taken from, see the
+/// module docs. The budgets are a preference rather than a cap:
+/// once only links over their domain's budget are left, they are
+/// still crawled.
+pub struct DomainBudget {
+    sample_size: usize,
+    max_links: usize,
+    samples: Mutex<Samples>,
+}
+
+fn domain_of(url: &str) -> String {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(String::from))
+        .unwrap_or_default()
+}
+
+impl DomainBudget {
+    /// Samples `sample_size` pages of every domain, then
+    /// shares the `max_links` of the crawl between them
+    pub fn new(sample_size: usize, max_links: usize) -> Self {
+        DomainBudget {
+            sample_size: sample_size.max(1),
+            max_links,
+            samples: Default::default(),
+        }
+    }
+
+    /// Takes the link to crawl next off `link_queue`: one of a
+    /// domain still being sampled, otherwise one of a domain under
+    /// its budget, preferring the ones not on the `busy_hosts`.
+    /// The newest links are looked at first, or the ones from a
+    /// random link on when shuffling
+    pub fn pick(
+        &self,
+        link_queue: &mut VecDeque<LinkPath>,
+        busy_hosts: &HashSet<String>,
+        shuffle: bool,
+    ) -> Option<LinkPath> {
+        let len = link_queue.len();
+        if len == 0 {
+            return None;
+        }
+        let start = if shuffle {
+            rand::thread_rng().gen_range(0..len)
+        } else {
+            len - 1
+        };
+
+        let mut samples = self.samples.lock().unwrap();
+        let Samples { domains, budgets } = &mut *samples;
+        let budgets = budgets.get_or_insert_with(|| self.budgets(domains));
+
+        // By rank: sampled first, then under budget, each on a free host first
+        let mut chosen: Option<(usize, usize, String)> = None;
+        for index in (0..len.min(SCAN_WINDOW)).map(|i| (start + len - i) % len) {
+            let domain = domain_of(&link_queue[index].child);
+            let sample = domains.get(&domain);
+            let budget = budgets.get(&domain).copied().unwrap_or(self.sample_size);
+            let rank = if sample.is_none_or(|sample| sample.warming_up(self.sample_size)) {
+                0
+            } else if sample.is_some_and(|sample| sample.claimed < budget) {
+                2
+            } else {
+                continue;
+            };
+            let rank = rank + usize::from(busy_hosts.contains(&domain));
+
+            if chosen.as_ref().is_none_or(|(best, _, _)| rank < *best) {
+                chosen = Some((rank, index, domain));
+            }
+            if rank == 0 {
+                break;
+            }
+        }
+
+        let (_, index, domain) = chosen?;
+        domains.entry(domain).or_default().claimed += 1;
+        // A shuffled queue has no order to keep, and otherwise the
+        // link is one of the newest, so few links are moved
+        if shuffle {
+            link_queue.swap_remove_back(index)
+        } else {
+            link_queue.remove(index)
+        }
+    }
+
+    /// Counts a link taken off the queue without `pick`
+    pub fn claim(&self, url: &str) {
+        let mut samples = self.samples.lock().unwrap();
+        samples.domains.entry(domain_of(url)).or_default().claimed += 1;
+    }
+
+    /// Learns from a page of `domain` that took `fetch_ms` to
+    /// fetch, `bytes` long, or that failed
+    pub fn record(&self, domain: &str, fetch_ms: f64, bytes: u64, failed: bool) {
+        let mut samples = self.samples.lock().unwrap();
+        let samples = &mut *samples;
+        let sample = samples.domains.entry(domain.to_string()).or_default();
+        sample.first_fetch.get_or_insert_with(Instant::now);
+        sample.fetched += 1;
+        sample.fetch_ms += fetch_ms;
+        sample.bytes += bytes;
+        if failed {
+            sample.errors += 1;
+        }
+
+        // The domain started or finished its sample, changing the shares
+        if sample.fetched == 1 || sample.fetched == self.sample_size {
+            samples.budgets = None;
+        }
+    }
+
+    /// Counts a link found on `url`'s domain
+    pub fn discovered(&self, url: &str) {
+        let mut samples = self.samples.lock().unwrap();
+        samples
+            .domains
+            .entry(domain_of(url))
+            .or_default()
+            .discovered += 1;
+    }
+
+    /// The pages each domain may have, at least its sample
+    fn budgets(&self, domains: &HashMap<String, DomainSample>) -> HashMap<String, usize> {
+        let total_weight: f64 = domains.values().map(DomainSample::weight).sum();
+        domains
+            .iter()
+            .map(|(domain, sample)| {
+                let share = if total_weight > 0.0 {
+                    sample.weight() / total_weight
+                } else {
+                    0.0
+                };
+                let budget = (self.max_links as f64 * share).round() as usize;
+                (domain.clone(), budget.max(self.sample_size))
+            })
+            .collect()
+    }
+
+    /// What was learned about each domain, and its budget
+    pub fn estimates(&self) -> HashMap<String, WarmUpEstimate> {
+        let mut samples = self.samples.lock().unwrap();
+        let Samples { domains, budgets } = &mut *samples;
+        let budgets = budgets.get_or_insert_with(|| self.budgets(domains));
+        domains
+            .iter()
+            .filter(|(_, sample)| sample.fetched > 0)
+            .map(|(domain, sample)| {
+                let fetched = sample.fetched as f64;
+                let estimate = WarmUpEstimate {
+                    sampled: sample.fetched.min(self.sample_size),
+                    mean_fetch_ms: sample.fetch_ms / fetched,
+                    mean_bytes: (sample.bytes as f64 / fetched) as u64,
+                    error_rate: sample.errors as f64 / fetched,
+                    discovered: sample.discovered,
+                    budget: budgets.get(domain).copied().unwrap_or(self.sample_size),
+                };
+                (domain.clone(), estimate)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(urls: &[&str]) -> VecDeque<LinkPath> {
+        urls.iter()
+            .map(|url| LinkPath {
+                child: url.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn pick(budget: &DomainBudget, queue: &mut VecDeque<LinkPath>, busy: &[&str]) -> String {
+        let busy_hosts = busy.iter().map(|host| host.to_string()).collect();
+        budget.pick(queue, &busy_hosts, false).unwrap().child
+    }
+
+    #[test]
+    fn new_domains_are_sampled_before_the_ones_over_budget() {
+        let budget = DomainBudget::new(1, 2);
+        budget.claim("https://a.com/");
+        budget.record("a.com", 100.0, 1000, false);
+        budget.claim("https://a.com/1");
+
+        let mut queue = queue(&["https://b.com/", "https://a.com/2"]);
+        assert_eq!(pick(&budget, &mut queue, &[]), "https://b.com/");
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn free_hosts_are_picked_first() {
+        let budget = DomainBudget::new(2, 10);
+        let mut queue = queue(&["https://a.com/", "https://b.com/"]);
+
+        assert_eq!(pick(&budget, &mut queue, &["b.com"]), "https://a.com/");
+        // Only a busy host is left, it is still crawled
+        assert_eq!(pick(&budget, &mut queue, &["b.com"]), "https://b.com/");
+        assert!(budget.pick(&mut queue, &HashSet::new(), true).is_none());
+    }
+
+    #[test]
+    fn budgets_are_kept_until_a_sample_ends() {
+        let budget = DomainBudget::new(2, 100);
+        budget.record("a.com", 100.0, 1000, false);
+        budget.record("b.com", 100.0, 1000, false);
+        let budgets = |budget: &DomainBudget| {
+            let estimates = budget.estimates();
+            (estimates["a.com"].budget, estimates["b.com"].budget)
+        };
+        assert_eq!(budgets(&budget), (50, 50));
+
+        // Finding more links alone doesn't change the shares...
+        for _ in 0..3 {
+            budget.discovered("https://a.com/");
+        }
+        assert_eq!(budgets(&budget), (50, 50));
+
+        // ...until the sample of a domain ends
+        budget.record("a.com", 100.0, 1000, false);
+        assert_eq!(budgets(&budget), (75, 25));
+    }
+}
@@ -0,0 +1,242 @@
+//! Pages serving the same, or nearly the same, content
+//! under different urls, see the `analyze` subcommand
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::model::{Link, LinkGraph};
+
+/// How many words make up each feature of a SimHash
+const SHINGLE_WORDS: usize = 3;
+
+/// Pages serving the same content, e.g. the
+/// facets of a listing all showing the same items
+#[derive(Debug, Serialize)]
+pub struct DuplicateCluster {
+    /// the content hash the pages share, or the SimHash
+    /// of the first page for the near duplicates
+    pub fingerprint: String,
+    /// the urls of the pages, sorted
+    pub urls: Vec<String>,
+}
+
+/// The 64 bit FNV-1a hash of `data`, which unlike the std
+/// hashers is the same in every build, as the SimHashes
+/// are kept in the saved graphs
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The SimHash fingerprint of `text`, from its shingles of
+/// `SHINGLE_WORDS` lowercased words. Texts differing by a few
+/// words have fingerprints differing by a few bits. `None`
+/// for a text without words
+pub fn simhash(text: &str) -> Option<u64> {
+    let words: Vec<String> = text.split_whitespace().map(str::to_lowercase).collect();
+    if words.is_empty() {
+        return None;
+    }
+
+    let mut weights = [0i64; 64];
+    for shingle in words.windows(SHINGLE_WORDS.min(words.len())) {
+        let hash = fnv1a(shingle.join(" ").as_bytes());
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            match hash >> bit & 1 {
+                1 => *weight += 1,
+                _ => *weight -= 1,
+            }
+        }
+    }
+
+    Some(
+        weights
+            .iter()
+            .enumerate()
+            .filter(|(_, weight)| **weight > 0)
+            .fold(0, |simhash, (bit, _)| simhash | 1 << bit),
+    )
+}
+
+/// Whether `link` is a page worth comparing: served with a
+/// 200, as error pages are often all alike, and not redirected
+/// as it would share its target's content
+fn comparable(link: &Link) -> bool {
+    link.status == Some(200) && link.redirect.is_none() && link.blocked.is_none()
+}
+
+/// Sorts the urls of each cluster, then the clusters from the largest
+fn into_clusters(groups: impl IntoIterator<Item = (String, Vec<String>)>) -> Vec<DuplicateCluster> {
+    let mut clusters: Vec<DuplicateCluster> = groups
+        .into_iter()
+        .filter(|(_, urls)| urls.len() > 1)
+        .map(|(fingerprint, mut urls)| {
+            urls.sort();
+            DuplicateCluster { fingerprint, urls }
+        })
+        .collect();
+    clusters.sort_by(|a, b| b.urls.len().cmp(&a.urls.len()).then(a.urls.cmp(&b.urls)));
+    clusters
+}
+
+/// The clusters of pages with the same content
+/// (see `content_hash`), the largest first
+pub fn exact_duplicates(link_graph: &LinkGraph) -> Vec<DuplicateCluster> {
+    let mut by_hash: BTreeMap<String, Vec<String>> = Default::default();
+    for (_, link) in link_graph {
+        if let Some(content_hash) = link.content_hash.as_ref().filter(|_| comparable(link)) {
+            by_hash
+                .entry(content_hash.clone())
+                .or_default()
+                .push(link.url.clone());
+        }
+    }
+
+    into_clusters(by_hash)
+}
+
+/// Finds the root of `page` in a union-find `parents`
+fn root(parents: &mut [usize], mut page: usize) -> usize {
+    while parents[page] != page {
+        parents[page] = parents[parents[page]];
+        page = parents[page];
+    }
+    page
+}
+
+/// The clusters of pages whose SimHashes differ by at most
+/// `max_distance` bits, the largest first, leaving out the
+/// ones only made of exact duplicates. A page is in the same
+/// cluster as every page it is close to, so the pages at
+/// both ends of a cluster can be further apart.
+pub fn near_duplicates(link_graph: &LinkGraph, max_distance: u32) -> Vec<DuplicateCluster> {
+    let pages: Vec<(&Link, u64)> = link_graph
+        .into_iter()
+        .filter(|(_, link)| comparable(link))
+        .filter_map(|(_, link)| Some((link, link.simhash?)))
+        .collect();
+
+    // Split in `max_distance + 1` bands, two close enough
+    // fingerprints are the same in at least one of them
+    let bands = max_distance as usize + 1;
+    let band_bits = 64 / bands;
+    let mut parents: Vec<usize> = (0..pages.len()).collect();
+    for band in 0..bands {
+        let shift = band * band_bits;
+        let bits = if band == bands - 1 {
+            64 - shift
+        } else {
+            band_bits
+        };
+        let mask = if bits == 64 {
+            u64::MAX
+        } else {
+            (1 << bits) - 1
+        };
+
+        let mut buckets: HashMap<u64, Vec<usize>> = Default::default();
+        for (i, (_, simhash)) in pages.iter().enumerate() {
+            buckets.entry(simhash >> shift & mask).or_default().push(i);
+        }
+        for bucket in buckets.values() {
+            for (n, &a) in bucket.iter().enumerate() {
+                for &b in bucket[n + 1..].iter() {
+                    if (pages[a].1 ^ pages[b].1).count_ones() <= max_distance {
+                        let (root_a, root_b) = (root(&mut parents, a), root(&mut parents, b));
+                        parents[root_b] = root_a;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<&Link>> = Default::default();
+    for (i, (link, _)) in pages.iter().enumerate() {
+        let cluster = root(&mut parents, i);
+        groups.entry(cluster).or_default().push(link);
+    }
+
+    into_clusters(groups.into_values().filter_map(|links| {
+        let first = links.first()?;
+        let exact = links
+            .iter()
+            .all(|link| link.content_hash.is_some() && link.content_hash == first.content_hash);
+        (!exact).then(|| {
+            (
+                format!("{:016x}", first.simhash.unwrap_or_default()),
+                links.iter().map(|link| link.url.clone()).collect(),
+            )
+        })
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::ScrapeOutput;
+
+    const ARTICLE: &str = "the crawler visits every page of the site once, \
+        following the links it finds on each page and keeping their titles, \
+        images and text so that the pages serving the same content under \
+        several urls can be found once the crawl is over";
+
+    fn graph_of(pages: &[(&str, String)]) -> LinkGraph {
+        let mut graph = LinkGraph::default();
+        for (url, text) in pages {
+            let page = ScrapeOutput {
+                status: Some(200),
+                simhash: simhash(text),
+                body: Some(text.clone()),
+                ..Default::default()
+            };
+            graph.update(url, "", &page).unwrap();
+        }
+        graph
+    }
+
+    fn distance(a: &str, b: &str) -> u32 {
+        (simhash(a).unwrap() ^ simhash(b).unwrap()).count_ones()
+    }
+
+    #[test]
+    fn simhash_distance_follows_the_similarity() {
+        let near = ARTICLE.replace("titles", "headings");
+        let unrelated = "a recipe for bread: mix the flour with water, salt \
+            and yeast, knead the dough for ten minutes and bake it until golden";
+
+        assert_eq!(distance(ARTICLE, &ARTICLE.to_uppercase()), 0);
+        assert!(distance(ARTICLE, &near) <= 8);
+        assert!(distance(ARTICLE, unrelated) > 16);
+        assert_eq!(simhash("  "), None);
+    }
+
+    #[test]
+    fn near_duplicates_cluster_the_similar_pages() {
+        let graph = graph_of(&[
+            ("a", ARTICLE.to_string()),
+            ("b", ARTICLE.replace("titles", "headings")),
+            (
+                "c",
+                ARTICLE.replace("once the crawl is over", "after crawling"),
+            ),
+            (
+                "d",
+                String::from("a recipe for bread, with flour, water and salt"),
+            ),
+        ]);
+
+        let clusters = near_duplicates(&graph, 10);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].urls, ["a", "b", "c"]);
+        assert!(exact_duplicates(&graph).is_empty());
+    }
+
+    #[test]
+    fn exact_duplicates_are_only_clustered_once() {
+        let graph = graph_of(&[("a", ARTICLE.to_string()), ("b", ARTICLE.to_string())]);
+
+        assert_eq!(exact_duplicates(&graph)[0].urls, ["a", "b"]);
+        assert!(near_duplicates(&graph, 3).is_empty());
+    }
+}
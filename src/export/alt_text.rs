@@ -0,0 +1,155 @@
+//! The images without alt text, by the page showing them,
+//! for accessibility reviews
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+use super::tables::{pages, write_rows};
+use crate::model::LinkGraph;
+
+/// An image without alt text on a page
+#[derive(Serialize)]
+pub struct MissingAltImage<'a> {
+    pub image: &'a str,
+    /// how many of the exported pages show this image without
+    /// alt text, e.g. a logo fixed once in the page template
+    pub pages: usize,
+}
+
+/// The images without alt text of a page
+#[derive(Serialize)]
+pub struct MissingAltPage<'a> {
+    pub page: &'a str,
+    /// the most shown images first
+    pub images: Vec<MissingAltImage<'a>>,
+}
+
+/// A row of the alt text CSV
+#[derive(Serialize)]
+struct MissingAltRow<'a> {
+    page: &'a str,
+    image: &'a str,
+    pages: usize,
+}
+
+/// The pages on the `domains` with images without alt
+/// text, the pages with the most of them first
+pub fn missing_alts<'a>(
+    link_graph: &'a LinkGraph,
+    domains: &HashSet<String>,
+) -> Vec<MissingAltPage<'a>> {
+    let by_page: Vec<(&str, Vec<&str>)> = pages(link_graph, domains)
+        .into_iter()
+        .map(|link| {
+            let mut images: Vec<&str> = link
+                .images
+                .iter()
+                .filter(|image| image.missing_alt())
+                .map(|image| image.link.as_str())
+                .collect();
+            images.sort();
+            images.dedup();
+            (link.url.as_str(), images)
+        })
+        .filter(|(_, images)| !images.is_empty())
+        .collect();
+
+    let mut frequencies: BTreeMap<&str, usize> = Default::default();
+    for image in by_page.iter().flat_map(|(_, images)| images) {
+        *frequencies.entry(image).or_default() += 1;
+    }
+
+    let mut missing: Vec<MissingAltPage> = by_page
+        .into_iter()
+        .map(|(page, images)| {
+            let mut images: Vec<MissingAltImage> = images
+                .into_iter()
+                .map(|image| MissingAltImage {
+                    image,
+                    pages: frequencies[image],
+                })
+                .collect();
+            images.sort_by(|a, b| b.pages.cmp(&a.pages).then_with(|| a.image.cmp(b.image)));
+            MissingAltPage { page, images }
+        })
+        .collect();
+    // `pages` is sorted by url, which the stable sort keeps for ties
+    missing.sort_by_key(|page| std::cmp::Reverse(page.images.len()));
+    missing
+}
+
+/// The images without alt text on the `domains`, as JSON
+pub fn to_alt_text_json(link_graph: &LinkGraph, domains: &HashSet<String>) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&missing_alts(
+        link_graph, domains,
+    ))?)
+}
+
+/// The images without alt text on the `domains`, a row per
+/// page and image, as CSV or as TSV if `tsv` is set
+pub fn to_alt_text_csv(
+    link_graph: &LinkGraph,
+    domains: &HashSet<String>,
+    tsv: bool,
+) -> Result<String> {
+    let missing = missing_alts(link_graph, domains);
+    let rows = missing.iter().flat_map(|page| {
+        page.images.iter().map(|image| MissingAltRow {
+            page: page.page,
+            image: image.image,
+            pages: image.pages,
+        })
+    });
+    write_rows(rows, tsv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::{extract_page, ScrapeConfig, ScrapeOption};
+    use url::Url;
+
+    fn graph_of(pages: &[(&str, &str)]) -> LinkGraph {
+        let config = ScrapeConfig {
+            options: vec![ScrapeOption::Images, ScrapeOption::CssImages],
+            max_field_length: 4096,
+            parsers: Default::default(),
+            custom_selectors: Default::default(),
+            extractors: Default::default(),
+            searches: Default::default(),
+        };
+        let mut graph = LinkGraph::default();
+        for (url, html) in pages {
+            let page = extract_page(html, &Url::parse(url).unwrap(), &config);
+            graph.update(url, "", &page).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn only_images_without_an_alt_attribute_are_listed() {
+        let graph = graph_of(&[
+            (
+                "https://a.com/",
+                r#"<img src="/logo.png"><img src="/line.png" alt="">
+                <div style="background-image: url(/bg.jpg)"></div>"#,
+            ),
+            (
+                "https://a.com/blog",
+                r#"<img src="/logo.png"><img src="/post.jpg"><img src="/me.jpg" alt="Me">"#,
+            ),
+            ("https://b.com/", r#"<img src="/logo.png">"#),
+        ]);
+        let domains = HashSet::from(["a.com".to_string()]);
+
+        let csv = to_alt_text_csv(&graph, &domains, false).unwrap();
+        assert_eq!(
+            csv,
+            "page,image,pages\n\
+            https://a.com/blog,https://a.com/logo.png,2\n\
+            https://a.com/blog,https://a.com/post.jpg,1\n\
+            https://a.com/,https://a.com/logo.png,2\n"
+        );
+    }
+}
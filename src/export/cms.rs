@@ -0,0 +1,70 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use url::Url;
+
+use crate::model::{Link, LinkGraph};
+
+/// A row of the import files CMS and redirect managers take,
+/// with the title cased column names they expect
+#[derive(Serialize)]
+pub struct CmsRecord<'a> {
+    #[serde(rename = "URL")]
+    pub url: &'a str,
+    #[serde(rename = "Status")]
+    pub status: Option<u16>,
+    #[serde(rename = "Redirect Target")]
+    pub redirect_target: Option<&'a str>,
+    #[serde(rename = "Canonical")]
+    pub canonical: Option<&'a str>,
+    #[serde(rename = "Title")]
+    pub title: Option<&'a str>,
+}
+
+impl<'a> From<&'a Link> for CmsRecord<'a> {
+    fn from(link: &'a Link) -> Self {
+        CmsRecord {
+            url: &link.url,
+            status: link.status,
+            redirect_target: link.redirect.as_deref(),
+            canonical: link.canonical.as_deref(),
+            title: link.title(),
+        }
+    }
+}
+
+/// The pages that were requested (including the
+/// broken ones) on one of the `domains`, by url
+fn cms_records<'a>(link_graph: &'a LinkGraph, domains: &HashSet<String>) -> Vec<CmsRecord<'a>> {
+    let mut links: Vec<&Link> = link_graph
+        .into_iter()
+        .map(|(_, link)| link)
+        .filter(|link| link.status.is_some())
+        .filter(|link| {
+            Url::parse(&link.url)
+                .ok()
+                .and_then(|url| url.host_str().map(|h| domains.contains(h)))
+                .unwrap_or(false)
+        })
+        .collect();
+    links.sort_by(|a, b| a.url.cmp(&b.url));
+
+    links.into_iter().map(CmsRecord::from).collect()
+}
+
+/// A CSV of the pages on the given `domains`, for CMS imports
+pub fn to_cms_csv(link_graph: &LinkGraph, domains: &HashSet<String>) -> Result<String> {
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for record in cms_records(link_graph, domains) {
+        writer.serialize(record)?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// The same records as `to_cms_csv`, as a JSON array
+pub fn to_cms_json(link_graph: &LinkGraph, domains: &HashSet<String>) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&cms_records(
+        link_graph, domains,
+    ))?)
+}
@@ -0,0 +1,256 @@
+//! Exports of the link graph for graph tools:
+//! GraphML (yEd, Gephi), GEXF (Gephi) and DOT (Graphviz)
+
+use anyhow::Result;
+use quick_xml::escape::escape;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+use url::Url;
+
+use crate::model::{Link, LinkGraph, LinkId};
+use crate::stats::depths_from_seeds;
+
+/// Limits on the pages exported, so big crawls
+/// still give a graph that can be rendered
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GraphFilter {
+    /// only the pages this many links away from the seeds
+    pub max_depth: Option<usize>,
+    /// only this many of the most linked to pages
+    pub max_nodes: Option<usize>,
+}
+
+/// A page of the exported graph, with its attributes
+struct Node<'a> {
+    link: &'a Link,
+    title: Option<&'a str>,
+    /// links away from the seeds, `None` if no path leads there
+    depth: Option<usize>,
+}
+
+/// The pages on one of the `domains` that pass the `filter`,
+/// sorted by url, and the links between them
+fn nodes_and_edges<'a>(
+    link_graph: &'a LinkGraph,
+    domains: &HashSet<String>,
+    filter: GraphFilter,
+) -> (Vec<Node<'a>>, Vec<(LinkId, LinkId)>) {
+    let depths = depths_from_seeds(link_graph);
+    let mut nodes: Vec<Node> = link_graph
+        .into_iter()
+        .map(|(_, link)| link)
+        .filter(|link| {
+            Url::parse(&link.url)
+                .ok()
+                .and_then(|url| url.host_str().map(|h| domains.contains(h)))
+                .unwrap_or(false)
+        })
+        .map(|link| Node {
+            link,
+            title: link.title(),
+            depth: depths.get(&link.id).copied(),
+        })
+        .filter(|node| {
+            filter
+                .max_depth
+                .is_none_or(|max| node.depth.is_some_and(|depth| depth <= max))
+        })
+        .collect();
+    nodes.sort_by(|a, b| a.link.url.cmp(&b.link.url));
+    let mut edges = edges_between(&nodes);
+
+    if let Some(max_nodes) = filter.max_nodes.filter(|max| *max < nodes.len()) {
+        let mut in_degrees: HashMap<LinkId, usize> = Default::default();
+        for (_, target) in edges.iter() {
+            *in_degrees.entry(*target).or_default() += 1;
+        }
+        let in_degree = |node: &Node| in_degrees.get(&node.link.id).copied().unwrap_or(0);
+
+        let mut ranked: Vec<&Node> = nodes.iter().collect();
+        // The ties go to the first urls, so the export is stable
+        ranked.sort_by(|a, b| {
+            in_degree(b)
+                .cmp(&in_degree(a))
+                .then(a.link.url.cmp(&b.link.url))
+        });
+        let kept: HashSet<LinkId> = ranked
+            .into_iter()
+            .take(max_nodes)
+            .map(|node| node.link.id)
+            .collect();
+
+        nodes.retain(|node| kept.contains(&node.link.id));
+        edges = edges_between(&nodes);
+    }
+
+    (nodes, edges)
+}
+
+/// The links between the `nodes`, as (source, target)
+fn edges_between(nodes: &[Node]) -> Vec<(LinkId, LinkId)> {
+    let ids: HashSet<LinkId> = nodes.iter().map(|node| node.link.id).collect();
+    nodes
+        .iter()
+        .flat_map(|node| {
+            node.link
+                .children
+                .iter()
+                .filter(|child| ids.contains(child))
+                .map(|child| (node.link.id, *child))
+        })
+        .collect()
+}
+
+/// A GraphML file of the pages on the given `domains`, with
+/// their url, title, status and depth from the seeds
+pub fn to_graphml(
+    link_graph: &LinkGraph,
+    domains: &HashSet<String>,
+    filter: GraphFilter,
+) -> Result<String> {
+    let (nodes, edges) = nodes_and_edges(link_graph, domains, filter);
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n  \
+         <key id=\"url\" for=\"node\" attr.name=\"url\" attr.type=\"string\"/>\n  \
+         <key id=\"title\" for=\"node\" attr.name=\"title\" attr.type=\"string\"/>\n  \
+         <key id=\"status\" for=\"node\" attr.name=\"status\" attr.type=\"int\"/>\n  \
+         <key id=\"depth\" for=\"node\" attr.name=\"depth\" attr.type=\"int\"/>\n  \
+         <graph id=\"crawl\" edgedefault=\"directed\">\n",
+    );
+
+    for node in nodes.iter() {
+        writeln!(xml, "    <node id=\"n{}\">", node.link.id)?;
+        writeln!(
+            xml,
+            "      <data key=\"url\">{}</data>",
+            escape(&node.link.url)
+        )?;
+        if let Some(title) = node.title {
+            writeln!(xml, "      <data key=\"title\">{}</data>", escape(title))?;
+        }
+        if let Some(status) = node.link.status {
+            writeln!(xml, "      <data key=\"status\">{}</data>", status)?;
+        }
+        if let Some(depth) = node.depth {
+            writeln!(xml, "      <data key=\"depth\">{}</data>", depth)?;
+        }
+        writeln!(xml, "    </node>")?;
+    }
+    for (source, target) in edges {
+        writeln!(
+            xml,
+            "    <edge source=\"n{}\" target=\"n{}\"/>",
+            source, target
+        )?;
+    }
+
+    xml.push_str("  </graph>\n</graphml>\n");
+    Ok(xml)
+}
+
+/// The ids of the GEXF node attributes
+const GEXF_ATTRIBUTES: [(&str, &str); 3] = [
+    ("url", "string"),
+    ("status", "integer"),
+    ("depth", "integer"),
+];
+
+/// A GEXF file of the pages on the given `domains`, labelled
+/// with their title, with their url, status and depth
+pub fn to_gexf(
+    link_graph: &LinkGraph,
+    domains: &HashSet<String>,
+    filter: GraphFilter,
+) -> Result<String> {
+    let (nodes, edges) = nodes_and_edges(link_graph, domains, filter);
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n  \
+         <graph defaultedgetype=\"directed\">\n    \
+         <attributes class=\"node\">\n",
+    );
+    for (id, kind) in GEXF_ATTRIBUTES {
+        writeln!(
+            xml,
+            "      <attribute id=\"{}\" title=\"{}\" type=\"{}\"/>",
+            id, id, kind
+        )?;
+    }
+    xml.push_str("    </attributes>\n    <nodes>\n");
+
+    for node in nodes.iter() {
+        writeln!(
+            xml,
+            "      <node id=\"{}\" label=\"{}\">",
+            node.link.id,
+            escape(node.title.unwrap_or(&node.link.url))
+        )?;
+        writeln!(xml, "        <attvalues>")?;
+        // In the order of GEXF_ATTRIBUTES
+        let values = [
+            Some(node.link.url.clone()),
+            node.link.status.map(|s| s.to_string()),
+            node.depth.map(|d| d.to_string()),
+        ];
+        for ((id, _), value) in GEXF_ATTRIBUTES.iter().zip(values) {
+            if let Some(value) = value {
+                writeln!(
+                    xml,
+                    "          <attvalue for=\"{}\" value=\"{}\"/>",
+                    id,
+                    escape(&value)
+                )?;
+            }
+        }
+        writeln!(xml, "        </attvalues>")?;
+        writeln!(xml, "      </node>")?;
+    }
+    xml.push_str("    </nodes>\n    <edges>\n");
+
+    for (index, (source, target)) in edges.into_iter().enumerate() {
+        writeln!(
+            xml,
+            "      <edge id=\"{}\" source=\"{}\" target=\"{}\"/>",
+            index, source, target
+        )?;
+    }
+
+    xml.push_str("    </edges>\n  </graph>\n</gexf>\n");
+    Ok(xml)
+}
+
+/// Escapes `text` for a quoted DOT string
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// A Graphviz DOT file of the pages on the given `domains`,
+/// labelled with their title and url
+pub fn to_dot(
+    link_graph: &LinkGraph,
+    domains: &HashSet<String>,
+    filter: GraphFilter,
+) -> Result<String> {
+    let (nodes, edges) = nodes_and_edges(link_graph, domains, filter);
+
+    let mut dot = String::from("digraph crawl {\n  node [shape=box];\n");
+    for node in nodes.iter() {
+        let url = dot_escape(&node.link.url);
+        let label = match node.title.filter(|title| !title.is_empty()) {
+            Some(title) => format!("{}\\n{}", dot_escape(title), url),
+            None => url,
+        };
+        writeln!(dot, "  n{} [label=\"{}\"];", node.link.id, label)?;
+    }
+    for (source, target) in edges {
+        writeln!(dot, "  n{} -> n{};", source, target)?;
+    }
+
+    dot.push_str("}\n");
+    Ok(dot)
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::model::{Image, LinkGraph};
+
+/// A row of images_edges.csv: an image shown on a page,
+/// and the file it was downloaded to
+#[derive(Serialize)]
+pub struct ImageEdge<'a> {
+    pub page_url: &'a str,
+    pub image_url: &'a str,
+    /// empty when the image has no `alt`
+    pub alt: Option<&'a str>,
+    /// empty when the image wasn't downloaded
+    pub file: Option<&'a str>,
+}
+
+/// A CSV with a row for each image of each page of `link_graph`,
+/// joined with the `images` of the image database by their link
+pub fn to_images_edges_csv(
+    link_graph: &LinkGraph,
+    images: &HashMap<String, Image>,
+) -> Result<String> {
+    let files: HashMap<&str, &str> = images
+        .values()
+        .filter_map(|image| Some((image.link.as_str(), image.file.as_deref()?)))
+        .collect();
+
+    let mut edges: Vec<ImageEdge> = link_graph
+        .into_iter()
+        .flat_map(|(_, link)| {
+            link.images.iter().map(|image| ImageEdge {
+                page_url: &link.url,
+                image_url: &image.link,
+                alt: image.alt.as_deref(),
+                file: files.get(image.link.as_str()).copied(),
+            })
+        })
+        .collect();
+    edges.sort_by(|a, b| (a.page_url, a.image_url).cmp(&(b.page_url, b.image_url)));
+
+    let mut writer = csv::Writer::from_writer(vec![]);
+    for edge in edges {
+        writer.serialize(edge)?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
@@ -0,0 +1,8 @@
+pub mod alt_text;
+pub mod cms;
+pub mod graph;
+pub mod images_edges;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+pub mod sitemap;
+pub mod tables;
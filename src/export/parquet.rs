@@ -0,0 +1,161 @@
+//! Parquet files of the pages, links and images, the same
+//! tables as the CSV exports, for DuckDB, Spark and the like
+
+use anyhow::{Context, Result};
+use parquet::{
+    data_type::{ByteArray, ByteArrayType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+use std::{collections::HashSet, fs::File, path::Path, sync::Arc};
+
+use super::tables::{edge_rows, image_rows, page_rows};
+use crate::model::LinkGraph;
+
+/// The values of a column, `None` for the nulls
+enum Values {
+    Text(Vec<Option<String>>),
+    Int(Vec<Option<i64>>),
+}
+
+/// Writes the `columns` as a single row group to `path`
+fn write_table(path: &Path, columns: Vec<(&str, Values)>) -> Result<()> {
+    let fields: Vec<String> = columns
+        .iter()
+        .map(|(name, values)| match values {
+            Values::Text(_) => format!("OPTIONAL BYTE_ARRAY {} (UTF8);", name),
+            Values::Int(_) => format!("OPTIONAL INT64 {};", name),
+        })
+        .collect();
+    let schema = parse_message_type(&format!("message crawl {{ {} }}", fields.join(" ")))?;
+
+    let file = File::create(path).with_context(|| format!("could not create {:?}", path))?;
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(file, Arc::new(schema), properties)?;
+    let mut row_group = writer.next_row_group()?;
+    for (_, values) in columns {
+        let mut column = row_group
+            .next_column()?
+            .context("more columns than in the schema")?;
+        match values {
+            Values::Text(values) => {
+                let levels: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+                let values: Vec<ByteArray> = values
+                    .iter()
+                    .flatten()
+                    .map(|v| ByteArray::from(v.as_str()))
+                    .collect();
+                column
+                    .typed::<ByteArrayType>()
+                    .write_batch(&values, Some(&levels), None)?;
+            }
+            Values::Int(values) => {
+                let levels: Vec<i16> = values.iter().map(|v| v.is_some() as i16).collect();
+                let values: Vec<i64> = values.into_iter().flatten().collect();
+                column
+                    .typed::<Int64Type>()
+                    .write_batch(&values, Some(&levels), None)?;
+            }
+        }
+        column.close()?;
+    }
+    row_group.close()?;
+    writer.close()?;
+
+    Ok(())
+}
+
+/// Writes links.parquet, edges.parquet and images.parquet of
+/// the pages on the `domains` to the `directory`
+pub fn to_parquet(
+    link_graph: &LinkGraph,
+    domains: &HashSet<String>,
+    directory: &Path,
+) -> Result<()> {
+    std::fs::create_dir_all(directory)
+        .with_context(|| format!("could not create {:?}", directory))?;
+    let text = |value: &str| Some(value.to_string());
+
+    let pages = page_rows(link_graph, domains);
+    write_table(
+        &directory.join("links.parquet"),
+        vec![
+            (
+                "url",
+                Values::Text(pages.iter().map(|r| text(r.url)).collect()),
+            ),
+            (
+                "title",
+                Values::Text(pages.iter().map(|r| r.title.and_then(text)).collect()),
+            ),
+            (
+                "status",
+                Values::Int(pages.iter().map(|r| r.status.map(i64::from)).collect()),
+            ),
+            (
+                "depth",
+                Values::Int(pages.iter().map(|r| r.depth.map(|d| d as i64)).collect()),
+            ),
+            (
+                "in_degree",
+                Values::Int(pages.iter().map(|r| Some(r.in_degree as i64)).collect()),
+            ),
+            (
+                "out_degree",
+                Values::Int(pages.iter().map(|r| Some(r.out_degree as i64)).collect()),
+            ),
+        ],
+    )?;
+
+    let edges = edge_rows(link_graph, domains);
+    write_table(
+        &directory.join("edges.parquet"),
+        vec![
+            (
+                "parent",
+                Values::Text(edges.iter().map(|r| text(r.parent)).collect()),
+            ),
+            (
+                "child",
+                Values::Text(edges.iter().map(|r| text(r.child)).collect()),
+            ),
+            (
+                "anchor",
+                Values::Text(edges.iter().map(|r| text(r.anchor)).collect()),
+            ),
+            (
+                "rel",
+                Values::Text(edges.iter().map(|r| text(&r.rel)).collect()),
+            ),
+        ],
+    )?;
+
+    let images = image_rows(link_graph, domains);
+    write_table(
+        &directory.join("images.parquet"),
+        vec![
+            (
+                "page_url",
+                Values::Text(images.iter().map(|r| text(r.page_url)).collect()),
+            ),
+            (
+                "image_url",
+                Values::Text(images.iter().map(|r| text(r.image_url)).collect()),
+            ),
+            (
+                "alt",
+                Values::Text(images.iter().map(|r| r.alt.and_then(text)).collect()),
+            ),
+            (
+                "width",
+                Values::Int(images.iter().map(|r| r.width.map(i64::from)).collect()),
+            ),
+            (
+                "height",
+                Values::Int(images.iter().map(|r| r.height.map(i64::from)).collect()),
+            ),
+        ],
+    )?;
+
+    Ok(())
+}
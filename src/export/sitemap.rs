@@ -0,0 +1,99 @@
+use anyhow::Result;
+use chrono::DateTime;
+use quick_xml::escape::escape;
+use std::{collections::HashSet, fmt::Write};
+use url::Url;
+
+use crate::model::{Link, LinkGraph};
+
+/// Converts an HTTP Last-Modified date into the
+/// W3C date format sitemaps use, e.g. 2024-01-31
+fn sitemap_date(last_modified: &str) -> Option<String> {
+    DateTime::parse_from_rfc2822(last_modified)
+        .ok()
+        .map(|date| date.format("%Y-%m-%d").to_string())
+}
+
+/// Whether the link should be in the sitemap: a page that was
+/// crawled successfully, on one of the `domains`
+fn include_link(link: &Link, domains: &HashSet<String>) -> bool {
+    let on_domain = Url::parse(&link.url)
+        .ok()
+        .and_then(|url| url.host_str().map(|h| domains.contains(h)))
+        .unwrap_or(false);
+
+    on_domain && link.timings.is_some() && link.blocked.is_none()
+}
+
+/// Builds a sitemap.xml listing the crawled pages on the
+/// given `domains` (usually the domains of the seeds)
+pub fn to_sitemap(link_graph: &LinkGraph, domains: &HashSet<String>) -> Result<String> {
+    let mut links: Vec<&Link> = link_graph
+        .into_iter()
+        .map(|(_, link)| link)
+        .filter(|link| include_link(link, domains))
+        .collect();
+    links.sort_by(|a, b| a.url.cmp(&b.url));
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+
+    for link in links {
+        writeln!(xml, "  <url>")?;
+        writeln!(xml, "    <loc>{}</loc>", escape(&link.url))?;
+        if let Some(lastmod) = link.last_modified.as_deref().and_then(sitemap_date) {
+            writeln!(xml, "    <lastmod>{}</lastmod>", lastmod)?;
+        }
+        writeln!(xml, "  </url>")?;
+    }
+
+    xml.push_str("</urlset>\n");
+    Ok(xml)
+}
+
+/// Google only reads this many images per page
+const MAX_IMAGES_PER_PAGE: usize = 1000;
+
+/// Builds an image sitemap listing the images (the
+/// `image:image` extension) of the crawled pages on the
+/// given `domains`. The images themselves can be anywhere,
+/// e.g. on a CDN
+pub fn to_image_sitemap(link_graph: &LinkGraph, domains: &HashSet<String>) -> Result<String> {
+    let mut links: Vec<&Link> = link_graph
+        .into_iter()
+        .map(|(_, link)| link)
+        .filter(|link| include_link(link, domains) && !link.images.is_empty())
+        .collect();
+    links.sort_by(|a, b| a.url.cmp(&b.url));
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\"\n        \
+         xmlns:image=\"http://www.google.com/schemas/sitemap-image/1.1\">\n",
+    );
+
+    for link in links {
+        // The same image is often on a page several times
+        let mut seen: HashSet<&str> = Default::default();
+        let images = link
+            .images
+            .iter()
+            .map(|image| image.link.as_str())
+            .filter(|image| seen.insert(image))
+            .take(MAX_IMAGES_PER_PAGE);
+
+        writeln!(xml, "  <url>")?;
+        writeln!(xml, "    <loc>{}</loc>", escape(&link.url))?;
+        for image in images {
+            writeln!(xml, "    <image:image>")?;
+            writeln!(xml, "      <image:loc>{}</image:loc>", escape(image))?;
+            writeln!(xml, "    </image:image>")?;
+        }
+        writeln!(xml, "  </url>")?;
+    }
+
+    xml.push_str("</urlset>\n");
+    Ok(xml)
+}
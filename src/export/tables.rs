@@ -0,0 +1,151 @@
+//! Flat exports of the link graph for spreadsheets: one
+//! CSV (or TSV) of the pages, of the links and of the images
+
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashSet;
+use url::Url;
+
+use crate::model::{Link, LinkGraph};
+use crate::stats::depths_from_seeds;
+
+/// A row of links.csv
+#[derive(Serialize)]
+pub struct PageRow<'a> {
+    pub url: &'a str,
+    pub title: Option<&'a str>,
+    pub status: Option<u16>,
+    /// links away from the seeds, empty if no path leads there
+    pub depth: Option<usize>,
+    /// the crawled pages linking to this one
+    pub in_degree: usize,
+    /// the crawled pages this one links to
+    pub out_degree: usize,
+}
+
+/// A row of edges.csv
+#[derive(Serialize)]
+pub struct EdgeRow<'a> {
+    pub parent: &'a str,
+    pub child: &'a str,
+    pub anchor: &'a str,
+    /// the values of the anchor's `rel`, space separated
+    pub rel: String,
+}
+
+/// A row of images.csv
+#[derive(Serialize)]
+pub struct ImageRow<'a> {
+    pub page_url: &'a str,
+    pub image_url: &'a str,
+    /// empty when the image has no `alt`
+    pub alt: Option<&'a str>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+/// The pages on one of the `domains`, by url
+pub(crate) fn pages<'a>(link_graph: &'a LinkGraph, domains: &HashSet<String>) -> Vec<&'a Link> {
+    let mut links: Vec<&Link> = link_graph
+        .into_iter()
+        .map(|(_, link)| link)
+        .filter(|link| {
+            Url::parse(&link.url)
+                .ok()
+                .and_then(|url| url.host_str().map(|h| domains.contains(h)))
+                .unwrap_or(false)
+        })
+        .collect();
+    links.sort_by(|a, b| a.url.cmp(&b.url));
+    links
+}
+
+/// Writes the `rows` as CSV, or as TSV if `tsv` is set
+pub fn write_rows<T: Serialize>(rows: impl IntoIterator<Item = T>, tsv: bool) -> Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(if tsv { b'\t' } else { b',' })
+        .from_writer(vec![]);
+    for row in rows {
+        writer.serialize(row)?;
+    }
+
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+/// A row per page on the `domains`
+pub fn page_rows<'a>(link_graph: &'a LinkGraph, domains: &HashSet<String>) -> Vec<PageRow<'a>> {
+    let depths = depths_from_seeds(link_graph);
+    let in_degrees = link_graph.in_degrees();
+    pages(link_graph, domains)
+        .into_iter()
+        .map(|link| PageRow {
+            url: &link.url,
+            title: link.title(),
+            status: link.status,
+            depth: depths.get(&link.id).copied(),
+            in_degree: in_degrees.get(&link.id).copied().unwrap_or(0),
+            out_degree: link.children.len(),
+        })
+        .collect()
+}
+
+/// A row per link found on the pages on the `domains`
+pub fn edge_rows<'a>(link_graph: &'a LinkGraph, domains: &HashSet<String>) -> Vec<EdgeRow<'a>> {
+    pages(link_graph, domains)
+        .into_iter()
+        .flat_map(|link| {
+            link.edges.iter().map(|edge| EdgeRow {
+                parent: &link.url,
+                child: &edge.to,
+                anchor: &edge.anchor_text,
+                rel: edge.rel.join(" "),
+            })
+        })
+        .collect()
+}
+
+/// A row per image on the pages on the `domains`
+pub fn image_rows<'a>(link_graph: &'a LinkGraph, domains: &HashSet<String>) -> Vec<ImageRow<'a>> {
+    pages(link_graph, domains)
+        .into_iter()
+        .flat_map(|link| {
+            link.images.iter().map(|image| {
+                let (width, height) = image.dimensions();
+                ImageRow {
+                    page_url: &link.url,
+                    image_url: &image.link,
+                    alt: image.alt.as_deref(),
+                    width,
+                    height,
+                }
+            })
+        })
+        .collect()
+}
+
+/// links.csv: a row per page on the `domains`
+pub fn to_links_csv(
+    link_graph: &LinkGraph,
+    domains: &HashSet<String>,
+    tsv: bool,
+) -> Result<String> {
+    write_rows(page_rows(link_graph, domains), tsv)
+}
+
+/// edges.csv: a row per link found on the pages on the `domains`
+pub fn to_edges_csv(
+    link_graph: &LinkGraph,
+    domains: &HashSet<String>,
+    tsv: bool,
+) -> Result<String> {
+    write_rows(edge_rows(link_graph, domains), tsv)
+}
+
+/// images.csv: a row per image on the pages on the `domains`
+pub fn to_images_csv(
+    link_graph: &LinkGraph,
+    domains: &HashSet<String>,
+    tsv: bool,
+) -> Result<String> {
+    write_rows(image_rows(link_graph, domains), tsv)
+}
@@ -0,0 +1,65 @@
+use anyhow::Result;
+use quick_xml::{events::Event, Reader};
+
+/// Parses an RSS or Atom feed, returning the link of
+/// every item (RSS) or entry (Atom) in it
+pub fn parse_feed(xml: &str) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut links: Vec<String> = Default::default();
+    let mut in_entry = false;
+    let mut in_link = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"item" | b"entry" => in_entry = true,
+                b"link" if in_entry => {
+                    // Atom links are in the href attribute
+                    match atom_link(&e)? {
+                        Some(link) => links.push(link),
+                        None => in_link = true,
+                    }
+                }
+                _ => {}
+            },
+            Event::Empty(e) if in_entry && e.local_name().as_ref() == b"link" => {
+                if let Some(link) = atom_link(&e)? {
+                    links.push(link);
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"item" | b"entry" => in_entry = false,
+                b"link" => in_link = false,
+                _ => {}
+            },
+            Event::Text(text) if in_link => links.push(text.unescape()?.trim().to_string()),
+            Event::CData(text) if in_link => {
+                links.push(String::from_utf8_lossy(&text).trim().to_string())
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(links)
+}
+
+/// Gets the href of an Atom `<link>`, skipping the ones that
+/// don't point to the entry itself (e.g. rel="enclosure")
+fn atom_link(element: &quick_xml::events::BytesStart) -> Result<Option<String>> {
+    let mut href: Option<String> = None;
+    let mut alternate = true;
+
+    for attribute in element.attributes() {
+        let attribute = attribute?;
+        match attribute.key.local_name().as_ref() {
+            b"href" => href = Some(attribute.unescape_value()?.to_string()),
+            b"rel" => alternate = attribute.unescape_value()? == "alternate",
+            _ => {}
+        }
+    }
+
+    Ok(href.filter(|_| alternate))
+}
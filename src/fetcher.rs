@@ -0,0 +1,271 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use encoding_rs::{Encoding, UTF_8};
+use regex::Regex;
+use reqwest::{
+    header::{HeaderMap, CONTENT_RANGE, RANGE},
+    Client, StatusCode, Version,
+};
+use std::{
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+use url::Url;
+
+use crate::user_agents::UserAgents;
+
+const REQUEST_TIMEOUT_S: u64 = 2;
+
+/// A response returned by a `Fetcher`
+pub struct FetchResponse {
+    /// the url of the response, after following redirects
+    pub url: Url,
+    pub status: StatusCode,
+    /// the HTTP version negotiated with the server
+    pub version: Version,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    /// set when only the start of the body was
+    /// fetched, see `ReqwestFetcher::max_body_bytes`
+    pub truncated: bool,
+}
+
+/// How far into the body the charset declarations are looked for
+const CHARSET_PRESCAN_BYTES: usize = 1024;
+
+/// `<meta charset="...">`, `<meta http-equiv="Content-Type"
+/// content="text/html; charset=...">` or `<?xml encoding="...">`
+static CHARSET_DECLARATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?i)<(?:meta[^>]+charset|\?xml[^>]+encoding)\s*=\s*["']?\s*([a-z0-9_\-:.]+)"#)
+        .unwrap()
+});
+
+impl FetchResponse {
+    /// Decodes the body using, in this order, its byte order
+    /// mark, the charset in the content type header or the one
+    /// declared at the start of the document, defaulting to UTF-8
+    pub fn text(&self) -> String {
+        let (text, _, _) = self.encoding().decode(&self.body);
+        text.into_owned()
+    }
+
+    fn encoding(&self) -> &'static Encoding {
+        if let Some((encoding, _)) = Encoding::for_bom(&self.body) {
+            return encoding;
+        }
+
+        let header_charset = self
+            .headers
+            .get("content-type")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|content_type| {
+                content_type
+                    .split(';')
+                    .filter_map(|param| param.trim().strip_prefix("charset="))
+                    .next()
+                    .map(|charset| charset.trim_matches('"'))
+            })
+            .and_then(|charset| Encoding::for_label(charset.as_bytes()));
+        if let Some(encoding) = header_charset {
+            return encoding;
+        }
+
+        // The declarations are ASCII in every charset pages use
+        let start = &self.body[..self.body.len().min(CHARSET_PRESCAN_BYTES)];
+        CHARSET_DECLARATION
+            .captures(&String::from_utf8_lossy(start))
+            .and_then(|c| Encoding::for_label(c[1].as_bytes()))
+            // A document can't declare itself UTF-16 in ASCII
+            .map(|encoding| encoding.output_encoding())
+            .unwrap_or(UTF_8)
+    }
+}
+
+/// The HTTP layer used by the crawler to get pages. The
+/// default is `ReqwestFetcher`, but anything able to turn a
+/// url into a response (canned responses, caching proxies,
+/// curl-impersonate) can be plugged in instead.
+#[async_trait]
+pub trait Fetcher: Send + Sync {
+    async fn fetch(&self, url: &Url) -> Result<FetchResponse>;
+
+    /// Fetches `url` sending the extra request `headers`, e.g.
+    /// the validators of a cached copy. Fetchers unable to send
+    /// headers just fetch the page
+    async fn fetch_with_headers(&self, url: &Url, _headers: HeaderMap) -> Result<FetchResponse> {
+        self.fetch(url).await
+    }
+
+    /// Fetches the page at `url` sending the extra `headers`.
+    /// Unlike the other requests (robots.txt, sitemaps, probes)
+    /// only the start of a page's body may be fetched, see
+    /// `ReqwestFetcher::max_body_bytes`
+    async fn fetch_page(&self, url: &Url, headers: HeaderMap) -> Result<FetchResponse> {
+        self.fetch_with_headers(url, headers).await
+    }
+}
+
+/// Fetches pages with a `reqwest::Client`
+pub struct ReqwestFetcher {
+    client: Client,
+    /// the user agents to rotate between, if any
+    user_agents: Option<Arc<UserAgents>>,
+    /// how much of each page body is fetched, if limited
+    max_body_bytes: Option<usize>,
+}
+
+impl ReqwestFetcher {
+    pub fn new(client: Client, user_agents: Option<Arc<UserAgents>>) -> Self {
+        ReqwestFetcher {
+            client,
+            user_agents,
+            max_body_bytes: None,
+        }
+    }
+
+    /// Only fetches the first `max_body_bytes` of each page
+    /// body, enough for the head and the early links of huge
+    /// pages. The other requests always get the whole body.
+    /// A limit of 0 is ignored, as no page fits in it
+    pub fn max_body_bytes(mut self, max_body_bytes: Option<usize>) -> Self {
+        self.max_body_bytes = max_body_bytes.filter(|bytes| *bytes > 0);
+        self
+    }
+}
+
+/// Whether a `206 Partial Content` response left some of the
+/// body out, going by its `Content-Range: bytes 0-1023/4096`
+fn is_partial(headers: &HeaderMap, body_length: usize) -> bool {
+    headers
+        .get(CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|range| range.rsplit_once('/'))
+        .and_then(|(_, total)| total.parse::<usize>().ok())
+        .is_none_or(|total| total > body_length)
+}
+
+#[async_trait]
+impl Fetcher for ReqwestFetcher {
+    async fn fetch(&self, url: &Url) -> Result<FetchResponse> {
+        self.fetch_with_headers(url, HeaderMap::new()).await
+    }
+
+    async fn fetch_with_headers(&self, url: &Url, headers: HeaderMap) -> Result<FetchResponse> {
+        self.get(url, headers, None).await
+    }
+
+    async fn fetch_page(&self, url: &Url, headers: HeaderMap) -> Result<FetchResponse> {
+        self.get(url, headers, self.max_body_bytes).await
+    }
+}
+
+impl ReqwestFetcher {
+    /// Gets `url`, reading at most `max_body_bytes` of its body
+    async fn get(
+        &self,
+        url: &Url,
+        headers: HeaderMap,
+        max_body_bytes: Option<usize>,
+    ) -> Result<FetchResponse> {
+        let mut request = self
+            .client
+            .get(url.clone())
+            .headers(headers)
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_S));
+
+        if let Some(user_agents) = &self.user_agents {
+            request = request.header("user-agent", user_agents.pick(url));
+        }
+
+        if let Some(max_body_bytes) = max_body_bytes {
+            request = request.header(RANGE, format!("bytes=0-{}", max_body_bytes - 1));
+        }
+
+        let mut response = request.send().await?;
+        let url = response.url().clone();
+        let mut status = response.status();
+        let version = response.version();
+        let headers = response.headers().clone();
+
+        // Servers ignoring the range still send the whole
+        // body, so stop reading once we have enough of it
+        let mut body: Vec<u8> = Default::default();
+        let mut truncated = false;
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if let Some(max_body_bytes) = max_body_bytes {
+                if body.len() > max_body_bytes {
+                    body.truncate(max_body_bytes);
+                    truncated = true;
+                    break;
+                }
+            }
+        }
+
+        if status == StatusCode::PARTIAL_CONTENT && max_body_bytes.is_some() {
+            truncated |= is_partial(&headers, body.len());
+            status = StatusCode::OK;
+        }
+
+        Ok(FetchResponse {
+            url,
+            status,
+            version,
+            headers,
+            body,
+            truncated,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http, routing::get, Router};
+
+    /// Serves a 4 KB page ignoring the `Range` header, echoed back in `x-range`
+    async fn page_server() -> Url {
+        let router = Router::new().route(
+            "/",
+            get(|headers: http::HeaderMap| async move {
+                let range = headers
+                    .get(http::header::RANGE)
+                    .and_then(|v| v.to_str().ok())
+                    .unwrap_or_default()
+                    .to_string();
+                ([("x-range", range)], vec![b'a'; 4096])
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        Url::parse(&url).unwrap()
+    }
+
+    #[tokio::test]
+    async fn only_pages_are_cut_short() {
+        let url = page_server().await;
+        let fetcher = ReqwestFetcher::new(Client::new(), None).max_body_bytes(Some(1024));
+
+        let page = fetcher.fetch_page(&url, HeaderMap::new()).await.unwrap();
+        assert_eq!(page.headers["x-range"], "bytes=0-1023");
+        assert_eq!(page.body.len(), 1024);
+        assert!(page.truncated);
+
+        let other = fetcher.fetch(&url).await.unwrap();
+        assert_eq!(other.headers["x-range"], "");
+        assert_eq!(other.body.len(), 4096);
+        assert!(!other.truncated);
+    }
+
+    #[tokio::test]
+    async fn a_zero_limit_fetches_the_whole_page() {
+        let url = page_server().await;
+        let fetcher = ReqwestFetcher::new(Client::new(), None).max_body_bytes(Some(0));
+
+        let page = fetcher.fetch_page(&url, HeaderMap::new()).await.unwrap();
+        assert_eq!(page.headers["x-range"], "");
+        assert_eq!(page.body.len(), 4096);
+        assert!(!page.truncated);
+    }
+}
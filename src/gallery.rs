@@ -0,0 +1,112 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::{collections::HashMap, fmt::Write};
+
+use crate::{image_store::ImageStore, model::Image};
+
+/// The parts of database.json needed for the gallery
+#[derive(Deserialize)]
+struct ImageDatabaseFile {
+    images: HashMap<String, Image>,
+}
+
+/// Escapes text so it can be placed inside HTML
+/// elements and attributes
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Formats a size in bytes for humans, e.g. 12.3 KB
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Reads the database.json in `store` and writes a
+/// gallery.html next to it, showing every downloaded image
+/// with its alt text, size and the page it was found on.
+/// Returns where the gallery is.
+pub async fn write_gallery(store: &dyn ImageStore) -> Result<String> {
+    let database_location = store.location("database.json");
+    let database = store
+        .read("database.json")
+        .await
+        .with_context(|| format!("could not read {}", database_location))?
+        .ok_or_else(|| anyhow!("could not read {}: not found", database_location))?;
+    let database: ImageDatabaseFile = serde_json::from_slice(&database)?;
+
+    let mut images: Vec<&Image> = database
+        .images
+        .values()
+        .filter(|i| i.file.is_some())
+        .collect();
+    images.sort_by(|a, b| a.page.cmp(&b.page).then(a.file.cmp(&b.file)));
+
+    let mut cards = String::new();
+    for image in images {
+        let file = escape_html(image.file.as_deref().unwrap_or_default());
+        let alt = escape_html(image.alt.as_deref().unwrap_or_default());
+        let page = escape_html(image.page.as_deref().unwrap_or_default());
+
+        write!(
+            cards,
+            r#"<figure>
+  <a href="{file}"><img src="{file}" alt="{alt}" loading="lazy"></a>
+  <figcaption>
+    <p>{alt_text}</p>
+    <p><a href="{page}">{page}</a></p>
+    <p>{size}</p>
+  </figcaption>
+</figure>
+"#,
+            alt_text = if alt.is_empty() {
+                "<em>no alt text</em>"
+            } else {
+                &alt
+            },
+            size = image.size.map(format_size).unwrap_or_default(),
+        )?;
+    }
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Image gallery</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+main {{ display: grid; grid-template-columns: repeat(auto-fill, minmax(200px, 1fr)); gap: 1em; }}
+figure {{ margin: 0; border: 1px solid #ddd; padding: 0.5em; overflow-wrap: anywhere; }}
+img {{ width: 100%; height: 150px; object-fit: contain; background: #f4f4f4; }}
+figcaption p {{ margin: 0.25em 0; font-size: 0.8em; }}
+</style>
+</head>
+<body>
+<h1>Image gallery</h1>
+<main>
+{cards}</main>
+</body>
+</html>
+"#
+    );
+
+    store.write("gallery.html", html.into_bytes()).await?;
+    Ok(store.location("gallery.html"))
+}
@@ -0,0 +1,330 @@
+//! A read-only HTTP API over a saved link graph, so the crawl
+//! can be queried without parsing its JSON, see `serve-graph`
+
+use axum::{
+    extract::{FromRef, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use url::Url;
+
+use crate::graphql;
+use crate::model::{Link, LinkGraph, LinkId};
+
+/// How many links a page of results holds by default
+pub(crate) const DEFAULT_LIMIT: usize = 100;
+/// The most links a page of results can hold
+pub(crate) const MAX_LIMIT: usize = 1000;
+
+type GraphRef = Arc<LinkGraph>;
+
+/// The crawled pages linking to each page, see `LinkGraph::referrers`
+pub type Referrers = HashMap<LinkId, BTreeSet<LinkId>>;
+
+/// The graph served, with the referrers of its pages
+/// worked out once rather than on every request
+#[derive(Clone)]
+struct ApiState {
+    link_graph: GraphRef,
+    referrers: Arc<Referrers>,
+}
+
+impl FromRef<ApiState> for GraphRef {
+    fn from_ref(state: &ApiState) -> Self {
+        state.link_graph.clone()
+    }
+}
+
+impl FromRef<ApiState> for Arc<Referrers> {
+    fn from_ref(state: &ApiState) -> Self {
+        state.referrers.clone()
+    }
+}
+
+/// A link in a list of results, the whole
+/// link is at `/link/{id}`
+#[derive(Debug, Serialize)]
+pub struct LinkSummary<'a> {
+    pub id: LinkId,
+    pub url: &'a str,
+    pub status: Option<u16>,
+    pub titles: &'a [String],
+}
+
+impl<'a> From<&'a Link> for LinkSummary<'a> {
+    fn from(link: &'a Link) -> Self {
+        LinkSummary {
+            id: link.id,
+            url: &link.url,
+            status: link.status,
+            titles: &link.titles,
+        }
+    }
+}
+
+/// A page of the links matching a query
+#[derive(Debug, Serialize)]
+pub struct LinkPage<'a> {
+    /// how many links match, over all the pages
+    pub total: usize,
+    pub offset: usize,
+    pub links: Vec<LinkSummary<'a>>,
+}
+
+/// Which page of the results to return
+#[derive(Debug, Deserialize)]
+pub struct Paging {
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+}
+
+impl Paging {
+    fn page<'a>(&self, links: impl Iterator<Item = &'a Link>) -> LinkPage<'a> {
+        let links: Vec<&Link> = links.collect();
+        let limit = self.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+        LinkPage {
+            total: links.len(),
+            offset: self.offset,
+            links: links
+                .into_iter()
+                .skip(self.offset)
+                .take(limit)
+                .map(LinkSummary::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LinksQuery {
+    /// only the links on this host
+    domain: Option<String>,
+    /// only the links served with this status
+    status: Option<u16>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    /// the text one of the titles has to contain, ignoring case
+    title: String,
+}
+
+/// An error answered as `{"error": "..."}`
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = serde_json::json!({ "error": self.1 });
+        (self.0, Json(body)).into_response()
+    }
+}
+
+/// Whether `link` is on `host`, which is lowercase
+pub(crate) fn on_host(link: &Link, host: &str) -> bool {
+    Url::parse(&link.url)
+        .ok()
+        .and_then(|url| url.host_str().map(|h| h == host))
+        .unwrap_or(false)
+}
+
+/// Whether one of the titles of `link` contains `needle`,
+/// ignoring case. `needle` is lowercase
+pub(crate) fn title_contains(link: &Link, needle: &str) -> bool {
+    let title = link.headings.iter().flat_map(|h| h.title.iter());
+    link.titles
+        .iter()
+        .chain(title)
+        .any(|title| title.to_lowercase().contains(needle))
+}
+
+fn find_link(link_graph: &LinkGraph, id: LinkId) -> Result<&Link, ApiError> {
+    link_graph
+        .get_by_id(id)
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("no link with id {}", id)))
+}
+
+/// The size of the graph and where it was crawled from
+async fn summary(State(link_graph): State<GraphRef>) -> Response {
+    Json(serde_json::json!({
+        "pages": link_graph.len(),
+        "seeds": link_graph.seeds(),
+        "tags": link_graph.tags(),
+    }))
+    .into_response()
+}
+
+/// The links, by id, optionally on a domain or with a status
+async fn links(
+    State(link_graph): State<GraphRef>,
+    Query(query): Query<LinksQuery>,
+    Query(paging): Query<Paging>,
+) -> Response {
+    let domain = query.domain.map(|domain| domain.to_lowercase());
+    let links = link_graph.into_iter().map(|(_, link)| link).filter(|link| {
+        domain.as_ref().is_none_or(|domain| on_host(link, domain))
+            && query
+                .status
+                .is_none_or(|status| link.status == Some(status))
+    });
+    Json(paging.page(links)).into_response()
+}
+
+/// Everything known about a link
+async fn link(State(link_graph): State<GraphRef>, Path(id): Path<LinkId>) -> Response {
+    match find_link(&link_graph, id) {
+        Ok(link) => Json(link).into_response(),
+        Err(e) => e.into_response(),
+    }
+}
+
+/// The crawled pages a link's page links to
+async fn children(
+    State(link_graph): State<GraphRef>,
+    Path(id): Path<LinkId>,
+    Query(paging): Query<Paging>,
+) -> Response {
+    match find_link(&link_graph, id) {
+        Ok(link) => {
+            let children = link
+                .children
+                .iter()
+                .filter_map(|id| link_graph.get_by_id(*id));
+            Json(paging.page(children)).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// The crawled pages linking to a link
+async fn parents(
+    State(link_graph): State<GraphRef>,
+    State(referrers): State<Arc<Referrers>>,
+    Path(id): Path<LinkId>,
+    Query(paging): Query<Paging>,
+) -> Response {
+    match find_link(&link_graph, id) {
+        Ok(link) => {
+            let parents = referrers
+                .get(&link.id)
+                .into_iter()
+                .flatten()
+                .filter_map(|id| link_graph.get_by_id(*id));
+            Json(paging.page(parents)).into_response()
+        }
+        Err(e) => e.into_response(),
+    }
+}
+
+/// The links with a title containing the query
+async fn search(
+    State(link_graph): State<GraphRef>,
+    Query(query): Query<SearchQuery>,
+    Query(paging): Query<Paging>,
+) -> Response {
+    let needle = query.title.to_lowercase();
+    let links = link_graph
+        .into_iter()
+        .map(|(_, link)| link)
+        .filter(|link| title_contains(link, &needle));
+    Json(paging.page(links)).into_response()
+}
+
+/// The routes of the API over `link_graph`:
+///
+/// - `GET /`: the number of pages, the seeds and the tags
+/// - `GET /links?domain=&status=`: the links, by id
+/// - `GET /link/{id}`: everything known about a link
+/// - `GET /link/{id}/children` and `/link/{id}/parents`, the
+///   crawled pages it links to and the ones linking to it
+/// - `GET /search?title=`: the links with matching titles
+/// - `POST /graphql`: the GraphQL API, see `graphql`, which
+///   `GET /graphql` explores
+///
+/// The lists take `offset` and `limit` (at most 1000) query
+/// parameters and answer `{"total", "offset", "links"}`
+pub fn router(link_graph: LinkGraph) -> Router {
+    let state = ApiState {
+        referrers: Arc::new(link_graph.referrers()),
+        link_graph: Arc::new(link_graph),
+    };
+    Router::new()
+        .route("/", get(summary))
+        .route("/links", get(links))
+        .route("/link/:id", get(link))
+        .route("/link/:id/children", get(children))
+        .route("/link/:id/parents", get(parents))
+        .route("/search", get(search))
+        .with_state(state.clone())
+        .merge(graphql::router(state.link_graph, state.referrers))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::model::{Edge, ScrapeOutput};
+
+    /// `a` links to `b` and `c`, and `b` to `c`,
+    /// but `c` was only queued from `a`
+    pub(crate) fn linked_graph() -> LinkGraph {
+        let linking = |links: &[&str]| ScrapeOutput {
+            links: links.iter().map(|to| to.to_string()).collect(),
+            edges: links
+                .iter()
+                .map(|to| Edge {
+                    to: to.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut graph = LinkGraph::default();
+        graph
+            .update(
+                "https://a.com/",
+                "",
+                &linking(&["https://a.com/b", "https://a.com/c"]),
+            )
+            .unwrap();
+        graph
+            .update(
+                "https://a.com/b",
+                "https://a.com/",
+                &linking(&["https://a.com/c"]),
+            )
+            .unwrap();
+        graph
+            .update("https://a.com/c", "https://a.com/", &linking(&[]))
+            .unwrap();
+        graph
+    }
+
+    #[tokio::test]
+    async fn parents_lists_every_page_linking_to_a_link() {
+        let link_graph = linked_graph();
+        let c = link_graph.get("https://a.com/c").unwrap().id;
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://{}/link/{}/parents",
+            listener.local_addr().unwrap(),
+            c
+        );
+        tokio::spawn(async move { axum::serve(listener, router(link_graph)).await });
+
+        let page: serde_json::Value = reqwest::get(url).await.unwrap().json().await.unwrap();
+        let mut urls: Vec<&str> = page["links"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|link| link["url"].as_str().unwrap())
+            .collect();
+        urls.sort();
+        assert_eq!(page["total"], 2);
+        assert_eq!(urls, ["https://a.com/", "https://a.com/b"]);
+    }
+}
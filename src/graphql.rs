@@ -0,0 +1,441 @@
+//! A GraphQL API over a saved link graph, for exploring it
+//! with nested queries (e.g. the titles of the children of
+//! the pages of a domain), see `serve-graph`
+
+use async_graphql::{
+    http::GraphiQLSource, ComplexObject, Context, EmptyMutation, EmptySubscription, InputObject,
+    Object, OutputType, Result, Schema, SimpleObject, ID,
+};
+use axum::{
+    extract::State,
+    response::{Html, IntoResponse},
+    routing::get,
+    Json, Router,
+};
+use std::sync::Arc;
+
+use crate::graph_api::{on_host, title_contains, Referrers, DEFAULT_LIMIT, MAX_LIMIT};
+use crate::model::{Edge, Image, Link, LinkGraph, LinkId};
+
+/// How deep the queries can nest, as each level
+/// of children can multiply the links to resolve
+const MAX_DEPTH: usize = 12;
+/// How many fields a query can resolve, each list
+/// counting its fields once per item it can return
+const MAX_COMPLEXITY: usize = 50_000;
+/// How many links the lists nested in a link (its
+/// children and parents) hold by default, and at most
+const DEFAULT_NESTED_LIMIT: usize = 20;
+const MAX_NESTED_LIMIT: usize = 100;
+
+pub type GraphSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+fn link_graph<'a>(ctx: &Context<'a>) -> &'a LinkGraph {
+    ctx.data_unchecked::<Arc<LinkGraph>>()
+}
+
+fn referrers<'a>(ctx: &Context<'a>) -> &'a Referrers {
+    ctx.data_unchecked::<Arc<Referrers>>()
+}
+
+/// The complexity of a list returning up to `limit` items of
+/// `child_complexity` each, see `MAX_COMPLEXITY`
+fn list_complexity(limit: Option<usize>, child_complexity: usize) -> usize {
+    limit
+        .unwrap_or(DEFAULT_LIMIT)
+        .min(MAX_LIMIT)
+        .saturating_mul(child_complexity)
+}
+
+/// The complexity of the children or parents of a link
+fn nested_complexity(limit: Option<usize>, child_complexity: usize) -> usize {
+    limit
+        .unwrap_or(DEFAULT_NESTED_LIMIT)
+        .min(MAX_NESTED_LIMIT)
+        .saturating_mul(child_complexity)
+}
+
+/// A page of the items matching a query
+#[derive(SimpleObject)]
+#[graphql(concrete(name = "LinkPage", params(LinkNode)))]
+#[graphql(concrete(name = "EdgePage", params(EdgeNode)))]
+#[graphql(concrete(name = "ImagePage", params(ImageNode)))]
+pub struct Page<T: OutputType> {
+    /// how many items match, over all the pages
+    total: usize,
+    offset: usize,
+    nodes: Vec<T>,
+}
+
+impl<T: OutputType> Page<T> {
+    fn of(items: impl Iterator<Item = T>, offset: usize, limit: Option<usize>) -> Self {
+        Self::limited(items, offset, limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT))
+    }
+
+    /// The page of a list nested in a link, see `MAX_NESTED_LIMIT`
+    fn nested(items: impl Iterator<Item = T>, offset: usize, limit: Option<usize>) -> Self {
+        let limit = limit.unwrap_or(DEFAULT_NESTED_LIMIT).min(MAX_NESTED_LIMIT);
+        Self::limited(items, offset, limit)
+    }
+
+    fn limited(items: impl Iterator<Item = T>, offset: usize, limit: usize) -> Self {
+        let items: Vec<T> = items.collect();
+        Page {
+            total: items.len(),
+            offset,
+            nodes: items.into_iter().skip(offset).take(limit).collect(),
+        }
+    }
+}
+
+/// Which links to return
+#[derive(InputObject, Default)]
+pub struct LinkFilter {
+    /// only the links on this host
+    domain: Option<String>,
+    /// only the links served with this status
+    status: Option<u16>,
+    /// only the links with a title containing this, ignoring case
+    title_contains: Option<String>,
+}
+
+impl LinkFilter {
+    fn matches(&self, link: &Link) -> bool {
+        self.domain
+            .as_ref()
+            .is_none_or(|domain| on_host(link, &domain.to_lowercase()))
+            && self.status.is_none_or(|status| link.status == Some(status))
+            && self
+                .title_contains
+                .as_ref()
+                .is_none_or(|title| title_contains(link, &title.to_lowercase()))
+    }
+}
+
+/// A crawled link
+pub struct LinkNode(LinkId);
+
+impl LinkNode {
+    fn link<'a>(&self, ctx: &Context<'a>) -> Result<&'a Link> {
+        link_graph(ctx)
+            .get_by_id(self.0)
+            .ok_or_else(|| format!("no link with id {}", self.0).into())
+    }
+}
+
+#[Object]
+impl LinkNode {
+    async fn id(&self) -> ID {
+        ID(self.0.to_string())
+    }
+
+    async fn url<'a>(&self, ctx: &Context<'a>) -> Result<&'a str> {
+        Ok(&self.link(ctx)?.url)
+    }
+
+    /// the status the page was served with
+    async fn status(&self, ctx: &Context<'_>) -> Result<Option<u16>> {
+        Ok(self.link(ctx)?.status)
+    }
+
+    /// why the page couldn't be crawled, e.g. `timeout`
+    async fn error(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        let error = self.link(ctx)?.error;
+        Ok(error.and_then(|e| Some(serde_json::to_value(e).ok()?.as_str()?.to_string())))
+    }
+
+    async fn titles<'a>(&self, ctx: &Context<'a>) -> Result<&'a [String]> {
+        Ok(&self.link(ctx)?.titles)
+    }
+
+    /// the size of the body of the page, in bytes
+    async fn size(&self, ctx: &Context<'_>) -> Result<Option<u64>> {
+        Ok(self.link(ctx)?.size)
+    }
+
+    async fn content_type<'a>(&self, ctx: &Context<'a>) -> Result<Option<&'a str>> {
+        Ok(self.link(ctx)?.content_type.as_deref())
+    }
+
+    /// where the page redirected to
+    async fn redirect<'a>(&self, ctx: &Context<'a>) -> Result<Option<&'a str>> {
+        Ok(self.link(ctx)?.redirect.as_deref())
+    }
+
+    /// the crawled pages this page links to
+    #[graphql(complexity = "nested_complexity(limit, child_complexity)")]
+    async fn children(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<LinkFilter>,
+        #[graphql(default)] offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Page<LinkNode>> {
+        let link = self.link(ctx)?;
+        Ok(related(ctx, &link.children, filter, offset, limit))
+    }
+
+    /// the crawled pages linking to this page
+    #[graphql(complexity = "nested_complexity(limit, child_complexity)")]
+    async fn parents(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<LinkFilter>,
+        #[graphql(default)] offset: usize,
+        limit: Option<usize>,
+    ) -> Result<Page<LinkNode>> {
+        let referrers = referrers(ctx).get(&self.0).into_iter().flatten();
+        Ok(related(ctx, referrers, filter, offset, limit))
+    }
+
+    /// the links on this page, crawled or not
+    async fn edges(&self, ctx: &Context<'_>) -> Result<Vec<EdgeNode>> {
+        let link = self.link(ctx)?;
+        Ok(link
+            .edges
+            .iter()
+            .map(|edge| EdgeNode::new(link, edge))
+            .collect())
+    }
+
+    /// the images shown on this page
+    async fn images(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] missing_alt: bool,
+    ) -> Result<Vec<ImageNode>> {
+        let link = self.link(ctx)?;
+        Ok(link
+            .images
+            .iter()
+            .filter(|image| !missing_alt || image.missing_alt())
+            .map(|image| ImageNode::new(link, image))
+            .collect())
+    }
+}
+
+/// The links of `ids` matching the `filter`, if any
+fn related<'a>(
+    ctx: &Context<'_>,
+    ids: impl IntoIterator<Item = &'a LinkId>,
+    filter: Option<LinkFilter>,
+    offset: usize,
+    limit: Option<usize>,
+) -> Page<LinkNode> {
+    let link_graph = link_graph(ctx);
+    let filter = filter.unwrap_or_default();
+    let links = ids
+        .into_iter()
+        .filter_map(|id| link_graph.get_by_id(*id))
+        .filter(|link| filter.matches(link))
+        .map(|link| LinkNode(link.id));
+    Page::nested(links, offset, limit)
+}
+
+/// An `<a>` on a crawled page
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct EdgeNode {
+    /// the url of the page the link is on
+    from: String,
+    /// the url linked to
+    to: String,
+    anchor_text: String,
+    /// the values of the anchor's `rel`, e.g. `nofollow`
+    rel: Vec<String>,
+}
+
+impl EdgeNode {
+    fn new(page: &Link, edge: &Edge) -> Self {
+        EdgeNode {
+            from: page.url.clone(),
+            to: edge.to.clone(),
+            anchor_text: edge.anchor_text.clone(),
+            rel: edge.rel.clone(),
+        }
+    }
+}
+
+#[ComplexObject]
+impl EdgeNode {
+    /// the page linked to, if it was crawled
+    async fn target(&self, ctx: &Context<'_>) -> Option<LinkNode> {
+        link_graph(ctx).get(&self.to).map(|link| LinkNode(link.id))
+    }
+}
+
+/// An image shown on a crawled page
+#[derive(SimpleObject)]
+pub struct ImageNode {
+    url: String,
+    /// empty for a decorative image, null without an `alt`
+    alt: Option<String>,
+    /// the url of the page showing the image
+    page: String,
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+impl ImageNode {
+    fn new(page: &Link, image: &Image) -> Self {
+        let (width, height) = image.dimensions();
+        ImageNode {
+            url: image.link.clone(),
+            alt: image.alt.clone(),
+            page: page.url.clone(),
+            width,
+            height,
+        }
+    }
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// The link with the given `id` or `url`
+    async fn link(
+        &self,
+        ctx: &Context<'_>,
+        id: Option<ID>,
+        url: Option<String>,
+    ) -> Result<Option<LinkNode>> {
+        let link_graph = link_graph(ctx);
+        let link = match (id, url) {
+            (Some(id), _) => link_graph.get_by_id(id.parse()?),
+            (None, Some(url)) => link_graph.get(&url),
+            (None, None) => return Err("either an id or a url is needed".into()),
+        };
+        Ok(link.map(|link| LinkNode(link.id)))
+    }
+
+    /// The crawled links, by id
+    #[graphql(complexity = "list_complexity(limit, child_complexity)")]
+    async fn links(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<LinkFilter>,
+        #[graphql(default)] offset: usize,
+        limit: Option<usize>,
+    ) -> Page<LinkNode> {
+        let filter = filter.unwrap_or_default();
+        let links = link_graph(ctx)
+            .into_iter()
+            .filter(|(_, link)| filter.matches(link))
+            .map(|(id, _)| LinkNode(*id));
+        Page::of(links, offset, limit)
+    }
+
+    /// The links on the crawled pages, optionally only
+    /// the ones with a `rel`, e.g. `nofollow`
+    #[graphql(complexity = "list_complexity(limit, child_complexity)")]
+    async fn edges(
+        &self,
+        ctx: &Context<'_>,
+        rel: Option<String>,
+        #[graphql(default)] offset: usize,
+        limit: Option<usize>,
+    ) -> Page<EdgeNode> {
+        let edges = link_graph(ctx)
+            .edges()
+            .filter(|(_, edge)| rel.as_ref().is_none_or(|rel| edge.rel.contains(rel)))
+            .map(|(page, edge)| EdgeNode::new(page, edge));
+        Page::of(edges, offset, limit)
+    }
+
+    /// The images shown on the crawled pages, a node
+    /// per page showing them
+    #[graphql(complexity = "list_complexity(limit, child_complexity)")]
+    async fn images(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] missing_alt: bool,
+        #[graphql(default)] offset: usize,
+        limit: Option<usize>,
+    ) -> Page<ImageNode> {
+        let images = link_graph(ctx)
+            .into_iter()
+            .flat_map(|(_, link)| link.images.iter().map(move |image| (link, image)))
+            .filter(|(_, image)| !missing_alt || image.missing_alt())
+            .map(|(page, image)| ImageNode::new(page, image));
+        Page::of(images, offset, limit)
+    }
+}
+
+/// The schema of the API over `link_graph`, with
+/// the `referrers` of its pages, see `LinkGraph::referrers`
+pub fn schema(link_graph: Arc<LinkGraph>, referrers: Arc<Referrers>) -> GraphSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(link_graph)
+        .data(referrers)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+async fn graphiql() -> impl IntoResponse {
+    Html(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+async fn execute(
+    State(schema): State<GraphSchema>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    Json(schema.execute(request).await)
+}
+
+/// `POST /graphql` answering the queries, and
+/// `GET /graphql` exploring them in GraphiQL
+pub fn router(link_graph: Arc<LinkGraph>, referrers: Arc<Referrers>) -> Router {
+    Router::new()
+        .route("/graphql", get(graphiql).post(execute))
+        .with_state(schema(link_graph, referrers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph_api::tests::linked_graph;
+
+    fn test_schema() -> GraphSchema {
+        let link_graph = linked_graph();
+        let referrers = Arc::new(link_graph.referrers());
+        schema(Arc::new(link_graph), referrers)
+    }
+
+    #[tokio::test]
+    async fn parents_are_the_pages_linking_to_a_link() {
+        let response = test_schema()
+            .execute(r#"{ link(url: "https://a.com/c") { parents { total nodes { url } } } }"#)
+            .await;
+
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let parents = response.data.into_json().unwrap()["link"]["parents"].clone();
+        let mut urls: Vec<&str> = parents["nodes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|node| node["url"].as_str().unwrap())
+            .collect();
+        urls.sort();
+        assert_eq!(parents["total"], 2);
+        assert_eq!(urls, ["https://a.com/", "https://a.com/b"]);
+    }
+
+    #[tokio::test]
+    async fn nested_lists_are_limited() {
+        let schema = test_schema();
+        let nested = schema
+            .execute(
+                "{ links(limit: 1000) { nodes { children(limit: 100) { nodes {
+                    children(limit: 100) { nodes { url } } } } } } }",
+            )
+            .await;
+        assert!(nested.errors[0].message.contains("too complex"));
+
+        let shallow = schema
+            .execute("{ links { nodes { url children { nodes { url } } } } }")
+            .await;
+        assert!(shallow.errors.is_empty(), "{:?}", shallow.errors);
+    }
+}
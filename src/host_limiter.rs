@@ -0,0 +1,56 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+
+/// Caps how many requests are made to each host
+/// at the same time, whatever the number of workers
+pub struct HostLimiter {
+    limit: usize,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl HostLimiter {
+    pub fn new(limit: usize) -> Self {
+        HostLimiter {
+            limit: limit.max(1),
+            hosts: Default::default(),
+        }
+    }
+
+    /// Whether a request to `host` could start right away
+    pub async fn has_free_slot(&self, host: &str) -> bool {
+        self.hosts
+            .lock()
+            .await
+            .get(host)
+            .is_none_or(|semaphore| semaphore.available_permits() > 0)
+    }
+
+    /// The hosts without a free slot, see `has_free_slot`
+    pub async fn busy_hosts(&self) -> HashSet<String> {
+        self.hosts
+            .lock()
+            .await
+            .iter()
+            .filter(|(_, semaphore)| semaphore.available_permits() == 0)
+            .map(|(host, _)| host.clone())
+            .collect()
+    }
+
+    /// Waits for a free slot for `host`, which is
+    /// given back when the permit is dropped
+    pub async fn acquire(&self, host: &str) -> OwnedSemaphorePermit {
+        let semaphore = self
+            .hosts
+            .lock()
+            .await
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.limit)))
+            .clone();
+
+        // The semaphores are never closed
+        semaphore.acquire_owned().await.unwrap()
+    }
+}
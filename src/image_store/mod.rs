@@ -0,0 +1,194 @@
+//! Where the downloaded images, their database and gallery
+//! are written: a directory, or an S3 bucket with the `s3`
+//! feature, see `--img-dest`
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use std::{
+    collections::HashSet,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::{fs, io::AsyncWriteExt};
+use tokio_stream::StreamExt;
+
+#[cfg(feature = "s3")]
+pub mod s3;
+
+/// The body of a file being downloaded, chunk by chunk
+pub type Chunks<'a> = BoxStream<'a, Result<Bytes>>;
+
+/// A flat set of named files the images are saved to
+#[async_trait]
+pub trait ImageStore: Send + Sync {
+    /// The names of the files already stored
+    async fn list(&self) -> Result<HashSet<String>>;
+
+    /// The contents of the file `name`, if it is stored
+    async fn read(&self, name: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Stores `contents` as the file `name`, replacing it
+    async fn write(&self, name: &str, contents: Vec<u8>) -> Result<()>;
+
+    /// Stores the `chunks` as the file `name` as they arrive,
+    /// replacing it. Nothing is left if they fail halfway
+    async fn write_stream(&self, name: &str, chunks: Chunks<'_>) -> Result<()>;
+
+    async fn remove(&self, name: &str) -> Result<()>;
+
+    /// Where the file `name` is, for the user
+    fn location(&self, name: &str) -> String;
+}
+
+/// Keeps the files in a directory
+pub struct DirectoryStore {
+    directory: PathBuf,
+}
+
+impl DirectoryStore {
+    /// Uses `directory`, creating it if needed
+    pub async fn create(directory: &str) -> Result<DirectoryStore> {
+        fs::create_dir_all(directory)
+            .await
+            .with_context(|| format!("could not create {}", directory))?;
+        Ok(DirectoryStore {
+            directory: PathBuf::from(directory),
+        })
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+}
+
+#[async_trait]
+impl ImageStore for DirectoryStore {
+    async fn list(&self) -> Result<HashSet<String>> {
+        let mut names: HashSet<String> = Default::default();
+        let mut entries = fs::read_dir(&self.directory).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_file() {
+                names.insert(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        Ok(names)
+    }
+
+    async fn read(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        match fs::read(self.directory.join(name)).await {
+            Ok(contents) => Ok(Some(contents)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn write(&self, name: &str, contents: Vec<u8>) -> Result<()> {
+        fs::write(self.directory.join(name), contents).await?;
+        Ok(())
+    }
+
+    async fn write_stream(&self, name: &str, mut chunks: Chunks<'_>) -> Result<()> {
+        let path = self.directory.join(name);
+        let written = async {
+            let mut file = fs::File::create(&path).await?;
+            while let Some(chunk) = chunks.next().await {
+                file.write_all(&chunk?).await?;
+            }
+            file.flush().await?;
+            Ok(())
+        }
+        .await;
+        if written.is_err() {
+            let _ = fs::remove_file(&path).await;
+        }
+        written
+    }
+
+    async fn remove(&self, name: &str) -> Result<()> {
+        fs::remove_file(self.directory.join(name)).await?;
+        Ok(())
+    }
+
+    fn location(&self, name: &str) -> String {
+        self.directory.join(name).display().to_string()
+    }
+}
+
+/// Opens the store at `destination`, either a directory or
+/// `s3://BUCKET/PREFIX`. The S3 credentials and region come from
+/// the environment (see the AWS docs), and `s3_endpoint` points
+/// to an S3 compatible server instead, e.g. MinIO
+pub async fn open_image_store(
+    destination: &str,
+    s3_endpoint: Option<&str>,
+) -> Result<Arc<dyn ImageStore>> {
+    if destination.starts_with("s3://") {
+        open_s3(destination, s3_endpoint).await
+    } else {
+        Ok(Arc::new(DirectoryStore::create(destination).await?))
+    }
+}
+
+#[cfg(feature = "s3")]
+async fn open_s3(uri: &str, endpoint: Option<&str>) -> Result<Arc<dyn ImageStore>> {
+    Ok(Arc::new(s3::S3Store::connect(uri, endpoint).await?))
+}
+
+#[cfg(not(feature = "s3"))]
+async fn open_s3(_uri: &str, _endpoint: Option<&str>) -> Result<Arc<dyn ImageStore>> {
+    anyhow::bail!("saving the images to S3 needs the crawler built with `--features s3`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    async fn temp_store() -> DirectoryStore {
+        let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        DirectoryStore::create(directory.to_str().unwrap())
+            .await
+            .unwrap()
+    }
+
+    fn chunks(chunks: Vec<Result<&'static [u8]>>) -> Chunks<'static> {
+        Box::pin(tokio_stream::iter(
+            chunks
+                .into_iter()
+                .map(|chunk| chunk.map(Bytes::from_static)),
+        ))
+    }
+
+    #[tokio::test]
+    async fn directory_store_keeps_the_files_written() {
+        let store = temp_store().await;
+        store.write("a.json", b"{}".to_vec()).await.unwrap();
+        store
+            .write_stream("b.png", chunks(vec![Ok(b"ab"), Ok(b"cd")]))
+            .await
+            .unwrap();
+
+        assert_eq!(store.read("a.json").await.unwrap(), Some(b"{}".to_vec()));
+        assert_eq!(store.read("b.png").await.unwrap(), Some(b"abcd".to_vec()));
+        assert_eq!(store.read("c.png").await.unwrap(), None);
+        let names = HashSet::from([String::from("a.json"), String::from("b.png")]);
+        assert_eq!(store.list().await.unwrap(), names);
+
+        store.remove("a.json").await.unwrap();
+        assert_eq!(store.read("a.json").await.unwrap(), None);
+        fs::remove_dir_all(store.directory()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn directory_store_drops_a_failed_download() {
+        let store = temp_store().await;
+        let failed = chunks(vec![Ok(b"ab"), Err(anyhow!("connection reset"))]);
+
+        assert!(store.write_stream("b.png", failed).await.is_err());
+        assert!(store.list().await.unwrap().is_empty());
+        fs::remove_dir_all(store.directory()).await.unwrap();
+    }
+}
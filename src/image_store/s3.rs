@@ -0,0 +1,241 @@
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use aws_config::BehaviorVersion;
+use aws_sdk_s3::{
+    error::DisplayErrorContext,
+    primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart},
+    Client,
+};
+use std::collections::HashSet;
+use tokio_stream::StreamExt;
+use url::Url;
+
+use super::{Chunks, ImageStore};
+
+/// The size of the parts of the multipart uploads, S3
+/// needs at least 5MiB for all the parts but the last
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// Keeps the files in an S3 (or S3 compatible) bucket,
+/// under a prefix
+pub struct S3Store {
+    client: Client,
+    bucket: String,
+    /// empty, or ending with a `/`
+    prefix: String,
+}
+
+impl S3Store {
+    /// Connects to the bucket of `s3://BUCKET/PREFIX`, through
+    /// `endpoint` if given instead of AWS
+    pub async fn connect(uri: &str, endpoint: Option<&str>) -> Result<S3Store> {
+        let url = Url::parse(uri)?;
+        let Some(bucket) = url.host_str() else {
+            bail!("no bucket in {}", uri);
+        };
+        let mut prefix = url.path().trim_matches('/').to_string();
+        if !prefix.is_empty() {
+            prefix.push('/');
+        }
+
+        let config = aws_config::defaults(BehaviorVersion::latest()).load().await;
+        let mut s3_config = aws_sdk_s3::config::Builder::from(&config);
+        if let Some(endpoint) = endpoint {
+            // MinIO and most other servers don't do bucket subdomains
+            s3_config = s3_config.endpoint_url(endpoint).force_path_style(true);
+        }
+
+        Ok(S3Store {
+            client: Client::from_conf(s3_config.build()),
+            bucket: bucket.to_string(),
+            prefix,
+        })
+    }
+
+    fn key(&self, name: &str) -> String {
+        format!("{}{}", self.prefix, name)
+    }
+
+    /// Uploads `first` and the rest of the `chunks` as the parts
+    /// of the multipart upload `upload_id`, in order
+    async fn upload_parts(
+        &self,
+        name: &str,
+        upload_id: &str,
+        first: Vec<u8>,
+        chunks: &mut Chunks<'_>,
+    ) -> Result<Vec<CompletedPart>> {
+        let mut parts: Vec<CompletedPart> = Default::default();
+        let mut part = first;
+        while !part.is_empty() {
+            let part_number = parts.len() as i32 + 1;
+            let uploaded = self
+                .client
+                .upload_part()
+                .bucket(&self.bucket)
+                .key(self.key(name))
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .body(ByteStream::from(part))
+                .send()
+                .await
+                .map_err(|e| anyhow!("{}", DisplayErrorContext(e)))?;
+            parts.push(
+                CompletedPart::builder()
+                    .set_e_tag(uploaded.e_tag)
+                    .part_number(part_number)
+                    .build(),
+            );
+            part = next_part(chunks).await?;
+        }
+        Ok(parts)
+    }
+}
+
+/// The next `PART_SIZE` bytes of `chunks`, or
+/// what is left of them, empty once they ended
+async fn next_part(chunks: &mut Chunks<'_>) -> Result<Vec<u8>> {
+    let mut part: Vec<u8> = Vec::with_capacity(PART_SIZE);
+    while part.len() < PART_SIZE {
+        match chunks.next().await {
+            Some(chunk) => part.extend_from_slice(&chunk?),
+            None => break,
+        }
+    }
+    Ok(part)
+}
+
+/// The Content-Type the file `name` is served with,
+/// so the images and gallery open in a browser
+fn content_type(name: &str) -> &'static str {
+    match name.rsplit('.').next().unwrap_or_default() {
+        "gif" => "image/gif",
+        "jpg" => "image/jpeg",
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "tif" => "image/tiff",
+        "json" | "index" => "application/json",
+        "csv" => "text/csv",
+        "html" => "text/html; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+#[async_trait]
+impl ImageStore for S3Store {
+    async fn list(&self) -> Result<HashSet<String>> {
+        let mut names: HashSet<String> = Default::default();
+        let mut pages = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(&self.prefix)
+            .into_paginator()
+            .send();
+        while let Some(page) = pages.next().await {
+            let page = page.map_err(|e| anyhow!("{}", DisplayErrorContext(e)))?;
+            for object in page.contents() {
+                if let Some(name) = object.key().and_then(|k| k.strip_prefix(&self.prefix)) {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    async fn read(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let request = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(self.key(name));
+        match request.send().await {
+            Ok(object) => Ok(Some(object.body.collect().await?.into_bytes().to_vec())),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_no_such_key()) => Ok(None),
+            Err(e) => Err(anyhow!("{}", DisplayErrorContext(e))),
+        }
+    }
+
+    async fn write(&self, name: &str, contents: Vec<u8>) -> Result<()> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(self.key(name))
+            .content_type(content_type(name))
+            .body(ByteStream::from(contents))
+            .send()
+            .await
+            .map_err(|e| anyhow!("{}", DisplayErrorContext(e)))?;
+        Ok(())
+    }
+
+    async fn write_stream(&self, name: &str, mut chunks: Chunks<'_>) -> Result<()> {
+        let first = next_part(&mut chunks).await?;
+        if first.len() < PART_SIZE {
+            return self.write(name, first).await;
+        }
+
+        // Only a part is kept in memory at a time
+        let upload = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(self.key(name))
+            .content_type(content_type(name))
+            .send()
+            .await
+            .map_err(|e| anyhow!("{}", DisplayErrorContext(e)))?;
+        let Some(upload_id) = upload.upload_id else {
+            bail!("no upload id for {}", self.location(name));
+        };
+
+        let parts = match self
+            .upload_parts(name, &upload_id, first, &mut chunks)
+            .await
+        {
+            Ok(parts) => parts,
+            Err(e) => {
+                let _ = self
+                    .client
+                    .abort_multipart_upload()
+                    .bucket(&self.bucket)
+                    .key(self.key(name))
+                    .upload_id(&upload_id)
+                    .send()
+                    .await;
+                return Err(e);
+            }
+        };
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(self.key(name))
+            .upload_id(&upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| anyhow!("{}", DisplayErrorContext(e)))?;
+        Ok(())
+    }
+
+    async fn remove(&self, name: &str) -> Result<()> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(self.key(name))
+            .send()
+            .await
+            .map_err(|e| anyhow!("{}", DisplayErrorContext(e)))?;
+        Ok(())
+    }
+
+    fn location(&self, name: &str) -> String {
+        format!("s3://{}/{}", self.bucket, self.key(name))
+    }
+}
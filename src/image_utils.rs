@@ -18,50 +18,423 @@ input:
 }
 */
 
-use anyhow::{anyhow, bail, Result};
-use std::collections::HashMap;
-use std::path::Path;
-
-use log2::*;
-use reqwest::{Client, Response};
-use tokio::fs::{create_dir, File};
-use tokio::io::AsyncWriteExt;
+use anyhow::{anyhow, bail, Context, Result};
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::Cursor;
+
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{Client, Response, StatusCode};
 use tokio_stream::StreamExt;
+use tracing::{error, info};
 use uuid::Uuid;
 
+use crate::image_store::ImageStore;
 use crate::model::{Image, LinkGraph};
+use crate::response_cache::sha256_hex;
+use sha2::{Digest, Sha256};
+
+/// The contents of the image database file, with
+/// the tags of the crawl that found the images
+#[derive(Serialize)]
+pub struct ImageDatabase<'a> {
+    pub tags: &'a BTreeMap<String, String>,
+    pub images: &'a HashMap<String, Image>,
+}
+
+/// The images of the database.json a previous run
+/// wrote, the tags aren't needed
+#[derive(Deserialize)]
+struct StoredImageDatabase {
+    images: HashMap<String, Image>,
+}
+
+/// Loads the images of the database.json a previous
+/// run left in `store`, if there is one
+pub async fn load_image_database(store: &dyn ImageStore) -> Result<Option<HashMap<String, Image>>> {
+    let Some(contents) = store.read("database.json").await? else {
+        return Ok(None);
+    };
+    let database: StoredImageDatabase = serde_json::from_slice(&contents)
+        .with_context(|| format!("invalid image database {}", store.location("database.json")))?;
+    Ok(Some(database.images))
+}
+
+/// Gives the images already downloaded by a previous run
+/// (see `load_image_database`) their files back, so they
+/// aren't downloaded again. Returns the names of these images,
+/// the other references to the same links are removed like
+/// `download_images` does. `stored` are the files in the store
+pub fn reuse_previous_images(
+    images: &mut HashMap<String, Image>,
+    previous: &HashMap<String, Image>,
+    stored: &HashSet<String>,
+) -> HashSet<String> {
+    let previous_by_link: HashMap<&str, &Image> = previous
+        .values()
+        .filter(|image| {
+            image
+                .file
+                .as_ref()
+                .is_some_and(|file| stored.contains(file))
+        })
+        .map(|image| (image.link.as_str(), image))
+        .collect();
+
+    let mut reused: HashSet<String> = Default::default();
+    let mut reused_links: HashSet<String> = Default::default();
+    let mut duplicates: Vec<String> = Default::default();
+    for (name, image) in images.iter_mut() {
+        let Some(old) = previous_by_link.get(image.link.as_str()) else {
+            continue;
+        };
+        if !reused_links.insert(image.link.clone()) {
+            duplicates.push(name.clone());
+            continue;
+        }
+
+        image.final_link = old.final_link.clone();
+        image.file = old.file.clone();
+        image.size = old.size;
+        image.sha256 = old.sha256.clone();
+        image.natural_width = old.natural_width;
+        image.natural_height = old.natural_height;
+        reused.insert(name.clone());
+    }
+
+    for name in duplicates {
+        images.remove(&name);
+    }
+
+    reused
+}
+
+/// How the images of a site changed since a previous run
+#[derive(Serialize, Default)]
+pub struct ImageChanges {
+    /// the links of the images that weren't there before
+    pub added: BTreeSet<String>,
+    /// the links of the images that aren't there anymore
+    pub removed: BTreeSet<String>,
+    /// new links serving the same image as a previous
+    /// one, as new link -> previous link
+    pub moved: BTreeMap<String, String>,
+    /// the links of the images still there
+    pub unchanged: BTreeSet<String>,
+}
+
+impl ImageChanges {
+    pub fn between(previous: &HashMap<String, Image>, current: &HashMap<String, Image>) -> Self {
+        let links = |images: &HashMap<String, Image>| -> BTreeSet<String> {
+            images.values().map(|image| image.link.clone()).collect()
+        };
+        let (previous_links, current_links) = (links(previous), links(current));
+
+        let mut previous_by_content: HashMap<&str, Vec<&str>> = Default::default();
+        for image in previous.values() {
+            if let Some(sha256) = image.sha256.as_deref() {
+                previous_by_content
+                    .entry(sha256)
+                    .or_default()
+                    .push(image.link.as_str());
+            }
+        }
+
+        let mut changes = ImageChanges {
+            removed: previous_links.difference(&current_links).cloned().collect(),
+            unchanged: previous_links
+                .intersection(&current_links)
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+        for image in current.values() {
+            if previous_links.contains(&image.link) {
+                continue;
+            }
+
+            let same_content = image
+                .sha256
+                .as_deref()
+                .and_then(|sha256| previous_by_content.get(sha256))
+                .and_then(|links| links.iter().find(|link| changes.removed.contains(**link)));
+            match same_content {
+                Some(previous_link) => {
+                    changes
+                        .moved
+                        .insert(image.link.clone(), previous_link.to_string());
+                }
+                None => {
+                    changes.added.insert(image.link.clone());
+                }
+            }
+        }
+
+        changes
+    }
+}
 
 /// Convert all the images in the found scraped
 /// links to the (Uuid name, image) format
 pub fn convert_links_to_images(links: &LinkGraph) -> HashMap<String, Image> {
     links
         .into_iter()
-        .flat_map(|(_, link)| link.images.clone())
+        .flat_map(|(_, link)| {
+            link.images.iter().map(|img| Image {
+                page: Some(link.url.clone()),
+                ..img.clone()
+            })
+        })
         .map(|img| (Uuid::new_v4().to_string(), img))
         .collect()
 }
 
-/// This function downloads one image into the destination
+/// Orders the image names by how much we want to download
+/// them, for when there are more images than we can download:
+/// the largest declared sizes first, then the images unique to
+/// one page over the ones repeated across the site (logos, icons),
+/// then the images on the pages with the highest PageRank.
+pub fn prioritize_images(images: &HashMap<String, Image>, link_graph: &LinkGraph) -> Vec<String> {
+    let page_ranks = link_graph.page_rank();
+
+    let mut pages_per_image: HashMap<&str, usize> = Default::default();
+    for image in images.values() {
+        *pages_per_image.entry(image.link.as_str()).or_default() += 1;
+    }
+
+    let area = |image: &Image| image.width.unwrap_or(0) as u64 * image.height.unwrap_or(0) as u64;
+    let page_rank = |image: &Image| {
+        image
+            .page
+            .as_deref()
+            .and_then(|page| link_graph.get(page))
+            .and_then(|link| page_ranks.get(&link.id))
+            .copied()
+            .unwrap_or(0.0)
+    };
+
+    let mut names: Vec<&String> = images.keys().collect();
+    names.sort_by(|a, b| {
+        let (a, b) = (&images[*a], &images[*b]);
+        area(b)
+            .cmp(&area(a))
+            .then(pages_per_image[a.link.as_str()].cmp(&pages_per_image[b.link.as_str()]))
+            .then(page_rank(b).total_cmp(&page_rank(a)))
+    });
+
+    names.into_iter().cloned().collect()
+}
+
+/// An image file saved to the image store
+#[derive(Serialize, Deserialize, Clone)]
+struct SavedImage {
+    /// the (post-redirect) link the image was served from
+    final_link: String,
+    file: String,
+    size: u64,
+    /// the hex sha256 of the image, missing
+    /// from the indexes of older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+    /// the real dimensions of the image, if they were measured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    height: Option<u32>,
+    /// the validators sent back when the image was
+    /// downloaded, to ask the server if it changed since
+    #[serde(skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_modified: Option<String>,
+}
+
+/// The `images.index` of an image store, mapping the hash
+/// of each downloaded link to the file it was saved as, so later
+/// crawls don't download the images that haven't changed again
+#[derive(Serialize, Deserialize, Default)]
+pub struct ImageIndex {
+    images: BTreeMap<String, SavedImage>,
+}
+
+impl ImageIndex {
+    const FILE_NAME: &'static str = "images.index";
+
+    /// Loads the index of `store`, empty if there isn't one yet
+    pub async fn load(store: &dyn ImageStore) -> Result<ImageIndex> {
+        match store.read(Self::FILE_NAME).await? {
+            Some(contents) => serde_json::from_slice(&contents).with_context(|| {
+                format!("invalid image index {}", store.location(Self::FILE_NAME))
+            }),
+            None => Ok(Default::default()),
+        }
+    }
+
+    pub async fn save(&self, store: &dyn ImageStore) -> Result<()> {
+        store
+            .write(Self::FILE_NAME, serde_json::to_vec(self)?)
+            .await
+    }
+
+    /// The file `link` was saved to, if it is still `stored`
+    fn get(&self, link: &str, stored: &HashSet<String>) -> Option<&SavedImage> {
+        self.images
+            .get(&sha256_hex(link.as_bytes()))
+            .filter(|saved| stored.contains(&saved.file))
+    }
+
+    /// Another image with the `sha256` content, if it is still `stored`
+    fn find_content(&self, sha256: &str, stored: &HashSet<String>) -> Option<&SavedImage> {
+        self.images
+            .values()
+            .filter(|saved| saved.sha256.as_deref() == Some(sha256))
+            .find(|saved| stored.contains(&saved.file))
+    }
+
+    /// Records the file `link` was saved to, returning
+    /// the one it was saved to before, if any
+    fn insert(&mut self, link: &str, saved: SavedImage) -> Option<SavedImage> {
+        self.images.insert(sha256_hex(link.as_bytes()), saved)
+    }
+
+    /// Whether an image is saved to `file`
+    fn uses(&self, file: &str) -> bool {
+        self.images.values().any(|saved| saved.file == file)
+    }
+}
+
+/// The result of downloading a single image
+enum DownloadOutcome {
+    /// the image was saved to the store
+    Saved(SavedImage),
+    /// the image saved by a previous run hasn't changed
+    Unchanged(SavedImage),
+    /// the final link had already been downloaded
+    /// under another name, so nothing was written
+    Duplicate(String),
+}
+
+/// How much of the start of an image is kept to read its
+/// dimensions from, the formats put them in the header
+const MEASURED_BYTES: usize = 256 * 1024;
+
+/// This function streams one image into the store
 /// using the tokio stream io extensions. Note that this
 /// contains modified code from https://gist.github.com/giuliano-oliveira/4d11d6b3bb003dba3a1b53f43d81b30d
-/// destination - the path to the destination without the extension!
-async fn download_image(link: &str, destination: &str, client: &Client) -> Result<()> {
-    // Download the image
-    let res = client.get(link).send().await?;
+/// name - the name of the file in the store without the extension!
+/// seen_links - the final links downloaded so far, used to skip the
+/// same asset being referenced through different (redirecting) urls
+/// previous - the file a previous run saved the image to, which is
+/// kept if the server says the image didn't change
+/// measure - whether to read the real dimensions of the image
+async fn download_image(
+    link: &str,
+    name: &str,
+    store: &dyn ImageStore,
+    client: &Client,
+    seen_links: &mut HashSet<String>,
+    previous: Option<&SavedImage>,
+    measure: bool,
+) -> Result<DownloadOutcome> {
+    // Download the image, without validators the
+    // content tells whether it changed
+    let mut request = client.get(link);
+    if let Some(previous) = previous {
+        if let Some(etag) = &previous.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &previous.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+    let res = request.send().await?;
+
+    if let (Some(previous), StatusCode::NOT_MODIFIED) = (previous, res.status()) {
+        return Ok(unchanged(previous, seen_links));
+    }
+    // Error pages can be images too, e.g. a CDN's placeholder
+    if !res.status().is_success() {
+        bail!("the server answered {}", res.status());
+    }
+
+    // Redirects have been followed by now, so this is the real asset
+    let final_link = res.url().to_string();
+    if seen_links.contains(&final_link) {
+        return Ok(DownloadOutcome::Duplicate(final_link));
+    }
 
     // Get the content type here
     let extension = get_extension(&res)?;
+    let header = |name| {
+        res.headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(String::from)
+    };
+    let etag = header(ETAG);
+    let last_modified = header(LAST_MODIFIED);
 
-    let mut file = File::create(destination.to_string() + "." + extension).await?;
-    let mut stream = res.bytes_stream();
+    let file_name = format!("{}.{}", name, extension);
 
-    // download chunks
-    while let Some(item) = stream.next().await {
-        let chunk = item?;
-        file.write_all(&chunk).await?;
+    // download chunks, straight into the store
+    let mut hasher = Sha256::new();
+    let mut size: u64 = 0;
+    let mut head: Vec<u8> = Default::default();
+    let chunks = res.bytes_stream().map(|chunk| -> Result<Bytes> {
+        let chunk = chunk?;
+        hasher.update(&chunk);
+        size += chunk.len() as u64;
+        if measure && head.len() < MEASURED_BYTES {
+            let kept = chunk.len().min(MEASURED_BYTES - head.len());
+            head.extend_from_slice(&chunk[..kept]);
+        }
+        Ok(chunk)
+    });
+    store.write_stream(&file_name, Box::pin(chunks)).await?;
+    // Only once stored, so a failed download doesn't
+    // turn the other urls of the asset into duplicates
+    seen_links.insert(final_link.clone());
+
+    let mut dimensions = None;
+    if measure {
+        match measure_dimensions(head).await {
+            Ok(measured) => dimensions = Some(measured),
+            Err(e) => info!("Could not measure image {}: {}", link, e),
+        }
     }
 
-    Ok(())
+    Ok(DownloadOutcome::Saved(SavedImage {
+        final_link,
+        file: file_name,
+        size,
+        sha256: Some(format!("{:x}", hasher.finalize())),
+        width: dimensions.map(|(width, _)| width),
+        height: dimensions.map(|(_, height)| height),
+        etag,
+        last_modified,
+    }))
+}
+
+/// The width and height of the image starting with `head`,
+/// failing for the formats that can't be measured (SVG)
+async fn measure_dimensions(head: Vec<u8>) -> Result<(u32, u32)> {
+    // Only the header is decoded, but keep it off the runtime
+    tokio::task::spawn_blocking(move || {
+        image::ImageReader::new(Cursor::new(head))
+            .with_guessed_format()?
+            .into_dimensions()
+            .map_err(anyhow::Error::from)
+    })
+    .await?
+}
+
+/// Reuses the file saved by a previous run
+fn unchanged(previous: &SavedImage, seen_links: &mut HashSet<String>) -> DownloadOutcome {
+    if !seen_links.insert(previous.final_link.clone()) {
+        return DownloadOutcome::Duplicate(previous.final_link.clone());
+    }
+    DownloadOutcome::Unchanged(previous.clone())
 }
 
 fn get_extension(res: &Response) -> Result<&str> {
@@ -84,40 +457,291 @@ fn get_extension(res: &Response) -> Result<&str> {
 }
 
 /// Takes in the hashmap (image name, image info), downloads the images
-/// and saves them to disk.
+/// in the `priority` order (see `prioritize_images`) and saves them to the store. Each downloaded image gets its final link
+/// recorded, and images that redirect to an already downloaded asset
+/// are removed from the map so they are only stored once. Images in
+/// the store's `ImageIndex` keep their file unless they changed, going
+/// by their validators or else their content, and the file a changed
+/// image replaces is removed. An error response (even one served
+/// as an image) leaves the index and the saved file as they are.
+/// With `measure`, the real dimensions of the images are read as they
+/// are downloaded, skipping the formats that can't be measured (SVG)
 pub async fn download_images(
-    images: &HashMap<String, Image>,
-    save_directory: &str,
+    images: &mut HashMap<String, Image>,
+    priority: &[String],
+    store: &dyn ImageStore,
     max_links: u64,
+    client: &Client,
+    measure: bool,
 ) -> Result<()> {
-    let directory_path = Path::new(&save_directory);
-    if !directory_path.is_dir() {
-        // bail!("given save directory is invalid");
-        create_dir(directory_path).await?;
-    }
-
-    let client = reqwest::Client::new();
-    for (name, image) in images.iter().take(max_links as usize) {
-        // directory + name + extension
-        let destination_path = directory_path.join(name);
-        let destination = destination_path
-            .to_str()
-            .ok_or_else(|| anyhow!("could not get destination path"))?;
-
-        if let Err(e) = download_image(&image.link, destination, &client).await {
-            error!("Could not download image {}, error: {}", image.link, e);
+    let mut stored = store.list().await?;
+    let mut index = ImageIndex::load(store).await?;
+    let mut seen_links: HashSet<String> = Default::default();
+    let mut duplicates: Vec<String> = Default::default();
+    for name in priority.iter().take(max_links as usize) {
+        let Some(image) = images.get_mut(name) else {
+            continue;
+        };
+
+        let previous = index.get(&image.link, &stored);
+        let downloaded = download_image(
+            &image.link,
+            name,
+            store,
+            client,
+            &mut seen_links,
+            previous,
+            measure,
+        );
+        match downloaded.await {
+            Ok(DownloadOutcome::Saved(mut saved)) => {
+                // The same content under a new url is only stored once
+                let same_content = saved
+                    .sha256
+                    .as_deref()
+                    .and_then(|sha256| index.find_content(sha256, &stored))
+                    .filter(|existing| existing.file != saved.file)
+                    .map(|existing| existing.file.clone());
+                match same_content {
+                    Some(existing) => {
+                        info!("Image {} is the same as {}", image.link, existing);
+                        store.remove(&saved.file).await?;
+                        saved.file = existing;
+                    }
+                    None => {
+                        stored.insert(saved.file.clone());
+                    }
+                }
+
+                image.final_link = Some(saved.final_link.clone());
+                image.file = Some(saved.file.clone());
+                image.size = Some(saved.size);
+                image.sha256 = saved.sha256.clone();
+                image.natural_width = saved.width;
+                image.natural_height = saved.height;
+                let file = saved.file.clone();
+                let replaced = index.insert(&image.link, saved);
+
+                // The image changed, so its previous file is left unused
+                if let Some(old) = replaced.filter(|old| old.file != file && !index.uses(&old.file))
+                {
+                    match store.remove(&old.file).await {
+                        Ok(()) => {
+                            stored.remove(&old.file);
+                        }
+                        Err(e) => error!(
+                            "Could not remove replaced image {}: {}",
+                            store.location(&old.file),
+                            e
+                        ),
+                    }
+                }
+            }
+            Ok(DownloadOutcome::Unchanged(saved)) => {
+                info!("Image {} unchanged since {}", image.link, saved.file);
+                image.final_link = Some(saved.final_link);
+                image.file = Some(saved.file);
+                image.size = Some(saved.size);
+                image.sha256 = saved.sha256;
+                if measure {
+                    image.natural_width = saved.width;
+                    image.natural_height = saved.height;
+                }
+            }
+            Ok(DownloadOutcome::Duplicate(final_link)) => {
+                info!("Image {} already downloaded as {}", image.link, final_link);
+                duplicates.push(name.clone());
+            }
+            Err(e) => {
+                error!("Could not download image {}, error: {}", image.link, e);
+            }
+        }
+    }
+
+    for name in duplicates {
+        images.remove(&name);
+    }
+
+    index.save(store).await
+}
+
+/// Removes the images narrower or shorter than `min_size` pixels
+/// (tracking pixels, spacers), deleting their files when no other
+/// image uses them. Returns how many images were removed
+pub async fn remove_small_images(
+    images: &mut HashMap<String, Image>,
+    min_size: u32,
+    store: &dyn ImageStore,
+) -> Result<usize> {
+    let is_small = |image: &Image| {
+        let (width, height) = image.dimensions();
+        width.is_some_and(|w| w < min_size) || height.is_some_and(|h| h < min_size)
+    };
+
+    let small: Vec<String> = images
+        .iter()
+        .filter(|(_, image)| is_small(image))
+        .map(|(name, _)| name.clone())
+        .collect();
+
+    for name in small.iter() {
+        let Some(file) = images.remove(name).and_then(|image| image.file) else {
+            continue;
+        };
+        // Identical images share their file
+        if images
+            .values()
+            .any(|image| image.file.as_ref() == Some(&file))
+        {
+            continue;
+        }
+
+        if let Err(e) = store.remove(&file).await {
+            error!(
+                "Could not remove small image {}: {}",
+                store.location(&file),
+                e
+            );
         }
     }
 
-    Ok(())
+    Ok(small.len())
 }
 
-// #[cfg(test)]
-// mod tests {
-//     // use crate::model::Image;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_store::DirectoryStore;
+    use axum::{extract::State, http::header::CONTENT_TYPE, routing::get, Router};
+    use std::sync::{Arc, Mutex};
+
+    /// The status and contents served for the image
+    type Served = Arc<Mutex<(u16, Vec<u8>)>>;
+
+    /// Serves the image in `served` at `/image.png`, without validators
+    async fn image_server(served: Served) -> String {
+        let router = Router::new()
+            .route(
+                "/image.png",
+                get(|State(served): State<Served>| async move {
+                    let (status, contents) = served.lock().unwrap().clone();
+                    (
+                        axum::http::StatusCode::from_u16(status).unwrap(),
+                        [(CONTENT_TYPE, "image/png")],
+                        contents,
+                    )
+                }),
+            )
+            .with_state(served);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/image.png", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        url
+    }
+
+    /// Downloads the image at `url` under each of the `names` in one
+    /// run, which names the images anew like `convert_links_to_images`.
+    /// Returns the file of each image left in the map
+    async fn download(
+        store: &DirectoryStore,
+        url: &str,
+        names: &[&str],
+    ) -> HashMap<String, Option<String>> {
+        let mut images: HashMap<String, Image> = names
+            .iter()
+            .map(|name| {
+                let image = Image {
+                    link: url.to_string(),
+                    ..Default::default()
+                };
+                (name.to_string(), image)
+            })
+            .collect();
+        let priority: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+        download_images(&mut images, &priority, store, 10, &Client::new(), false)
+            .await
+            .unwrap();
+        images
+            .into_iter()
+            .map(|(name, image)| (name, image.file))
+            .collect()
+    }
+
+    async fn directory_store() -> (DirectoryStore, std::path::PathBuf) {
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let store = DirectoryStore::create(directory.to_str().unwrap())
+            .await
+            .unwrap();
+        (store, directory)
+    }
+
+    #[tokio::test]
+    async fn images_without_validators_are_refreshed() {
+        let served = Arc::new(Mutex::new((200, b"first".to_vec())));
+        let url = image_server(served.clone()).await;
+        let (store, directory) = directory_store().await;
+        let file = |name: &str, files: HashMap<String, Option<String>>| files[name].clone();
+
+        let first = download(&store, &url, &["first"]).await;
+        assert_eq!(file("first", first).as_deref(), Some("first.png"));
+        served.lock().unwrap().1 = b"second".to_vec();
+        let second = download(&store, &url, &["second"]).await;
+        assert_eq!(file("second", second).as_deref(), Some("second.png"));
+        // The same content again keeps the file
+        let third = download(&store, &url, &["third"]).await;
+        assert_eq!(file("third", third).as_deref(), Some("second.png"));
 
-//     #[test]
-//     fn a_unit_test() {
-//         assert!(true);
-//     }
-// }
+        let files = HashSet::from([String::from("second.png"), String::from("images.index")]);
+        assert_eq!(store.list().await.unwrap(), files);
+        assert_eq!(store.read("second.png").await.unwrap().unwrap(), b"second");
+        tokio::fs::remove_dir_all(directory).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn error_responses_are_not_saved() {
+        let served = Arc::new(Mutex::new((404, b"placeholder".to_vec())));
+        let url = image_server(served).await;
+        let (store, directory) = directory_store().await;
+
+        // Not stored, so neither url is a duplicate of the other
+        let files = download(&store, &url, &["a", "b"]).await;
+        assert_eq!(
+            files,
+            HashMap::from([("a".into(), None), ("b".into(), None)])
+        );
+
+        let files = HashSet::from([String::from("images.index")]);
+        assert_eq!(store.list().await.unwrap(), files);
+        tokio::fs::remove_dir_all(directory).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn error_responses_keep_the_saved_image() {
+        let served = Arc::new(Mutex::new((200, b"good".to_vec())));
+        let url = image_server(served.clone()).await;
+        let (store, directory) = directory_store().await;
+
+        download(&store, &url, &["first"]).await;
+        *served.lock().unwrap() = (500, b"placeholder".to_vec());
+        let files = download(&store, &url, &["second"]).await;
+        assert_eq!(files["second"], None);
+
+        let index = ImageIndex::load(&store).await.unwrap();
+        let stored = store.list().await.unwrap();
+        assert_eq!(index.get(&url, &stored).unwrap().file, "first.png");
+        assert_eq!(store.read("first.png").await.unwrap().unwrap(), b"good");
+        tokio::fs::remove_dir_all(directory).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn dimensions_are_read_from_the_start_of_the_image() {
+        let mut png: Vec<u8> = Default::default();
+        image::RgbImage::new(300, 200)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png.truncate(64);
+
+        assert_eq!(measure_dimensions(png).await.unwrap(), (300, 200));
+        assert!(measure_dimensions(b"<svg></svg>".to_vec()).await.is_err());
+    }
+}
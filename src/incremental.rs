@@ -0,0 +1,261 @@
+//! Recrawling a site from the graph of a previous crawl,
+//! only scraping again the pages that changed since
+
+use anyhow::Result;
+use chrono::DateTime;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, IF_MODIFIED_SINCE, IF_NONE_MATCH},
+    StatusCode,
+};
+use std::{
+    collections::{BTreeSet, HashSet},
+    sync::Mutex,
+};
+
+use crate::fetcher::FetchResponse;
+use crate::model::{content_hash, Link, LinkGraph, RecrawlSummary};
+
+/// Whether `link` was crawled, rather than only linked to
+fn crawled(link: &Link) -> bool {
+    link.provenance.is_some() || link.status.is_some()
+}
+
+/// The crawl an incremental crawl starts from. Its pages are
+/// asked for again with their validators, and the ones that
+/// didn't change are carried over instead of scraped again
+pub struct PreviousCrawl {
+    link_graph: LinkGraph,
+    /// the pages this crawl found unchanged
+    unchanged: Mutex<BTreeSet<String>>,
+}
+
+impl PreviousCrawl {
+    pub fn new(link_graph: LinkGraph) -> Self {
+        PreviousCrawl {
+            link_graph,
+            unchanged: Default::default(),
+        }
+    }
+
+    /// The pages the previous crawl crawled, the most recently
+    /// modified first as they are the likeliest to change again
+    pub fn pages(&self) -> Vec<String> {
+        let mut pages: Vec<&Link> = self
+            .link_graph
+            .into_iter()
+            .map(|(_, link)| link)
+            .filter(|link| crawled(link))
+            .collect();
+        pages.sort_by_key(|link| {
+            std::cmp::Reverse(
+                link.last_modified
+                    .as_deref()
+                    .and_then(|date| DateTime::parse_from_rfc2822(date).ok()),
+            )
+        });
+
+        pages.into_iter().map(|link| link.url.clone()).collect()
+    }
+
+    /// Whether the previous crawl crawled `url`
+    pub fn contains(&self, url: &str) -> bool {
+        self.link_graph.get(url).is_some_and(crawled)
+    }
+
+    /// The headers asking the server whether the page
+    /// at `url` changed since the previous crawl
+    pub fn validators(&self, url: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let Some(link) = self.link_graph.get(url) else {
+            return headers;
+        };
+
+        let value =
+            |value: &Option<String>| value.as_deref().and_then(|v| HeaderValue::from_str(v).ok());
+        if let Some(etag) = value(&link.etag) {
+            headers.insert(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = value(&link.last_modified) {
+            headers.insert(IF_MODIFIED_SINCE, last_modified);
+        }
+        headers
+    }
+
+    /// Whether `response` shows the page at `url` didn't change
+    /// since the previous crawl: the server answered 304 Not
+    /// Modified, sent the same body again or the same error
+    pub fn is_unchanged(&self, url: &str, response: &FetchResponse) -> bool {
+        let Some(link) = self.link_graph.get(url).filter(|link| crawled(link)) else {
+            return false;
+        };
+
+        match response.status {
+            StatusCode::NOT_MODIFIED => true,
+            // The graph hashes the decoded body
+            StatusCode::OK => link
+                .content_hash
+                .as_ref()
+                .is_some_and(|hash| *hash == content_hash(&response.text())),
+            status => link.status == Some(status.as_u16()),
+        }
+    }
+
+    pub fn mark_unchanged(&self, url: &str) {
+        self.unchanged.lock().unwrap().insert(url.to_string());
+    }
+
+    /// Adds the pages of the previous crawl that weren't crawled
+    /// again to `link_graph`, making it the next run of the
+    /// previous crawl, and records how the two compare
+    pub fn merge_into(self, link_graph: &mut LinkGraph) -> Result<()> {
+        let unchanged = self.unchanged.into_inner().unwrap();
+        let previous: HashSet<&str> = self
+            .link_graph
+            .into_iter()
+            .filter(|(_, link)| crawled(link))
+            .map(|(_, link)| link.url.as_str())
+            .collect();
+
+        let mut summary = RecrawlSummary {
+            unchanged: unchanged.len(),
+            ..Default::default()
+        };
+        for (_, link) in link_graph.into_iter().filter(|(_, link)| crawled(link)) {
+            if previous.contains(link.url.as_str()) {
+                summary.changed += 1;
+            } else {
+                summary.new += 1;
+            }
+        }
+        summary.unchecked = previous
+            .len()
+            .saturating_sub(summary.changed + summary.unchanged);
+
+        link_graph.carry_over(self.link_graph)?;
+        link_graph.mark_seen(unchanged.iter().map(String::as_str));
+        link_graph.set_recrawl(summary);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Provenance, ScrapeOutput};
+    use reqwest::Version;
+    use url::Url;
+
+    /// A graph of the crawled pages at `urls`, with the
+    /// (url, body, etag, last modified) of each
+    fn previous_graph(pages: &[(&str, &str, Option<&str>, Option<&str>)]) -> LinkGraph {
+        let mut graph = LinkGraph::default();
+        for (url, body, etag, last_modified) in pages {
+            let output = ScrapeOutput {
+                status: Some(200),
+                body: Some(body.to_string()),
+                etag: etag.map(String::from),
+                last_modified: last_modified.map(String::from),
+                ..Default::default()
+            };
+            graph.update(url, "", &output).unwrap();
+            graph.set_provenance(url, Provenance::default()).unwrap();
+        }
+        graph
+    }
+
+    fn response(url: &str, status: StatusCode, body: &str) -> FetchResponse {
+        FetchResponse {
+            url: Url::parse(url).unwrap(),
+            status,
+            version: Version::HTTP_11,
+            headers: HeaderMap::new(),
+            body: body.as_bytes().to_vec(),
+            truncated: false,
+        }
+    }
+
+    #[test]
+    fn validators_come_from_the_previous_page() {
+        let previous = PreviousCrawl::new(previous_graph(&[(
+            "https://a.com/",
+            "a",
+            Some("\"v1\""),
+            Some("Mon, 01 Jan 2024 00:00:00 GMT"),
+        )]));
+
+        let headers = previous.validators("https://a.com/");
+        assert_eq!(headers[IF_NONE_MATCH], "\"v1\"");
+        assert_eq!(headers[IF_MODIFIED_SINCE], "Mon, 01 Jan 2024 00:00:00 GMT");
+        assert!(previous.validators("https://a.com/new").is_empty());
+    }
+
+    #[test]
+    fn pages_put_the_most_recently_modified_first() {
+        let previous = PreviousCrawl::new(previous_graph(&[
+            (
+                "https://a.com/old",
+                "",
+                None,
+                Some("Mon, 01 Jan 2024 00:00:00 GMT"),
+            ),
+            (
+                "https://a.com/new",
+                "",
+                None,
+                Some("Sat, 01 Jun 2024 00:00:00 GMT"),
+            ),
+        ]));
+
+        assert_eq!(previous.pages(), ["https://a.com/new", "https://a.com/old"]);
+    }
+
+    #[test]
+    fn unchanged_pages_are_detected() {
+        let url = "https://a.com/";
+        let previous =
+            PreviousCrawl::new(previous_graph(&[(url, "<p>same  text</p>", None, None)]));
+
+        let not_modified = response(url, StatusCode::NOT_MODIFIED, "");
+        assert!(previous.is_unchanged(url, &not_modified));
+        let same_body = response(url, StatusCode::OK, "<p>same text</p>");
+        assert!(previous.is_unchanged(url, &same_body));
+        let new_body = response(url, StatusCode::OK, "<p>new text</p>");
+        assert!(!previous.is_unchanged(url, &new_body));
+        let unknown = "https://a.com/new";
+        assert!(!previous.is_unchanged(unknown, &response(unknown, StatusCode::NOT_MODIFIED, "")));
+    }
+
+    #[test]
+    fn merge_into_carries_over_the_unchanged_pages() {
+        let previous = PreviousCrawl::new(previous_graph(&[
+            ("https://a.com/", "a", None, None),
+            ("https://a.com/same", "same", None, None),
+            ("https://a.com/gone", "gone", None, None),
+        ]));
+        previous.mark_unchanged("https://a.com/same");
+
+        // Crawled again and changed, and a new page
+        let mut link_graph = previous_graph(&[
+            ("https://a.com/", "a, changed", None, None),
+            ("https://a.com/new", "new", None, None),
+        ]);
+        previous.merge_into(&mut link_graph).unwrap();
+
+        let recrawl = link_graph.recrawl().unwrap();
+        assert_eq!(
+            (
+                recrawl.changed,
+                recrawl.unchanged,
+                recrawl.new,
+                recrawl.unchecked
+            ),
+            (1, 1, 1, 1)
+        );
+        let changed = link_graph.get("https://a.com/").unwrap();
+        assert_eq!(changed.content_hash, Some(content_hash("a, changed")));
+        let same = link_graph.get("https://a.com/same").unwrap();
+        assert_eq!(same.last_seen_run, link_graph.run());
+        let gone = link_graph.get("https://a.com/gone").unwrap();
+        assert!(gone.last_seen_run < link_graph.run());
+    }
+}
@@ -0,0 +1,53 @@
+//! A web crawler collecting the links, titles and images of
+//! websites into a [`LinkGraph`](model::LinkGraph)
+
+pub mod auto_tune;
+pub mod bench;
+pub mod block_detection;
+pub mod broken_links;
+pub mod client;
+pub mod config;
+pub mod cookies;
+mod crawl;
+pub mod crawler;
+pub mod diff;
+pub mod domain_budget;
+pub mod duplicates;
+pub mod export;
+pub mod feed;
+pub mod fetcher;
+pub mod gallery;
+pub mod graph_api;
+pub mod graphql;
+pub mod host_limiter;
+pub mod image_store;
+pub mod image_utils;
+pub mod incremental;
+pub mod logger;
+pub mod login;
+pub mod model;
+pub mod observer;
+pub mod page_weight;
+pub mod parsers;
+pub mod policy_cache;
+pub mod politeness;
+pub mod proxy_pool;
+pub mod rate_limiter;
+pub mod report;
+pub mod response_cache;
+pub mod robots;
+pub mod schedule;
+pub mod scope;
+pub mod script;
+pub mod seeds;
+pub mod seo_audit;
+pub mod sink;
+pub mod sitemap;
+pub mod snapshot;
+pub mod stats;
+pub mod url_params;
+pub mod user_agents;
+pub mod wasm_plugin;
+pub mod well_known;
+
+pub use crawl::{Crawler, CrawlerBuilder, SharedLimits};
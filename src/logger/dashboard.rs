@@ -0,0 +1,144 @@
+use anyhow::Result;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    widgets::{Block, Gauge, List, Paragraph, Sparkline},
+    DefaultTerminal,
+};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use super::recent_lines;
+
+/// How many ticks of throughput the graph shows
+const THROUGHPUT_TICKS: usize = 120;
+/// How many workers are listed, leaving room for the logs
+const MAX_WORKER_ROWS: usize = 16;
+
+/// What the dashboard shows, read from the crawl every tick
+pub struct CrawlSnapshot {
+    /// the links in the graph so far
+    pub crawled: usize,
+    pub max_links: usize,
+    /// the links waiting to be fetched
+    pub queued: usize,
+    /// the pages that couldn't be scraped
+    pub failed: usize,
+    /// the url each fetch worker is downloading, if any
+    pub workers: Vec<Option<String>>,
+}
+
+/// A full-screen view of a running crawl: its progress, queue,
+/// workers, throughput, errors and latest log lines. The
+/// terminal is given back when it is dropped
+pub struct Dashboard {
+    terminal: DefaultTerminal,
+    started: Instant,
+    /// the links found in each tick, the latest last
+    throughput: VecDeque<u64>,
+    last_crawled: Option<usize>,
+}
+
+impl Dashboard {
+    pub fn new() -> Result<Self> {
+        Ok(Dashboard {
+            terminal: ratatui::try_init()?,
+            started: Instant::now(),
+            throughput: VecDeque::with_capacity(THROUGHPUT_TICKS),
+            last_crawled: None,
+        })
+    }
+
+    /// Whether the user pressed q or Ctrl-C since the last call,
+    /// which the terminal no longer turns into a signal
+    pub fn quit_requested(&self) -> Result<bool> {
+        while event::poll(Duration::ZERO)? {
+            if let Event::Key(key) = event::read()? {
+                let ctrl_c =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.kind == KeyEventKind::Press && (ctrl_c || key.code == KeyCode::Char('q')) {
+                    return Ok(true);
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    /// Draws the `snapshot`, counting the links found since the
+    /// last one towards the throughput graph
+    pub fn draw(&mut self, snapshot: &CrawlSnapshot) -> Result<()> {
+        let found = snapshot.crawled - self.last_crawled.unwrap_or(snapshot.crawled);
+        self.last_crawled = Some(snapshot.crawled);
+        if self.throughput.len() == THROUGHPUT_TICKS {
+            self.throughput.pop_front();
+        }
+        self.throughput.push_back(found as u64);
+
+        let elapsed = self.started.elapsed();
+        let rate = snapshot.crawled as f64 / elapsed.as_secs_f64().max(1.0);
+        let progress = snapshot.crawled as f64 / snapshot.max_links.max(1) as f64;
+        let throughput: Vec<u64> = self.throughput.iter().copied().collect();
+
+        self.terminal.draw(|frame| {
+            let [progress_area, counters_area, throughput_area, workers_area, logs_area] =
+                Layout::vertical([
+                    Constraint::Length(3),
+                    Constraint::Length(3),
+                    Constraint::Length(6),
+                    Constraint::Length(snapshot.workers.len().min(MAX_WORKER_ROWS) as u16 + 2),
+                    Constraint::Min(3),
+                ])
+                .areas(frame.area());
+
+            frame.render_widget(
+                Gauge::default()
+                    .block(Block::bordered().title("Links found (q to quit)"))
+                    .gauge_style(Style::default().fg(Color::Cyan))
+                    .label(format!("{}/{}", snapshot.crawled, snapshot.max_links))
+                    .ratio(progress.min(1.0)),
+                progress_area,
+            );
+            frame.render_widget(
+                Paragraph::new(format!(
+                    "queued {}   failed {}   elapsed {}s   {:.1} links/s",
+                    snapshot.queued,
+                    snapshot.failed,
+                    elapsed.as_secs(),
+                    rate
+                ))
+                .block(Block::bordered().title("Crawl")),
+                counters_area,
+            );
+            frame.render_widget(
+                Sparkline::default()
+                    .block(Block::bordered().title("Throughput"))
+                    .style(Style::default().fg(Color::Green))
+                    .data(&throughput),
+                throughput_area,
+            );
+            frame.render_widget(
+                List::new(snapshot.workers.iter().enumerate().map(|(worker, url)| {
+                    format!("{:>3}  {}", worker, url.as_deref().unwrap_or("idle"))
+                }))
+                .block(Block::bordered().title("Workers")),
+                workers_area,
+            );
+
+            let lines = recent_lines(logs_area.height.saturating_sub(2) as usize);
+            frame.render_widget(
+                List::new(lines).block(Block::bordered().title("Log")),
+                logs_area,
+            );
+        })?;
+        Ok(())
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
@@ -1,2 +1,84 @@
+use anyhow::{anyhow, Result};
+use std::{
+    collections::VecDeque,
+    fs::OpenOptions,
+    io,
+    sync::{Mutex, OnceLock},
+};
+use tracing::Level;
+use tracing_subscriber::{
+    filter::LevelFilter, fmt, layer::SubscriberExt, util::SubscriberInitExt, Layer,
+};
+
+pub mod dashboard;
 pub mod progress_bar;
 pub mod spinner;
+
+/// How many of the latest log lines are kept for the dashboard
+const RECENT_LINES: usize = 200;
+
+/// The latest log lines, the newest last
+static RECENT: OnceLock<Mutex<VecDeque<String>>> = OnceLock::new();
+
+/// How the log lines are written
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum LogFormat {
+    /// one line of text per event
+    Text,
+    /// one JSON object per event, with the fields
+    /// and spans (e.g. the url being fetched) it is in
+    Json,
+}
+
+/// Keeps the lines written to it in `RECENT`
+struct RecentLines;
+
+impl io::Write for RecentLines {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut recent = RECENT.get_or_init(Default::default).lock().unwrap();
+        for line in String::from_utf8_lossy(buf).lines() {
+            if recent.len() == RECENT_LINES {
+                recent.pop_front();
+            }
+            recent.push_back(line.to_string());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Appends the logs of the crawler, and of the libraries it
+/// uses, to the file at `path`, from `level` up. The latest
+/// lines are also kept for `recent_lines`
+pub fn init(path: &str, format: LogFormat, level: Level) -> Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let file_layer = fmt::layer().with_writer(Mutex::new(file)).with_ansi(false);
+    let file_layer = match format {
+        LogFormat::Text => file_layer.boxed(),
+        LogFormat::Json => file_layer.json().boxed(),
+    };
+    let recent_layer = fmt::layer()
+        .with_writer(|| RecentLines)
+        .with_ansi(false)
+        .without_time()
+        .with_target(false);
+
+    tracing_subscriber::registry()
+        .with(file_layer.with_filter(LevelFilter::from_level(level)))
+        .with(recent_layer.with_filter(LevelFilter::from_level(level)))
+        .try_init()
+        .map_err(|e| anyhow!("could not set up the logs: {}", e))
+}
+
+/// The latest `count` log lines, the newest last
+pub fn recent_lines(count: usize) -> Vec<String> {
+    let recent = RECENT.get_or_init(Default::default).lock().unwrap();
+    recent
+        .iter()
+        .skip(recent.len().saturating_sub(count))
+        .cloned()
+        .collect()
+}
@@ -9,6 +9,12 @@ pub enum Colour {
     Green,
 }
 
+impl Default for Spinner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Spinner {
     pub fn new() -> Self {
         let spinner = indicatif::ProgressBar::new_spinner();
@@ -0,0 +1,23 @@
+use anyhow::{bail, Result};
+use reqwest::Client;
+use tracing::info;
+
+/// Submits the login form `fields` to `login_url` before the
+/// crawl starts. The session cookies the site sends back are
+/// kept in the client's cookie jar, which is shared with every
+/// worker, so the crawl runs as the logged in user.
+pub async fn login(client: &Client, login_url: &str, fields: &[(String, String)]) -> Result<()> {
+    let response = client.post(login_url).form(fields).send().await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        bail!("login to {} failed with status {}", login_url, status);
+    }
+
+    info!(
+        "Logged in through {}, landed on {}",
+        login_url,
+        response.url()
+    );
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::{LinkGraph, LinkId};
+
+impl LinkGraph {
+    /// Computes the betweenness centrality of every link in the
+    /// graph, following the parent -> child edges: the share of
+    /// the shortest paths between two other pages going through
+    /// it, from 0 to 1. Uses Brandes' algorithm, a breadth first
+    /// walk from each page, so on graphs with more links than
+    /// `max_sources` it is estimated from `max_sources` of them.
+    pub fn betweenness(&self, max_sources: usize) -> HashMap<LinkId, f64> {
+        let ids: Vec<LinkId> = self.into_iter().map(|(id, _)| *id).collect();
+        let n_links = ids.len();
+        if n_links < 3 {
+            return ids.into_iter().map(|id| (id, 0.0)).collect();
+        }
+
+        let index: HashMap<LinkId, usize> =
+            ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let children: Vec<Vec<usize>> = self
+            .into_iter()
+            .map(|(_, link)| {
+                link.children
+                    .iter()
+                    .filter_map(|child| index.get(child).copied())
+                    .collect()
+            })
+            .collect();
+
+        // Evenly spread sources, so the estimate is the same every time
+        let n_sources = max_sources.clamp(1, n_links);
+        let sources = (0..n_sources).map(|i| i * n_links / n_sources);
+
+        let mut centrality = vec![0.0; n_links];
+        let mut distances: Vec<Option<usize>> = vec![None; n_links];
+        let mut paths = vec![0.0; n_links];
+        let mut dependencies = vec![0.0; n_links];
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n_links];
+        for source in sources {
+            distances.fill(None);
+            paths.fill(0.0);
+            dependencies.fill(0.0);
+            predecessors.iter_mut().for_each(Vec::clear);
+
+            distances[source] = Some(0);
+            paths[source] = 1.0;
+            let mut visited = Vec::new();
+            let mut queue = VecDeque::from([source]);
+            while let Some(page) = queue.pop_front() {
+                visited.push(page);
+                let distance = distances[page].unwrap_or_default() + 1;
+                for &child in children[page].iter() {
+                    if distances[child].is_none() {
+                        distances[child] = Some(distance);
+                        queue.push_back(child);
+                    }
+                    if distances[child] == Some(distance) {
+                        paths[child] += paths[page];
+                        predecessors[child].push(page);
+                    }
+                }
+            }
+
+            // From the furthest pages back to the source
+            for &page in visited.iter().rev() {
+                for &predecessor in predecessors[page].iter() {
+                    dependencies[predecessor] +=
+                        paths[predecessor] / paths[page] * (1.0 + dependencies[page]);
+                }
+                if page != source {
+                    centrality[page] += dependencies[page];
+                }
+            }
+        }
+
+        // Scaled up to every source, then down to the pairs of pages
+        let scale = n_links as f64 / n_sources as f64 / ((n_links - 1) * (n_links - 2)) as f64;
+        ids.into_iter()
+            .zip(centrality)
+            .map(|(id, centrality)| (id, centrality * scale))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::{LinkGraph, ScrapeOutput};
+
+    #[test]
+    fn middle_of_a_path_is_between_its_ends() {
+        let mut graph = LinkGraph::default();
+        let links = |links: &[&str]| ScrapeOutput {
+            links: links.iter().map(|l| l.to_string()).collect(),
+            ..Default::default()
+        };
+        graph.update("a", "", &links(&["b"])).unwrap();
+        graph.update("b", "a", &links(&["c"])).unwrap();
+        graph.update("c", "b", &links(&[])).unwrap();
+
+        let betweenness = graph.betweenness(usize::MAX);
+        let of = |url: &str| betweenness[&graph.get(url).unwrap().id];
+        // Of the 2 ordered pairs without b, a -> c goes through it
+        assert_eq!(of("b"), 0.5);
+        assert_eq!(of("a"), 0.0);
+        assert_eq!(of("c"), 0.0);
+    }
+}
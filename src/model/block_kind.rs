@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// The kind of anti-bot page served instead of the real content
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+    /// a Cloudflare "checking your browser" or challenge page
+    CloudflareChallenge,
+    /// an Akamai "Access Denied" page
+    Akamai,
+    /// a page asking to solve a CAPTCHA
+    Captcha,
+    /// any other page saying automated traffic was blocked
+    BotDetected,
+}
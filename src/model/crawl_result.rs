@@ -0,0 +1,15 @@
+use serde::Serialize;
+
+use crate::model::scrape_output::ScrapeOutput;
+
+/// A page scraped during the crawl, published as
+/// soon as it is added to the link graph
+#[derive(Debug, Serialize)]
+pub struct CrawlResult {
+    /// the url of the page
+    pub url: String,
+    /// the page the url was found on, empty for the seeds
+    pub parent: String,
+    /// everything scraped from the page
+    pub page: ScrapeOutput,
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::model::server_fingerprint::ServerFingerprint;
+use crate::model::warm_up_estimate::WarmUpEstimate;
+use crate::model::well_known_file::WellKnownFile;
+
+/// What was learned about a domain during the crawl
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DomainInfo {
+    /// the delay between requests the crawler
+    /// settled on for this domain
+    pub request_delay_ms: u64,
+    /// how many times the crawler slowed down
+    /// because the responses got slower
+    pub slowdowns: u32,
+    /// pages that looked like anti-bot or CAPTCHA
+    /// interstitials instead of real content
+    pub soft_blocks: Vec<String>,
+    /// the HTTP version and server headers of
+    /// the first page crawled on this domain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub server: Option<ServerFingerprint>,
+    /// the standard files (robots.txt, security.txt...)
+    /// probed on this domain, by path
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub well_known: BTreeMap<String, WellKnownFile>,
+    /// the pages of this domain that couldn't be crawled
+    #[serde(default, skip_serializing_if = "ErrorCounts::is_empty")]
+    pub errors: ErrorCounts,
+    /// the estimates the crawl budget of this
+    /// domain was based on, see `--warm-up`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warm_up: Option<WarmUpEstimate>,
+}
+
+impl DomainInfo {
+    /// Adds what another crawl of the same domain learned about it
+    pub fn merge(&mut self, other: DomainInfo) {
+        self.request_delay_ms = self.request_delay_ms.max(other.request_delay_ms);
+        self.slowdowns += other.slowdowns;
+        for page in other.soft_blocks {
+            if !self.soft_blocks.contains(&page) {
+                self.soft_blocks.push(page);
+            }
+        }
+        self.server = self.server.take().or(other.server);
+        for (path, file) in other.well_known {
+            self.well_known.entry(path).or_insert(file);
+        }
+        self.errors.extend(&other.errors);
+        self.warm_up = self.warm_up.take().or(other.warm_up);
+    }
+}
+
+/// Why a page couldn't be crawled
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// the request took too long
+    Timeout,
+    /// the host name couldn't be resolved
+    Dns,
+    /// the connection couldn't be made, e.g. refused or TLS
+    Connect,
+    /// a 4xx status
+    ClientError,
+    /// a 5xx status
+    ServerError,
+    Other,
+}
+
+/// How many pages failed with each kind of error
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ErrorCounts {
+    pub timeout: u32,
+    pub dns: u32,
+    pub connect: u32,
+    /// 4xx statuses
+    pub client_error: u32,
+    /// 5xx statuses
+    pub server_error: u32,
+    pub other: u32,
+}
+
+impl ErrorCounts {
+    pub fn add(&mut self, kind: ErrorKind) {
+        let count = match kind {
+            ErrorKind::Timeout => &mut self.timeout,
+            ErrorKind::Dns => &mut self.dns,
+            ErrorKind::Connect => &mut self.connect,
+            ErrorKind::ClientError => &mut self.client_error,
+            ErrorKind::ServerError => &mut self.server_error,
+            ErrorKind::Other => &mut self.other,
+        };
+        *count += 1;
+    }
+
+    /// Adds the `other` counts to these
+    pub fn extend(&mut self, other: &ErrorCounts) {
+        self.timeout += other.timeout;
+        self.dns += other.dns;
+        self.connect += other.connect;
+        self.client_error += other.client_error;
+        self.server_error += other.server_error;
+        self.other += other.other;
+    }
+
+    pub fn total(&self) -> u32 {
+        self.timeout + self.dns + self.connect + self.client_error + self.server_error + self.other
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.total() == 0
+    }
+
+    /// The non zero counts with their names, e.g. ("5xx", 3)
+    pub fn named(&self) -> Vec<(&'static str, u32)> {
+        [
+            ("timeout", self.timeout),
+            ("dns", self.dns),
+            ("connect", self.connect),
+            ("4xx", self.client_error),
+            ("5xx", self.server_error),
+            ("other", self.other),
+        ]
+        .into_iter()
+        .filter(|(_, count)| *count > 0)
+        .collect()
+    }
+}
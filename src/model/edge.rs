@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// An `<a>` on a page, linking to another page
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Edge {
+    /// the absolute url linked to
+    pub to: String,
+    /// the text of the anchor, or the alt text of
+    /// its image for the image links
+    pub anchor_text: String,
+    /// the values of the anchor's `rel`, e.g. `nofollow`
+    pub rel: Vec<String>,
+}
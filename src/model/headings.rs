@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+/// The `<title>` and top level headings of a page,
+/// scraped with the titles for the SEO audit
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Headings {
+    /// the text of the first `<title>` that isn't blank
+    pub title: Option<String>,
+    /// how many `<h1>`s the page has
+    pub h1: u32,
+}
@@ -1,9 +1,65 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Image {
     /// the link for this image
     pub link: String,
-    /// the alternative text found within the image
-    pub alt: String,
+    /// the `alt` attribute of the image, `None` when it has
+    /// none. An empty one marks the image as decorative
+    #[serde(default)]
+    pub alt: Option<String>,
+    /// set for the CSS background images, which
+    /// can't have an alternative text
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub background: bool,
+    /// the width declared in the `width` attribute
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    /// the height declared in the `height` attribute
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// every url the image can be loaded from (`srcset`,
+    /// `<picture>` sources, lazy-loading attributes), when
+    /// there is more than one. `link` is the biggest of them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<String>,
+    /// the link the image was served from after following
+    /// any redirects, only known once the image is downloaded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub final_link: Option<String>,
+    /// the page the image was found on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<String>,
+    /// the file the image was saved to, in the image directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// the size of the downloaded image, in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// the real width of the downloaded image, in pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub natural_width: Option<u32>,
+    /// the real height of the downloaded image, in pixels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub natural_height: Option<u32>,
+    /// the hex sha256 of the downloaded image
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+impl Image {
+    /// Whether the image should have an alternative text but has
+    /// none, leaving out the decorative (`alt=""`) and CSS images
+    pub fn missing_alt(&self) -> bool {
+        !self.background && self.alt.is_none()
+    }
+
+    /// The (width, height) of the image, the real one if
+    /// it was measured, otherwise the declared one
+    pub fn dimensions(&self) -> (Option<u32>, Option<u32>) {
+        (
+            self.natural_width.or(self.width),
+            self.natural_height.or(self.height),
+        )
+    }
 }
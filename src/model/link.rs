@@ -1,59 +1,290 @@
-use serde::Serialize;
-use std::sync::atomic::{AtomicU64, Ordering};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use url::Url;
 
+use crate::model::block_kind::BlockKind;
+use crate::model::domain_info::ErrorKind;
+use crate::model::edge::Edge;
+use crate::model::headings::Headings;
 use crate::model::image::Image;
-
-/// Counter to increment our current created link id
-static LINK_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+use crate::model::media::Media;
+use crate::model::page_timings::PageTimings;
+use crate::model::provenance::Provenance;
+use crate::model::search_hits::SearchHits;
+use crate::model::security_headers::SecurityHeaders;
+use crate::model::social_meta::SocialMeta;
 
 /// Type for the Link ID
 pub type LinkId = u64;
 
-#[derive(Debug, Serialize)]
+/// Keeps the ids within the integers a JSON number
+/// can hold exactly, for JavaScript consumers
+pub const LINK_ID_MASK: LinkId = (1 << 53) - 1;
+
+/// The stable id of `url`, a hash of the normalized url,
+/// so the same page has the same id in every crawl. The
+/// scheme and host are lowercased and the default port
+/// and the fragment are dropped before hashing.
+pub fn link_id(url: &str) -> LinkId {
+    let normalized = match Url::parse(url) {
+        Ok(mut parsed) => {
+            parsed.set_fragment(None);
+            parsed.to_string()
+        }
+        Err(_) => url.to_string(),
+    };
+
+    let digest = Sha256::digest(normalized.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    LinkId::from_be_bytes(bytes) & LINK_ID_MASK
+}
+
+/// The hash of what a page serves: the hex sha256 of its
+/// `body` with the runs of whitespace collapsed, so pages
+/// only differing in their indentation or line endings match
+pub fn content_hash(body: &str) -> String {
+    let mut hasher = Sha256::new();
+    for (i, word) in body.split_whitespace().enumerate() {
+        if i > 0 {
+            hasher.update(b" ");
+        }
+        hasher.update(word.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Link {
-    /// unique ID for this link
+    /// stable ID of this link, see `link_id`
     pub id: LinkId,
     /// the URL string for this link
     pub url: String,
     /// list of links contained inside this webpage
-    pub children: Vec<LinkId>,
+    pub children: BTreeSet<LinkId>,
+    /// the anchor text and `rel` of each link on this webpage
+    pub edges: Vec<Edge>,
     /// list of webages that link to this webpage
-    pub parents: Vec<LinkId>,
+    pub parents: BTreeSet<LinkId>,
+    /// why this webpage was crawled
+    pub provenance: Option<Provenance>,
+    /// the last run of a recurring crawl this webpage
+    /// was crawled in, see `LinkGraph::carry_over`
+    #[serde(default)]
+    pub last_seen_run: u32,
     /// list of images found on the webpage
     pub images: Vec<Image>,
     /// list of titles found on this webpage
     pub titles: Vec<String>,
+    /// the `<title>` and number of `<h1>`s of this webpage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headings: Option<Headings>,
+    /// the iframes, videos and audio embedded in this webpage
+    pub media: Vec<Media>,
+    /// the status code this webpage was served with
+    pub status: Option<u16>,
+    /// why this webpage couldn't be crawled, if it failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorKind>,
+    /// the size of the body of this webpage, in bytes
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// the hash of the body of this webpage, see `content_hash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+    /// the url this webpage redirected to, if it did
+    pub redirect: Option<String>,
+    /// the canonical url this webpage declares
+    pub canonical: Option<String>,
+    /// the description meta tag of this webpage
+    pub description: Option<String>,
+    /// the keywords meta tag of this webpage
+    pub keywords: Vec<String>,
+    /// the Open Graph and Twitter Card tags of this webpage
+    pub social: Option<SocialMeta>,
+    /// the JSON-LD blocks of this webpage
+    pub structured_data: Vec<Value>,
+    /// the readable text of this webpage
+    pub text: Option<String>,
+    /// where each `--search` term (by pattern) was
+    /// found in the text of this webpage
+    pub search_hits: BTreeMap<String, SearchHits>,
+    /// the primary tag of the `<html lang>` of this webpage, e.g. `en`
+    pub declared_language: Option<String>,
+    /// the ISO 639-3 code of the language detected
+    /// from the text of this webpage, e.g. `eng`
+    pub detected_language: Option<String>,
+    /// the SimHash fingerprint of the readable
+    /// text of this webpage, see `duplicates::simhash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub simhash: Option<u64>,
+    /// links and images loaded over plain HTTP
+    /// from this (HTTPS) webpage
+    pub mixed_content: Vec<String>,
+    /// the scraped fields cut short for being too long
+    pub truncated_fields: Vec<String>,
+    /// the RSS/Atom feeds this webpage advertises
+    pub feeds: Vec<String>,
+    /// how long scraping this webpage took
+    pub timings: Option<PageTimings>,
+    /// set when the crawler was served an anti-bot
+    /// or CAPTCHA page instead of this webpage
+    pub blocked: Option<BlockKind>,
+    /// the Last-Modified header sent with this webpage
+    pub last_modified: Option<String>,
+    /// the ETag header sent with this webpage
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// the CSP, HSTS and other security headers
+    /// sent with this webpage, see `--security-headers`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security_headers: Option<SecurityHeaders>,
+    /// the Content-Type header sent with this webpage
+    pub content_type: Option<String>,
+    /// the records the parse rules found in this
+    /// (non HTML) webpage, by rule name
+    pub extracted: BTreeMap<String, Value>,
+    /// the text of the elements matched by each
+    /// custom selector (`--extract`), by selector name
+    pub custom: BTreeMap<String, Vec<String>>,
 }
 
-impl Default for Link {
-    fn default() -> Link {
-        Link {
-            id: LINK_ID_COUNTER.fetch_add(1, Ordering::SeqCst),
-            url: String::from(""),
-            children: Default::default(),
-            parents: Default::default(),
-            images: Default::default(),
-            titles: Default::default(),
+/// Appends the `new` items that aren't in `items` yet,
+/// `same` telling whether two items are the same
+fn extend_unique<T>(
+    items: &mut Vec<T>,
+    new: impl IntoIterator<Item = T>,
+    same: impl Fn(&T, &T) -> bool,
+) {
+    for item in new {
+        if !items.iter().any(|existing| same(existing, &item)) {
+            items.push(item);
         }
     }
 }
 
 impl Link {
+    /// Adds what was found about the same page
+    /// elsewhere, keeping this link's id
+    pub fn merge(&mut self, other: Link) {
+        self.children.extend(other.children);
+        self.add_edges(other.edges);
+        self.parents.extend(other.parents);
+        self.provenance = self.provenance.take().or(other.provenance);
+        self.last_seen_run = self.last_seen_run.max(other.last_seen_run);
+        self.add_images(other.images);
+        self.add_titles(other.titles);
+        self.headings = self.headings.take().or(other.headings);
+        self.add_media(other.media);
+        self.status = self.status.or(other.status);
+        self.error = self.error.or(other.error);
+        self.size = self.size.or(other.size);
+        self.content_hash = self.content_hash.take().or(other.content_hash);
+        self.redirect = self.redirect.take().or(other.redirect);
+        self.canonical = self.canonical.take().or(other.canonical);
+        self.description = self.description.take().or(other.description);
+        self.keywords.extend(other.keywords);
+        match (&mut self.social, other.social) {
+            (Some(social), Some(other)) => social.merge(other),
+            (social, other) => *social = social.take().or(other),
+        }
+        self.structured_data.extend(other.structured_data);
+        self.text = self.text.take().or(other.text);
+        for (search, hits) in other.search_hits {
+            self.search_hits.entry(search).or_insert(hits);
+        }
+        self.declared_language = self.declared_language.take().or(other.declared_language);
+        self.detected_language = self.detected_language.take().or(other.detected_language);
+        self.simhash = self.simhash.or(other.simhash);
+        self.mixed_content.extend(other.mixed_content);
+        self.truncated_fields.extend(other.truncated_fields);
+        self.feeds.extend(other.feeds);
+        self.timings = self.timings.or(other.timings);
+        self.blocked = self.blocked.or(other.blocked);
+        self.last_modified = self.last_modified.take().or(other.last_modified);
+        self.etag = self.etag.take().or(other.etag);
+        self.security_headers = self.security_headers.take().or(other.security_headers);
+        self.content_type = self.content_type.take().or(other.content_type);
+        self.extracted.extend(other.extracted);
+        for (name, values) in other.custom {
+            self.custom.entry(name).or_default().extend(values);
+        }
+    }
+
+    /// The `<title>` of this webpage, if it was scraped
+    pub fn title(&self) -> Option<&str> {
+        self.headings.as_ref()?.title.as_deref().map(str::trim)
+    }
+
+    /// Adds the titles this link doesn't have yet, a page
+    /// scraped twice shouldn't list its titles twice
+    pub fn add_titles(&mut self, titles: impl IntoIterator<Item = String>) {
+        extend_unique(&mut self.titles, titles, |a, b| a == b);
+    }
+
+    /// Adds the images this link doesn't have yet, by link
+    pub fn add_images(&mut self, images: impl IntoIterator<Item = Image>) {
+        extend_unique(&mut self.images, images, |a, b| a.link == b.link);
+    }
+
+    /// Adds the edges this link doesn't have yet
+    pub fn add_edges(&mut self, edges: impl IntoIterator<Item = Edge>) {
+        extend_unique(&mut self.edges, edges, |a, b| a == b);
+    }
+
+    /// Adds the media this link doesn't have yet
+    pub fn add_media(&mut self, media: impl IntoIterator<Item = Media>) {
+        extend_unique(&mut self.media, media, |a, b| a == b);
+    }
+
     pub fn new(
+        id: LinkId,
         url: String,
-        children: Vec<LinkId>,
-        parents: Vec<LinkId>,
+        children: BTreeSet<LinkId>,
+        parents: BTreeSet<LinkId>,
         images: Vec<Image>,
         titles: Vec<String>,
     ) -> Link {
-        let id = LINK_ID_COUNTER.fetch_add(1, Ordering::SeqCst);
         Link {
             url,
             id,
             children,
+            edges: Default::default(),
             parents,
+            provenance: Default::default(),
+            last_seen_run: Default::default(),
             images,
             titles,
+            headings: Default::default(),
+            media: Default::default(),
+            status: Default::default(),
+            error: Default::default(),
+            size: Default::default(),
+            content_hash: Default::default(),
+            redirect: Default::default(),
+            canonical: Default::default(),
+            description: Default::default(),
+            keywords: Default::default(),
+            social: Default::default(),
+            structured_data: Default::default(),
+            text: Default::default(),
+            search_hits: Default::default(),
+            declared_language: Default::default(),
+            detected_language: Default::default(),
+            simhash: Default::default(),
+            mixed_content: Default::default(),
+            truncated_fields: Default::default(),
+            feeds: Default::default(),
+            timings: Default::default(),
+            blocked: Default::default(),
+            last_modified: Default::default(),
+            etag: Default::default(),
+            security_headers: Default::default(),
+            content_type: Default::default(),
+            extracted: Default::default(),
+            custom: Default::default(),
         }
     }
 }
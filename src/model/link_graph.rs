@@ -1,30 +1,51 @@
 use anyhow::{anyhow, Context, Result};
-use serde::Serialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use url::Url;
 
-use super::{Image, Link, LinkId};
+use super::{
+    content_hash, link_id, DomainInfo, Edge, Link, LinkId, Provenance, RecrawlSummary,
+    ScrapeOutput, LINK_ID_MASK,
+};
 
-#[derive(Default, Debug, Serialize)]
+#[derive(Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LinkGraph {
-    links: HashMap<LinkId, Link>,
-    link_ids: HashMap<String, LinkId>,
+    // ordered maps, so the serialized graph
+    // doesn't change order between runs
+    links: BTreeMap<LinkId, Link>,
+    link_ids: BTreeMap<String, LinkId>,
+    /// user given (name, value) tags attributing this crawl
+    tags: BTreeMap<String, String>,
+    /// what was learned about each domain crawled
+    domains: BTreeMap<String, DomainInfo>,
+    /// the urls the crawl started from
+    seeds: Vec<String>,
+    /// the links found by each isolated sub-crawl, by seed
+    sections: BTreeMap<String, Vec<LinkId>>,
+    /// how many runs of a recurring crawl came before
+    /// this one, see `LinkGraph::carry_over`
+    run: u32,
+    /// how this crawl compares to the one it
+    /// started from, for incremental crawls
+    #[serde(skip_serializing_if = "Option::is_none")]
+    recrawl: Option<RecrawlSummary>,
 }
 
 impl LinkGraph {
-    // Update a link
-    pub fn update(
-        &mut self,
-        url: &str,
-        parent: &str,
-        children: &[String],
-        images: &[Image],
-        titles: &[String],
-    ) -> Result<()> {
+    /// Loads a graph saved by a previous crawl
+    pub fn from_json(json: &str) -> Result<LinkGraph> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    // Update a link with what was scraped from its page
+    pub fn update(&mut self, url: &str, parent: &str, scrape_output: &ScrapeOutput) -> Result<()> {
         let maybe_parent = self.link_ids.get(parent).cloned();
 
         // for each child, add their id (if it exists) to this
         // links children
-        let valid_children: Vec<LinkId> = children
+        let valid_children: BTreeSet<LinkId> = scrape_output
+            .links
             .iter()
             .filter_map(|c| self.link_ids.get(c).cloned())
             .collect();
@@ -32,14 +53,97 @@ impl LinkGraph {
         let link = self.force_get_link_id(url)?;
 
         if let Some(parent_id) = maybe_parent {
-            link.parents.push(parent_id);
+            link.parents.insert(parent_id);
         }
 
         link.children.extend(valid_children);
+        link.add_edges(scrape_output.edges.iter().cloned());
 
         // TODO : reduce all these cloned (maybe use moved values)
-        link.images.extend(images.iter().cloned());
-        link.titles.extend(titles.iter().cloned());
+        link.add_images(scrape_output.images.iter().cloned());
+        link.add_titles(scrape_output.titles.iter().cloned());
+        link.add_media(scrape_output.media.iter().cloned());
+        if scrape_output.headings.is_some() {
+            link.headings = scrape_output.headings.clone();
+        }
+        if scrape_output.status.is_some() {
+            link.status = scrape_output.status;
+        }
+        if scrape_output.error.is_some() {
+            link.error = scrape_output.error;
+        }
+        if let Some(body) = &scrape_output.body {
+            link.size = Some(body.len() as u64);
+            link.content_hash = Some(content_hash(body));
+        }
+        if scrape_output.redirect.is_some() {
+            link.redirect = scrape_output.redirect.clone();
+        }
+        if scrape_output.canonical.is_some() {
+            link.canonical = scrape_output.canonical.clone();
+        }
+        if scrape_output.description.is_some() {
+            link.description = scrape_output.description.clone();
+        }
+        link.keywords.extend(scrape_output.keywords.iter().cloned());
+        if scrape_output.social.is_some() {
+            link.social = scrape_output.social.clone();
+        }
+        link.structured_data
+            .extend(scrape_output.structured_data.iter().cloned());
+        if scrape_output.text.is_some() {
+            link.text = scrape_output.text.clone();
+        }
+        link.search_hits.extend(
+            scrape_output
+                .search_hits
+                .iter()
+                .map(|(search, hits)| (search.clone(), hits.clone())),
+        );
+        if scrape_output.declared_language.is_some() {
+            link.declared_language = scrape_output.declared_language.clone();
+        }
+        if scrape_output.detected_language.is_some() {
+            link.detected_language = scrape_output.detected_language.clone();
+        }
+        if scrape_output.simhash.is_some() {
+            link.simhash = scrape_output.simhash;
+        }
+        link.mixed_content
+            .extend(scrape_output.mixed_content.iter().cloned());
+        link.truncated_fields
+            .extend(scrape_output.truncated_fields.iter().cloned());
+        link.feeds.extend(scrape_output.feeds.iter().cloned());
+        if scrape_output.last_modified.is_some() {
+            link.last_modified = scrape_output.last_modified.clone();
+        }
+        if scrape_output.etag.is_some() {
+            link.etag = scrape_output.etag.clone();
+        }
+        if scrape_output.security_headers.is_some() {
+            link.security_headers = scrape_output.security_headers.clone();
+        }
+        if scrape_output.content_type.is_some() {
+            link.content_type = scrape_output.content_type.clone();
+        }
+        link.extracted.extend(
+            scrape_output
+                .extracted
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        );
+        for (name, values) in scrape_output.custom.iter() {
+            link.custom
+                .entry(name.clone())
+                .or_default()
+                .extend(values.iter().cloned());
+        }
+        if scrape_output.blocked.is_some() {
+            link.blocked = scrape_output.blocked;
+        }
+        if scrape_output.timings.is_some() {
+            link.timings = scrape_output.timings;
+        }
         let this_link_id = link.id;
 
         if let Some(parent_id) = maybe_parent {
@@ -51,22 +155,263 @@ impl LinkGraph {
                 .get_mut(&parent_id)
                 .context("could not find parent link")?;
 
-            parent_link.children.push(this_link_id);
+            parent_link.children.insert(this_link_id);
         }
 
-        // Potentially there's a chance that we might visit the same
-        // link through different parents, meaning that we will get
-        // duplicated children, images, titles -> need a way to
-        // unduplicate all of this (I.e. use sets)
         Ok(())
     }
 
+    /// Adds the graph of an isolated sub-crawl to this one,
+    /// listing its links in a section named after its seed.
+    /// Pages found by several sub-crawls are only kept once
+    pub fn merge_section(&mut self, other: LinkGraph) -> Result<()> {
+        let mut new_ids: HashMap<LinkId, LinkId> = Default::default();
+        for (id, link) in other.links.iter() {
+            new_ids.insert(*id, self.force_get_link_id(&link.url)?.id);
+        }
+
+        let mut section: Vec<LinkId> = Default::default();
+        for (id, mut link) in other.links {
+            let new_id = new_ids[&id];
+            let remap = |ids: &BTreeSet<LinkId>| -> BTreeSet<LinkId> {
+                ids.iter()
+                    .filter_map(|id| new_ids.get(id).copied())
+                    .collect()
+            };
+            link.children = remap(&link.children);
+            link.parents = remap(&link.parents);
+
+            self.links
+                .get_mut(&new_id)
+                .context("could not find merged link")?
+                .merge(link);
+            section.push(new_id);
+        }
+
+        section.sort_unstable();
+        let section_name = other.seeds.first().cloned().unwrap_or_default();
+        self.sections.insert(section_name, section);
+        self.seeds.extend(other.seeds);
+        self.tags.extend(other.tags);
+        for (domain, info) in other.domains {
+            self.domains.entry(domain).or_default().merge(info);
+        }
+        Ok(())
+    }
+
+    /// Makes this crawl the next run of the recurring crawl that
+    /// found `previous`, carrying over the pages it didn't crawl
+    /// again. Returns how many pages were carried over
+    pub fn carry_over(&mut self, previous: LinkGraph) -> Result<usize> {
+        self.run = previous.run + 1;
+        for link in self.links.values_mut() {
+            link.last_seen_run = self.run;
+        }
+
+        let mut new_ids: HashMap<LinkId, LinkId> = Default::default();
+        for (id, link) in previous.links.iter() {
+            new_ids.insert(*id, self.force_get_link_id(&link.url)?.id);
+        }
+
+        let mut carried: Vec<LinkId> = Default::default();
+        for (id, mut link) in previous.links {
+            let new_id = new_ids[&id];
+            let current = self
+                .links
+                .get_mut(&new_id)
+                .context("could not find carried over link")?;
+            // Crawled again (rather than only linked to, as every
+            // crawled page has a provenance), so the new record
+            // replaces it
+            if current.provenance.is_some() {
+                continue;
+            }
+
+            let remap = |ids: &BTreeSet<LinkId>| -> BTreeSet<LinkId> {
+                ids.iter()
+                    .filter_map(|id| new_ids.get(id).copied())
+                    .collect()
+            };
+            link.id = new_id;
+            link.children = remap(&link.children);
+            link.parents = remap(&link.parents);
+            // Only linked to by this run, which is kept
+            link.parents.extend(current.parents.iter());
+            *current = link;
+            carried.push(new_id);
+        }
+
+        // The pages crawled again are still linked from the carried ones
+        for id in carried.iter() {
+            for child in self.links[id].children.clone() {
+                let child = self.links.get_mut(&child).context("could not find child")?;
+                if child.last_seen_run == self.run {
+                    child.parents.insert(*id);
+                }
+            }
+        }
+
+        for (domain, info) in previous.domains {
+            self.domains.entry(domain).or_insert(info);
+        }
+        Ok(carried.len())
+    }
+
+    /// Removes the pages no run of the recurring crawl has seen
+    /// in the last `max_unseen_runs` runs, returning their urls
+    pub fn prune_unseen(&mut self, max_unseen_runs: u32) -> Vec<String> {
+        let run = self.run;
+        let stale: HashSet<LinkId> = self
+            .links
+            .values()
+            .filter(|link| run - link.last_seen_run.min(run) >= max_unseen_runs)
+            .map(|link| link.id)
+            .collect();
+
+        let mut removed = Vec::new();
+        for id in stale.iter() {
+            if let Some(link) = self.links.remove(id) {
+                self.link_ids.remove(&link.url);
+                removed.push(link.url);
+            }
+        }
+
+        for link in self.links.values_mut() {
+            link.children.retain(|id| !stale.contains(id));
+            link.parents.retain(|id| !stale.contains(id));
+        }
+        for section in self.sections.values_mut() {
+            section.retain(|id| !stale.contains(id));
+        }
+
+        removed.sort();
+        removed
+    }
+
+    /// How many runs of a recurring crawl came before this one
+    pub fn run(&self) -> u32 {
+        self.run
+    }
+
+    /// Counts the pages at `urls` as seen by this run, e.g.
+    /// the ones an incremental crawl found unchanged
+    pub fn mark_seen<'a>(&mut self, urls: impl IntoIterator<Item = &'a str>) {
+        for url in urls {
+            if let Some(link) = self.link_ids.get(url).and_then(|id| self.links.get_mut(id)) {
+                link.last_seen_run = self.run;
+            }
+        }
+    }
+
+    pub fn set_recrawl(&mut self, recrawl: RecrawlSummary) {
+        self.recrawl = Some(recrawl);
+    }
+
+    pub fn recrawl(&self) -> Option<&RecrawlSummary> {
+        self.recrawl.as_ref()
+    }
+
+    pub fn sections(&self) -> &BTreeMap<String, Vec<LinkId>> {
+        &self.sections
+    }
+
+    pub fn set_tags(&mut self, tags: BTreeMap<String, String>) {
+        self.tags = tags;
+    }
+
+    pub fn tags(&self) -> &BTreeMap<String, String> {
+        &self.tags
+    }
+
+    pub fn set_domains(&mut self, domains: impl IntoIterator<Item = (String, DomainInfo)>) {
+        self.domains = domains.into_iter().collect();
+    }
+
+    pub fn domains(&self) -> &BTreeMap<String, DomainInfo> {
+        &self.domains
+    }
+
+    pub fn set_seeds(&mut self, seeds: Vec<String>) {
+        self.seeds = seeds;
+    }
+
+    pub fn seeds(&self) -> &[String] {
+        &self.seeds
+    }
+
+    /// The domains of the urls the crawl started from
+    pub fn seed_domains(&self) -> HashSet<String> {
+        self.seeds
+            .iter()
+            .filter_map(|s| Url::parse(s).ok()?.host_str().map(|h| h.to_string()))
+            .collect()
+    }
+
+    /// Gets the link with the given `url`, if it was found
+    pub fn get(&self, url: &str) -> Option<&Link> {
+        self.link_ids.get(url).and_then(|id| self.links.get(id))
+    }
+
+    /// Gets the link with the given `id`, if it was found
+    pub fn get_by_id(&self, id: LinkId) -> Option<&Link> {
+        self.links.get(&id)
+    }
+
     pub fn len(&self) -> usize {
         self.links.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
     pub fn link_visited(&self, url: &str) -> bool {
-        self.link_ids.get(url).is_some()
+        self.link_ids.contains_key(url)
+    }
+
+    /// Every link found on the crawled pages, with the page it is on
+    pub fn edges(&self) -> impl Iterator<Item = (&Link, &Edge)> {
+        self.links
+            .values()
+            .flat_map(|link| link.edges.iter().map(move |edge| (link, edge)))
+    }
+
+    /// The crawled pages linking to each page, by id: the pages
+    /// listing it in their children or in their edges. Unlike the
+    /// `parents` of a link, which only keep the page it was queued
+    /// from, these are all the pages referencing it
+    pub fn referrers(&self) -> HashMap<LinkId, BTreeSet<LinkId>> {
+        let mut referrers: HashMap<LinkId, BTreeSet<LinkId>> = Default::default();
+        for link in self.links.values() {
+            let edge_ids = link
+                .edges
+                .iter()
+                .filter_map(|edge| self.link_ids.get(&edge.to));
+            for target in link.children.iter().chain(edge_ids) {
+                if *target != link.id {
+                    referrers.entry(*target).or_default().insert(link.id);
+                }
+            }
+        }
+        referrers
+    }
+
+    /// The number of crawled pages linking to each page, by id,
+    /// see `referrers`. The pages nothing links to are left out
+    pub fn in_degrees(&self) -> HashMap<LinkId, usize> {
+        self.referrers()
+            .into_iter()
+            .map(|(id, referrers)| (id, referrers.len()))
+            .collect()
+    }
+
+    /// Records why the link with the given `url` was crawled,
+    /// unless it was already crawled for another reason
+    pub fn set_provenance(&mut self, url: &str, provenance: Provenance) -> Result<()> {
+        self.force_get_link_id(url)?
+            .provenance
+            .get_or_insert(provenance);
+        Ok(())
     }
 
     /// This function will retrieve a valid link ID if the
@@ -78,7 +423,15 @@ impl LinkGraph {
         let this_link_id = if let Some(link_id) = self.link_ids.get(url) {
             *link_id
         } else {
+            // On the (unlikely) collision of two hashes, the
+            // next free id is taken instead
+            let mut id = link_id(url);
+            while self.links.contains_key(&id) {
+                id = (id + 1) & LINK_ID_MASK;
+            }
+
             let new_link = Link {
+                id,
                 url: url.to_string(),
                 ..Default::default()
             };
@@ -103,9 +456,253 @@ impl LinkGraph {
 
 impl<'a> IntoIterator for &'a LinkGraph {
     type Item = (&'a LinkId, &'a Link);
-    type IntoIter = std::collections::hash_map::Iter<'a, LinkId, Link>;
+    type IntoIter = std::collections::btree_map::Iter<'a, LinkId, Link>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.links.iter()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{ErrorCounts, ErrorKind, Image};
+
+    fn page(links: &[&str], titles: &[&str], images: &[&str]) -> ScrapeOutput {
+        ScrapeOutput {
+            links: links.iter().map(|l| l.to_string()).collect(),
+            titles: titles.iter().map(|t| t.to_string()).collect(),
+            images: images
+                .iter()
+                .map(|link| Image {
+                    link: link.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn ids(graph: &LinkGraph, urls: &[&str]) -> BTreeSet<LinkId> {
+        urls.iter().map(|url| graph.get(url).unwrap().id).collect()
+    }
+
+    #[test]
+    fn page_reached_twice_is_recorded_once() {
+        let mut graph = LinkGraph::default();
+        let b_page = page(&["a", "c"], &["B", "B"], &["img.png", "img.png"]);
+
+        graph
+            .update("a", "", &page(&["b", "c"], &["A"], &[]))
+            .unwrap();
+        graph.update("b", "a", &b_page).unwrap();
+        graph.update("c", "a", &page(&["b"], &["C"], &[])).unwrap();
+        graph.update("b", "c", &b_page).unwrap();
+        graph.update("b", "a", &b_page).unwrap();
+
+        let b = graph.get("b").unwrap();
+        assert_eq!(b.parents, ids(&graph, &["a", "c"]));
+        assert_eq!(b.children, ids(&graph, &["a", "c"]));
+        assert_eq!(b.titles, vec!["B"]);
+        assert_eq!(b.images.len(), 1);
+
+        let a = graph.get("a").unwrap();
+        assert_eq!(a.children, ids(&graph, &["b", "c"]));
+        assert_eq!(a.titles, vec!["A"]);
+    }
+
+    #[test]
+    fn merged_sections_dont_duplicate_shared_pages() {
+        let mut first = LinkGraph::default();
+        first
+            .update("a", "", &page(&["b"], &["A"], &["x.png"]))
+            .unwrap();
+        first.update("b", "a", &page(&["a"], &["B"], &[])).unwrap();
+
+        let mut second = LinkGraph::default();
+        second.update("b", "", &page(&["a"], &["B"], &[])).unwrap();
+        second
+            .update("a", "b", &page(&["b"], &["A"], &["x.png"]))
+            .unwrap();
+
+        let mut merged = LinkGraph::default();
+        merged.merge_section(first).unwrap();
+        merged.merge_section(second).unwrap();
+
+        assert_eq!(merged.len(), 2);
+        for (url, other) in [("a", "b"), ("b", "a")] {
+            let link = merged.get(url).unwrap();
+            assert_eq!(link.children, ids(&merged, &[other]));
+            assert_eq!(link.parents, ids(&merged, &[other]));
+            assert_eq!(link.titles.len(), 1);
+        }
+        assert_eq!(merged.get("a").unwrap().images.len(), 1);
+    }
+
+    #[test]
+    fn merged_sections_add_up_the_shared_domains() {
+        let section = |slowdowns: u32, delay: u64, error: ErrorKind| {
+            let mut errors = ErrorCounts::default();
+            errors.add(error);
+            let mut graph = LinkGraph::default();
+            graph.set_domains([(
+                String::from("a.com"),
+                DomainInfo {
+                    request_delay_ms: delay,
+                    slowdowns,
+                    errors,
+                    ..Default::default()
+                },
+            )]);
+            graph
+        };
+
+        let mut merged = LinkGraph::default();
+        merged
+            .merge_section(section(1, 500, ErrorKind::Timeout))
+            .unwrap();
+        merged
+            .merge_section(section(2, 200, ErrorKind::ServerError))
+            .unwrap();
+
+        let domain = &merged.domains()["a.com"];
+        assert_eq!(domain.slowdowns, 3);
+        assert_eq!(domain.request_delay_ms, 500);
+        assert_eq!(domain.errors.total(), 2);
+    }
+
+    #[test]
+    fn ids_serialize_sorted_and_unique() {
+        let mut graph = LinkGraph::default();
+        graph.update("a", "", &page(&[], &[], &[])).unwrap();
+        graph.update("b", "a", &page(&["a"], &[], &[])).unwrap();
+        graph.update("b", "a", &page(&["a"], &[], &[])).unwrap();
+
+        let json = serde_json::to_value(&graph).unwrap();
+        let a = graph.get("a").unwrap().id;
+        let b = graph.get("b").unwrap().id.to_string();
+        assert_eq!(json["links"][&b]["parents"], serde_json::json!([a]));
+        assert_eq!(json["links"][&b]["children"], serde_json::json!([a]));
+    }
+
+    #[test]
+    fn referrers_include_every_page_linking_to_a_page() {
+        let mut graph = LinkGraph::default();
+        let linking = |links: &[&str]| ScrapeOutput {
+            edges: links
+                .iter()
+                .map(|to| Edge {
+                    to: to.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..page(links, &[], &[])
+        };
+        graph.update("a", "", &linking(&["b", "c"])).unwrap();
+        graph.update("b", "a", &linking(&["c"])).unwrap();
+        graph.update("c", "a", &page(&[], &[], &[])).unwrap();
+
+        let referrers = graph.referrers();
+        let c = graph.get("c").unwrap();
+        assert_eq!(c.parents, ids(&graph, &["a"]));
+        assert_eq!(referrers[&c.id], ids(&graph, &["a", "b"]));
+        assert_eq!(graph.in_degrees()[&c.id], 2);
+    }
+
+    #[test]
+    fn carry_over_keeps_the_pages_crawled_again() {
+        let mut previous = LinkGraph::default();
+        previous
+            .update("a", "", &page(&["b"], &["Old A"], &[]))
+            .unwrap();
+        previous
+            .update("b", "a", &page(&[], &["Old B"], &[]))
+            .unwrap();
+        for url in ["a", "b"] {
+            previous.set_provenance(url, Provenance::default()).unwrap();
+        }
+
+        // "a" is crawled again, "b" is only linked to
+        let mut graph = LinkGraph::default();
+        graph
+            .update("a", "", &page(&["b"], &["New A"], &[]))
+            .unwrap();
+        graph.set_provenance("a", Provenance::default()).unwrap();
+        graph.update("b", "a", &page(&[], &[], &[])).unwrap();
+
+        assert_eq!(graph.carry_over(previous).unwrap(), 1);
+        assert_eq!(graph.get("a").unwrap().titles, vec!["New A"]);
+        assert_eq!(graph.get("b").unwrap().titles, vec!["Old B"]);
+        assert_eq!(graph.get("b").unwrap().parents, ids(&graph, &["a"]));
+    }
+
+    /// A run of a recurring crawl finding only "a", after `previous`
+    fn run_crawling_a(previous: LinkGraph) -> LinkGraph {
+        let mut graph = LinkGraph::default();
+        graph.update("a", "", &page(&[], &["A"], &[])).unwrap();
+        graph.set_provenance("a", Provenance::default()).unwrap();
+        graph.carry_over(previous).unwrap();
+        graph
+    }
+
+    #[test]
+    fn carry_over_keeps_the_pages_not_seen_again() {
+        let mut previous = LinkGraph::default();
+        previous
+            .update("a", "", &page(&["b"], &["A"], &[]))
+            .unwrap();
+        previous
+            .update("b", "a", &page(&[], &["Old B"], &[]))
+            .unwrap();
+        for url in ["a", "b"] {
+            previous.set_provenance(url, Provenance::default()).unwrap();
+        }
+
+        let graph = run_crawling_a(previous);
+        let b = graph.get("b").unwrap();
+        assert_eq!(graph.run(), 1);
+        assert_eq!(b.titles, vec!["Old B"]);
+        assert_eq!(b.last_seen_run, 0);
+        assert_eq!(graph.get("a").unwrap().last_seen_run, 1);
+    }
+
+    #[test]
+    fn prune_unseen_removes_the_pages_unseen_for_n_runs() {
+        let mut graph = LinkGraph::default();
+        graph.update("a", "", &page(&["b"], &["A"], &[])).unwrap();
+        graph.update("b", "a", &page(&[], &["B"], &[])).unwrap();
+        for url in ["a", "b"] {
+            graph.set_provenance(url, Provenance::default()).unwrap();
+        }
+
+        graph = run_crawling_a(graph);
+        assert!(graph.prune_unseen(2).is_empty());
+        assert!(graph.get("b").is_some());
+
+        graph = run_crawling_a(graph);
+        assert_eq!(graph.prune_unseen(2), vec!["b"]);
+        assert!(graph.get("b").is_none());
+    }
+
+    #[test]
+    fn ids_are_stable_across_graphs() {
+        let mut first = LinkGraph::default();
+        first
+            .update("https://a.com/", "", &page(&[], &[], &[]))
+            .unwrap();
+        first
+            .update("https://b.com/x", "https://a.com/", &page(&[], &[], &[]))
+            .unwrap();
+
+        let mut second = LinkGraph::default();
+        second
+            .update("https://b.com/x", "", &page(&[], &[], &[]))
+            .unwrap();
+
+        let id = link_id("https://b.com/x");
+        assert_eq!(first.get("https://b.com/x").unwrap().id, id);
+        assert_eq!(second.get("https://b.com/x").unwrap().id, id);
+        assert_eq!(link_id("HTTPS://B.com:443/x#top"), id);
+        assert!(id <= LINK_ID_MASK);
+    }
+}
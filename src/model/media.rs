@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// The kind of element a piece of media is embedded with
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaKind {
+    /// an `<iframe src>`, e.g. an embedded video player
+    Iframe,
+    /// a `<video>` or one of its `<source>`s
+    Video,
+    /// an `<audio>` or one of its `<source>`s
+    Audio,
+}
+
+/// A piece of media embedded in a page
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Media {
+    /// how the media is embedded
+    pub kind: MediaKind,
+    /// the absolute link of the media
+    pub link: String,
+}
@@ -1,7 +1,41 @@
+mod betweenness;
+mod block_kind;
+mod crawl_result;
+mod domain_info;
+mod edge;
+mod headings;
 mod image;
 mod link;
 mod link_graph;
+mod media;
+mod page_rank;
+mod page_timings;
+mod provenance;
+mod recrawl_summary;
+mod scrape_output;
+mod search_hits;
+mod security_headers;
+mod server_fingerprint;
+mod social_meta;
+mod warm_up_estimate;
+mod well_known_file;
 
+pub use block_kind::*;
+pub use crawl_result::*;
+pub use domain_info::*;
+pub use edge::*;
+pub use headings::*;
 pub use image::*;
 pub use link::*;
 pub use link_graph::*;
+pub use media::*;
+pub use page_timings::*;
+pub use provenance::*;
+pub use recrawl_summary::*;
+pub use scrape_output::*;
+pub use search_hits::*;
+pub use security_headers::*;
+pub use server_fingerprint::*;
+pub use social_meta::*;
+pub use warm_up_estimate::*;
+pub use well_known_file::*;
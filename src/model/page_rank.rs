@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+
+use super::{LinkGraph, LinkId};
+
+const DAMPING_FACTOR: f64 = 0.85;
+const ITERATIONS: usize = 30;
+
+impl LinkGraph {
+    /// Computes the PageRank of every link in the graph,
+    /// following the parent -> child edges. The ranks add
+    /// up to one.
+    pub fn page_rank(&self) -> HashMap<LinkId, f64> {
+        let n_links = self.len();
+        if n_links == 0 {
+            return Default::default();
+        }
+
+        let initial_rank = 1.0 / n_links as f64;
+        let mut ranks: HashMap<LinkId, f64> = self
+            .into_iter()
+            .map(|(id, _)| (*id, initial_rank))
+            .collect();
+
+        for _ in 0..ITERATIONS {
+            // Pages without links share their rank with everyone
+            let dangling_rank: f64 = self
+                .into_iter()
+                .filter(|(_, link)| link.children.is_empty())
+                .map(|(id, _)| ranks[id])
+                .sum();
+
+            let base_rank = (1.0 - DAMPING_FACTOR) / n_links as f64
+                + DAMPING_FACTOR * dangling_rank / n_links as f64;
+            let mut next_ranks: HashMap<LinkId, f64> =
+                ranks.keys().map(|id| (*id, base_rank)).collect();
+
+            for (id, link) in self {
+                if link.children.is_empty() {
+                    continue;
+                }
+
+                let share = DAMPING_FACTOR * ranks[id] / link.children.len() as f64;
+                for child in link.children.iter() {
+                    if let Some(rank) = next_ranks.get_mut(child) {
+                        *rank += share;
+                    }
+                }
+            }
+
+            ranks = next_ranks;
+        }
+
+        ranks
+    }
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+/// How long each step of scraping a page took, in milliseconds
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct PageTimings {
+    /// requesting the page and downloading its body
+    pub fetch_ms: f64,
+    /// parsing the HTML into a DOM
+    pub parse_ms: f64,
+    /// finding the links and running the scrape options
+    pub extract_ms: f64,
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+/// How the crawler came across a link
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Discovery {
+    /// given by the user to start from
+    Seed,
+    /// listed in a sitemap
+    Sitemap,
+    /// an `<a href>` on the parent page
+    #[default]
+    Anchor,
+    /// an entry of a feed the parent page advertises
+    Feed,
+    /// where the parent page redirected to
+    Redirect,
+    /// crawled by the previous crawl, see `--incremental`
+    Previous,
+}
+
+/// Why a page was crawled
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Provenance {
+    /// the page the link was found on, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    /// how the link was found
+    pub discovery: Discovery,
+    /// the scope file `allow` pattern the link matched,
+    /// when the scope is limited to some patterns
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope_rule: Option<String>,
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// How the pages of an incremental crawl compare to the
+/// crawl it started from, see `--incremental`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RecrawlSummary {
+    /// the pages of the previous crawl that changed,
+    /// or that couldn't be fetched this time
+    pub changed: usize,
+    /// the pages of the previous crawl that didn't change,
+    /// answered with a 304, the same body or the same error
+    pub unchanged: usize,
+    /// the pages the previous crawl didn't have
+    pub new: usize,
+    /// the pages of the previous crawl that weren't checked
+    /// again, e.g. for running out of links to crawl
+    pub unchecked: usize,
+}
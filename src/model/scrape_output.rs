@@ -0,0 +1,91 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::model::block_kind::BlockKind;
+use crate::model::domain_info::ErrorKind;
+use crate::model::edge::Edge;
+use crate::model::headings::Headings;
+use crate::model::image::Image;
+use crate::model::media::Media;
+use crate::model::page_timings::PageTimings;
+use crate::model::search_hits::SearchHits;
+use crate::model::security_headers::SecurityHeaders;
+use crate::model::server_fingerprint::ServerFingerprint;
+use crate::model::social_meta::SocialMeta;
+
+/// Everything scraped from a single page
+#[derive(Debug, Default, Serialize)]
+pub struct ScrapeOutput {
+    pub links: Vec<String>,
+    /// the anchor text and `rel` of each link
+    pub edges: Vec<Edge>,
+    pub images: Vec<Image>,
+    pub titles: Vec<String>,
+    /// the `<title>` and number of `<h1>`s of the page
+    pub headings: Option<Headings>,
+    /// the iframes, videos and audio embedded in the page
+    pub media: Vec<Media>,
+    /// the status code the page was served with
+    pub status: Option<u16>,
+    /// why the page couldn't be crawled, if it failed
+    pub error: Option<ErrorKind>,
+    /// the url the page redirected to, if it did
+    pub redirect: Option<String>,
+    /// the canonical url the page declares
+    pub canonical: Option<String>,
+    /// the description meta tag of the page
+    pub description: Option<String>,
+    /// the keywords meta tag of the page
+    pub keywords: Vec<String>,
+    /// the Open Graph and Twitter Card tags of the page
+    pub social: Option<SocialMeta>,
+    /// the JSON-LD blocks of the page
+    pub structured_data: Vec<Value>,
+    /// the readable text of the page
+    pub text: Option<String>,
+    /// where each `--search` term (by pattern) was
+    /// found in the text of the page
+    pub search_hits: BTreeMap<String, SearchHits>,
+    /// the primary tag of the `<html lang>` of the page, e.g. `en`
+    pub declared_language: Option<String>,
+    /// the ISO 639-3 code of the language detected
+    /// from the text of the page, e.g. `eng`
+    pub detected_language: Option<String>,
+    /// the SimHash fingerprint of the text of the page
+    pub simhash: Option<u64>,
+    /// links and images loaded over plain HTTP
+    /// from an HTTPS page
+    pub mixed_content: Vec<String>,
+    /// the fields cut short for being too long
+    pub truncated_fields: Vec<String>,
+    /// the RSS/Atom feeds the page advertises
+    pub feeds: Vec<String>,
+    /// the links of the entries of those feeds,
+    /// which are in `links` too
+    pub feed_links: Vec<String>,
+    /// how long scraping the page took, if it was scraped
+    pub timings: Option<PageTimings>,
+    /// set when the page was an anti-bot or CAPTCHA
+    /// interstitial rather than the real content
+    pub blocked: Option<BlockKind>,
+    /// the Last-Modified header of the page, if sent
+    pub last_modified: Option<String>,
+    /// the ETag header of the page, if sent
+    pub etag: Option<String>,
+    /// the Content-Type header of the page, if sent
+    pub content_type: Option<String>,
+    /// the HTTP version and server headers of the response
+    pub server: Option<ServerFingerprint>,
+    /// the security headers of the response, if they were kept
+    pub security_headers: Option<SecurityHeaders>,
+    /// the records the parse rules found in a
+    /// non HTML page, by rule name
+    pub extracted: BTreeMap<String, Value>,
+    /// the text of the elements matched by each
+    /// custom selector, by selector name
+    pub custom: BTreeMap<String, Vec<String>>,
+    /// the decoded body of the page, if it was fetched
+    #[serde(skip)]
+    pub body: Option<String>,
+}
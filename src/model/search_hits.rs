@@ -0,0 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+/// Where a `--search` term was found on a page
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SearchHits {
+    /// how many times the term matched the page's text
+    pub count: usize,
+    /// the first matches, with some of the text around them
+    pub snippets: Vec<String>,
+}
@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+
+/// The security related response headers of a page
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SecurityHeaders {
+    /// the `Content-Security-Policy` header
+    pub content_security_policy: Option<String>,
+    /// the `Strict-Transport-Security` header
+    pub strict_transport_security: Option<String>,
+    /// the `X-Frame-Options` header
+    pub x_frame_options: Option<String>,
+    /// the `X-Content-Type-Options` header
+    pub x_content_type_options: Option<String>,
+}
+
+impl SecurityHeaders {
+    /// The names of the checks a page served with these headers
+    /// fails. HSTS is only checked for `https` pages, and the CSP
+    /// `frame-ancestors` directive can stand in for X-Frame-Options
+    pub fn failures(&self, https: bool) -> Vec<&'static str> {
+        let csp = self.content_security_policy.as_deref().unwrap_or_default();
+        let hsts = self
+            .strict_transport_security
+            .as_deref()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        let nosniff = self
+            .x_content_type_options
+            .as_deref()
+            .is_some_and(|value| value.trim().eq_ignore_ascii_case("nosniff"));
+
+        [
+            ("csp", !csp.trim().is_empty()),
+            (
+                "hsts",
+                !https || (hsts.contains("max-age") && !hsts.contains("max-age=0")),
+            ),
+            (
+                "x-frame-options",
+                self.x_frame_options.is_some() || csp.contains("frame-ancestors"),
+            ),
+            ("x-content-type-options", nosniff),
+        ]
+        .into_iter()
+        .filter(|(_, passes)| !passes)
+        .map(|(check, _)| check)
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SecurityHeaders;
+
+    #[test]
+    fn checks_the_headers_that_apply() {
+        let headers = SecurityHeaders {
+            content_security_policy: Some("frame-ancestors 'none'".to_string()),
+            x_content_type_options: Some("nosniff".to_string()),
+            ..Default::default()
+        };
+        assert!(headers.failures(false).is_empty());
+        assert_eq!(headers.failures(true), vec!["hsts"]);
+
+        let headers = SecurityHeaders {
+            strict_transport_security: Some("max-age=0".to_string()),
+            x_content_type_options: Some("sniff".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            headers.failures(true),
+            vec!["csp", "hsts", "x-frame-options", "x-content-type-options"]
+        );
+    }
+}
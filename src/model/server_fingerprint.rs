@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What a server says about itself in its responses
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServerFingerprint {
+    /// the HTTP version negotiated, e.g. `HTTP/2.0`
+    pub http_version: String,
+    /// the `Server` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// the other headers naming the software, CDN or
+    /// caches in front of the site (`X-Powered-By`, `Via`...)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+}
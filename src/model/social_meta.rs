@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The Open Graph and Twitter Card tags of a page,
+/// which decide how it is previewed when shared
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SocialMeta {
+    /// the `og:*` properties without their prefix,
+    /// e.g. `title`, `image`, `image:width`
+    pub open_graph: BTreeMap<String, String>,
+    /// the `twitter:*` properties without their prefix,
+    /// e.g. `card`, `site`
+    pub twitter: BTreeMap<String, String>,
+}
+
+impl SocialMeta {
+    pub fn is_empty(&self) -> bool {
+        self.open_graph.is_empty() && self.twitter.is_empty()
+    }
+
+    /// The title shown in the previews, Twitter
+    /// falls back on the Open Graph one
+    pub fn title(&self) -> Option<&str> {
+        self.get("title")
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.get("description")
+    }
+
+    pub fn image(&self) -> Option<&str> {
+        self.get("image")
+    }
+
+    fn get(&self, property: &str) -> Option<&str> {
+        self.open_graph
+            .get(property)
+            .or_else(|| self.twitter.get(property))
+            .map(String::as_str)
+    }
+
+    /// Adds the properties missing from this page's
+    /// tags, the first value of each is kept
+    pub fn merge(&mut self, other: SocialMeta) {
+        for (property, value) in other.open_graph {
+            self.open_graph.entry(property).or_insert(value);
+        }
+        for (property, value) in other.twitter {
+            self.twitter.entry(property).or_insert(value);
+        }
+    }
+}
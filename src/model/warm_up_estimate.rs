@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// What the first pages crawled on a domain told about it,
+/// see `--warm-up`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WarmUpEstimate {
+    /// how many pages were sampled
+    pub sampled: usize,
+    /// the average time the sampled pages took to arrive
+    pub mean_fetch_ms: f64,
+    /// the average size of the sampled pages, in bytes
+    pub mean_bytes: u64,
+    /// the share of the sampled pages that failed
+    pub error_rate: f64,
+    /// the links found on this domain so far
+    pub discovered: usize,
+    /// the share of the crawl's links given to this domain
+    pub budget: usize,
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// A standard file probed on a domain, e.g. `/robots.txt`
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WellKnownFile {
+    /// the status code the file was served with
+    pub status: u16,
+    /// the start of the file, when it was found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// set when the content was cut short
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl WellKnownFile {
+    /// Whether the domain serves the file
+    pub fn found(&self) -> bool {
+        self.content.is_some()
+    }
+}
@@ -0,0 +1,39 @@
+use anyhow::Error;
+
+use crate::model::ScrapeOutput;
+
+/// Callbacks run by the crawl workers, registered with
+/// `CrawlerBuilder::observer`, e.g. to scrape extra data
+/// from each page without changing the crawler
+///
+/// ```
+/// use rusty_crawler::{model::ScrapeOutput, observer::CrawlObserver};
+///
+/// struct Prices;
+///
+/// impl CrawlObserver for Prices {
+///     fn on_page_scraped(&self, _url: &str, page: &mut ScrapeOutput) {
+///         let body = page.body.as_deref().unwrap_or_default();
+///         if let Some((_, price)) = body.split_once("price: ") {
+///             let price = price.split_whitespace().next().unwrap_or_default();
+///             page.extracted.insert("price".into(), price.into());
+///         }
+///     }
+/// }
+///
+/// let crawler = rusty_crawler::Crawler::builder()
+///     .seed("https://example.com/")
+///     .observer(Prices);
+/// ```
+pub trait CrawlObserver: Send + Sync {
+    /// Called with every page scraped, before it is added to
+    /// the link graph. Anything put in `page.extracted` is
+    /// saved with the page
+    fn on_page_scraped(&self, _url: &str, _page: &mut ScrapeOutput) {}
+
+    /// Called when a link not visited yet is queued
+    fn on_link_discovered(&self, _url: &str, _parent: &str) {}
+
+    /// Called when a page could not be scraped
+    fn on_error(&self, _url: &str, _error: &Error) {}
+}
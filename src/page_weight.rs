@@ -0,0 +1,214 @@
+//! How heavy the crawled pages are to load, going by the
+//! size of their HTML and of their images, see
+//! `crawl --heaviest-pages`
+
+use futures::{stream, StreamExt};
+use reqwest::{header::CONTENT_LENGTH, Client};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    time::Duration,
+};
+use tokio::time::Instant;
+use tracing::{error, info};
+use url::Url;
+
+use crate::{fetcher::Fetcher, model::LinkGraph, rate_limiter::RateLimiter, robots::Robots};
+
+/// How many hosts have their images weighed at the same time
+const CONCURRENT_HOSTS: usize = 8;
+/// The least time between two requests to the same host
+const MIN_HOST_DELAY: Duration = Duration::from_millis(250);
+
+/// What a page weighs
+#[derive(Debug)]
+pub struct PageWeight<'a> {
+    pub url: &'a str,
+    /// the size of the body of the page
+    pub html_bytes: u64,
+    /// how many images the page shows
+    pub images: usize,
+    /// the size of the images that were weighed
+    pub image_bytes: u64,
+    /// how many of the images couldn't be weighed
+    pub unweighed_images: usize,
+}
+
+impl PageWeight<'_> {
+    pub fn total_bytes(&self) -> u64 {
+        self.html_bytes + self.image_bytes
+    }
+}
+
+/// Keeps the requests weighing the images as polite as the crawl
+pub struct WeighingLimits<'a> {
+    /// the robots.txt rules the images must be allowed by, if
+    /// respected, and the fetcher getting them
+    pub robots: Option<(&'a Robots, &'a dyn Fetcher)>,
+    /// spaces out all the requests, see `ScopeRules::request_interval`
+    pub rate_limiter: &'a RateLimiter,
+    /// the delay between two requests to each domain, as
+    /// learned by the crawl (see `DomainInfo::request_delay_ms`)
+    pub domain_delays: HashMap<String, Duration>,
+}
+
+impl WeighingLimits<'_> {
+    async fn allowed(&self, url: &Url) -> bool {
+        let Some((robots, fetcher)) = self.robots else {
+            return true;
+        };
+        match robots.rules_for(fetcher, url).await {
+            Ok(rules) => rules.allowed(url),
+            Err(e) => {
+                error!("Could not get robots.txt for {}: {}", url, e);
+                true
+            }
+        }
+    }
+}
+
+/// The urls of the images shown on the pages of `link_graph`
+pub fn image_urls(link_graph: &LinkGraph) -> BTreeSet<&str> {
+    link_graph
+        .into_iter()
+        .flat_map(|(_, link)| link.images.iter().map(|image| image.link.as_str()))
+        .collect()
+}
+
+/// Sends a HEAD request for each of the `image_urls`, a few
+/// hosts at a time and within the `limits`, returning the
+/// Content-Length of the images, by url. The images that
+/// couldn't be weighed are left out
+pub async fn weigh_images(
+    client: &Client,
+    image_urls: BTreeSet<&str>,
+    limits: &WeighingLimits<'_>,
+) -> HashMap<String, u64> {
+    let mut by_host: BTreeMap<String, Vec<(&str, Url)>> = Default::default();
+    for image_url in image_urls {
+        match Url::parse(image_url) {
+            Ok(url) => by_host
+                .entry(url.host_str().unwrap_or_default().to_string())
+                .or_default()
+                .push((image_url, url)),
+            Err(e) => info!("Could not weigh image {}: {}", image_url, e),
+        }
+    }
+
+    stream::iter(by_host)
+        .map(|(host, urls)| async move {
+            let delay = limits
+                .domain_delays
+                .get(&host)
+                .map_or(MIN_HOST_DELAY, |delay| (*delay).max(MIN_HOST_DELAY));
+            let mut next_request = Instant::now();
+            let mut sizes: Vec<(String, u64)> = Default::default();
+            for (image_url, url) in urls {
+                if !limits.allowed(&url).await {
+                    info!("Image disallowed by robots.txt: {}", image_url);
+                    continue;
+                }
+
+                tokio::time::sleep_until(next_request).await;
+                limits.rate_limiter.wait().await;
+                next_request = Instant::now() + delay;
+                if let Some(length) = weigh_image(client, url).await {
+                    sizes.push((image_url.to_string(), length));
+                }
+            }
+            sizes
+        })
+        .buffer_unordered(CONCURRENT_HOSTS)
+        .flat_map(stream::iter)
+        .collect()
+        .await
+}
+
+/// The Content-Length of the image at `url`, from a HEAD request
+async fn weigh_image(client: &Client, url: Url) -> Option<u64> {
+    let response = match client.head(url.clone()).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            info!("Could not weigh image {}: {}", url, response.status());
+            return None;
+        }
+        Err(e) => {
+            info!("Could not weigh image {}: {}", url, e);
+            return None;
+        }
+    };
+    // HEAD responses have no body to take the length from
+    response
+        .headers()
+        .get(CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// The `top` heaviest fetched pages of `link_graph`, the
+/// images weighing what `image_sizes` says they do
+pub fn heaviest_pages<'a>(
+    link_graph: &'a LinkGraph,
+    image_sizes: &HashMap<String, u64>,
+    top: usize,
+) -> Vec<PageWeight<'a>> {
+    let mut pages: Vec<PageWeight> = link_graph
+        .into_iter()
+        .filter_map(|(_, link)| {
+            let sizes: Vec<Option<u64>> = link
+                .images
+                .iter()
+                .map(|image| image_sizes.get(&image.link).copied())
+                .collect();
+            Some(PageWeight {
+                url: &link.url,
+                html_bytes: link.size?,
+                images: sizes.len(),
+                image_bytes: sizes.iter().flatten().sum(),
+                unweighed_images: sizes.iter().filter(|size| size.is_none()).count(),
+            })
+        })
+        .collect();
+
+    pages.sort_by_key(|page| std::cmp::Reverse(page.total_bytes()));
+    pages.truncate(top);
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fetcher::ReqwestFetcher, policy_cache::PolicyCache};
+    use axum::{routing::get, Router};
+
+    #[tokio::test]
+    async fn images_disallowed_by_robots_are_not_weighed() {
+        let router = Router::new()
+            .route(
+                "/robots.txt",
+                get(|| async { "User-agent: *\nDisallow: /private/\n" }),
+            )
+            .route("/a.png", get(|| async { vec![0u8; 10] }))
+            .route("/private/b.png", get(|| async { vec![0u8; 20] }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let origin = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let client = Client::new();
+        let fetcher = ReqwestFetcher::new(client.clone(), None);
+        let robots = Robots::new(PolicyCache::load(None, Duration::ZERO).unwrap());
+        let limits = WeighingLimits {
+            robots: Some((&robots, &fetcher)),
+            rate_limiter: &RateLimiter::new(None),
+            domain_delays: Default::default(),
+        };
+        let (a, b) = (
+            format!("{}/a.png", origin),
+            format!("{}/private/b.png", origin),
+        );
+
+        let sizes = weigh_images(&client, BTreeSet::from([a.as_str(), b.as_str()]), &limits).await;
+        assert_eq!(sizes, HashMap::from([(a, 10)]));
+    }
+}
@@ -0,0 +1,205 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+use url::Url;
+
+use crate::crawler::{extract_page, get_url, ScrapeConfig};
+use crate::feed::parse_feed;
+use crate::model::ScrapeOutput;
+
+/// Turns the body of a response into what is scraped from it
+pub trait ContentParser: Send + Sync {
+    fn parse(&self, body: &str, page_url: &Url, config: &ScrapeConfig) -> Result<ScrapeOutput>;
+}
+
+/// The rules used to get records out of the
+/// responses that aren't HTML, e.g.
+///
+/// ```json
+/// {
+///     "json": { "price": "/product/price" },
+///     "text": { "version": "version: (\\S+)" }
+/// }
+/// ```
+#[derive(Deserialize, Default)]
+pub struct ParseRules {
+    /// name -> JSON pointer, for JSON responses
+    #[serde(default)]
+    pub json: BTreeMap<String, String>,
+    /// name -> regex, for plain text responses. The first
+    /// capture group is kept if there is one
+    #[serde(default)]
+    pub text: BTreeMap<String, String>,
+}
+
+impl ParseRules {
+    pub fn from_file(path: &str) -> Result<ParseRules> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read parse rules {}", path))?;
+        serde_json::from_str(&contents).with_context(|| format!("invalid parse rules {}", path))
+    }
+}
+
+/// Scrapes the links, images and titles of HTML pages
+pub struct HtmlParser;
+
+impl ContentParser for HtmlParser {
+    fn parse(&self, body: &str, page_url: &Url, config: &ScrapeConfig) -> Result<ScrapeOutput> {
+        Ok(extract_page(body, page_url, config))
+    }
+}
+
+/// Gets the entries of RSS/Atom feeds as links
+pub struct FeedParser;
+
+impl ContentParser for FeedParser {
+    fn parse(&self, body: &str, page_url: &Url, _config: &ScrapeConfig) -> Result<ScrapeOutput> {
+        let links = parse_feed(body)?
+            .iter()
+            .filter_map(|l| get_url(l, page_url.clone()).ok())
+            .map(|url| url.to_string())
+            .collect();
+
+        Ok(ScrapeOutput {
+            links,
+            ..Default::default()
+        })
+    }
+}
+
+/// Keeps the values at the given JSON pointers
+pub struct JsonParser {
+    rules: BTreeMap<String, String>,
+}
+
+impl ContentParser for JsonParser {
+    fn parse(&self, body: &str, _page_url: &Url, _config: &ScrapeConfig) -> Result<ScrapeOutput> {
+        let json: Value = serde_json::from_str(body)?;
+        let extracted = self
+            .rules
+            .iter()
+            .filter_map(|(name, pointer)| Some((name.clone(), json.pointer(pointer)?.clone())))
+            .collect();
+
+        Ok(ScrapeOutput {
+            extracted,
+            ..Default::default()
+        })
+    }
+}
+
+/// Keeps every match of the given regexes
+pub struct TextParser {
+    rules: Vec<(String, Regex)>,
+}
+
+impl TextParser {
+    fn new(rules: &BTreeMap<String, String>) -> Result<TextParser> {
+        let rules = rules
+            .iter()
+            .map(|(name, pattern)| Ok((name.clone(), Regex::new(pattern)?)))
+            .collect::<Result<_>>()?;
+
+        Ok(TextParser { rules })
+    }
+}
+
+impl ContentParser for TextParser {
+    fn parse(&self, body: &str, _page_url: &Url, _config: &ScrapeConfig) -> Result<ScrapeOutput> {
+        let extracted = self
+            .rules
+            .iter()
+            .map(|(name, regex)| {
+                let matches = regex
+                    .captures_iter(body)
+                    .filter_map(|c| c.get(1).or_else(|| c.get(0)))
+                    .map(|m| Value::String(m.as_str().to_string()))
+                    .collect();
+                (name.clone(), Value::Array(matches))
+            })
+            .collect();
+
+        Ok(ScrapeOutput {
+            extracted,
+            ..Default::default()
+        })
+    }
+}
+
+/// Picks the parser of each response by its content type
+#[derive(Clone)]
+pub struct ParserRegistry {
+    parsers: HashMap<String, Arc<dyn ContentParser>>,
+}
+
+impl Default for ParserRegistry {
+    fn default() -> Self {
+        // No rules can't fail to compile
+        ParserRegistry::with_rules(&Default::default()).unwrap()
+    }
+}
+
+impl ParserRegistry {
+    /// The built in parsers, with `rules` for the JSON
+    /// and plain text responses
+    pub fn with_rules(rules: &ParseRules) -> Result<ParserRegistry> {
+        let mut registry = ParserRegistry {
+            parsers: Default::default(),
+        };
+
+        let html: Arc<dyn ContentParser> = Arc::new(HtmlParser);
+        registry.register("text/html", html.clone());
+        registry.register("application/xhtml+xml", html);
+
+        let feed: Arc<dyn ContentParser> = Arc::new(FeedParser);
+        for content_type in [
+            "application/rss+xml",
+            "application/atom+xml",
+            "application/xml",
+            "text/xml",
+        ] {
+            registry.register(content_type, feed.clone());
+        }
+
+        registry.register(
+            "application/json",
+            Arc::new(JsonParser {
+                rules: rules.json.clone(),
+            }),
+        );
+        registry.register("text/plain", Arc::new(TextParser::new(&rules.text)?));
+
+        Ok(registry)
+    }
+
+    /// Parses the responses of `content_type` (e.g. `text/csv`)
+    /// with `parser`, replacing the parser it had
+    pub fn register(&mut self, content_type: &str, parser: Arc<dyn ContentParser>) {
+        self.parsers
+            .insert(content_type.to_ascii_lowercase(), parser);
+    }
+
+    /// The parser for a `Content-Type` header, pages
+    /// without one are assumed to be HTML
+    pub fn parser_for(&self, content_type: Option<&str>) -> Option<&dyn ContentParser> {
+        let essence = content_type
+            .and_then(|c| c.split(';').next())
+            .map(|c| c.trim().to_ascii_lowercase())
+            .unwrap_or_else(|| String::from("text/html"));
+
+        let parser = self.parsers.get(&essence).or_else(|| {
+            // e.g. application/ld+json
+            essence
+                .ends_with("+json")
+                .then(|| self.parsers.get("application/json"))
+                .flatten()
+        })?;
+
+        Some(parser.as_ref())
+    }
+}
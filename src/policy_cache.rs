@@ -0,0 +1,121 @@
+use anyhow::{Context, Result};
+use flate2::read::GzDecoder;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::Read,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::Mutex;
+use url::Url;
+
+use crate::fetcher::{FetchResponse, Fetcher};
+
+/// A policy file (robots.txt, sitemap) as it was
+/// fetched, with the time it was fetched at
+#[derive(Clone, Serialize, Deserialize)]
+struct CachedFile {
+    /// seconds since the unix epoch
+    fetched_at: u64,
+    /// the body of the file, `None` when the site
+    /// does not have one
+    body: Option<String>,
+}
+
+/// Caches policy files per url, optionally persisting them
+/// to disk so recurring crawls don't fetch the same files
+/// again until they are older than the ttl
+pub struct PolicyCache {
+    path: Option<String>,
+    ttl: Duration,
+    entries: Mutex<HashMap<String, CachedFile>>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Gets the text of a policy file, which may be gzipped
+/// (e.g. sitemap.xml.gz)
+fn decode_body(response: &FetchResponse) -> Result<String> {
+    const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+    if !response.body.starts_with(&GZIP_MAGIC) {
+        return Ok(response.text());
+    }
+
+    let mut text = String::new();
+    GzDecoder::new(response.body.as_slice())
+        .read_to_string(&mut text)
+        .context("could not decompress gzipped file")?;
+    Ok(text)
+}
+
+impl PolicyCache {
+    /// Creates the cache, loading the entries saved in the file
+    /// at `path` if it exists. Without a path nothing is persisted.
+    pub fn load(path: Option<String>, ttl: Duration) -> Result<PolicyCache> {
+        let entries = match &path {
+            Some(path) if Path::new(path).is_file() => {
+                let contents = std::fs::read_to_string(path)
+                    .with_context(|| format!("could not read policy cache {}", path))?;
+                serde_json::from_str(&contents)
+                    .with_context(|| format!("invalid policy cache {}", path))?
+            }
+            _ => Default::default(),
+        };
+
+        Ok(PolicyCache {
+            path,
+            ttl,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Gets the body of the file at `url`, from the cache if it
+    /// has not expired, otherwise from the network. Returns `None`
+    /// if the site does not have the file.
+    pub async fn fetch(&self, fetcher: &dyn Fetcher, url: &Url) -> Result<Option<String>> {
+        if let Some(cached) = self.entries.lock().await.get(url.as_str()) {
+            if now_secs().saturating_sub(cached.fetched_at) < self.ttl.as_secs() {
+                return Ok(cached.body.clone());
+            }
+        }
+
+        let response = fetcher.fetch(url).await?;
+
+        let body = match response.status {
+            StatusCode::OK => Some(decode_body(&response)?),
+            status if status.is_client_error() => None,
+            status => anyhow::bail!("could not fetch {}, status {}", url, status),
+        };
+
+        self.entries.lock().await.insert(
+            url.to_string(),
+            CachedFile {
+                fetched_at: now_secs(),
+                body: body.clone(),
+            },
+        );
+
+        Ok(body)
+    }
+
+    /// Writes the cache back to disk, if it has a path
+    pub async fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        // Keep the entries locked while writing, so crawls
+        // sharing this cache don't write the file at once
+        let entries = self.entries.lock().await;
+        let json = serde_json::to_string(&*entries)?;
+        tokio::fs::write(path, json).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use std::{collections::HashMap, time::Duration};
+use tokio::{sync::Mutex, time::Instant};
+
+use crate::model::{DomainInfo, ErrorKind, ServerFingerprint};
+
+/// Responses this many times slower than the
+/// domain's best average mean it is struggling
+const SLOWDOWN_FACTOR: f64 = 2.0;
+/// Weight of the newest response in the average
+const AVERAGE_WEIGHT: f64 = 0.3;
+/// Responses needed before judging a domain
+const MIN_RESPONSES: u32 = 3;
+const MIN_DELAY: Duration = Duration::from_millis(250);
+const SOFT_BLOCK_DELAY: Duration = Duration::from_secs(2);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct DomainState {
+    responses: u32,
+    average_ms: f64,
+    baseline_ms: Option<f64>,
+    delay: Duration,
+    next_request: Option<Instant>,
+    info: DomainInfo,
+}
+
+/// Learns how fast each domain can be crawled from its
+/// responses, slowing down the ones whose responses degrade
+/// or that start serving anti-bot pages
+#[derive(Default)]
+pub struct DomainPoliteness {
+    domains: Mutex<HashMap<String, DomainState>>,
+}
+
+impl DomainPoliteness {
+    /// Waits until the next request to `domain` is allowed
+    pub async fn wait(&self, domain: &str) {
+        let mut domains = self.domains.lock().await;
+        let state = domains.entry(domain.to_string()).or_default();
+        if state.delay.is_zero() {
+            return;
+        }
+
+        let now = Instant::now();
+        let request_at = state.next_request.unwrap_or(now).max(now);
+        state.next_request = Some(request_at + state.delay);
+        drop(domains);
+
+        tokio::time::sleep_until(request_at).await;
+    }
+
+    /// Learns from a response of `domain`, which took `fetch_ms`
+    /// to arrive. `soft_block` is set when the page `url` looked
+    /// like an anti-bot or CAPTCHA interstitial.
+    pub async fn record(&self, domain: &str, url: &str, fetch_ms: f64, soft_block: bool) {
+        let mut domains = self.domains.lock().await;
+        let state = domains.entry(domain.to_string()).or_default();
+
+        if soft_block {
+            state.delay = (state.delay * 4).max(SOFT_BLOCK_DELAY).min(MAX_DELAY);
+            state.info.soft_blocks.push(url.to_string());
+            state.info.request_delay_ms = state.delay.as_millis() as u64;
+            return;
+        }
+
+        state.responses += 1;
+        state.average_ms = if state.responses == 1 {
+            fetch_ms
+        } else {
+            AVERAGE_WEIGHT * fetch_ms + (1.0 - AVERAGE_WEIGHT) * state.average_ms
+        };
+
+        if state.responses < MIN_RESPONSES {
+            return;
+        }
+
+        let baseline_ms = state
+            .baseline_ms
+            .unwrap_or(state.average_ms)
+            .min(state.average_ms);
+        state.baseline_ms = Some(baseline_ms);
+
+        if state.average_ms > baseline_ms * SLOWDOWN_FACTOR {
+            state.delay = (state.delay * 2).max(MIN_DELAY).min(MAX_DELAY);
+            state.info.slowdowns += 1;
+            // Judge the new delay against the current speed
+            state.average_ms = baseline_ms;
+        } else if !state.delay.is_zero() {
+            // Recovering, so slowly give the speed back
+            state.delay = state.delay.mul_f64(0.9);
+            if state.delay < MIN_DELAY / 4 {
+                state.delay = Duration::ZERO;
+            }
+        }
+
+        state.info.request_delay_ms = state.delay.as_millis() as u64;
+    }
+
+    /// Keeps the fingerprint of the first response of `domain`
+    pub async fn record_server(&self, domain: &str, server: &ServerFingerprint) {
+        let mut domains = self.domains.lock().await;
+        let state = domains.entry(domain.to_string()).or_default();
+        state.info.server.get_or_insert_with(|| server.clone());
+    }
+
+    /// Counts a page of `domain` that couldn't be crawled
+    pub async fn record_error(&self, domain: &str, kind: ErrorKind) {
+        let mut domains = self.domains.lock().await;
+        let state = domains.entry(domain.to_string()).or_default();
+        state.info.errors.add(kind);
+    }
+
+    /// What was learned about each domain since the last call,
+    /// so that crawlers sharing the politeness don't count the
+    /// same slowdowns and errors twice
+    pub async fn take_domain_infos(&self) -> HashMap<String, DomainInfo> {
+        self.domains
+            .lock()
+            .await
+            .iter_mut()
+            .map(|(domain, state)| {
+                // The delay still holds for the next requests
+                let info = DomainInfo {
+                    request_delay_ms: state.info.request_delay_ms,
+                    ..Default::default()
+                };
+                (domain.clone(), std::mem::replace(&mut state.info, info))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn domain_infos_are_only_taken_once() {
+        let politeness = DomainPoliteness::default();
+        politeness
+            .record("a.com", "https://a.com/", 10.0, true)
+            .await;
+        politeness.record_error("a.com", ErrorKind::Timeout).await;
+
+        let first = politeness.take_domain_infos().await;
+        assert_eq!(first["a.com"].errors.total(), 1);
+        assert_eq!(first["a.com"].soft_blocks, ["https://a.com/"]);
+
+        let second = politeness.take_domain_infos().await;
+        assert_eq!(second["a.com"].errors.total(), 0);
+        assert!(second["a.com"].soft_blocks.is_empty());
+        assert_eq!(
+            second["a.com"].request_delay_ms,
+            first["a.com"].request_delay_ms
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::header::HeaderMap;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use tracing::warn;
+use url::Url;
+
+use crate::client::ClientOptions;
+use crate::fetcher::{FetchResponse, Fetcher, ReqwestFetcher};
+
+/// Consecutive failures after which a proxy is
+/// considered dead and taken out of the rotation
+const MAX_PROXY_FAILURES: u32 = 3;
+
+/// How the proxy for each request is picked
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+pub enum ProxyRotation {
+    /// use the next proxy for every request
+    PerRequest,
+    /// always use the same proxy for the same host
+    PerHost,
+    /// always use the same proxy for the same crawl worker
+    PerWorker,
+}
+
+struct PooledProxy {
+    url: String,
+    fetcher: ReqwestFetcher,
+    failures: AtomicU32,
+    dead: AtomicBool,
+}
+
+/// A `Fetcher` rotating the requests between a pool
+/// of proxies, evicting the ones that keep failing
+pub struct ProxyPoolFetcher {
+    proxies: Vec<PooledProxy>,
+    rotation: ProxyRotation,
+    next: AtomicUsize,
+}
+
+/// Reads the proxy list file, one proxy url per line
+pub fn load_proxy_list(path: &str) -> Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read proxy list {}", path))?;
+
+    Ok(contents
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| l.to_string())
+        .collect())
+}
+
+impl ProxyPoolFetcher {
+    /// Creates a client for each of the `proxies`, all
+    /// sharing the other `client_options`
+    pub fn new(
+        client_options: &ClientOptions,
+        proxies: Vec<String>,
+        rotation: ProxyRotation,
+    ) -> Result<Self> {
+        if proxies.is_empty() {
+            bail!("the proxy list is empty");
+        }
+
+        let proxies = proxies
+            .into_iter()
+            .map(|url| {
+                let options = ClientOptions {
+                    proxy: Some(url.clone()),
+                    ..client_options.clone()
+                };
+
+                Ok(PooledProxy {
+                    fetcher: ReqwestFetcher::new(
+                        options.build_client()?,
+                        options.user_agents.clone(),
+                    )
+                    .max_body_bytes(options.max_body_bytes),
+                    url,
+                    failures: AtomicU32::new(0),
+                    dead: AtomicBool::new(false),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ProxyPoolFetcher {
+            proxies,
+            rotation,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// The fetcher of crawl worker number `worker`, which
+    /// sticks to one proxy with `ProxyRotation::PerWorker`
+    pub fn for_worker(self: &Arc<Self>, worker: usize) -> Arc<dyn Fetcher> {
+        Arc::new(WorkerProxyFetcher {
+            pool: self.clone(),
+            worker,
+        })
+    }
+
+    fn pick(&self, url: &Url, worker: Option<usize>) -> Result<&PooledProxy> {
+        let alive: Vec<&PooledProxy> = self
+            .proxies
+            .iter()
+            .filter(|p| !p.dead.load(Ordering::Relaxed))
+            .collect();
+
+        if alive.is_empty() {
+            bail!("all the proxies in the pool are dead");
+        }
+
+        let index = match (self.rotation, worker) {
+            (ProxyRotation::PerHost, _) => {
+                let mut hasher = DefaultHasher::new();
+                url.host_str().unwrap_or("").hash(&mut hasher);
+                hasher.finish() as usize
+            }
+            (ProxyRotation::PerWorker, Some(worker)) => worker,
+            // Requests made outside the workers (e.g. for the
+            // sitemaps) rotate between the proxies too
+            (ProxyRotation::PerRequest | ProxyRotation::PerWorker, _) => {
+                self.next.fetch_add(1, Ordering::Relaxed)
+            }
+        };
+
+        Ok(alive[index % alive.len()])
+    }
+
+    /// Fetches `url` through the proxy picked for `worker`,
+    /// as a page (see `Fetcher::fetch_page`) if `page` is set
+    async fn fetch_with(
+        &self,
+        url: &Url,
+        worker: Option<usize>,
+        headers: HeaderMap,
+        page: bool,
+    ) -> Result<FetchResponse> {
+        let proxy = self.pick(url, worker)?;
+
+        let response = if page {
+            proxy.fetcher.fetch_page(url, headers).await
+        } else {
+            proxy.fetcher.fetch_with_headers(url, headers).await
+        };
+        match response {
+            Ok(response) => {
+                proxy.failures.store(0, Ordering::Relaxed);
+                Ok(response)
+            }
+            Err(e) => {
+                let failures = proxy.failures.fetch_add(1, Ordering::Relaxed) + 1;
+                if failures >= MAX_PROXY_FAILURES && !proxy.dead.swap(true, Ordering::Relaxed) {
+                    warn!("Evicting proxy {} after {} failures", proxy.url, failures);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Fetcher for ProxyPoolFetcher {
+    async fn fetch(&self, url: &Url) -> Result<FetchResponse> {
+        self.fetch_with(url, None, HeaderMap::new(), false).await
+    }
+
+    async fn fetch_with_headers(&self, url: &Url, headers: HeaderMap) -> Result<FetchResponse> {
+        self.fetch_with(url, None, headers, false).await
+    }
+
+    async fn fetch_page(&self, url: &Url, headers: HeaderMap) -> Result<FetchResponse> {
+        self.fetch_with(url, None, headers, true).await
+    }
+}
+
+/// The pool as seen by one of the crawl workers
+struct WorkerProxyFetcher {
+    pool: Arc<ProxyPoolFetcher>,
+    worker: usize,
+}
+
+#[async_trait]
+impl Fetcher for WorkerProxyFetcher {
+    async fn fetch(&self, url: &Url) -> Result<FetchResponse> {
+        self.pool
+            .fetch_with(url, Some(self.worker), HeaderMap::new(), false)
+            .await
+    }
+
+    async fn fetch_with_headers(&self, url: &Url, headers: HeaderMap) -> Result<FetchResponse> {
+        self.pool
+            .fetch_with(url, Some(self.worker), headers, false)
+            .await
+    }
+
+    async fn fetch_page(&self, url: &Url, headers: HeaderMap) -> Result<FetchResponse> {
+        self.pool
+            .fetch_with(url, Some(self.worker), headers, true)
+            .await
+    }
+}
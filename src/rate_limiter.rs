@@ -0,0 +1,39 @@
+use std::time::Duration;
+use tokio::{sync::Mutex, time::Instant};
+
+/// Spaces out requests shared between all the
+/// workers so that at most one request is made
+/// every `interval`
+pub struct RateLimiter {
+    interval: Mutex<Option<Duration>>,
+    next_request: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(interval: Option<Duration>) -> Self {
+        RateLimiter {
+            interval: Mutex::new(interval),
+            next_request: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Changes the interval, taking effect from the next request
+    pub async fn set_interval(&self, interval: Option<Duration>) {
+        *self.interval.lock().await = interval;
+    }
+
+    /// Waits until the caller is allowed to make a request
+    pub async fn wait(&self) {
+        let Some(interval) = *self.interval.lock().await else {
+            return;
+        };
+
+        let mut next_request = self.next_request.lock().await;
+        let now = Instant::now();
+        let request_at = (*next_request).max(now);
+        *next_request = request_at + interval;
+        drop(next_request);
+
+        tokio::time::sleep_until(request_at).await;
+    }
+}
@@ -0,0 +1,270 @@
+//! A self-contained HTML report of a crawl, with bar charts
+//! of its statuses and depths, see `crawl --report`
+
+use anyhow::Result;
+use std::{collections::HashSet, fmt::Write};
+
+use crate::broken_links::broken_links;
+use crate::gallery::{escape_html, format_size};
+use crate::model::{Link, LinkGraph};
+use crate::stats::CrawlStats;
+
+/// How many rows the tables of pages are cut to
+const TOP_PAGES: usize = 20;
+
+/// A chart of one bar per `(label, value)`, as wide
+/// as the value relative to the biggest one
+fn bar_chart(bars: &[(String, usize)]) -> Result<String> {
+    let max = bars
+        .iter()
+        .map(|(_, value)| *value)
+        .max()
+        .unwrap_or(1)
+        .max(1);
+
+    let mut chart = String::from("<table class=\"chart\">\n");
+    for (label, value) in bars {
+        writeln!(
+            chart,
+            r#"<tr><th>{}</th><td><div class="bar" style="width: {:.1}%"></div>{}</td></tr>"#,
+            escape_html(label),
+            *value as f64 / max as f64 * 90.0,
+            value
+        )?;
+    }
+    chart.push_str("</table>\n");
+    Ok(chart)
+}
+
+/// A table with the `headings`, if any, and one row per
+/// item of `rows`, whose cells are already escaped
+fn table(headings: &[&str], rows: Vec<Vec<String>>) -> Result<String> {
+    let mut table = String::from("<table>\n");
+    if !headings.is_empty() {
+        table.push_str("<tr>");
+        for heading in headings {
+            write!(table, "<th>{}</th>", heading)?;
+        }
+        table.push_str("</tr>\n");
+    }
+    for row in rows {
+        table.push_str("<tr>");
+        for cell in row {
+            write!(table, "<td>{}</td>", cell)?;
+        }
+        table.push_str("</tr>\n");
+    }
+    table.push_str("</table>\n");
+    Ok(table)
+}
+
+/// A link to `url`, showing the url
+fn link_to(url: &str) -> String {
+    let url = escape_html(url);
+    format!(r#"<a href="{url}">{url}</a>"#)
+}
+
+/// The report of the crawl in `link_graph`: its size, status
+/// codes, depths, most linked to pages, broken links and images
+pub fn to_html_report(link_graph: &LinkGraph) -> Result<String> {
+    let stats = CrawlStats::from_graph(link_graph);
+    let mut body = String::new();
+
+    let seeds: Vec<String> = link_graph.seeds().iter().map(|s| link_to(s)).collect();
+    let images: Vec<_> = link_graph
+        .into_iter()
+        .flat_map(|(_, link)| link.images.iter())
+        .collect();
+    let unique_images: HashSet<&str> = images.iter().map(|image| image.link.as_str()).collect();
+    let total_bytes: u64 = link_graph
+        .into_iter()
+        .filter_map(|(_, link)| link.size)
+        .sum();
+    writeln!(
+        body,
+        "<h2>Summary</h2>\n{}",
+        table(
+            &[],
+            vec![
+                vec![String::from("Starting urls"), seeds.join("<br>")],
+                vec![String::from("Pages"), stats.pages.to_string()],
+                vec![String::from("Links between them"), stats.edges.to_string()],
+                vec![String::from("Domains"), stats.domains.len().to_string()],
+                vec![String::from("HTML downloaded"), format_size(total_bytes)],
+            ]
+        )?
+    )?;
+
+    let statuses: Vec<(String, usize)> = stats
+        .statuses
+        .iter()
+        .map(|(status, pages)| match status {
+            Some(status) => (status.to_string(), *pages),
+            None => (String::from("unknown"), *pages),
+        })
+        .collect();
+    writeln!(body, "<h2>Status codes</h2>\n{}", bar_chart(&statuses)?)?;
+
+    let mut depths: Vec<(String, usize)> = stats
+        .depths
+        .iter()
+        .map(|(depth, pages)| (depth.to_string(), *pages))
+        .collect();
+    if stats.unreachable > 0 {
+        depths.push((String::from("none"), stats.unreachable));
+    }
+    writeln!(
+        body,
+        "<h2>Depth from the starting urls</h2>\n{}",
+        bar_chart(&depths)?
+    )?;
+
+    let page_rank = link_graph.page_rank();
+    let in_degrees = link_graph.in_degrees();
+    let in_degree = |link: &Link| in_degrees.get(&link.id).copied().unwrap_or(0);
+    let mut linked: Vec<&Link> = link_graph.into_iter().map(|(_, link)| link).collect();
+    linked.sort_by(|a, b| {
+        in_degree(b)
+            .cmp(&in_degree(a))
+            .then_with(|| a.url.cmp(&b.url))
+    });
+    let rows = linked
+        .into_iter()
+        .take(TOP_PAGES)
+        .map(|link| {
+            vec![
+                link_to(&link.url),
+                in_degree(link).to_string(),
+                format!(
+                    "{:.5}",
+                    page_rank.get(&link.id).copied().unwrap_or_default()
+                ),
+            ]
+        })
+        .collect();
+    writeln!(
+        body,
+        "<h2>Most linked to pages</h2>\n{}",
+        table(&["Page", "Linked from", "PageRank"], rows)?
+    )?;
+
+    let broken = broken_links(link_graph);
+    let rows = broken
+        .iter()
+        .map(|link| {
+            let referrers: Vec<String> = link.referrers.iter().map(|r| link_to(r)).collect();
+            let status = match (link.status, link.error) {
+                (Some(status), _) => status.to_string(),
+                (None, Some(error)) => format!("{:?}", error),
+                (None, None) => String::new(),
+            };
+            vec![link_to(&link.url), status, referrers.join("<br>")]
+        })
+        .collect();
+    writeln!(
+        body,
+        "<h2>Broken links ({})</h2>\n{}",
+        broken.len(),
+        table(&["Page", "Status", "Linked from"], rows)?
+    )?;
+
+    let without_alt = images.iter().filter(|image| image.missing_alt()).count();
+    let pages_with_images = link_graph
+        .into_iter()
+        .filter(|(_, link)| !link.images.is_empty())
+        .count();
+    writeln!(
+        body,
+        "<h2>Images</h2>\n{}",
+        table(
+            &[],
+            vec![
+                vec![String::from("Images shown"), images.len().to_string()],
+                vec![
+                    String::from("Distinct images"),
+                    unique_images.len().to_string()
+                ],
+                vec![String::from("Without alt text"), without_alt.to_string()],
+                vec![
+                    String::from("Pages with images"),
+                    pages_with_images.to_string()
+                ],
+            ]
+        )?
+    )?;
+
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Crawl report</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; max-width: 70em; }}
+table {{ border-collapse: collapse; margin-bottom: 1em; }}
+th, td {{ text-align: left; padding: 0.25em 0.75em; border-bottom: 1px solid #ddd; overflow-wrap: anywhere; }}
+.chart {{ width: 100%; }}
+.chart th {{ width: 6em; }}
+.bar {{ display: inline-block; height: 1em; margin-right: 0.5em; background: #4a7fd4; vertical-align: middle; }}
+</style>
+</head>
+<body>
+<h1>Crawl report</h1>
+{body}</body>
+</html>
+"#
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::{extract_page, ScrapeConfig, ScrapeOption};
+    use url::Url;
+
+    #[test]
+    fn the_report_sums_up_the_crawl() {
+        let config = ScrapeConfig {
+            options: vec![ScrapeOption::Images],
+            max_field_length: 4096,
+            parsers: Default::default(),
+            custom_selectors: Default::default(),
+            extractors: Default::default(),
+            searches: Default::default(),
+        };
+        let pages = [
+            (
+                "https://a.com/",
+                200,
+                r#"<a href="/about">About</a><a href="/gone">Gone</a>
+                <img src="/logo.png" alt="Logo"><img src="/photo.jpg">"#,
+            ),
+            (
+                "https://a.com/about",
+                200,
+                r#"<a href="/">Home</a><img src="/logo.png" alt="Logo">"#,
+            ),
+            ("https://a.com/gone", 404, ""),
+        ];
+        let mut graph = LinkGraph::default();
+        graph.set_seeds(vec![String::from("https://a.com/")]);
+        for (url, status, html) in pages {
+            let mut page = extract_page(html, &Url::parse(url).unwrap(), &config);
+            page.status = Some(status);
+            graph.update(url, "", &page).unwrap();
+        }
+
+        let report = to_html_report(&graph).unwrap();
+        assert!(report.contains("<tr><td>Pages</td><td>3</td></tr>"));
+        assert!(report.contains(
+            r#"<tr><th>404</th><td><div class="bar" style="width: 45.0%"></div>1</td></tr>"#
+        ));
+        assert!(report.contains("<h2>Broken links (1)</h2>"));
+        assert!(report.contains(
+            r#"<td><a href="https://a.com/gone">https://a.com/gone</a></td><td>404</td><td><a href="https://a.com/">https://a.com/</a></td>"#
+        ));
+        assert!(report.contains("<tr><td>Images shown</td><td>3</td></tr>"));
+        assert!(report.contains("<tr><td>Distinct images</td><td>2</td></tr>"));
+        assert!(report.contains("<tr><td>Without alt text</td><td>1</td></tr>"));
+    }
+}
@@ -0,0 +1,326 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::{
+    header::{
+        HeaderMap, HeaderName, HeaderValue, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED,
+    },
+    StatusCode, Version,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::{error, info};
+use url::Url;
+
+use crate::fetcher::{FetchResponse, Fetcher};
+
+/// What is stored for each cached url. The body itself
+/// is stored separately, addressed by the hash of its
+/// contents so identical pages are only stored once.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    url: String,
+    final_url: String,
+    status: u16,
+    /// e.g. `HTTP/2.0`
+    #[serde(default)]
+    version: Option<String>,
+    headers: Vec<(String, String)>,
+    /// the validators of the cached response
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// the hash of the body, which is also its file name
+    body_hash: String,
+    /// whether only the start of the body was fetched
+    #[serde(default)]
+    truncated: bool,
+}
+
+/// Hex encoded sha256 of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Parses a version written as `{:?}`, the entries
+/// cached before the version was kept are assumed HTTP/1.1
+fn parse_version(version: Option<&str>) -> Version {
+    match version {
+        Some("HTTP/0.9") => Version::HTTP_09,
+        Some("HTTP/1.0") => Version::HTTP_10,
+        Some("HTTP/2.0") => Version::HTTP_2,
+        Some("HTTP/3.0") => Version::HTTP_3,
+        _ => Version::HTTP_11,
+    }
+}
+
+/// Reads the cached response stored in the entry at `entry_path`
+async fn read_entry(directory: &Path, entry_path: &Path) -> Result<FetchResponse> {
+    let entry = tokio::fs::read_to_string(entry_path).await?;
+    let entry: CacheEntry = serde_json::from_str(&entry)?;
+    let body = tokio::fs::read(directory.join("bodies").join(&entry.body_hash)).await?;
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in entry.headers.iter() {
+        headers.insert(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_str(value)?,
+        );
+    }
+
+    Ok(FetchResponse {
+        url: Url::parse(&entry.final_url)?,
+        status: StatusCode::from_u16(entry.status)?,
+        version: parse_version(entry.version.as_deref()),
+        headers,
+        body,
+        truncated: entry.truncated,
+    })
+}
+
+/// Every response cached in `directory` by a `CachingFetcher`,
+/// skipping the entries that can't be read
+pub async fn load_cached_responses(directory: &str) -> Result<Vec<FetchResponse>> {
+    let directory = PathBuf::from(directory);
+    let mut entries = tokio::fs::read_dir(&directory)
+        .await
+        .with_context(|| format!("could not read cache directory {:?}", directory))?;
+
+    let mut responses = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().is_none_or(|e| e != "json") {
+            continue;
+        }
+
+        match read_entry(&directory, &path).await {
+            Ok(response) => responses.push(response),
+            Err(e) => error!("Could not read cached response {:?}: {}", path, e),
+        }
+    }
+
+    Ok(responses)
+}
+
+/// A `Fetcher` keeping every successful response in a local
+/// directory, so repeated runs against the same site are
+/// served from disk instead of the network
+pub struct CachingFetcher {
+    inner: Arc<dyn Fetcher>,
+    directory: PathBuf,
+    /// ask the server whether the cached responses changed
+    /// instead of trusting them, see `revalidate`
+    revalidate: bool,
+}
+
+impl CachingFetcher {
+    pub fn new(inner: Arc<dyn Fetcher>, directory: &str) -> Result<Self> {
+        let directory = PathBuf::from(directory);
+        std::fs::create_dir_all(directory.join("bodies"))
+            .with_context(|| format!("could not create cache directory {:?}", directory))?;
+
+        Ok(CachingFetcher {
+            inner,
+            directory,
+            revalidate: false,
+        })
+    }
+
+    /// Sends the ETag and Last-Modified of the cached responses
+    /// as `If-None-Match` and `If-Modified-Since`, serving them
+    /// from disk only when the server answers 304 Not Modified.
+    /// The responses cached without validators are fetched again.
+    pub fn revalidate(mut self, revalidate: bool) -> Self {
+        self.revalidate = revalidate;
+        self
+    }
+
+    fn entry_path(&self, url: &Url) -> PathBuf {
+        self.directory
+            .join(sha256_hex(url.as_str().as_bytes()) + ".json")
+    }
+
+    fn body_path(&self, body_hash: &str) -> PathBuf {
+        self.directory.join("bodies").join(body_hash)
+    }
+
+    async fn read(&self, url: &Url) -> Result<FetchResponse> {
+        read_entry(&self.directory, &self.entry_path(url)).await
+    }
+
+    async fn write(&self, url: &Url, response: &FetchResponse) -> Result<()> {
+        let body_hash = sha256_hex(&response.body);
+        let body_path = self.body_path(&body_hash);
+        if !body_path.is_file() {
+            tokio::fs::write(body_path, &response.body).await?;
+        }
+
+        let header = |name: &str| {
+            response
+                .headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+
+        let entry = CacheEntry {
+            url: url.to_string(),
+            final_url: response.url.to_string(),
+            status: response.status.as_u16(),
+            version: Some(format!("{:?}", response.version)),
+            headers: response
+                .headers
+                .iter()
+                .filter_map(|(n, v)| Some((n.to_string(), v.to_str().ok()?.to_string())))
+                .collect(),
+            etag: header("etag"),
+            last_modified: header("last-modified"),
+            body_hash,
+            truncated: response.truncated,
+        };
+
+        tokio::fs::write(self.entry_path(url), serde_json::to_string(&entry)?).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Fetcher for CachingFetcher {
+    async fn fetch(&self, url: &Url) -> Result<FetchResponse> {
+        self.fetch_with_headers(url, HeaderMap::new()).await
+    }
+
+    async fn fetch_with_headers(&self, url: &Url, headers: HeaderMap) -> Result<FetchResponse> {
+        self.fetch_cached(url, headers, false).await
+    }
+
+    async fn fetch_page(&self, url: &Url, headers: HeaderMap) -> Result<FetchResponse> {
+        self.fetch_cached(url, headers, true).await
+    }
+}
+
+impl CachingFetcher {
+    /// Fetches `url` from the cache, or from the inner fetcher as
+    /// a page (see `Fetcher::fetch_page`) if `page` is set. The
+    /// caller's own validators (e.g. an incremental crawl's) ask
+    /// the server rather than the cache, and its 304 Not Modified
+    /// is handed back as is
+    async fn fetch_cached(
+        &self,
+        url: &Url,
+        headers: HeaderMap,
+        page: bool,
+    ) -> Result<FetchResponse> {
+        let fetch_inner = |headers| async move {
+            if page {
+                self.inner.fetch_page(url, headers).await
+            } else {
+                self.inner.fetch_with_headers(url, headers).await
+            }
+        };
+
+        if headers.contains_key(IF_NONE_MATCH) || headers.contains_key(IF_MODIFIED_SINCE) {
+            let response = fetch_inner(headers).await?;
+            if response.status == StatusCode::OK {
+                if let Err(e) = self.write(url, &response).await {
+                    error!("Could not cache response for {}: {}", url, e);
+                }
+            }
+            return Ok(response);
+        }
+
+        let mut cached = None;
+        if self.entry_path(url).is_file() {
+            match self.read(url).await {
+                Ok(response) => cached = Some(response),
+                Err(e) => error!("Could not read cached response for {}: {}", url, e),
+            }
+        }
+
+        let mut validators = headers;
+        match cached {
+            Some(cached) if !self.revalidate => return Ok(cached),
+            Some(ref cached) => {
+                if let Some(etag) = cached.headers.get(ETAG) {
+                    validators.insert(IF_NONE_MATCH, etag.clone());
+                }
+                if let Some(last_modified) = cached.headers.get(LAST_MODIFIED) {
+                    validators.insert(IF_MODIFIED_SINCE, last_modified.clone());
+                }
+            }
+            None => {}
+        }
+
+        let response = fetch_inner(validators).await?;
+        if let (Some(cached), StatusCode::NOT_MODIFIED) = (cached, response.status) {
+            info!("Cached response for {} unchanged", url);
+            return Ok(cached);
+        }
+        if response.status == StatusCode::OK {
+            if let Err(e) = self.write(url, &response).await {
+                error!("Could not cache response for {}: {}", url, e);
+            }
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Answers 304 to the requests with validators, recording them
+    #[derive(Default)]
+    struct ValidatingFetcher {
+        requests: Mutex<Vec<HeaderMap>>,
+    }
+
+    #[async_trait]
+    impl Fetcher for ValidatingFetcher {
+        async fn fetch(&self, url: &Url) -> Result<FetchResponse> {
+            self.fetch_with_headers(url, HeaderMap::new()).await
+        }
+
+        async fn fetch_with_headers(&self, url: &Url, headers: HeaderMap) -> Result<FetchResponse> {
+            let status = if headers.contains_key(IF_NONE_MATCH) {
+                StatusCode::NOT_MODIFIED
+            } else {
+                StatusCode::OK
+            };
+            self.requests.lock().unwrap().push(headers);
+            Ok(FetchResponse {
+                url: url.clone(),
+                status,
+                version: Version::HTTP_11,
+                headers: HeaderMap::from_iter([(ETAG, HeaderValue::from_static("\"v1\""))]),
+                body: b"<html></html>".to_vec(),
+                truncated: false,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn callers_validators_reach_the_server() {
+        let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let inner = Arc::new(ValidatingFetcher::default());
+        let fetcher = CachingFetcher::new(inner.clone(), directory.to_str().unwrap()).unwrap();
+        let url = Url::parse("https://example.com/").unwrap();
+
+        // Cached by the first fetch
+        assert_eq!(fetcher.fetch(&url).await.unwrap().status, StatusCode::OK);
+
+        let validators =
+            HeaderMap::from_iter([(IF_NONE_MATCH, HeaderValue::from_static("\"v1\""))]);
+        let response = fetcher.fetch_with_headers(&url, validators).await.unwrap();
+        assert_eq!(response.status, StatusCode::NOT_MODIFIED);
+
+        let requests = inner.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1][IF_NONE_MATCH], "\"v1\"");
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+use anyhow::Result;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::RwLock;
+use url::Url;
+
+use crate::fetcher::Fetcher;
+use crate::policy_cache::PolicyCache;
+
+/// The user agent name we look for in robots.txt
+/// groups, falling back to the `*` group
+const ROBOTS_USER_AGENT: &str = "rusty_crawler";
+
+/// The rules in a robots.txt that apply to this crawler
+#[derive(Default, Debug)]
+pub struct RobotsRules {
+    allow: Vec<String>,
+    disallow: Vec<String>,
+    /// the sitemaps advertised in the robots.txt
+    pub sitemaps: Vec<String>,
+}
+
+impl RobotsRules {
+    /// Parses the body of a robots.txt file, keeping the
+    /// group for our user agent, or the `*` group otherwise
+    pub fn parse(body: &str) -> RobotsRules {
+        let mut ours = RobotsRules::default();
+        let mut wildcard = RobotsRules::default();
+        let mut sitemaps: Vec<String> = Default::default();
+        // whether a group names our user agent, even if
+        // its only rule is an empty `Disallow:`
+        let mut has_own_group = false;
+
+        // the agents of the group being read
+        let mut agents: Vec<String> = Default::default();
+        let mut reading_agents = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((field, value)) = line.split_once(':') else {
+                continue;
+            };
+            let field = field.trim().to_ascii_lowercase();
+            let value = value.trim();
+
+            match field.as_str() {
+                "user-agent" => {
+                    if !reading_agents {
+                        agents.clear();
+                    }
+                    let agent = value.to_ascii_lowercase();
+                    has_own_group |= agent == ROBOTS_USER_AGENT;
+                    agents.push(agent);
+                    reading_agents = true;
+                }
+                "allow" | "disallow" => {
+                    reading_agents = false;
+                    for agent in agents.iter() {
+                        let rules = match agent.as_str() {
+                            ROBOTS_USER_AGENT => &mut ours,
+                            "*" => &mut wildcard,
+                            _ => continue,
+                        };
+
+                        // An empty disallow allows everything
+                        if value.is_empty() {
+                            continue;
+                        }
+
+                        if field == "allow" {
+                            rules.allow.push(value.to_string());
+                        } else {
+                            rules.disallow.push(value.to_string());
+                        }
+                    }
+                }
+                "sitemap" => sitemaps.push(value.to_string()),
+                _ => reading_agents = false,
+            }
+        }
+
+        let mut rules = if has_own_group { ours } else { wildcard };
+        rules.sitemaps = sitemaps;
+        rules
+    }
+
+    /// Whether the crawler may visit `url`. The longest matching
+    /// rule wins, and allow wins when both match equally.
+    pub fn allowed(&self, url: &Url) -> bool {
+        let mut path = url.path().to_string();
+        if let Some(query) = url.query() {
+            path = path + "?" + query;
+        }
+
+        let longest_match = |rules: &[String]| {
+            rules
+                .iter()
+                .filter(|r| rule_matches(r, &path))
+                .map(|r| r.len())
+                .max()
+        };
+
+        match (longest_match(&self.allow), longest_match(&self.disallow)) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(allow), Some(disallow)) => allow >= disallow,
+        }
+    }
+}
+
+/// Matches a robots.txt path rule, where `*` matches any
+/// sequence of characters and a trailing `$` anchors the end
+fn rule_matches(rule: &str, path: &str) -> bool {
+    let (rule, anchored) = match rule.strip_suffix('$') {
+        Some(rule) => (rule, true),
+        None => (rule, false),
+    };
+
+    let parts: Vec<&str> = rule.split('*').collect();
+    let Some(mut rest) = path.strip_prefix(parts[0]) else {
+        return false;
+    };
+
+    let Some((last, middle)) = parts[1..].split_last() else {
+        return !anchored || rest.is_empty();
+    };
+
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    if anchored {
+        rest.ends_with(last)
+    } else {
+        rest.contains(last)
+    }
+}
+
+/// Fetches and keeps the robots.txt rules of
+/// every domain visited during the crawl
+pub struct Robots {
+    cache: PolicyCache,
+    rules: RwLock<HashMap<String, Arc<RobotsRules>>>,
+}
+
+impl Robots {
+    pub fn new(cache: PolicyCache) -> Self {
+        Robots {
+            cache,
+            rules: Default::default(),
+        }
+    }
+
+    /// The cache holding the fetched policy files
+    pub fn cache(&self) -> &PolicyCache {
+        &self.cache
+    }
+
+    /// Gets the robots.txt rules for the domain of `url`. Sites
+    /// without a robots.txt get rules allowing everything.
+    pub async fn rules_for(&self, fetcher: &dyn Fetcher, url: &Url) -> Result<Arc<RobotsRules>> {
+        let origin = url.origin().ascii_serialization();
+        if let Some(rules) = self.rules.read().await.get(&origin) {
+            return Ok(rules.clone());
+        }
+
+        let robots_url = Url::parse(&origin)?.join("/robots.txt")?;
+        let rules = match self.cache.fetch(fetcher, &robots_url).await? {
+            Some(body) => Arc::new(RobotsRules::parse(&body)),
+            None => Default::default(),
+        };
+
+        self.rules.write().await.insert(origin, rules.clone());
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn own_group_allowing_everything_overrides_the_wildcard() {
+        let rules = RobotsRules::parse(
+            "User-agent: *\nDisallow: /\n\nUser-agent: rusty_crawler\nDisallow:\n",
+        );
+        let url = Url::parse("https://a.com/page").unwrap();
+
+        assert!(rules.allowed(&url));
+        assert!(!RobotsRules::parse("User-agent: *\nDisallow: /\n").allowed(&url));
+    }
+}
@@ -0,0 +1,101 @@
+//! When the recurring crawls of `crawl --daemon` run,
+//! and where each run writes its outputs
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, TimeZone};
+use croner::Cron;
+use std::path::Path;
+
+/// A cron schedule, e.g. `0 3 * * *` for every day at 3am
+pub struct Schedule {
+    cron: Cron,
+}
+
+impl Schedule {
+    /// Parses the five fields (minute, hour, day of the month,
+    /// month and day of the week) of a cron expression
+    pub fn parse(expression: &str) -> Result<Schedule> {
+        let cron = Cron::new(expression)
+            .parse()
+            .map_err(|e| anyhow!("invalid schedule {}: {}", expression, e))?;
+        Ok(Schedule { cron })
+    }
+
+    /// The first time of the schedule after `time`
+    pub fn next_after<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Result<DateTime<Tz>> {
+        self.cron
+            .find_next_occurrence(time, false)
+            .map_err(|e| anyhow!("no next run for {}: {}", self.cron.pattern, e))
+    }
+}
+
+/// The `path` of an output with the `stamp` of a run before its
+/// extension, e.g. `links-20240101T030000.json` for `links.json`
+pub fn timestamped(path: &str, stamp: &str) -> String {
+    let path = Path::new(path);
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, stamp, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, stamp),
+    };
+    path.with_file_name(file_name).to_string_lossy().to_string()
+}
+
+/// The `--sink` of a run with the `stamp` of the run in the name
+/// of its file or directory, see `timestamped`. The sinks sending
+/// the pages elsewhere (webhooks, Kafka, NATS) are left as they are
+pub fn timestamped_sink(uri: &str, stamp: &str) -> String {
+    for scheme in ["jsonl:", "sqlite:", "text:", "html:"] {
+        if let Some(path) = uri.strip_prefix(scheme) {
+            return format!("{}{}", scheme, timestamped(path, stamp));
+        }
+    }
+    uri.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn next_after_finds_the_next_daily_run() {
+        let schedule = Schedule::parse("0 3 * * *").unwrap();
+        let time = Utc.with_ymd_and_hms(2024, 1, 1, 12, 30, 0).unwrap();
+
+        let next = schedule.next_after(&time).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2024, 1, 2, 3, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn timestamped_keeps_the_directory_and_extension() {
+        assert_eq!(
+            timestamped("out/links.json", "20240101T030000"),
+            "out/links-20240101T030000.json"
+        );
+        assert_eq!(
+            timestamped("report", "20240101T030000"),
+            "report-20240101T030000"
+        );
+    }
+
+    #[test]
+    fn timestamped_sink_only_changes_the_local_files() {
+        let stamp = "20240101T030000";
+        assert_eq!(
+            timestamped_sink("sqlite:out/pages.db", stamp),
+            "sqlite:out/pages-20240101T030000.db"
+        );
+        assert_eq!(
+            timestamped_sink("html:saved", stamp),
+            "html:saved-20240101T030000"
+        );
+        assert_eq!(
+            timestamped_sink("https://a.com/hook", stamp),
+            "https://a.com/hook"
+        );
+    }
+}
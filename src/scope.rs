@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// The scope rules as written in the scope file, e.g.
+/// {
+///     "allow": ["^https://matgomes\\.com/"],
+///     "deny": ["\\.pdf$"],
+///     "requests_per_second": 2.0
+/// }
+/// or in the `[scope]` table of a TOML `--config` file
+#[derive(Deserialize, Default)]
+struct ScopeFile {
+    #[serde(default)]
+    allow: Vec<String>,
+    #[serde(default)]
+    deny: Vec<String>,
+    requests_per_second: Option<f64>,
+}
+
+/// The `[scope]` table of a TOML config file
+#[derive(Deserialize)]
+struct ConfigFile {
+    #[serde(default)]
+    scope: ScopeFile,
+}
+
+/// Rules deciding which links the crawler is allowed
+/// to visit, and how quickly it may visit them
+#[derive(Default)]
+pub struct ScopeRules {
+    /// if not empty, a link must match one of these
+    allow: Vec<Regex>,
+    /// a link matching any of these is never visited
+    deny: Vec<Regex>,
+    /// the minimum time between two requests, if limited
+    pub request_interval: Option<Duration>,
+}
+
+impl ScopeRules {
+    /// Loads the rules from the JSON scope file at `path`,
+    /// or from the `[scope]` table if it is a `.toml` file
+    pub fn from_file(path: &str) -> Result<ScopeRules> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read scope file {}", path))?;
+        let scope_file: ScopeFile = if path.ends_with(".toml") {
+            toml::from_str::<ConfigFile>(&contents)
+                .map(|config| config.scope)
+                .with_context(|| format!("invalid scope file {}", path))?
+        } else {
+            serde_json::from_str(&contents)
+                .with_context(|| format!("invalid scope file {}", path))?
+        };
+
+        let compile = |patterns: &[String]| -> Result<Vec<Regex>> {
+            patterns
+                .iter()
+                .map(|p| Regex::new(p).with_context(|| format!("invalid scope pattern {}", p)))
+                .collect()
+        };
+
+        let request_interval = scope_file
+            .requests_per_second
+            .filter(|rps| *rps > 0.0)
+            .map(|rps| Duration::try_from_secs_f64(1.0 / rps))
+            .transpose()
+            .with_context(|| format!("requests_per_second is too small in {}", path))?;
+
+        Ok(ScopeRules {
+            allow: compile(&scope_file.allow)?,
+            deny: compile(&scope_file.deny)?,
+            request_interval,
+        })
+    }
+
+    /// Whether the given `url` should be visited
+    pub fn in_scope(&self, url: &str) -> bool {
+        if self.deny.iter().any(|r| r.is_match(url)) {
+            return false;
+        }
+
+        self.allow.is_empty() || self.allow.iter().any(|r| r.is_match(url))
+    }
+
+    /// The first `allow` pattern matching `url`, if any
+    pub fn matching_rule(&self, url: &str) -> Option<&str> {
+        self.allow
+            .iter()
+            .find(|r| r.is_match(url))
+            .map(|r| r.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(contents: &str) -> Result<ScopeRules> {
+        let path = std::env::temp_dir().join(format!("{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, contents).unwrap();
+        let rules = ScopeRules::from_file(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        rules
+    }
+
+    #[test]
+    fn tiny_request_rates_are_rejected() {
+        let rules = load(r#"{"requests_per_second": 2.0}"#).unwrap();
+        assert_eq!(rules.request_interval, Some(Duration::from_millis(500)));
+
+        assert!(load(r#"{"requests_per_second": 1e-300}"#).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::{anyhow, Context, Result};
+use rhai::{Array, Dynamic, Engine, Scope, AST};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tracing::error;
+
+use crate::model::ScrapeOutput;
+use crate::observer::CrawlObserver;
+
+/// Scripts stuck in a loop are stopped after this many operations
+const MAX_SCRIPT_OPERATIONS: u64 = 10_000_000;
+
+/// A Rhai script run on every scraped page. The script sees
+///
+/// - `url`: the url of the page
+/// - `text`: the body of the page
+/// - `titles`: the titles scraped from the page
+/// - `links`: the links found on the page
+/// - `fields`: the records extracted from the page
+///
+/// and can change `links` (rewriting urls, or removing the
+/// ones that shouldn't be crawled) and add to `fields`, e.g.
+///
+/// ```rhai
+/// links = links.filter(|link| !link.contains("/tag/"));
+/// fields.words = text.split(" ").len();
+/// ```
+pub struct ScriptHook {
+    engine: Engine,
+    ast: AST,
+}
+
+impl ScriptHook {
+    pub fn from_file(path: &str) -> Result<ScriptHook> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+
+        let ast = engine
+            .compile_file(path.into())
+            .map_err(|e| anyhow!("{}", e))
+            .with_context(|| format!("could not compile the script {}", path))?;
+
+        Ok(ScriptHook { engine, ast })
+    }
+
+    fn run(&self, url: &str, page: &mut ScrapeOutput) -> Result<()> {
+        let strings =
+            |values: &[String]| -> Array { values.iter().cloned().map(Dynamic::from).collect() };
+
+        let mut scope = Scope::new();
+        scope.push_constant("url", url.to_string());
+        scope.push_constant("text", page.body.clone().unwrap_or_default());
+        scope.push_constant("titles", strings(&page.titles));
+        scope.push("links", strings(&page.links));
+        scope.push("fields", rhai::serde::to_dynamic(&page.extracted)?);
+
+        self.engine
+            .run_ast_with_scope(&mut scope, &self.ast)
+            .map_err(|e| anyhow!("{}", e))?;
+
+        let links: Array = scope
+            .get_value("links")
+            .context("`links` is not an array anymore")?;
+        page.links = links
+            .into_iter()
+            .map(|link| link.into_string().map_err(|t| anyhow!("link is a {}", t)))
+            .collect::<Result<_>>()?;
+
+        let fields: Dynamic = scope.get_value("fields").unwrap_or_default();
+        page.extracted = rhai::serde::from_dynamic::<BTreeMap<String, Value>>(&fields)?;
+
+        Ok(())
+    }
+}
+
+impl CrawlObserver for ScriptHook {
+    fn on_page_scraped(&self, url: &str, page: &mut ScrapeOutput) {
+        if let Err(e) = self.run(url, page) {
+            error!("The page script failed on {}: {:#}", url, e);
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use anyhow::{Context, Result};
+use std::io::Read;
+use url::Url;
+
+/// Reads the seed urls from the file at `path`, or from
+/// stdin if `path` is `-`. Expects one url per line, and
+/// ignores empty lines and `#` comments.
+pub fn read_seed_file(path: &str) -> Result<Vec<String>> {
+    let contents = if path == "-" {
+        let mut contents = String::new();
+        std::io::stdin()
+            .read_to_string(&mut contents)
+            .context("could not read the seeds from stdin")?;
+        contents
+    } else {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("could not read seed file {}", path))?
+    };
+
+    contents
+        .lines()
+        .map(|l| l.trim())
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| {
+            Url::parse(l)
+                .map(|url| url.to_string())
+                .with_context(|| format!("invalid seed url {}", l))
+        })
+        .collect()
+}
@@ -0,0 +1,264 @@
+//! The on-page SEO problems of a crawl: missing or duplicate
+//! titles, missing descriptions, several `<h1>`s and images
+//! without alt text, see `crawl --seo-audit`
+
+use anyhow::Result;
+use serde::Serialize;
+use std::{collections::BTreeMap, fmt::Write};
+
+use crate::gallery::escape_html;
+use crate::model::{Headings, Link, LinkGraph};
+
+/// Pages sharing the same `<title>`
+#[derive(Debug, Serialize)]
+pub struct DuplicateTitle {
+    pub title: String,
+    /// the urls of the pages, sorted
+    pub urls: Vec<String>,
+}
+
+/// A page with more than one `<h1>`
+#[derive(Debug, Serialize)]
+pub struct MultipleH1 {
+    pub url: String,
+    pub h1: u32,
+}
+
+/// A page with images without alt text
+#[derive(Debug, Serialize)]
+pub struct MissingAlt {
+    pub url: String,
+    /// the urls of the images, in page order
+    pub images: Vec<String>,
+}
+
+/// The SEO problems found on the crawled pages,
+/// each list sorted by url
+#[derive(Debug, Default, Serialize)]
+pub struct SeoAudit {
+    /// how many HTML pages were audited
+    pub pages: usize,
+    pub missing_titles: Vec<String>,
+    pub duplicate_titles: Vec<DuplicateTitle>,
+    pub missing_descriptions: Vec<String>,
+    pub multiple_h1s: Vec<MultipleH1>,
+    pub missing_alts: Vec<MissingAlt>,
+}
+
+impl SeoAudit {
+    /// Audits the pages of `link_graph`, which should
+    /// be scraped with the titles, meta tags and images
+    pub fn of(link_graph: &LinkGraph) -> SeoAudit {
+        // Only the HTML pages have headings, not the errors or other documents
+        let mut pages: Vec<(&Link, &Headings)> = link_graph
+            .into_iter()
+            .map(|(_, link)| link)
+            .filter(|link| link.blocked.is_none())
+            .filter_map(|link| Some((link, link.headings.as_ref()?)))
+            .collect();
+        pages.sort_by(|(a, _), (b, _)| a.url.cmp(&b.url));
+
+        let mut audit = SeoAudit {
+            pages: pages.len(),
+            ..Default::default()
+        };
+        let mut titles: BTreeMap<&str, Vec<String>> = Default::default();
+        for (link, headings) in pages {
+            match &headings.title {
+                Some(title) => titles
+                    .entry(title.trim())
+                    .or_default()
+                    .push(link.url.clone()),
+                None => audit.missing_titles.push(link.url.clone()),
+            }
+            if link.description.is_none() {
+                audit.missing_descriptions.push(link.url.clone());
+            }
+            if headings.h1 > 1 {
+                audit.multiple_h1s.push(MultipleH1 {
+                    url: link.url.clone(),
+                    h1: headings.h1,
+                });
+            }
+
+            let images: Vec<String> = link
+                .images
+                .iter()
+                .filter(|image| image.missing_alt())
+                .map(|image| image.link.clone())
+                .collect();
+            if !images.is_empty() {
+                audit.missing_alts.push(MissingAlt {
+                    url: link.url.clone(),
+                    images,
+                });
+            }
+        }
+
+        audit.duplicate_titles = titles
+            .into_iter()
+            .filter(|(_, urls)| urls.len() > 1)
+            .map(|(title, urls)| DuplicateTitle {
+                title: title.to_string(),
+                urls,
+            })
+            .collect();
+        audit
+    }
+
+    /// How many problems were found, counting each
+    /// duplicate title and each page missing alt texts once
+    pub fn problems(&self) -> usize {
+        self.missing_titles.len()
+            + self.duplicate_titles.len()
+            + self.missing_descriptions.len()
+            + self.multiple_h1s.len()
+            + self.missing_alts.len()
+    }
+
+    /// The audit as a standalone HTML page
+    pub fn to_html(&self) -> Result<String> {
+        let link = |url: &str| {
+            let url = escape_html(url);
+            format!(r#"<a href="{url}">{url}</a>"#)
+        };
+
+        let mut sections = String::new();
+        let mut section = |heading: &str, items: Vec<String>| -> Result<()> {
+            write!(sections, "<h2>{} ({})</h2>\n<ul>\n", heading, items.len())?;
+            for item in items {
+                writeln!(sections, "<li>{}</li>", item)?;
+            }
+            sections.push_str("</ul>\n");
+            Ok(())
+        };
+        section(
+            "Missing titles",
+            self.missing_titles.iter().map(|url| link(url)).collect(),
+        )?;
+        section(
+            "Duplicate titles",
+            self.duplicate_titles
+                .iter()
+                .map(|duplicate| {
+                    let urls: Vec<String> = duplicate.urls.iter().map(|url| link(url)).collect();
+                    format!(
+                        "<q>{}</q><br>{}",
+                        escape_html(&duplicate.title),
+                        urls.join("<br>")
+                    )
+                })
+                .collect(),
+        )?;
+        section(
+            "Missing descriptions",
+            self.missing_descriptions
+                .iter()
+                .map(|url| link(url))
+                .collect(),
+        )?;
+        section(
+            "Several h1s",
+            self.multiple_h1s
+                .iter()
+                .map(|page| format!("{} h1s: {}", page.h1, link(&page.url)))
+                .collect(),
+        )?;
+        section(
+            "Images without alt text",
+            self.missing_alts
+                .iter()
+                .map(|page| {
+                    let images: Vec<String> = page.images.iter().map(|url| link(url)).collect();
+                    format!("{}<br>{}", link(&page.url), images.join("<br>"))
+                })
+                .collect(),
+        )?;
+
+        Ok(format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>SEO audit</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+li {{ margin: 0.5em 0; overflow-wrap: anywhere; }}
+li br + a {{ margin-left: 1em; }}
+</style>
+</head>
+<body>
+<h1>SEO audit</h1>
+<p>{problems} problems found on {pages} pages</p>
+{sections}</body>
+</html>
+"#,
+            problems = self.problems(),
+            pages = self.pages,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crawler::{extract_page, ScrapeConfig, ScrapeOption};
+    use url::Url;
+
+    fn graph_of(pages: &[(&str, &str)]) -> LinkGraph {
+        let config = ScrapeConfig {
+            options: vec![
+                ScrapeOption::Titles,
+                ScrapeOption::Meta,
+                ScrapeOption::Images,
+                ScrapeOption::CssImages,
+            ],
+            max_field_length: 4096,
+            parsers: Default::default(),
+            custom_selectors: Default::default(),
+            extractors: Default::default(),
+            searches: Default::default(),
+        };
+        let mut graph = LinkGraph::default();
+        for (url, html) in pages {
+            let page = extract_page(html, &Url::parse(url).unwrap(), &config);
+            graph.update(url, "", &page).unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn pages_are_audited() {
+        let graph = graph_of(&[
+            (
+                "https://a.com/",
+                r#"<title>Home</title><meta name="description" content="The home page">
+                <h1>Home</h1><img src="/logo.png" alt="Logo"><img src="/line.png" alt="">"#,
+            ),
+            (
+                "https://a.com/about",
+                r#"<title> Home </title><h1>About</h1><h1>Us</h1>
+                <img src="/team.jpg"><div style="background: url(/bg.jpg)"></div>"#,
+            ),
+            ("https://a.com/contact", "<h1>Contact</h1>"),
+        ]);
+
+        let audit = SeoAudit::of(&graph);
+        assert_eq!(audit.pages, 3);
+        assert_eq!(audit.missing_titles, ["https://a.com/contact"]);
+        assert_eq!(audit.duplicate_titles[0].title, "Home");
+        assert_eq!(
+            audit.duplicate_titles[0].urls,
+            ["https://a.com/", "https://a.com/about"]
+        );
+        assert_eq!(
+            audit.missing_descriptions,
+            ["https://a.com/about", "https://a.com/contact"]
+        );
+        assert_eq!(audit.multiple_h1s[0].url, "https://a.com/about");
+        assert_eq!(audit.multiple_h1s[0].h1, 2);
+        assert_eq!(audit.missing_alts[0].url, "https://a.com/about");
+        assert_eq!(audit.missing_alts[0].images, ["https://a.com/team.jpg"]);
+        assert_eq!(audit.problems(), 6);
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use super::{page_files::PageFiles, OutputSink};
+use crate::model::CrawlResult;
+
+/// Marks the saved HTML as UTF-8, taking precedence
+/// over the `<meta charset>` of the original page
+const UTF8_BOM: &str = "\u{FEFF}";
+
+/// Writes the HTML of each page to `<sha256 of the url>.html`,
+/// listing the files with their urls in `urls.tsv`, so the pages
+/// can be scraped again later without crawling them. The HTML is
+/// stored decoded to UTF-8, as it was scraped, starting with a
+/// byte order mark so it isn't read in the page's own charset.
+/// The third column of `urls.tsv` tells whether the page was cut
+/// short by `--max-page-kb`
+pub struct HtmlSink {
+    files: PageFiles,
+}
+
+impl HtmlSink {
+    pub async fn create(directory: &str) -> Result<HtmlSink> {
+        Ok(HtmlSink {
+            files: PageFiles::create(directory, "html").await?,
+        })
+    }
+}
+
+#[async_trait]
+impl OutputSink for HtmlSink {
+    async fn on_page(&self, result: &CrawlResult) -> Result<()> {
+        let page = &result.page;
+        // Anti-bot pages aren't worth scraping again
+        let Some(html) = page.body.as_ref().filter(|_| page.blocked.is_none()) else {
+            return Ok(());
+        };
+        if page
+            .content_type
+            .as_ref()
+            .is_some_and(|content_type| !content_type.contains("html"))
+        {
+            return Ok(());
+        }
+
+        let truncated = page.truncated_fields.iter().any(|field| field == "body");
+        let contents = format!("{}{}", UTF8_BOM, html);
+        self.files
+            .write(&result.url, contents.as_bytes(), &[&truncated.to_string()])
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fetcher::FetchResponse, model::ScrapeOutput, response_cache::sha256_hex};
+    use reqwest::{header::HeaderMap, StatusCode, Version};
+
+    #[tokio::test]
+    async fn saved_pages_are_read_back_as_utf8() {
+        let directory = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        let sink = HtmlSink::create(directory.to_str().unwrap()).await.unwrap();
+        let url = "https://a.com/";
+        let html = r#"<html><head><meta charset="iso-8859-1"></head><body>café</body></html>"#;
+        let result = CrawlResult {
+            url: url.to_string(),
+            parent: Default::default(),
+            page: ScrapeOutput {
+                body: Some(html.to_string()),
+                truncated_fields: vec![String::from("body")],
+                ..Default::default()
+            },
+        };
+        sink.on_page(&result).await.unwrap();
+
+        let file_name = sha256_hex(url.as_bytes()) + ".html";
+        let saved = FetchResponse {
+            url: url.parse().unwrap(),
+            status: StatusCode::OK,
+            version: Version::HTTP_11,
+            headers: HeaderMap::new(),
+            body: std::fs::read(directory.join(&file_name)).unwrap(),
+            truncated: false,
+        };
+        assert_eq!(saved.text(), html);
+        let urls = std::fs::read_to_string(directory.join("urls.tsv")).unwrap();
+        assert_eq!(urls, format!("{}\t{}\ttrue\n", file_name, url));
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
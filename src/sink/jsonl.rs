@@ -0,0 +1,45 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+
+use super::OutputSink;
+use crate::model::CrawlResult;
+
+/// Appends a JSON line per page to a file, which
+/// can be followed while the crawl is running
+pub struct JsonlSink {
+    file: Mutex<File>,
+}
+
+impl JsonlSink {
+    pub async fn create(path: &str) -> Result<JsonlSink> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await
+            .with_context(|| format!("could not open {}", path))?;
+
+        Ok(JsonlSink {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+#[async_trait]
+impl OutputSink for JsonlSink {
+    async fn on_page(&self, result: &CrawlResult) -> Result<()> {
+        let mut line = serde_json::to_vec(result)?;
+        line.push(b'\n');
+
+        // A whole line per write, so readers never see half a page
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
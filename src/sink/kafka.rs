@@ -0,0 +1,50 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use rdkafka::{
+    producer::{FutureProducer, FutureRecord},
+    util::Timeout,
+    ClientConfig,
+};
+
+use super::{server_and_topic, OutputSink};
+use crate::model::CrawlResult;
+
+/// The port of the broker when the sink doesn't give one
+const DEFAULT_PORT: u16 = 9092;
+
+/// Publishes every page as a JSON message to a Kafka
+/// topic, keyed by its url
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl KafkaSink {
+    /// Connects to the broker of `kafka://BROKER[:PORT]/TOPIC`
+    pub fn new(uri: &str) -> Result<KafkaSink> {
+        let (broker, topic) = server_and_topic(uri, DEFAULT_PORT)?;
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", &broker)
+            .set("message.timeout.ms", "30000")
+            .create()
+            .map_err(|e| anyhow!("could not connect to kafka at {}: {}", broker, e))?;
+        Ok(KafkaSink { producer, topic })
+    }
+}
+
+#[async_trait]
+impl OutputSink for KafkaSink {
+    async fn on_page(&self, result: &CrawlResult) -> Result<()> {
+        let payload = serde_json::to_vec(result)?;
+        let record = FutureRecord::to(&self.topic)
+            .key(&result.url)
+            .payload(&payload);
+        // Waits for the broker to take the message, queueing
+        // it for as long as message.timeout.ms allows
+        self.producer
+            .send(record, Timeout::Never)
+            .await
+            .map_err(|(e, _)| anyhow!("could not publish {} to kafka: {}", result.url, e))?;
+        Ok(())
+    }
+}
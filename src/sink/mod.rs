@@ -0,0 +1,158 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use futures::future::join_all;
+use std::sync::Arc;
+use tracing::error;
+
+use crate::model::{CrawlResult, Image, LinkGraph};
+
+pub mod html;
+pub mod jsonl;
+#[cfg(feature = "kafka")]
+pub mod kafka;
+#[cfg(feature = "nats")]
+pub mod nats;
+mod page_files;
+pub mod sqlite;
+pub mod text;
+pub mod webhook;
+
+/// Somewhere the crawl results are written to while crawling,
+/// registered with `CrawlerBuilder::sink`
+#[async_trait]
+pub trait OutputSink: Send + Sync {
+    /// Called with every page, once it is added to the link graph
+    async fn on_page(&self, result: &CrawlResult) -> Result<()>;
+
+    /// Called with every image found on `page`
+    async fn on_image(&self, _page: &str, _image: &Image) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called once the crawl is over
+    async fn on_finish(&self, _link_graph: &LinkGraph) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Sends every event to all of its sinks at the same
+/// time. A failing sink is logged without stopping the
+/// others, or the crawl
+#[derive(Clone, Default)]
+pub struct FanOut {
+    sinks: Vec<Arc<dyn OutputSink>>,
+}
+
+impl FanOut {
+    pub fn push(&mut self, sink: Arc<dyn OutputSink>) {
+        self.sinks.push(sink);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+
+    fn log_errors(results: Vec<Result<()>>) {
+        for e in results.into_iter().filter_map(Result::err) {
+            error!("Output sink failed: {:#}", e);
+        }
+    }
+}
+
+impl FromIterator<Arc<dyn OutputSink>> for FanOut {
+    fn from_iter<I: IntoIterator<Item = Arc<dyn OutputSink>>>(sinks: I) -> Self {
+        FanOut {
+            sinks: sinks.into_iter().collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl OutputSink for FanOut {
+    async fn on_page(&self, result: &CrawlResult) -> Result<()> {
+        FanOut::log_errors(join_all(self.sinks.iter().map(|s| s.on_page(result))).await);
+        Ok(())
+    }
+
+    async fn on_image(&self, page: &str, image: &Image) -> Result<()> {
+        FanOut::log_errors(join_all(self.sinks.iter().map(|s| s.on_image(page, image))).await);
+        Ok(())
+    }
+
+    async fn on_finish(&self, link_graph: &LinkGraph) -> Result<()> {
+        FanOut::log_errors(join_all(self.sinks.iter().map(|s| s.on_finish(link_graph))).await);
+        Ok(())
+    }
+}
+
+/// Opens the sink described by `uri`, one of
+/// - `jsonl:PATH` for a JSON line per page
+/// - `sqlite:PATH` for a SQLite database of the pages and images
+/// - `text:DIR` for a text file per page, see `text::TextSink`
+/// - `html:DIR` for the HTML of each page, see `html::HtmlSink`
+/// - an `http(s)://` url to POST the pages to, one at a time,
+///   see `webhook::WebhookSink`
+/// - `kafka://BROKER/TOPIC` to publish the pages to a Kafka
+///   topic, with the `kafka` feature
+/// - `nats://SERVER/SUBJECT` to publish them to a NATS
+///   subject, with the `nats` feature
+pub async fn open_sink(uri: &str) -> Result<Arc<dyn OutputSink>> {
+    if let Some(path) = uri.strip_prefix("jsonl:") {
+        Ok(Arc::new(jsonl::JsonlSink::create(path).await?))
+    } else if let Some(path) = uri.strip_prefix("sqlite:") {
+        Ok(Arc::new(sqlite::SqliteSink::open(path)?))
+    } else if let Some(directory) = uri.strip_prefix("text:") {
+        Ok(Arc::new(text::TextSink::create(directory).await?))
+    } else if let Some(directory) = uri.strip_prefix("html:") {
+        Ok(Arc::new(html::HtmlSink::create(directory).await?))
+    } else if uri.starts_with("http://") || uri.starts_with("https://") {
+        let options = webhook::WebhookOptions::default();
+        Ok(Arc::new(webhook::WebhookSink::new(uri, options)?))
+    } else if uri.starts_with("kafka://") {
+        open_kafka(uri)
+    } else if uri.starts_with("nats://") {
+        open_nats(uri).await
+    } else {
+        bail!(
+            "unknown sink {}, expected jsonl:PATH, sqlite:PATH, text:DIR, html:DIR, \
+             kafka://BROKER/TOPIC, nats://SERVER/SUBJECT or an http(s) url",
+            uri
+        )
+    }
+}
+
+#[cfg(feature = "kafka")]
+fn open_kafka(uri: &str) -> Result<Arc<dyn OutputSink>> {
+    Ok(Arc::new(kafka::KafkaSink::new(uri)?))
+}
+
+#[cfg(not(feature = "kafka"))]
+fn open_kafka(_uri: &str) -> Result<Arc<dyn OutputSink>> {
+    bail!("the kafka sink needs the crawler built with `--features kafka`")
+}
+
+#[cfg(feature = "nats")]
+async fn open_nats(uri: &str) -> Result<Arc<dyn OutputSink>> {
+    Ok(Arc::new(nats::NatsSink::connect(uri).await?))
+}
+
+#[cfg(not(feature = "nats"))]
+async fn open_nats(_uri: &str) -> Result<Arc<dyn OutputSink>> {
+    bail!("the nats sink needs the crawler built with `--features nats`")
+}
+
+/// The `host:port` of the server and the topic (or
+/// subject) of a `scheme://HOST[:PORT]/TOPIC` sink
+#[cfg(any(feature = "kafka", feature = "nats"))]
+fn server_and_topic(uri: &str, default_port: u16) -> Result<(String, String)> {
+    let url = url::Url::parse(uri)?;
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow::anyhow!("no server in the sink {}", uri))?;
+    let topic = url.path().trim_start_matches('/');
+    if topic.is_empty() {
+        bail!("no topic in the sink {}", uri);
+    }
+    let port = url.port().unwrap_or(default_port);
+    Ok((format!("{}:{}", host, port), topic.to_string()))
+}
@@ -0,0 +1,42 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+use super::{server_and_topic, OutputSink};
+use crate::model::{CrawlResult, LinkGraph};
+
+/// The port of the server when the sink doesn't give one
+const DEFAULT_PORT: u16 = 4222;
+
+/// Publishes every page as a JSON message to a NATS subject
+pub struct NatsSink {
+    client: async_nats::Client,
+    subject: String,
+}
+
+impl NatsSink {
+    /// Connects to the server of `nats://SERVER[:PORT]/SUBJECT`
+    pub async fn connect(uri: &str) -> Result<NatsSink> {
+        let (server, subject) = server_and_topic(uri, DEFAULT_PORT)?;
+        let client = async_nats::connect(&server)
+            .await
+            .map_err(|e| anyhow!("could not connect to nats at {}: {}", server, e))?;
+        Ok(NatsSink { client, subject })
+    }
+}
+
+#[async_trait]
+impl OutputSink for NatsSink {
+    async fn on_page(&self, result: &CrawlResult) -> Result<()> {
+        let payload = serde_json::to_vec(result)?;
+        self.client
+            .publish(self.subject.clone(), payload.into())
+            .await?;
+        Ok(())
+    }
+
+    /// Waits for the messages still buffered to be sent
+    async fn on_finish(&self, _link_graph: &LinkGraph) -> Result<()> {
+        self.client.flush().await?;
+        Ok(())
+    }
+}
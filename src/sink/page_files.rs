@@ -0,0 +1,53 @@
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use tokio::{fs, io::AsyncWriteExt, sync::Mutex};
+
+use crate::response_cache::sha256_hex;
+
+/// A directory with a file per page, named `<sha256 of the
+/// url>.<extension>`, listing the files with their urls (and
+/// any other columns) in `urls.tsv`
+pub(crate) struct PageFiles {
+    directory: PathBuf,
+    extension: &'static str,
+    urls: Mutex<fs::File>,
+}
+
+impl PageFiles {
+    pub async fn create(directory: &str, extension: &'static str) -> Result<PageFiles> {
+        let directory = PathBuf::from(directory);
+        fs::create_dir_all(&directory)
+            .await
+            .with_context(|| format!("could not create page directory {:?}", directory))?;
+        let urls = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(directory.join("urls.tsv"))
+            .await?;
+
+        Ok(PageFiles {
+            directory,
+            extension,
+            urls: Mutex::new(urls),
+        })
+    }
+
+    /// Writes the `contents` of the page at `url`, listed
+    /// in `urls.tsv` along with the extra `columns`
+    pub async fn write(&self, url: &str, contents: &[u8], columns: &[&str]) -> Result<()> {
+        let file_name = format!("{}.{}", sha256_hex(url.as_bytes()), self.extension);
+        fs::write(self.directory.join(&file_name), contents).await?;
+
+        let mut row = [file_name.as_str(), url].join("\t");
+        for column in columns {
+            row.push('\t');
+            row.push_str(column);
+        }
+        row.push('\n');
+
+        let mut urls = self.urls.lock().await;
+        urls.write_all(row.as_bytes()).await?;
+        urls.flush().await?;
+        Ok(())
+    }
+}
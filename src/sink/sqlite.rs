@@ -0,0 +1,89 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use rusqlite::{params, Connection};
+use std::sync::{Arc, Mutex};
+
+use super::OutputSink;
+use crate::model::{CrawlResult, Image};
+
+/// Keeps the pages and images in a SQLite database,
+/// replacing the rows of the pages crawled again. The
+/// writes run on the blocking threads, off the runtime
+pub struct SqliteSink {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteSink {
+    pub fn open(path: &str) -> Result<SqliteSink> {
+        let connection =
+            Connection::open(path).with_context(|| format!("could not open {}", path))?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS pages (
+                 url TEXT PRIMARY KEY,
+                 parent TEXT NOT NULL,
+                 status INTEGER,
+                 content_type TEXT,
+                 titles TEXT NOT NULL,
+                 page TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS images (
+                 page TEXT NOT NULL,
+                 link TEXT NOT NULL,
+                 alt TEXT NOT NULL,
+                 PRIMARY KEY (page, link)
+             );",
+        )?;
+
+        Ok(SqliteSink {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Runs `write` with the connection on a blocking thread
+    async fn write(
+        &self,
+        write: impl FnOnce(&Connection) -> rusqlite::Result<usize> + Send + 'static,
+    ) -> Result<()> {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let connection = connection
+                .lock()
+                .map_err(|_| anyhow!("the database connection is poisoned"))?;
+            write(&connection)?;
+            Ok(())
+        })
+        .await?
+    }
+}
+
+#[async_trait]
+impl OutputSink for SqliteSink {
+    async fn on_page(&self, result: &CrawlResult) -> Result<()> {
+        let url = result.url.clone();
+        let parent = result.parent.clone();
+        let status = result.page.status;
+        let content_type = result.page.content_type.clone();
+        let titles = serde_json::to_string(&result.page.titles)?;
+        let page = serde_json::to_string(&result.page)?;
+        self.write(move |connection| {
+            connection.execute(
+                "INSERT OR REPLACE INTO pages (url, parent, status, content_type, titles, page)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![url, parent, status, content_type, titles, page],
+            )
+        })
+        .await
+    }
+
+    async fn on_image(&self, page: &str, image: &Image) -> Result<()> {
+        let page = page.to_string();
+        let (link, alt) = (image.link.clone(), image.alt.clone());
+        self.write(move |connection| {
+            connection.execute(
+                "INSERT OR REPLACE INTO images (page, link, alt) VALUES (?1, ?2, ?3)",
+                params![page, link, alt],
+            )
+        })
+        .await
+    }
+}
@@ -0,0 +1,31 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use super::{page_files::PageFiles, OutputSink};
+use crate::model::CrawlResult;
+
+/// Writes the readable text of each page (see
+/// `ScrapeOption::Text`) to `<sha256 of the url>.txt`,
+/// listing the files with their urls in `urls.tsv`
+pub struct TextSink {
+    files: PageFiles,
+}
+
+impl TextSink {
+    pub async fn create(directory: &str) -> Result<TextSink> {
+        Ok(TextSink {
+            files: PageFiles::create(directory, "txt").await?,
+        })
+    }
+}
+
+#[async_trait]
+impl OutputSink for TextSink {
+    async fn on_page(&self, result: &CrawlResult) -> Result<()> {
+        let Some(text) = &result.page.text else {
+            return Ok(());
+        };
+
+        self.files.write(&result.url, text.as_bytes(), &[]).await
+    }
+}
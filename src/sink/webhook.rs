@@ -0,0 +1,266 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::Instant,
+};
+use tracing::{error, warn};
+
+use super::OutputSink;
+use crate::model::{CrawlResult, ErrorCounts, LinkGraph};
+
+/// How long the first retry of a failed POST waits,
+/// doubling with every retry after it
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// How long a POST may take before it is given up on
+const POST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How the events are grouped and sent
+#[derive(Clone, Debug)]
+pub struct WebhookOptions {
+    /// how many events are sent in each POST
+    pub batch_size: usize,
+    /// how long an event waits for its batch to fill
+    /// before the batch is sent anyway
+    pub flush_interval: Duration,
+    /// how many times a POST failing with a network error,
+    /// 429 or 5xx is tried again
+    pub retries: u32,
+}
+
+impl Default for WebhookOptions {
+    fn default() -> Self {
+        WebhookOptions {
+            batch_size: 1,
+            flush_interval: Duration::from_secs(5),
+            retries: 3,
+        }
+    }
+}
+
+/// What the crawl hands to the task sending the batches
+enum Message {
+    Event(Value),
+    /// send what is left, answering with how it went
+    Flush(oneshot::Sender<Result<()>>),
+}
+
+/// POSTs the crawl events as JSON to a url, in batches of
+/// `{"events": [...]}`. The events are
+/// - `page` for every page scraped
+/// - `error` for every page that failed, with its status or error
+/// - `finish` once the crawl is over, with a summary of it
+///
+/// The batches are sent from a background task, so a slow
+/// webhook doesn't hold up the crawl
+pub struct WebhookSink {
+    messages: mpsc::UnboundedSender<Message>,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str, options: WebhookOptions) -> Result<WebhookSink> {
+        let poster = Poster {
+            url: url.to_string(),
+            client: Client::builder().timeout(POST_TIMEOUT).build()?,
+            retries: options.retries,
+        };
+        let (messages, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(send_batches(poster, options, receiver));
+        Ok(WebhookSink { messages })
+    }
+
+    fn push(&self, event: Value) -> Result<()> {
+        self.messages
+            .send(Message::Event(event))
+            .map_err(|_| anyhow!("the webhook task is gone"))
+    }
+
+    /// Sends what is left in the batch, once it is sent
+    async fn flush(&self) -> Result<()> {
+        let (done, result) = oneshot::channel();
+        self.messages
+            .send(Message::Flush(done))
+            .map_err(|_| anyhow!("the webhook task is gone"))?;
+        result.await?
+    }
+}
+
+/// Batches the events coming from `messages`, sending each batch
+/// once it is full or its oldest event waited the flush interval
+async fn send_batches(
+    poster: Poster,
+    options: WebhookOptions,
+    mut messages: mpsc::UnboundedReceiver<Message>,
+) {
+    let mut events: Vec<Value> = vec![];
+    let mut deadline = Instant::now();
+    loop {
+        tokio::select! {
+            message = messages.recv() => match message {
+                Some(Message::Event(event)) => {
+                    if events.is_empty() {
+                        deadline = Instant::now() + options.flush_interval;
+                    }
+                    events.push(event);
+                    if events.len() >= options.batch_size {
+                        poster.post_logged(std::mem::take(&mut events)).await;
+                    }
+                }
+                Some(Message::Flush(done)) => {
+                    let mut result = Ok(());
+                    if !events.is_empty() {
+                        result = poster.post(std::mem::take(&mut events)).await;
+                    }
+                    let _ = done.send(result);
+                }
+                None => {
+                    if !events.is_empty() {
+                        poster.post_logged(events).await;
+                    }
+                    return;
+                }
+            },
+            _ = tokio::time::sleep_until(deadline), if !events.is_empty() => {
+                poster.post_logged(std::mem::take(&mut events)).await;
+            }
+        }
+    }
+}
+
+/// Sends the batches to the webhook
+struct Poster {
+    url: String,
+    client: Client,
+    retries: u32,
+}
+
+impl Poster {
+    /// Like `post`, logging the failures as nobody waits for them
+    async fn post_logged(&self, events: Vec<Value>) {
+        if let Err(e) = self.post(events).await {
+            error!("Output sink failed: {:#}", e);
+        }
+    }
+
+    /// POSTs the `events`, retrying with a growing delay
+    /// the failures that may go away
+    async fn post(&self, events: Vec<Value>) -> Result<()> {
+        let body = json!({ "events": events });
+        let mut delay = FIRST_RETRY_DELAY;
+        let mut retries_left = self.retries;
+        loop {
+            let error = match self.client.post(&self.url).json(&body).send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let error = anyhow!("webhook {} answered {}", self.url, status);
+                    if status != StatusCode::TOO_MANY_REQUESTS && !status.is_server_error() {
+                        return Err(error);
+                    }
+                    error
+                }
+                Err(e) => anyhow!("could not POST to webhook {}: {}", self.url, e),
+            };
+            if retries_left == 0 {
+                return Err(error.context(format!("dropped {} events", events.len())));
+            }
+            retries_left -= 1;
+            warn!("{:#}, retrying in {:?}", error, delay);
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+        }
+    }
+}
+
+#[async_trait]
+impl OutputSink for WebhookSink {
+    async fn on_page(&self, result: &CrawlResult) -> Result<()> {
+        let event = match result.page.error {
+            Some(error) => json!({
+                "event": "error",
+                "url": result.url,
+                "parent": result.parent,
+                "status": result.page.status,
+                "error": error,
+            }),
+            None => json!({ "event": "page", "page": result }),
+        };
+        self.push(event)
+    }
+
+    async fn on_finish(&self, link_graph: &LinkGraph) -> Result<()> {
+        let mut errors = ErrorCounts::default();
+        for info in link_graph.domains().values() {
+            errors.extend(&info.errors);
+        }
+        let crawled = link_graph
+            .into_iter()
+            .filter(|(_, link)| link.status.is_some() || link.error.is_some())
+            .count();
+        let bytes: u64 = link_graph
+            .into_iter()
+            .filter_map(|(_, link)| link.size)
+            .sum();
+
+        self.push(json!({
+            "event": "finish",
+            "seeds": link_graph.seeds(),
+            "pages": link_graph.len(),
+            "crawled": crawled,
+            "failed": errors.total(),
+            "errors": errors,
+            "bytes": bytes,
+        }))?;
+        self.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    /// A webhook keeping the batches it is sent, and its url
+    async fn webhook() -> (Arc<Mutex<Vec<Value>>>, String) {
+        let batches: Arc<Mutex<Vec<Value>>> = Default::default();
+        let router =
+            Router::new()
+                .route(
+                    "/",
+                    post(
+                        |State(batches): State<Arc<Mutex<Vec<Value>>>>,
+                         Json(batch): Json<Value>| async move {
+                            batches.lock().unwrap().push(batch);
+                        },
+                    ),
+                )
+                .with_state(batches.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        (batches, url)
+    }
+
+    #[tokio::test]
+    async fn batches_are_sent_after_the_flush_interval() {
+        let (batches, url) = webhook().await;
+        let options = WebhookOptions {
+            batch_size: 10,
+            flush_interval: Duration::from_millis(50),
+            retries: 0,
+        };
+        let sink = WebhookSink::new(&url, options).unwrap();
+
+        sink.push(json!({ "event": "page" })).unwrap();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+
+        let batches = batches.lock().unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0]["events"].as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+use quick_xml::{events::Event, Reader};
+use std::collections::{HashSet, VecDeque};
+use tracing::{error, info, warn};
+use url::Url;
+
+use crate::fetcher::Fetcher;
+use crate::policy_cache::PolicyCache;
+
+/// Limit on the nested sitemaps fetched from sitemap indexes,
+/// so a misbehaving site can't keep us fetching forever
+const MAX_SITEMAPS: usize = 1000;
+
+/// The urls found in a sitemap (`<urlset>`) or the nested
+/// sitemaps found in a sitemap index (`<sitemapindex>`)
+#[derive(Default)]
+struct ParsedSitemap {
+    urls: Vec<String>,
+    sitemaps: Vec<String>,
+}
+
+/// Parses the `<loc>` elements of a sitemap or sitemap index
+fn parse_sitemap(xml: &str) -> Result<ParsedSitemap> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut parsed = ParsedSitemap::default();
+    let mut in_sitemap = false;
+    let mut in_loc = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"sitemap" => in_sitemap = true,
+                b"loc" => in_loc = true,
+                _ => {}
+            },
+            Event::End(e) => match e.local_name().as_ref() {
+                b"sitemap" => in_sitemap = false,
+                b"loc" => in_loc = false,
+                _ => {}
+            },
+            Event::Text(text) if in_loc => {
+                let loc = text.unescape()?.trim().to_string();
+                if in_sitemap {
+                    parsed.sitemaps.push(loc);
+                } else {
+                    parsed.urls.push(loc);
+                }
+            }
+            Event::CData(text) if in_loc => {
+                let loc = String::from_utf8_lossy(&text).trim().to_string();
+                if in_sitemap {
+                    parsed.sitemaps.push(loc);
+                } else {
+                    parsed.urls.push(loc);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Fetches the given sitemaps, following any sitemap indexes,
+/// and returns all the page urls they list. Gzipped sitemaps
+/// are decompressed by the `PolicyCache`.
+pub async fn collect_sitemap_urls(
+    fetcher: &dyn Fetcher,
+    cache: &PolicyCache,
+    sitemaps: Vec<Url>,
+) -> Vec<String> {
+    let mut pending: VecDeque<Url> = sitemaps.into();
+    let mut fetched: HashSet<Url> = Default::default();
+    let mut urls: Vec<String> = Default::default();
+
+    while let Some(sitemap) = pending.pop_front() {
+        if fetched.len() >= MAX_SITEMAPS {
+            warn!("Stopped reading sitemaps after {}", MAX_SITEMAPS);
+            break;
+        }
+
+        if !fetched.insert(sitemap.clone()) {
+            continue;
+        }
+
+        let parsed = match cache.fetch(fetcher, &sitemap).await {
+            Ok(Some(body)) => parse_sitemap(&body),
+            Ok(None) => continue,
+            Err(e) => Err(e),
+        };
+
+        match parsed {
+            Ok(parsed) => {
+                info!(
+                    "Sitemap {} lists {} urls and {} sitemaps",
+                    sitemap,
+                    parsed.urls.len(),
+                    parsed.sitemaps.len()
+                );
+                urls.extend(parsed.urls);
+                pending.extend(parsed.sitemaps.iter().filter_map(|s| Url::parse(s).ok()));
+            }
+            Err(e) => error!("Could not read sitemap {}: {:#}", sitemap, e),
+        }
+    }
+
+    urls
+}
@@ -0,0 +1,41 @@
+//! The state file of a crawl: the link graph found so far and
+//! the links still queued, so the crawl can be resumed later
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tokio::fs;
+
+use crate::{crawler::LinkPath, model::LinkGraph};
+
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    link_graph: &'a LinkGraph,
+    queue: &'a VecDeque<LinkPath>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    link_graph: LinkGraph,
+    queue: VecDeque<LinkPath>,
+}
+
+pub async fn save_snapshot(
+    path: &str,
+    link_graph: &LinkGraph,
+    queue: &VecDeque<LinkPath>,
+) -> Result<()> {
+    let json = serde_json::to_string(&SnapshotRef { link_graph, queue })?;
+    fs::write(path, json)
+        .await
+        .with_context(|| format!("could not write the state file {}", path))
+}
+
+pub async fn load_snapshot(path: &str) -> Result<(LinkGraph, VecDeque<LinkPath>)> {
+    let json = fs::read_to_string(path)
+        .await
+        .with_context(|| format!("could not read the state file {}", path))?;
+    let snapshot: Snapshot = serde_json::from_str(&json)?;
+
+    Ok((snapshot.link_graph, snapshot.queue))
+}
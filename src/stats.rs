@@ -0,0 +1,107 @@
+//! Statistics about a saved crawl, see the `stats` subcommand
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use url::Url;
+
+use crate::model::{ErrorCounts, LinkGraph, LinkId};
+
+/// What a link graph holds, in numbers
+#[derive(Debug, Default)]
+pub struct CrawlStats {
+    pub pages: usize,
+    /// the links between the crawled pages
+    pub edges: usize,
+    /// how many pages are this many links away from
+    /// the seeds, following the shortest path
+    pub depths: BTreeMap<usize, usize>,
+    /// the pages no path from the seeds leads to
+    pub unreachable: usize,
+    /// how many pages were served with each status,
+    /// `None` for the ones that couldn't be fetched
+    pub statuses: BTreeMap<Option<u16>, usize>,
+    /// (domain, pages) from the most crawled domain
+    pub domains: Vec<(String, usize)>,
+    /// (url, bytes) from the largest page, for
+    /// the pages whose size was recorded
+    pub largest_pages: Vec<(String, u64)>,
+    /// (domain, errors) from the domain with the most
+    /// failed pages, for the domains with any
+    pub domain_errors: Vec<(String, ErrorCounts)>,
+}
+
+impl CrawlStats {
+    pub fn from_graph(link_graph: &LinkGraph) -> CrawlStats {
+        let mut stats = CrawlStats {
+            pages: link_graph.len(),
+            ..Default::default()
+        };
+
+        let mut domains: HashMap<String, usize> = Default::default();
+        for (_, link) in link_graph {
+            stats.edges += link.children.len();
+            *stats.statuses.entry(link.status).or_default() += 1;
+            if let Some(domain) = Url::parse(&link.url)
+                .ok()
+                .and_then(|url| url.host_str().map(String::from))
+            {
+                *domains.entry(domain).or_default() += 1;
+            }
+            if let Some(size) = link.size {
+                stats.largest_pages.push((link.url.clone(), size));
+            }
+        }
+
+        stats.domains = domains.into_iter().collect();
+        stats
+            .domains
+            .sort_by(|(a, a_pages), (b, b_pages)| b_pages.cmp(a_pages).then(a.cmp(b)));
+        stats
+            .largest_pages
+            .sort_by(|(a, a_size), (b, b_size)| b_size.cmp(a_size).then(a.cmp(b)));
+
+        stats.domain_errors = link_graph
+            .domains()
+            .iter()
+            .filter(|(_, info)| !info.errors.is_empty())
+            .map(|(domain, info)| (domain.clone(), info.errors.clone()))
+            .collect();
+        stats
+            .domain_errors
+            .sort_by_key(|(_, errors)| std::cmp::Reverse(errors.total()));
+
+        let depths = depths_from_seeds(link_graph);
+        for depth in depths.values() {
+            *stats.depths.entry(*depth).or_default() += 1;
+        }
+        stats.unreachable = stats.pages - depths.len();
+
+        stats
+    }
+}
+
+/// The depth of each page reachable from the seeds, by
+/// a breadth first walk of the links between the pages
+pub fn depths_from_seeds(link_graph: &LinkGraph) -> HashMap<LinkId, usize> {
+    let mut depths: HashMap<LinkId, usize> = Default::default();
+    let mut queue: VecDeque<LinkId> = Default::default();
+    for seed in link_graph.seeds() {
+        if let Some(link) = link_graph.get(seed) {
+            if depths.insert(link.id, 0).is_none() {
+                queue.push_back(link.id);
+            }
+        }
+    }
+
+    let links: HashMap<LinkId, _> = link_graph.into_iter().map(|(id, l)| (*id, l)).collect();
+    while let Some(id) = queue.pop_front() {
+        let depth = depths[&id];
+        for child in links[&id].children.iter() {
+            if links.contains_key(child) && !depths.contains_key(child) {
+                depths.insert(*child, depth + 1);
+                queue.push_back(*child);
+            }
+        }
+    }
+
+    depths
+}
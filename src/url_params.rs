@@ -0,0 +1,125 @@
+//! Finds the query parameters that don't change the content
+//! of the pages (tracking, session ids, sort orders of empty
+//! lists...), see the `params` subcommand
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use url::Url;
+
+use crate::model::LinkGraph;
+
+/// What the crawl tells about a query parameter
+#[derive(Debug)]
+pub struct ParamReport {
+    pub name: String,
+    /// the crawled pages with this parameter in their url
+    pub pages: usize,
+    /// the sets of pages whose urls only differ by the
+    /// value (or the presence) of this parameter
+    pub compared: usize,
+    /// the sets whose pages all had the same content
+    pub unchanged: usize,
+    /// an example set of urls with the same content
+    pub example: Vec<String>,
+}
+
+impl ParamReport {
+    /// Whether the parameter never changed the content,
+    /// so its urls can be skipped
+    pub fn ignorable(&self) -> bool {
+        self.compared > 0 && self.unchanged == self.compared
+    }
+
+    /// A `deny` pattern for the scope file skipping the
+    /// urls with this parameter
+    pub fn deny_pattern(&self) -> String {
+        format!("[?&]{}=", regex::escape(&self.name))
+    }
+}
+
+/// `url` without the `param` query parameter, with the
+/// other parameters sorted so the order doesn't matter
+fn without_param(url: &Url, param: &str) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| name != param)
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    pairs.sort();
+
+    let mut url = url.clone();
+    url.set_fragment(None);
+    if pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+/// Compares the content hashes of the crawled pages whose urls
+/// only differ by one query parameter, for every parameter seen.
+/// The parameters are sorted from the most ignorable.
+pub fn param_report(link_graph: &LinkGraph) -> Vec<ParamReport> {
+    let pages: Vec<(Url, &str)> = link_graph
+        .into_iter()
+        .filter(|(_, link)| link.status == Some(200))
+        .filter_map(|(_, link)| Some((Url::parse(&link.url).ok()?, link.content_hash.as_deref()?)))
+        .collect();
+    // The pages without parameters, by their normalized url
+    let hashes: HashMap<String, &str> = pages
+        .iter()
+        .map(|(url, hash)| (without_param(url, ""), *hash))
+        .collect();
+
+    // (param, url without it) -> the urls and hashes of the set
+    let mut sets: BTreeMap<(String, String), Vec<(String, &str)>> = Default::default();
+    let mut counts: HashMap<String, usize> = Default::default();
+    for (url, hash) in pages.iter() {
+        let names: HashSet<String> = url
+            .query_pairs()
+            .map(|(name, _)| name.into_owned())
+            .collect();
+        for name in names {
+            *counts.entry(name.clone()).or_default() += 1;
+            sets.entry((name.clone(), without_param(url, &name)))
+                .or_default()
+                .push((url.to_string(), *hash));
+        }
+    }
+
+    let mut reports: BTreeMap<String, ParamReport> = Default::default();
+    for ((name, base), mut set) in sets {
+        // The page without the parameter is part of the set too
+        if let Some(hash) = hashes.get(&base) {
+            set.push((base, *hash));
+        }
+        if set.len() < 2 {
+            continue;
+        }
+
+        let report = reports.entry(name.clone()).or_insert_with(|| ParamReport {
+            pages: counts[&name],
+            name,
+            compared: 0,
+            unchanged: 0,
+            example: Default::default(),
+        });
+        report.compared += 1;
+        if set.iter().all(|(_, hash)| *hash == set[0].1) {
+            report.unchanged += 1;
+            if report.example.is_empty() {
+                report.example = set.into_iter().map(|(url, _)| url).collect();
+                report.example.sort();
+            }
+        }
+    }
+
+    let mut reports: Vec<ParamReport> = reports.into_values().collect();
+    reports.sort_by(|a, b| {
+        b.ignorable()
+            .cmp(&a.ignorable())
+            .then(b.compared.cmp(&a.compared))
+            .then(a.name.cmp(&b.name))
+    });
+    reports
+}
@@ -0,0 +1,54 @@
+use anyhow::{bail, Context, Result};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use url::Url;
+
+/// A list of user agent strings rotated between requests
+pub struct UserAgents {
+    agents: Vec<String>,
+    /// always use the same user agent for the same host
+    sticky: bool,
+    next: AtomicUsize,
+}
+
+impl UserAgents {
+    /// Reads the user agents from the file at `path`,
+    /// one user agent per line
+    pub fn from_file(path: &str, sticky: bool) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read user agent file {}", path))?;
+
+        let agents: Vec<String> = contents
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| l.to_string())
+            .collect();
+
+        if agents.is_empty() {
+            bail!("the user agent file {} is empty", path);
+        }
+
+        Ok(UserAgents {
+            agents,
+            sticky,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    /// Picks the user agent for a request to `url`
+    pub fn pick(&self, url: &Url) -> &str {
+        let index = if self.sticky {
+            let mut hasher = DefaultHasher::new();
+            url.host_str().unwrap_or("").hash(&mut hasher);
+            hasher.finish() as usize
+        } else {
+            self.next.fetch_add(1, Ordering::Relaxed)
+        };
+
+        &self.agents[index % self.agents.len()]
+    }
+}
@@ -0,0 +1,164 @@
+//! Page extractors shipped as WebAssembly modules (`.wasm`, or
+//! `.wat` text for quick experiments). The modules run sandboxed:
+//! they get no imports (so no file system or network access),
+//! a bounded amount of fuel per page and a bounded memory.
+//!
+//! A module has to export
+//!
+//! - `memory`: its linear memory
+//! - `alloc(len: i32) -> i32`: reserves `len` bytes for the input
+//! - `extract(ptr: i32, len: i32) -> i64`: reads the input, a JSON
+//!   object `{"url": "...", "body": "..."}`, and returns where its
+//!   output is as `(ptr << 32) | len`. The output is a JSON object
+//!   whose fields are saved with the page
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, path::Path};
+use wasmi::{Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+/// Stops modules stuck in a loop
+const MAX_FUEL: u64 = 100_000_000;
+/// The most memory a module can have, in bytes, so
+/// one can't take all of the crawler's
+const MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+pub struct WasmExtractor {
+    /// the name of the module file, used in the logs
+    pub name: String,
+    engine: Engine,
+    module: Module,
+    /// the fuel of each run, see `MAX_FUEL`
+    fuel: u64,
+}
+
+impl WasmExtractor {
+    pub fn from_file(path: &str) -> Result<WasmExtractor> {
+        let bytes =
+            std::fs::read(path).with_context(|| format!("could not read the plugin {}", path))?;
+
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, &bytes[..])
+            .map_err(|e| anyhow!("invalid plugin {}: {}", path, e))?;
+
+        let name = Path::new(path)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+
+        Ok(WasmExtractor {
+            name,
+            engine,
+            module,
+            fuel: MAX_FUEL,
+        })
+    }
+
+    /// Runs the module on a page, in a fresh instance
+    /// so nothing leaks from one page to the next
+    pub fn extract(&self, url: &str, body: &str) -> Result<BTreeMap<String, Value>> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MAX_MEMORY)
+            .instances(1)
+            .memories(1)
+            .tables(1)
+            .build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits: &mut StoreLimits| limits);
+        store.set_fuel(self.fuel).map_err(|e| anyhow!("{}", e))?;
+
+        let instance = Linker::<StoreLimits>::new(&self.engine)
+            .instantiate_and_start(&mut store, &self.module)
+            .map_err(|e| anyhow!("could not instantiate: {}", e))?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .context("the plugin exports no memory")?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "alloc")
+            .map_err(|e| anyhow!("no alloc function: {}", e))?;
+        let extract = instance
+            .get_typed_func::<(i32, i32), i64>(&store, "extract")
+            .map_err(|e| anyhow!("no extract function: {}", e))?;
+
+        let input = serde_json::to_vec(&json!({ "url": url, "body": body }))?;
+        let input_len = i32::try_from(input.len()).context("page too large for the plugin")?;
+        let input_ptr = alloc
+            .call(&mut store, input_len)
+            .map_err(|e| anyhow!("alloc failed: {}", e))?;
+        memory
+            .write(&mut store, input_ptr as u32 as usize, &input)
+            .map_err(|e| anyhow!("could not write the input: {}", e))?;
+
+        let output = extract
+            .call(&mut store, (input_ptr, input_len))
+            .map_err(|e| anyhow!("extract failed: {}", e))?;
+        let output_ptr = (output >> 32) as u32 as usize;
+        let output_len = output as u32 as usize;
+
+        let data = memory.data(&store);
+        let Some(output) = data.get(output_ptr..output_ptr + output_len) else {
+            bail!("the output is outside the plugin memory");
+        };
+
+        serde_json::from_slice(output).context("the output is not a JSON object")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A module writing `{"answer": 42}` at 1024, with a
+    /// memory of `pages` and the body of `extract` inserted
+    fn module(pages: u32, extract: &str) -> String {
+        format!(
+            r#"(module
+                (memory (export "memory") {})
+                (data (i32.const 1024) "{{\"answer\": 42}}")
+                (func (export "alloc") (param i32) (result i32) i32.const 2048)
+                (func (export "extract") (param i32 i32) (result i64) {}))"#,
+            pages, extract
+        )
+    }
+
+    fn run(wat: &str) -> Result<BTreeMap<String, Value>> {
+        let path = std::env::temp_dir().join(format!("{}.wat", uuid::Uuid::new_v4()));
+        std::fs::write(&path, wat).unwrap();
+        let extractor = WasmExtractor::from_file(path.to_str().unwrap());
+        std::fs::remove_file(path).unwrap();
+        let mut extractor = extractor?;
+        // Enough for the modules that don't loop
+        extractor.fuel = 10_000;
+        extractor.extract("https://a.com/", "<html></html>")
+    }
+
+    #[test]
+    fn extract_returns_the_output_fields() {
+        let fields = run(&module(1, "i64.const 4398046511118")).unwrap();
+        assert_eq!(fields["answer"], 42);
+    }
+
+    #[test]
+    fn modules_run_out_of_fuel() {
+        let error = run(&module(1, "(loop br 0) i64.const 0")).unwrap_err();
+        assert!(error.to_string().contains("extract failed"), "{}", error);
+    }
+
+    #[test]
+    fn modules_cant_take_too_much_memory() {
+        // 2048 pages of 64KiB are 128MiB
+        let error = run(&module(2048, "i64.const 4398046511118")).unwrap_err();
+        assert!(
+            error.to_string().contains("could not instantiate"),
+            "{}",
+            error
+        );
+
+        // Traps unless growing the memory is refused
+        let grow = "(if (i32.ne (memory.grow (i32.const 2048)) (i32.const -1)) \
+            (then unreachable)) i64.const 4398046511118";
+        assert_eq!(run(&module(1, grow)).unwrap()["answer"], 42);
+    }
+}
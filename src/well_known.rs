@@ -0,0 +1,86 @@
+use futures::{stream, StreamExt};
+use reqwest::StatusCode;
+use std::collections::BTreeMap;
+use tracing::{error, info};
+use url::Url;
+
+use crate::fetcher::Fetcher;
+use crate::model::WellKnownFile;
+use crate::robots::Robots;
+
+/// The standard files probed on each domain
+pub const WELL_KNOWN_PATHS: [&str; 5] = [
+    "/robots.txt",
+    "/sitemap.xml",
+    "/.well-known/security.txt",
+    "/humans.txt",
+    "/feed",
+];
+/// How much of each file is kept, sitemaps and feeds can be big
+const MAX_CONTENT_CHARS: usize = 16 * 1024;
+/// How many domains are probed at the same time
+const CONCURRENT_DOMAINS: usize = 8;
+
+/// Fetches the `WELL_KNOWN_PATHS` of the site at `origin`, by
+/// path. The paths that couldn't be fetched are left out, as are
+/// the ones disallowed by the site's robots.txt when `robots` is given
+pub async fn probe_well_known(
+    fetcher: &dyn Fetcher,
+    origin: &Url,
+    robots: Option<&Robots>,
+) -> BTreeMap<String, WellKnownFile> {
+    let mut files = BTreeMap::new();
+    for path in WELL_KNOWN_PATHS {
+        let Ok(url) = origin.join(path) else {
+            continue;
+        };
+
+        if let Some(robots) = robots.filter(|_| path != "/robots.txt") {
+            match robots.rules_for(fetcher, &url).await {
+                Ok(rules) if !rules.allowed(&url) => {
+                    info!("Well-known file disallowed by robots.txt: {}", url);
+                    continue;
+                }
+                Ok(_) => {}
+                Err(e) => error!("Could not get robots.txt for {}: {}", url, e),
+            }
+        }
+
+        let response = match fetcher.fetch(&url).await {
+            Ok(response) => response,
+            Err(e) => {
+                error!("Could not probe {}: {:#}", url, e);
+                continue;
+            }
+        };
+
+        let mut file = WellKnownFile {
+            status: response.status.as_u16(),
+            ..Default::default()
+        };
+        if response.status == StatusCode::OK {
+            let text = response.text();
+            file.truncated = response.truncated || text.chars().count() > MAX_CONTENT_CHARS;
+            file.content = Some(text.chars().take(MAX_CONTENT_CHARS).collect());
+        }
+        files.insert(path.to_string(), file);
+    }
+
+    files
+}
+
+/// Probes the `origins` (domain, origin url) a few at a time,
+/// returning the files found on each domain
+pub async fn probe_domains(
+    fetcher: &dyn Fetcher,
+    origins: Vec<(String, Url)>,
+    robots: Option<&Robots>,
+) -> Vec<(String, BTreeMap<String, WellKnownFile>)> {
+    stream::iter(origins)
+        .map(|(domain, origin)| async move {
+            (domain, probe_well_known(fetcher, &origin, robots).await)
+        })
+        .buffer_unordered(CONCURRENT_DOMAINS)
+        .collect()
+        .await
+}